@@ -1,12 +1,71 @@
+use clap::{Parser, ValueEnum};
 use itertools::Itertools;
 use num_format::{Locale, ToFormattedString};
 use rayon::prelude::*;
 use scheduler::utils::*;
 use scheduler::SessionData;
+use serde::Serialize;
+use std::process::ExitCode;
 use std::sync::{atomic::AtomicBool, Arc};
 
+/// The most assignments the brute force search evaluates unless `--limit` says otherwise
+const DEFAULT_MAX_COMBINATIONS: usize = 10_000_000;
+
+/// How the comparison is written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Readable summaries along with the best and worst schedules
+    #[default]
+    Text,
+    /// A single JSON object with the score statistics, for benchmarking scripts and baselines
+    Json,
+}
+
+#[derive(Parser)]
+#[command(about = "Compares the local search scheduler against a brute force search")]
+struct Cli {
+    /// The format to write the comparison in
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// The most assignments the brute force search may evaluate, it refuses to run on anything
+    /// bigger
+    #[arg(long, default_value_t = DEFAULT_MAX_COMBINATIONS)]
+    limit: usize,
+}
+
+/// The spread of the scores found by a search
+#[derive(Debug, Serialize)]
+struct ScoreSummary {
+    count: usize,
+    avg: f32,
+    min: f32,
+    max: f32,
+}
+
+impl ScoreSummary {
+    fn from_scores(scores: &[f32]) -> Self {
+        Self {
+            count: scores.len(),
+            avg: scores.iter().sum::<f32>() / scores.len() as f32,
+            min: scores.iter().copied().fold(f32::INFINITY, f32::min),
+            max: scores.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+        }
+    }
+}
+
+/// The comparison written with `--format json`
+#[derive(Debug, Serialize)]
+struct Comparison {
+    sessions: usize,
+    slots: usize,
+    scheduler: ScoreSummary,
+    brute_force: ScoreSummary,
+}
+
 type ScheduleAssignment = Vec<((Option<i32>, i32, Option<i32>), (usize, usize))>;
 struct BruteForceResults {
+    num_sessions: usize,
+    num_slots: usize,
     scores: Vec<f32>,
     best_schedule: scheduler::SchedulerData,
     worst_schedule: scheduler::SchedulerData,
@@ -23,14 +82,32 @@ struct SchedulerResults {
     iterations: usize,
 }
 
-fn compare_schedulers() {
+fn compare_schedulers(format: OutputFormat, max_combinations: usize) -> Result<(), String> {
     let data = make_test_data(3, 3);
 
+    // Refuse before spending time on the scheduler runs
+    data.brute_force_combinations(max_combinations)?;
+
     let scheduler_results = run_scheduler(&data, 100);
-    print_scheduler_results(&scheduler_results);
+    if format == OutputFormat::Text {
+        print_scheduler_results(&scheduler_results);
+    }
 
-    let brute_force_results = run_brute_force(&data);
-    print_brute_force_results(&brute_force_results);
+    let brute_force_results = run_brute_force(&data, format == OutputFormat::Text, max_combinations)?;
+    match format {
+        OutputFormat::Text => print_brute_force_results(&brute_force_results),
+        OutputFormat::Json => {
+            let comparison = Comparison {
+                sessions: brute_force_results.num_sessions,
+                slots: brute_force_results.num_slots,
+                scheduler: ScoreSummary::from_scores(&scheduler_results.scores),
+                brute_force: ScoreSummary::from_scores(&brute_force_results.scores),
+            };
+            println!("{}", serde_json::to_string_pretty(&comparison).expect("the comparison serializes to JSON"));
+        }
+    }
+
+    Ok(())
 }
 
 
@@ -71,17 +148,56 @@ fn run_scheduler(data: &scheduler::SchedulerData, iterations: usize) -> Schedule
     }
 }
 
-fn run_brute_force(data: &scheduler::SchedulerData) -> BruteForceResults {
-    println!("=== BRUTE FORCE EVALUATION ===");
-    data.brute_force_all_assignments()
+fn run_brute_force(data: &scheduler::SchedulerData, verbose: bool, max_combinations: usize) -> Result<BruteForceResults, String> {
+    if verbose {
+        println!("=== BRUTE FORCE EVALUATION ===");
+    }
+    data.brute_force_all_assignments(verbose, max_combinations)
 }
 
 trait BruteForceScheduler {
-    fn brute_force_all_assignments(&self) -> BruteForceResults;
+    /// Counts the assignments a brute force search would evaluate, refusing searches that are too
+    /// big to run
+    ///
+    /// # Parameters
+    /// - `max_combinations`: The most assignments the search may evaluate
+    ///
+    /// # Returns
+    /// The number of assignments, every way of choosing the scheduled sessions and grouping them
+    /// into the timeslots
+    ///
+    /// # Errors
+    /// A message suggesting the local search instead if there are more than `max_combinations`
+    /// assignments
+    fn brute_force_combinations(&self, max_combinations: usize) -> Result<usize, String>;
+
+    fn brute_force_all_assignments(&self, verbose: bool, max_combinations: usize) -> Result<BruteForceResults, String>;
 }
 
 impl BruteForceScheduler for scheduler::SchedulerData {
-    fn brute_force_all_assignments(&self) -> BruteForceResults {
+    fn brute_force_combinations(&self, max_combinations: usize) -> Result<usize, String> {
+        let swappable_positions = self.get_swappable_sessions();
+        let num_slots = swappable_positions.len();
+        let num_sessions = swappable_positions
+            .iter()
+            .filter(|&&(row, col)| self.schedule_rows[row].schedule_items[col].session_id.is_some())
+            .count() + self.unassigned_sessions.len();
+        let time_slot_capacities = get_time_slot_capacities(self, &swappable_positions);
+
+        let combinations = num_of_combinations(num_sessions, num_slots)
+            .and_then(|combinations| combinations.checked_mul(num_of_ways_to_group(num_slots, &time_slot_capacities)?))
+            .filter(|&combinations| combinations <= max_combinations);
+
+        combinations.ok_or_else(|| format!(
+            "Brute forcing {num_sessions} sessions into {num_slots} slots needs more than the limit of {} assignments. \
+            Use the local search scheduler for inputs this size, or raise --limit.",
+            max_combinations.to_formatted_string(&Locale::en),
+        ))
+    }
+
+    fn brute_force_all_assignments(&self, verbose: bool, max_combinations: usize) -> Result<BruteForceResults, String> {
+        self.brute_force_combinations(max_combinations)?;
+
         let swappable_positions: Vec<(usize, usize)> = self.get_swappable_sessions();
         let mut all_sessions = Vec::new();
 
@@ -99,26 +215,32 @@ impl BruteForceScheduler for scheduler::SchedulerData {
         let num_slots = swappable_positions.len();
         let num_sessions = all_sessions.len();
 
-        println!("Sessions: {num_sessions}, Slots: {num_slots}\n");
+        if verbose {
+            println!("Sessions: {num_sessions}, Slots: {num_slots}\n");
+        }
 
         // Calculate capacity for each time slot
         let time_slot_capacities = get_time_slot_capacities(self, &swappable_positions);
 
-        println!("Creating C({num_sessions}, {num_slots}) combinations");
+        if verbose {
+            println!("Creating C({num_sessions}, {num_slots}) combinations");
+        }
         let combinations: Vec<_> = all_sessions.iter()
             .combinations(num_slots)
             .collect();
 
-        println!("Combinations created: {}\n", combinations.len().to_formatted_string(&Locale::en));
+        if verbose {
+            println!("Combinations created: {}\n", combinations.len().to_formatted_string(&Locale::en));
 
-        // Calculate total assignments
-        let total_assignments = combinations.len() *
-            num_of_ways_to_group(num_slots, &time_slot_capacities);
+            // Calculate total assignments, the limit check means these can't overflow
+            let ways_to_group = num_of_ways_to_group(num_slots, &time_slot_capacities).unwrap_or_default();
+            let total_assignments = combinations.len() * ways_to_group;
 
-        println!("Processing {} combinations with {} time slot assignments each = {} total assignments",
-            combinations.len().to_formatted_string(&Locale::en),
-            num_of_ways_to_group(num_slots, &time_slot_capacities).to_formatted_string(&Locale::en),
-            total_assignments.to_formatted_string(&Locale::en));
+            println!("Processing {} combinations with {} time slot assignments each = {} total assignments",
+                combinations.len().to_formatted_string(&Locale::en),
+                ways_to_group.to_formatted_string(&Locale::en),
+                total_assignments.to_formatted_string(&Locale::en));
+        }
 
         let best_data = std::sync::Mutex::new((f32::MAX, self.clone()));
         let worst_data = std::sync::Mutex::new((f32::MIN, self.clone()));
@@ -168,6 +290,8 @@ impl BruteForceScheduler for scheduler::SchedulerData {
                                 tag_id: session.2,
                                 speaker_id: None,
                                 speaker_votes: vec![],
+                                is_keynote: false,
+                                allowed_slots: None,
                             });
                         }
                     }
@@ -206,13 +330,15 @@ impl BruteForceScheduler for scheduler::SchedulerData {
         let best = best_data.into_inner().unwrap();
         let worst = worst_data.into_inner().unwrap();
 
-        BruteForceResults {
+        Ok(BruteForceResults {
+            num_sessions,
+            num_slots,
             scores: results,
             best_schedule: best.1,
             worst_schedule: worst.1,
             best_score: best.0,
             worst_score: worst.0,
-        }
+        })
     }
 }
 
@@ -324,24 +450,33 @@ fn generate_assignments_recursive(
     }
 }
 
-// Calculates the number of ways to divide n items into groups of given capacities
-fn num_of_ways_to_group(n: usize, capacities: &[usize]) -> usize {
-    let mut result = factorial(n);
+// Calculates the number of ways to divide n items into groups of given capacities, `None` if it
+// doesn't fit in a usize
+fn num_of_ways_to_group(n: usize, capacities: &[usize]) -> Option<usize> {
+    // Fill the groups one at a time, choosing each group's items from the ones left
+    let mut remaining = n;
+    let mut result: usize = 1;
     for &capacity in capacities {
-        if capacity > 0 {
-            result /= factorial(capacity);
-        }
+        result = result.checked_mul(num_of_combinations(remaining, capacity)?)?;
+        remaining = remaining.checked_sub(capacity)?;
     }
-    result
+    Some(result)
 }
 
-fn factorial(number: usize) -> usize {
-    let mut factorial = 1;
-    for i in 1..=number {
-        factorial *= i;
+// Calculates the number of ways to choose k of n items, `None` if it doesn't fit in a usize
+fn num_of_combinations(n: usize, k: usize) -> Option<usize> {
+    if k > n {
+        return Some(0);
     }
 
-    factorial
+    // Each partial product is itself a binomial coefficient, so the division is exact. A partial
+    // product overflowing counts as too many, which only happens far above any usable limit
+    let k = k.min(n - k);
+    let mut result: usize = 1;
+    for i in 0..k {
+        result = result.checked_mul(n - i)? / (i + 1);
+    }
+    Some(result)
 }
 
 fn print_brute_force_results(brute_force_results: &BruteForceResults) {
@@ -373,6 +508,13 @@ fn print_scheduler_results(scheduler_results: &SchedulerResults) {
     println!("Worst schedule: \n{}", scheduler_results.worst_schedule);
 }
 
-fn main() {
-    compare_schedulers();
-}
\ No newline at end of file
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match compare_schedulers(cli.format, cli.limit) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
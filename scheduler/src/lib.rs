@@ -1,17 +1,41 @@
-use rand::prelude::IteratorRandom;
+use rand::{prelude::IteratorRandom, Rng};
 use std::{
+    cmp::Ordering as CmpOrdering,
     collections::HashMap,
     fmt::{Display, Formatter},
+    str::FromStr,
     sync::{atomic::{AtomicBool, Ordering}, Arc}
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SessionData {
     pub session_id: Option<i32>,
     pub num_votes: i32,
     pub tag_id: Option<i32>,
     pub speaker_id: Option<i32>,
     pub speaker_votes: Vec<i32>,
+    pub is_keynote: bool,
+    /// The timeslots the session may be placed in, any timeslot when `None`
+    pub allowed_slots: Option<Vec<i32>>,
+}
+
+impl SessionData {
+    /// Checks whether the session may be placed in a timeslot
+    ///
+    /// # Parameters
+    /// - `time_slot_id`: The ID of the timeslot
+    ///
+    /// # Returns
+    /// `true` if the session has no allowed slots or the timeslot is one of them
+    pub fn allows_slot(&self, time_slot_id: i32) -> bool {
+        allows_slot(self.allowed_slots.as_deref(), time_slot_id)
+    }
+}
+
+/// Checks whether a timeslot is one of the allowed slots, every timeslot is allowed when there
+/// aren't any
+fn allows_slot(allowed_slots: Option<&[i32]>, time_slot_id: i32) -> bool {
+    allowed_slots.is_none_or(|allowed_slots| allowed_slots.contains(&time_slot_id))
 }
 
 #[derive(Debug, Clone)]
@@ -19,14 +43,416 @@ pub struct SchedulerData {
     pub schedule_rows: Vec<ScheduleRow>,
     pub capacity: i32,
     pub unassigned_sessions: Vec<SessionData>,
+    pub options: SchedulerOptions,
 }
 
+/// Tunable settings that change how the scheduler weighs a schedule
+///
+/// # Fields
+/// - `empty_slot_tolerance`: How many more votes an unscheduled session needs than a scheduled
+///   session (or an empty slot, which counts as 0 votes) before leaving it out is penalized.
+///   Sessions with no more votes than the tolerance aren't used to fill empty slots. A tolerance
+///   of 0 fills every slot it can and leaves the missing penalty as it was, empty slots aren't
+///   compared against.
+/// - `tie_breaker`: How to choose between schedules with the same score when running with restarts
+/// - `weights`: How heavily each penalty counts towards the score
+/// - `random_move_prob`: The chance of each local search iteration trying a single random move
+///   instead of scanning every swap for the best one, between 0 and 1
+/// - `max_iterations`: How many iterations each local search runs for, `3 * capacity^2` when
+///   `None`
+/// - `normalization`: How the penalties are scaled before they are weighted
+/// - `mutual_exclusions`: Pairs of session IDs that shouldn't share a timeslot, e.g. two sessions
+///   run by the same people
+/// - `tag_time_preference`: The part of the day sessions with a tag should be pulled towards, e.g.
+///   beginner sessions early and advanced ones late. Tags that aren't in the map can go anywhere.
+/// - `scoring_strategy`: How the penalties are combined when comparing schedules
+/// - `progress_callback`: Called with the `Progress` of the run after each restart
 #[derive(Debug, Clone)]
+pub struct SchedulerOptions {
+    pub empty_slot_tolerance: i32,
+    pub tie_breaker: TieBreaker,
+    pub weights: ScoringWeights,
+    pub random_move_prob: f64,
+    pub max_iterations: Option<usize>,
+    pub normalization: Normalization,
+    pub mutual_exclusions: Vec<(i32, i32)>,
+    pub tag_time_preference: HashMap<i32, SlotPreference>,
+    pub scoring_strategy: ScoringStrategy,
+    pub progress_callback: Option<ProgressCallback>,
+}
+
+/// The chance of a local search iteration trying a random move when it isn't configured
+pub const DEFAULT_RANDOM_MOVE_PROB: f64 = 0.5;
+
+impl Default for SchedulerOptions {
+    fn default() -> Self {
+        Self {
+            empty_slot_tolerance: 0,
+            tie_breaker: TieBreaker::default(),
+            weights: ScoringWeights::default(),
+            random_move_prob: DEFAULT_RANDOM_MOVE_PROB,
+            max_iterations: None,
+            normalization: Normalization::default(),
+            mutual_exclusions: Vec::new(),
+            tag_time_preference: HashMap::new(),
+            scoring_strategy: ScoringStrategy::default(),
+            progress_callback: None,
+        }
+    }
+}
+
+/// The part of the day sessions with a tag should be scheduled in
+///
+/// # Variants
+/// - `Early`: As close to the first timeslot as the schedule allows
+/// - `Late`: As close to the last timeslot as the schedule allows
+/// - `Any`: Anywhere, the same as not having a preference
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlotPreference {
+    Early,
+    Late,
+    #[default]
+    Any,
+}
+
+/// How heavily each penalty counts towards the score of a schedule
+///
+/// # Fields
+/// - `conflicting`: Weight of popular sessions sharing a timeslot
+/// - `missing`: Weight of popular sessions being left unscheduled
+/// - `late`: Weight of popular sessions being scheduled late in the day
+/// - `same_tag`: Weight of sessions with the same tag sharing a timeslot
+/// - `speaker_conflict`: Weight of speakers being scheduled against sessions they voted for
+/// - `keynote_conflict`: Weight of sessions being scheduled against a keynote, a keynote being
+///   out of the biggest room of its timeslot or left off the schedule
+/// - `popular_speaker_conflict`: Weight of popular speakers being scheduled against each other, a
+///   speaker's popularity being the total votes across all of their sessions. A weight of 0 turns
+///   the penalty off.
+/// - `wasted_capacity`: Weight of each seat in rooms left empty, so empty slots end up in the
+///   smaller rooms. A weight of 0 turns the penalty off.
+/// - `speaker_slot_fairness`: Weight of a speaker holding more than one of the most desirable
+///   slots, the early ones in large rooms. A weight of 0 turns the penalty off.
+/// - `mutual_exclusion`: Weight of a mutually exclusive pair of sessions sharing a timeslot
+/// - `tag_time_preference`: Weight of each timeslot a session is away from the part of the day its
+///   tag prefers
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoringWeights {
+    pub conflicting: f32,
+    pub missing: f32,
+    pub late: f32,
+    pub same_tag: f32,
+    pub speaker_conflict: f32,
+    pub keynote_conflict: f32,
+    pub popular_speaker_conflict: f32,
+    pub wasted_capacity: f32,
+    pub speaker_slot_fairness: f32,
+    pub mutual_exclusion: f32,
+    pub tag_time_preference: f32,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            conflicting: 0.5,
+            missing: 0.75,
+            late: 0.1,
+            same_tag: 0.3,
+            speaker_conflict: 0.1,
+            keynote_conflict: 1000.0,
+            popular_speaker_conflict: 0.0,
+            wasted_capacity: 0.0,
+            speaker_slot_fairness: 0.0,
+            mutual_exclusion: 1000.0,
+            tag_time_preference: 1.0,
+        }
+    }
+}
+
+/// Each of the penalties a schedule is scored with, see `ScoreBreakdown`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PenaltyKind {
+    Conflicting,
+    Missing,
+    Late,
+    SameTag,
+    SpeakerConflict,
+    KeynoteConflict,
+    PopularSpeakerConflict,
+    WastedCapacity,
+    SpeakerSlotFairness,
+    MutualExclusion,
+    TagTimePreference,
+}
+
+impl PenaltyKind {
+    /// The value of this penalty in a breakdown
+    pub fn of(self, breakdown: &ScoreBreakdown) -> i32 {
+        match self {
+            PenaltyKind::Conflicting => breakdown.conflicting,
+            PenaltyKind::Missing => breakdown.missing,
+            PenaltyKind::Late => breakdown.late,
+            PenaltyKind::SameTag => breakdown.same_tag,
+            PenaltyKind::SpeakerConflict => breakdown.speaker_conflict,
+            PenaltyKind::KeynoteConflict => breakdown.keynote_conflict,
+            PenaltyKind::PopularSpeakerConflict => breakdown.popular_speaker_conflict,
+            PenaltyKind::WastedCapacity => breakdown.wasted_capacity,
+            PenaltyKind::SpeakerSlotFairness => breakdown.speaker_slot_fairness,
+            PenaltyKind::MutualExclusion => breakdown.mutual_exclusion,
+            PenaltyKind::TagTimePreference => breakdown.tag_time_preference,
+        }
+    }
+}
+
+impl FromStr for PenaltyKind {
+    type Err = String;
+
+    /// Parses the name of a `ScoreBreakdown` field, such as `missing` or `speaker_conflict`,
+    /// ignoring case.
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind.trim().to_lowercase().as_str() {
+            "conflicting" => Ok(PenaltyKind::Conflicting),
+            "missing" => Ok(PenaltyKind::Missing),
+            "late" => Ok(PenaltyKind::Late),
+            "same_tag" => Ok(PenaltyKind::SameTag),
+            "speaker_conflict" => Ok(PenaltyKind::SpeakerConflict),
+            "keynote_conflict" => Ok(PenaltyKind::KeynoteConflict),
+            "popular_speaker_conflict" => Ok(PenaltyKind::PopularSpeakerConflict),
+            "wasted_capacity" => Ok(PenaltyKind::WastedCapacity),
+            "speaker_slot_fairness" => Ok(PenaltyKind::SpeakerSlotFairness),
+            "mutual_exclusion" => Ok(PenaltyKind::MutualExclusion),
+            "tag_time_preference" => Ok(PenaltyKind::TagTimePreference),
+            _ => Err(format!("unknown penalty {kind}")),
+        }
+    }
+}
+
+/// How the penalties are combined when the scheduler compares schedules
+///
+/// # Variants
+/// - `WeightedSum`: The schedule with the lowest weighted total is better
+/// - `Lexicographic`: The schedule with the lowest of the first listed penalty is better, the next
+///   penalty only decides between schedules that are tied on every penalty before it and the
+///   weighted total decides what's left. No amount of improvement in a later penalty makes up for
+///   an earlier one being worse.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ScoringStrategy {
+    #[default]
+    WeightedSum,
+    Lexicographic(Vec<PenaltyKind>),
+}
+
+impl FromStr for ScoringStrategy {
+    type Err = String;
+
+    /// Parses `weighted`, or `lexicographic:` followed by a comma separated list of penalties in
+    /// priority order such as `lexicographic:missing,late`, ignoring case.
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        let strategy = strategy.trim();
+        if strategy.eq_ignore_ascii_case("weighted") {
+            return Ok(ScoringStrategy::WeightedSum);
+        }
+        match strategy.split_once(':') {
+            Some((name, kinds)) if name.trim().eq_ignore_ascii_case("lexicographic") => {
+                let kinds = kinds.split(',').map(str::parse).collect::<Result<Vec<PenaltyKind>, _>>()?;
+                Ok(ScoringStrategy::Lexicographic(kinds))
+            },
+            _ => Err(format!("unknown scoring strategy {strategy}")),
+        }
+    }
+}
+
+/// What the scheduler minimizes, schedules compare by their penalties in priority order and then
+/// by their weighted total
+///
+/// # Fields
+/// - `penalties`: The penalties `ScoringStrategy::Lexicographic` lists, in its order, empty for a
+///   weighted sum
+/// - `total`: The weighted total, the same value `SchedulerData::score` returns
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Objective {
+    pub penalties: Vec<i32>,
+    pub total: f32,
+}
+
+/// How far a run with restarts has got
+///
+/// # Fields
+/// - `restart`: How many restarts have finished
+/// - `restarts`: How many restarts the run will do unless it is stopped early
+/// - `best_score`: The best score found so far
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub restart: usize,
+    pub restarts: usize,
+    pub best_score: f32,
+}
+
+/// A function the scheduler reports its `Progress` to while it runs
+///
+/// The function is called from the thread the scheduler runs on, so it shouldn't block for long.
+#[derive(Clone)]
+pub struct ProgressCallback(pub Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl ProgressCallback {
+    /// Creates a `ProgressCallback` that calls the given function
+    pub fn new(callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+}
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// How the scheduler chooses between schedules that have the same score
+///
+/// # Variants
+/// - `KeepFirst`: Keep the first schedule found with the best score
+/// - `MinimizeSpeakerRoomSwitches`: Prefer the schedule where speakers change rooms the fewest
+///   times between their sessions
+/// - `Custom`: Compare the schedules with the given function, a schedule that compares as `Less`
+///   is preferred
+#[derive(Debug, Clone, Copy, Default)]
+pub enum TieBreaker {
+    #[default]
+    KeepFirst,
+    MinimizeSpeakerRoomSwitches,
+    Custom(fn(&SchedulerData, &SchedulerData) -> CmpOrdering),
+}
+
+impl TieBreaker {
+    /// Checks whether a schedule is preferred over the current best schedule with the same score
+    ///
+    /// # Parameters
+    /// - `candidate`: The newly found schedule
+    /// - `best`: The best schedule found so far
+    ///
+    /// # Returns
+    /// `true` if `candidate` should replace `best`
+    pub fn prefers(&self, candidate: &SchedulerData, best: &SchedulerData) -> bool {
+        let ordering = match self {
+            TieBreaker::KeepFirst => CmpOrdering::Equal,
+            TieBreaker::MinimizeSpeakerRoomSwitches => candidate.speaker_room_switches().cmp(&best.speaker_room_switches()),
+            TieBreaker::Custom(compare) => compare(candidate, best),
+        };
+
+        ordering == CmpOrdering::Less
+    }
+}
+
+/// Breakdown of a schedule's score into the penalties it is made up of
+///
+/// # Fields
+/// - `conflicting`: Penalty for popular sessions sharing a timeslot
+/// - `missing`: Penalty for popular sessions being left unscheduled
+/// - `late`: Penalty for popular sessions being scheduled late in the day
+/// - `same_tag`: Penalty for sessions with the same tag sharing a timeslot
+/// - `speaker_conflict`: Penalty for speakers being scheduled against sessions they voted for
+/// - `keynote_conflict`: Penalty for sessions being scheduled against a keynote, a keynote being
+///   out of the biggest room of its timeslot or left off the schedule
+/// - `popular_speaker_conflict`: Penalty for popular speakers being scheduled against each other,
+///   0 when `ScoringWeights::popular_speaker_conflict` is 0
+/// - `wasted_capacity`: Penalty for the seats in rooms left empty, the available spots of each
+///   empty slot that could be filled
+/// - `speaker_slot_fairness`: Penalty for speakers holding more than one desirable slot, 0 when
+///   `ScoringWeights::speaker_slot_fairness` is 0
+/// - `mutual_exclusion`: Penalty for mutually exclusive pairs of sessions sharing a timeslot
+/// - `tag_time_preference`: Penalty for sessions being scheduled away from the part of the day
+///   their tag prefers, 0 without `SchedulerOptions::tag_time_preference`
+/// - `total`: The weighted sum of the penalties, lower is better
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ScoreBreakdown {
+    pub conflicting: i32,
+    pub missing: i32,
+    pub late: i32,
+    pub same_tag: i32,
+    pub speaker_conflict: i32,
+    pub keynote_conflict: i32,
+    pub popular_speaker_conflict: i32,
+    pub wasted_capacity: i32,
+    pub speaker_slot_fairness: i32,
+    pub mutual_exclusion: i32,
+    pub tag_time_preference: i32,
+    pub total: f32,
+}
+
+/// How the penalties are scaled before they are weighted
+///
+/// # Variants
+/// - `Raw`: The penalties are weighted as they are, so a penalty that runs into bigger numbers
+///   counts for more than another with the same weight
+/// - `MinMax`: Each penalty is scaled so its range maps onto 0 to 1 before it is weighted, so the
+///   weights compare the penalties on an equal footing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Normalization {
+    #[default]
+    Raw,
+    MinMax(PenaltyRanges),
+}
+
+/// The lowest and highest value of each penalty, see `SchedulerData::sample_penalty_ranges`
+///
+/// # Fields
+/// - `min`: The lowest value of each penalty, `total` is unused
+/// - `max`: The highest value of each penalty, `total` is unused
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PenaltyRanges {
+    pub min: ScoreBreakdown,
+    pub max: ScoreBreakdown,
+}
+
+impl PenaltyRanges {
+    fn widen(&mut self, breakdown: &ScoreBreakdown) {
+        for (min, max, penalty) in [
+            (&mut self.min.conflicting, &mut self.max.conflicting, breakdown.conflicting),
+            (&mut self.min.missing, &mut self.max.missing, breakdown.missing),
+            (&mut self.min.late, &mut self.max.late, breakdown.late),
+            (&mut self.min.same_tag, &mut self.max.same_tag, breakdown.same_tag),
+            (&mut self.min.speaker_conflict, &mut self.max.speaker_conflict, breakdown.speaker_conflict),
+            (&mut self.min.keynote_conflict, &mut self.max.keynote_conflict, breakdown.keynote_conflict),
+            (&mut self.min.popular_speaker_conflict, &mut self.max.popular_speaker_conflict, breakdown.popular_speaker_conflict),
+            (&mut self.min.wasted_capacity, &mut self.max.wasted_capacity, breakdown.wasted_capacity),
+            (&mut self.min.speaker_slot_fairness, &mut self.max.speaker_slot_fairness, breakdown.speaker_slot_fairness),
+            (&mut self.min.mutual_exclusion, &mut self.max.mutual_exclusion, breakdown.mutual_exclusion),
+            (&mut self.min.tag_time_preference, &mut self.max.tag_time_preference, breakdown.tag_time_preference),
+        ] {
+            *min = (*min).min(penalty);
+            *max = (*max).max(penalty);
+        }
+    }
+
+    /// Scales a penalty so its range maps onto 0 to 1, a penalty that doesn't vary is only shifted
+    fn scale(&self, penalty: fn(&ScoreBreakdown) -> i32, breakdown: &ScoreBreakdown) -> f32 {
+        let min = penalty(&self.min);
+        let span = (penalty(&self.max) - min).max(1);
+        (penalty(breakdown) - min) as f32 / span as f32
+    }
+}
+
+/// Diagnostics collected while running the scheduler with restarts
+///
+/// # Fields
+/// - `restarts`: How many restarts ran before finishing or being stopped
+/// - `iterations`: The total number of local search iterations across all restarts
+/// - `starting_score`: The score of the schedule before the scheduler filled any slots
+/// - `final_score`: The score of the best schedule found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunStats {
+    pub restarts: usize,
+    pub iterations: usize,
+    pub starting_score: f32,
+    pub final_score: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ScheduleRow {
     pub schedule_items: Vec<RoomTimeAssignment>,
 }
 
-#[derive(sqlx::FromRow, Debug, Clone)]
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
 pub struct RoomTimeAssignment {
     pub room_id: i32,
     pub time_slot_id: i32,
@@ -37,6 +463,24 @@ pub struct RoomTimeAssignment {
     pub tag_id: Option<i32>,
     pub speaker_id: Option<i32>,
     pub speaker_votes: Vec<i32>,
+    pub is_keynote: bool,
+    /// The timeslots the session in this cell may be placed in, any timeslot when `None`
+    pub allowed_slots: Option<Vec<i32>>,
+    pub room_capacity: i32,
+}
+
+impl RoomTimeAssignment {
+    /// Whether a session has been placed in this room and timeslot, regardless of how many votes
+    /// it has
+    pub fn is_assigned(&self) -> bool {
+        self.session_id.is_some()
+    }
+
+    /// Whether the session in this cell may be placed in a timeslot, always `true` when the cell
+    /// is empty
+    pub fn allows_slot(&self, time_slot_id: i32) -> bool {
+        allows_slot(self.allowed_slots.as_deref(), time_slot_id)
+    }
 }
 
 #[derive(Clone)]
@@ -53,9 +497,10 @@ impl Display for SchedulerData {
             let votes: Vec<String> = row.schedule_items
                 .iter()
                 .map(|session| {
-                    match session.session_id {
-                        Some(_) => session.num_votes.to_string(),
-                        None => "-".to_string(),
+                    if session.is_assigned() {
+                        session.num_votes.to_string()
+                    } else {
+                        "-".to_string()
                     }
                 })
                 .collect();
@@ -78,6 +523,10 @@ impl Display for SchedulerData {
 
 impl SchedulerData {
     pub fn randomly_fill_available_spots(&mut self) {
+        self.randomly_fill_available_spots_with(&mut rand::rng());
+    }
+
+    fn randomly_fill_available_spots_with<R: Rng + ?Sized>(&mut self, rng: &mut R) {
         // Iterate through each time slot row in the schedule
         // For each row check each room assignment
         // Skip any room assignments that already have sessions assigned (already_assigned being true)
@@ -89,21 +538,30 @@ impl SchedulerData {
                 if schedule_item.already_assigned {
                     continue;
                 } else {
-                    // If there are not anymore unassigned sessions we are done
-                    if self.unassigned_sessions.is_empty() {
+                    // If there are not anymore unassigned sessions worth a slot we are done, if
+                    // none of them can go in this timeslot they may still fit a later one
+                    if !self.unassigned_sessions.iter().any(|session| session.session_id.is_some() && self.options.is_worth_a_slot(session.num_votes)) {
                         return;
                     }
-                    let (i, session) = self.unassigned_sessions
+                    let Some((i, session)) = self.unassigned_sessions
                         .iter()
                         .enumerate()
-                        .choose(&mut rand::rng())
-                        .unwrap();
+                        .filter(|(_, session)| {
+                            session.session_id.is_some()
+                                && self.options.is_worth_a_slot(session.num_votes)
+                                && session.allows_slot(schedule_item.time_slot_id)
+                        })
+                        .choose(rng) else {
+                        continue;
+                    };
 
                     schedule_item.session_id = session.session_id;
                     schedule_item.num_votes = session.num_votes;
                     schedule_item.tag_id = session.tag_id;
                     schedule_item.speaker_id = session.speaker_id;
                     schedule_item.speaker_votes = session.speaker_votes.clone();
+                    schedule_item.is_keynote = session.is_keynote;
+                    schedule_item.allowed_slots = session.allowed_slots.clone();
 
                     self.unassigned_sessions.swap_remove(i);
                 }
@@ -111,30 +569,132 @@ impl SchedulerData {
         }
     }
 
+    /// Fills the empty slots with the most popular sessions first
+    ///
+    /// Sessions are placed in order of votes, highest first with ties broken by session id. They
+    /// are spread across the timeslots a room at a time, so the most popular sessions land in the
+    /// earliest timeslots with the fewest sessions already in them instead of competing with each
+    /// other. A session with allowed slots only goes in those timeslots, a slot none of the
+    /// remaining sessions are allowed in is left empty. Slots that are already assigned are left
+    /// alone.
+    pub fn fill_greedy_by_votes(&mut self) {
+        let mut candidates: Vec<usize> = self.unassigned_sessions
+            .iter()
+            .enumerate()
+            .filter(|(_, session)| session.session_id.is_some() && self.options.is_worth_a_slot(session.num_votes))
+            .map(|(i, _)| i)
+            .collect();
+        candidates.sort_by_key(|&i| (std::cmp::Reverse(self.unassigned_sessions[i].num_votes), self.unassigned_sessions[i].session_id));
+
+        // The empty slots of each timeslot, visited one per timeslot per round
+        let free_slots: Vec<Vec<usize>> = self.schedule_rows
+            .iter()
+            .map(|row| {
+                row.schedule_items
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, item)| !item.already_assigned && !item.is_assigned())
+                    .map(|(item_idx, _)| item_idx)
+                    .collect()
+            })
+            .collect();
+        let num_rounds = free_slots.iter().map(Vec::len).max().unwrap_or(0);
+        let fill_order = (0..num_rounds).flat_map(|round| {
+            free_slots
+                .iter()
+                .enumerate()
+                .filter_map(move |(row_idx, slots)| slots.get(round).map(|&item_idx| (row_idx, item_idx)))
+        });
+
+        // Each slot takes the most popular remaining session allowed in its timeslot
+        let mut placed = Vec::new();
+        for (row_idx, item_idx) in fill_order {
+            if candidates.is_empty() {
+                break;
+            }
+            let schedule_item = &mut self.schedule_rows[row_idx].schedule_items[item_idx];
+            let Some(candidate_idx) = candidates
+                .iter()
+                .position(|&session_idx| self.unassigned_sessions[session_idx].allows_slot(schedule_item.time_slot_id)) else {
+                continue;
+            };
+            let session_idx = candidates.remove(candidate_idx);
+            let session = &self.unassigned_sessions[session_idx];
+            schedule_item.session_id = session.session_id;
+            schedule_item.num_votes = session.num_votes;
+            schedule_item.tag_id = session.tag_id;
+            schedule_item.speaker_id = session.speaker_id;
+            schedule_item.speaker_votes = session.speaker_votes.clone();
+            schedule_item.is_keynote = session.is_keynote;
+            schedule_item.allowed_slots = session.allowed_slots.clone();
+            placed.push(session_idx);
+        }
+
+        // Remove from the back so the remaining indices stay valid
+        placed.sort_unstable_by(|a, b| b.cmp(a));
+        for session_idx in placed {
+            self.unassigned_sessions.swap_remove(session_idx);
+        }
+    }
+
     pub fn improve(&mut self, stop_flag: Arc<AtomicBool>) -> f32 {
-        use rand::{seq::IndexedRandom, Rng};
         let mut rng = rand::rng();
 
         // Start with randomly assigned schedule (preserves already assigned)
-        self.randomly_fill_available_spots();
+        self.randomly_fill_available_spots_with(&mut rng);
+
+        self.local_search(&mut rng, stop_flag).0.total
+    }
+
+    /// Improves the schedule starting from the most popular sessions placed greedily
+    ///
+    /// # Parameters
+    /// - `stop_flag`: Signals the function to stop early and return the current score
+    ///
+    /// # Returns
+    /// The score of the improved schedule
+    pub fn improve_greedy_start(&mut self, stop_flag: Arc<AtomicBool>) -> f32 {
+        self.fill_greedy_by_votes();
+
+        self.local_search(&mut rand::rng(), stop_flag).0.total
+    }
 
-        let mut current_score = self.score();
-        let max_iterations = 3 * self.capacity * self.capacity;
+    /// Runs the local search from the current schedule
+    ///
+    /// # Returns
+    /// The objective of the improved schedule and the number of iterations that ran
+    fn local_search<R: Rng + ?Sized>(&mut self, rng: &mut R, stop_flag: Arc<AtomicBool>) -> (Objective, usize) {
+        use rand::seq::IndexedRandom;
+
+        let mut current_score = self.objective();
+        let max_iterations = self.options.iterations_for(self.capacity);
+
+        // Get only the swappable positions, which don't change during the search
+        let swappable_sessions: Vec<(usize, usize)> = self.get_swappable_sessions();
+
+        // When there are more slots than sessions the slots can't all be filled, so the
+        // swappable positions can be empty and there may be no unassigned sessions left. Nothing
+        // can move if every slot is already assigned, or if there isn't a session in any of the
+        // swappable positions or waiting to be scheduled
+        let has_movable_session = !self.unassigned_sessions.is_empty()
+            || swappable_sessions
+                .iter()
+                .any(|&(row_idx, item_idx)| self.schedule_rows[row_idx].schedule_items[item_idx].is_assigned());
+        if swappable_sessions.is_empty() || !has_movable_session {
+            return (current_score, 0);
+        }
 
-        let mut best_score = current_score;
+        let mut best_score = current_score.clone();
         let mut best_action: Option<SwapAction> = None;
         for search_iter in 0..max_iterations {
             // Received an indication to stop, so return the current_score
             if stop_flag.load(Ordering::Relaxed) {
                 tracing::info!("Stopping current iteration of the scheduler");
-                return current_score;
+                return (current_score, search_iter);
             }
 
-            // Get only the swappable positions
-            let swappable_sessions: Vec<(usize, usize)> = self.get_swappable_sessions();
-
-            let coin_flip = rng.random_bool(0.5);
-            if coin_flip {
+            let random_move = rng.random_bool(self.options.random_move_probability());
+            if !random_move {
 
                 // Try all pair swaps between swappable positions within the schedule and the unassigned
                 for i in 0..swappable_sessions.len() {
@@ -145,10 +705,13 @@ impl SchedulerData {
 
                         // Perform the pair swap
                         let action = SwapAction::FromSchedule(pos1, pos2);
+                        if !self.is_allowed(&action) {
+                            continue;
+                        }
                         self.apply_action(&action);
 
                         // Evaluate the new score
-                        let new_score = self.score();
+                        let new_score = self.objective();
                         if new_score < best_score {
                             best_score = new_score;
                             best_action = Some(action.clone());
@@ -164,10 +727,13 @@ impl SchedulerData {
 
                         // Perform the swap with the unassigned sessions
                         let action = SwapAction::FromUnassigned(pos1, pos2);
+                        if !self.is_allowed(&action) {
+                            continue;
+                        }
                         self.apply_action(&action);
 
                         // Evaluate the new score
-                        let new_score = self.score();
+                        let new_score = self.objective();
                         if new_score < best_score {
                             best_score = new_score;
                             best_action = Some(action.clone());
@@ -178,7 +744,7 @@ impl SchedulerData {
                     }
                 }
             } else {
-                let pos1 = *swappable_sessions.choose(&mut rng).unwrap();
+                let pos1 = *swappable_sessions.choose(rng).unwrap();
                 let unassgned_sessions_len = self.unassigned_sessions.len();
                 let swappable_sessions_len = swappable_sessions.len();
                 let total_sessions_len = unassgned_sessions_len + swappable_sessions_len;
@@ -190,12 +756,16 @@ impl SchedulerData {
                     SwapAction::FromUnassigned(pos1, unassigned_idx)
                 } else {
                     // Swap with another session in the schedule
-                    let pos2 = *swappable_sessions.choose(&mut rng).unwrap();
+                    let pos2 = *swappable_sessions.choose(rng).unwrap();
                     SwapAction::FromSchedule(pos1, pos2)
                 };
+                // A move that puts a session outside its allowed slots is never made
+                if !self.is_allowed(&action) {
+                    continue;
+                }
 
                 self.apply_action(&action);
-                let new_score = self.score();
+                let new_score = self.objective();
                 self.reverse_action(&action);
 
                 // If the random move led to an improved score accept it, otherwise accept it with
@@ -211,7 +781,11 @@ impl SchedulerData {
                     let temperature_offset = 0.25;
                     let progress_through_iterations = search_iter as f64 / max_iterations as f64;
                     let temperature = (1.0 - (progress_through_iterations + temperature_offset)).max(0.0);
-                    if rng.random_bool(temperature) {
+                    // Under a lexicographic strategy a worse move is only taken when every listed
+                    // penalty stays as it is, only the weighted total may get worse. The
+                    // penalties are empty for a weighted sum so any worse move can be taken
+                    let keeps_penalties = new_score.penalties <= current_score.penalties;
+                    if keeps_penalties && rng.random_bool(temperature) {
                         best_action = Some(action);
                         best_score = new_score;
                     }
@@ -228,7 +802,7 @@ impl SchedulerData {
                 Some(action) => {
                     self.apply_action(action);
                     best_action = None;
-                    current_score = best_score;
+                    current_score = best_score.clone();
                 },
                 None => {
                     continue;
@@ -236,24 +810,77 @@ impl SchedulerData {
             }
         }
 
-        current_score
+        (current_score, max_iterations)
     }
 
     pub fn score(&mut self) -> f32 {
-        let conflicting_penalty = self.penalize_conflicting_popular_sessions();
-        let missing_popular_penalty = self.penalize_popular_sessions_missing();
-        let late_sessions_penalty = self.penalize_late_popular_sessions();
-        let same_tag_penalty = self.penalize_same_topic_time_slots();
-        let speaker_conflict_penalty = self.penalize_speaker_voting_conflicts();
+        self.score_breakdown().total
+    }
+
+    /// Scores the schedule the way the scheduler compares schedules, see `ScoringStrategy`
+    ///
+    /// # Returns
+    /// The `Objective` of the schedule, a schedule with a lower objective is better
+    pub fn objective(&self) -> Objective {
+        let breakdown = self.score_breakdown();
+        let penalties = match &self.options.scoring_strategy {
+            ScoringStrategy::WeightedSum => Vec::new(),
+            ScoringStrategy::Lexicographic(kinds) => kinds.iter().map(|kind| kind.of(&breakdown)).collect(),
+        };
+
+        Objective { penalties, total: breakdown.total }
+    }
 
-        self.weight_scores(conflicting_penalty, missing_popular_penalty, late_sessions_penalty, same_tag_penalty, speaker_conflict_penalty)
+    /// Scores the schedule and keeps each of the penalties that make up the score
+    ///
+    /// # Returns
+    /// A `ScoreBreakdown` with the unweighted penalties and the weighted total, the total is the
+    /// same value `score` returns
+    pub fn score_breakdown(&self) -> ScoreBreakdown {
+        let conflicting = self.penalize_conflicting_popular_sessions();
+        let missing = self.penalize_popular_sessions_missing();
+        let late = self.penalize_late_popular_sessions();
+        let same_tag = self.penalize_same_topic_time_slots();
+        let speaker_conflict = self.penalize_speaker_voting_conflicts();
+        let keynote_conflict = self.penalize_keynote_conflicts();
+        // Skipped when it carries no weight since scoring runs in the local search's inner loop
+        let popular_speaker_conflict = if self.options.counts(PenaltyKind::PopularSpeakerConflict, self.options.weights.popular_speaker_conflict) {
+            self.penalize_popular_speaker_conflicts()
+        } else {
+            0
+        };
+        let wasted_capacity = self.penalize_wasted_capacity();
+        let speaker_slot_fairness = if self.options.counts(PenaltyKind::SpeakerSlotFairness, self.options.weights.speaker_slot_fairness) {
+            self.penalize_speaker_slot_fairness()
+        } else {
+            0
+        };
+        let mutual_exclusion = self.penalize_mutual_exclusions();
+        let tag_time_preference = self.penalize_tag_time_preferences();
+
+        let mut breakdown = ScoreBreakdown {
+            conflicting,
+            missing,
+            late,
+            same_tag,
+            speaker_conflict,
+            keynote_conflict,
+            popular_speaker_conflict,
+            wasted_capacity,
+            speaker_slot_fairness,
+            mutual_exclusion,
+            tag_time_preference,
+            total: 0.0,
+        };
+        breakdown.total = self.weight_scores(&breakdown);
+        breakdown
     }
 
     fn penalize_conflicting_popular_sessions(&self) -> i32 {
         // Iterate through the rows of timeslots
         // For each timeslot row calculate their penalty
-        // Within each row only keep values that have session_ids and num_votes greater than 0
-        // Sort the row in descending order
+        // Within each row only keep the assigned sessions, sessions without votes sort to the end
+        // Sort the row in descending order, breaking ties by session_id so the order is deterministic
         // With a sliding window of 2 calculate the sum of adjacent pair products
         //      e.g. [a,b,c,d] (a * b) + (b * c) + (c * d)
         // Then sum up all the row sums to get our total penalty for all rows
@@ -262,10 +889,10 @@ impl SchedulerData {
             .map(|timeslot| {
                 let mut assigned_sessions: Vec<&RoomTimeAssignment> = timeslot.schedule_items
                     .iter()
-                    .filter(|session_assignment| session_assignment.session_id.is_some() && session_assignment.num_votes > 0)
+                    .filter(|session_assignment| session_assignment.is_assigned())
                     .collect();
 
-                assigned_sessions.sort_by(|a, b| b.num_votes.cmp(&a.num_votes));
+                assigned_sessions.sort_by_key(|session| (std::cmp::Reverse(session.num_votes), session.session_id));
                 assigned_sessions
                     .windows(2)
                     .map(|pair| pair[0].num_votes * pair[1].num_votes)
@@ -275,15 +902,20 @@ impl SchedulerData {
     }
 
     fn penalize_popular_sessions_missing(&self) -> i32 {
+        let tolerance = self.options.empty_slot_tolerance;
+
+        // Once a tolerance is set, empty slots that could still be filled count as a scheduled
+        // session with 0 votes. Without one only the scheduled sessions are compared against
         let scheduled_votes: Vec<i32> = self.schedule_rows
             .iter()
             .flat_map(|row| &row.schedule_items)
-            .filter(|session| session.session_id.is_some())
-            .map(|session| session.num_votes)
+            .filter(|session| session.is_assigned() || (tolerance > 0 && !session.already_assigned))
+            .map(|session| if session.is_assigned() { session.num_votes } else { 0 })
             .collect();
 
         let unassigned_votes: Vec<i32> = self.unassigned_sessions
             .iter()
+            .filter(|session| session.session_id.is_some())
             .map(|session| session.num_votes)
             .collect();
 
@@ -291,8 +923,8 @@ impl SchedulerData {
 
         for &scheduled_vote in &scheduled_votes {
             for &unassigned_vote in &unassigned_votes {
-                if unassigned_vote > scheduled_vote {
-                    penalty += (unassigned_vote - scheduled_vote) * 15;
+                if unassigned_vote - scheduled_vote > tolerance {
+                    penalty += (unassigned_vote - scheduled_vote - tolerance) * 15;
                 }
             }
         }
@@ -303,8 +935,8 @@ impl SchedulerData {
     fn penalize_late_popular_sessions(&self) -> i32 {
         // Iterate through the rows of timeslots
         // For each timeslot row calculate their penalty
-        // Within each row only keep values that have session_ids and num_votes greater than 0
-        // Sort the row in descending order
+        // Within each row only keep the assigned sessions, sessions without votes sort to the end
+        // Sort the row in descending order, breaking ties by session_id so the order is deterministic
         // With a sliding window of 2 calculate the sum of adjacent pair products
         //      e.g. [a,b,c,d] (a * b) + (b * c) + (c * d)
         // Then multiply the row sum by the row index to apply more of a penalty the later it is
@@ -315,10 +947,10 @@ impl SchedulerData {
             .map(|(row_idx, timeslot)| {
                 let mut assigned_sessions: Vec<&RoomTimeAssignment> = timeslot.schedule_items
                     .iter()
-                    .filter(|session_assignment| session_assignment.session_id.is_some() && session_assignment.num_votes > 0)
+                    .filter(|session_assignment| session_assignment.is_assigned())
                     .collect();
 
-                assigned_sessions.sort_by(|a, b| b.num_votes.cmp(&a.num_votes));
+                assigned_sessions.sort_by_key(|session| (std::cmp::Reverse(session.num_votes), session.session_id));
                 let assigned_sessions_sum: i32 = assigned_sessions
                     .windows(2)
                     .map(|pair| pair[0].num_votes * pair[1].num_votes)
@@ -345,7 +977,7 @@ impl SchedulerData {
             tag_counts.clear();
 
             for session in &timeslot.schedule_items {
-                if let (Some(_), Some(tag_id)) = (session.session_id, session.tag_id) {
+                if session.is_assigned() && let Some(tag_id) = session.tag_id {
                     let entry = tag_counts.entry(tag_id).or_insert((0, 0));
                     entry.0 += session.num_votes;
                     entry.1 += 1;
@@ -370,7 +1002,7 @@ impl SchedulerData {
                 let assigned_sessions: Vec<&RoomTimeAssignment> = timeslot.schedule_items
                     .iter()
                     .filter(|session_assignment| {
-                        session_assignment.session_id.is_some()
+                        session_assignment.is_assigned()
                             && session_assignment.speaker_id.is_some()
                     })
                     .collect();
@@ -382,15 +1014,13 @@ impl SchedulerData {
 
                     // Check if this speaker voted for any other session in the same time slot
                     for other_session in &assigned_sessions {
-                        if session.session_id != other_session.session_id {
-                            if let Some(other_session_id) = other_session.session_id {
-                                if speaker_votes.contains(&other_session_id) {
-                                    // Speaker is presenting while a session they voted for is also scheduled
-                                    // Apply penalty based on both sessions' popularity
-                                    // Note: Self-votes are naturally excluded since session.session_id != other_session.session_id
-                                    penalty += session.num_votes.max(1) * other_session.num_votes.max(1);
-                                }
-                            }
+                        if session.session_id != other_session.session_id
+                            && let Some(other_session_id) = other_session.session_id
+                            && speaker_votes.contains(&other_session_id) {
+                            // Speaker is presenting while a session they voted for is also scheduled
+                            // Apply penalty based on both sessions' popularity
+                            // Note: Self-votes are naturally excluded since session.session_id != other_session.session_id
+                            penalty += session.num_votes.max(1) * other_session.num_votes.max(1);
                         }
                     }
                 }
@@ -400,18 +1030,235 @@ impl SchedulerData {
             .sum()
     }
 
-    fn weight_scores(&self, penalty_conflicting: i32, penalty_missing: i32, penalty_late: i32, penalty_same_tag: i32, penalty_speaker_conflict: i32) -> f32 {
-        let weight_conflicting = 0.5;
-        let weight_missing = 0.75;
-        let weight_late = 0.1;
-        let weight_same_tag = 0.3;
-        let weight_speaker_conflict = 0.1;
+    fn penalize_keynote_conflicts(&self) -> i32 {
+        // Every session sharing a timeslot with a keynote is penalized once per keynote, regardless
+        // of its votes, so keynotes get a timeslot to themselves whenever the schedule allows it
+        // Each room in the timeslot bigger than the keynote's is penalized once too, so the keynote
+        // gets the biggest room
+        let scheduled: i32 = self.schedule_rows
+            .iter()
+            .map(|timeslot| {
+                let assigned = timeslot.schedule_items
+                    .iter()
+                    .filter(|item| item.is_assigned())
+                    .count() as i32;
+
+                timeslot.schedule_items
+                    .iter()
+                    .filter(|item| item.is_assigned() && item.is_keynote)
+                    .map(|keynote| {
+                        let bigger_rooms = timeslot.schedule_items
+                            .iter()
+                            .filter(|item| item.room_capacity > keynote.room_capacity)
+                            .count() as i32;
+                        (assigned - 1) + bigger_rooms
+                    })
+                    .sum::<i32>()
+            })
+            .sum();
+
+        // A keynote left off the schedule counts for more than the worst a scheduled keynote can
+        // get, sharing a full timeslot from its smallest room, so leaving it out never pays
+        let full_timeslot = self.schedule_rows
+            .iter()
+            .map(|timeslot| timeslot.schedule_items.len() as i32)
+            .max()
+            .unwrap_or(0);
+        let unscheduled = self.unassigned_sessions
+            .iter()
+            .filter(|session| session.session_id.is_some() && session.is_keynote)
+            .count() as i32;
+
+        scheduled + unscheduled * 2 * full_timeslot
+    }
+
+    fn penalize_mutual_exclusions(&self) -> i32 {
+        // Every excluded pair sharing a timeslot is penalized once, regardless of its votes, so the
+        // pair is split up whenever the schedule allows it
+        if self.options.mutual_exclusions.is_empty() {
+            return 0;
+        }
+
+        self.schedule_rows
+            .iter()
+            .map(|timeslot| {
+                let assigned: Vec<i32> = timeslot.schedule_items
+                    .iter()
+                    .filter(|item| item.is_assigned())
+                    .filter_map(|item| item.session_id)
+                    .collect();
+
+                self.options.mutual_exclusions
+                    .iter()
+                    .filter(|(session, other_session)| session != other_session && assigned.contains(session) && assigned.contains(other_session))
+                    .count() as i32
+            })
+            .sum()
+    }
+
+    fn penalize_tag_time_preferences(&self) -> i32 {
+        // Every session whose tag prefers a part of the day is penalized by how many timeslots it
+        // is away from the first (early) or last (late) timeslot, regardless of its votes, so
+        // sessions sharing a preference are pulled together rather than spread out
+        if self.options.tag_time_preference.is_empty() {
+            return 0;
+        }
+
+        let last_row = self.schedule_rows.len().saturating_sub(1) as i32;
+        self.schedule_rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row_idx, row)| row.schedule_items.iter().map(move |item| (row_idx as i32, item)))
+            .filter(|(_, item)| item.is_assigned())
+            .filter_map(|(row_idx, item)| {
+                match item.tag_id.and_then(|tag_id| self.options.tag_time_preference.get(&tag_id))? {
+                    SlotPreference::Early => Some(row_idx),
+                    SlotPreference::Late => Some(last_row - row_idx),
+                    SlotPreference::Any => None,
+                }
+            })
+            .sum()
+    }
+
+    fn penalize_popular_speaker_conflicts(&self) -> i32 {
+        // A speaker's popularity is the total votes across all of their sessions, scheduled or not
+        let mut speaker_totals: HashMap<i32, i32> = HashMap::new();
+        let scheduled = self.schedule_rows
+            .iter()
+            .flat_map(|row| &row.schedule_items)
+            .filter(|item| item.is_assigned())
+            .map(|item| (item.speaker_id, item.num_votes));
+        let unscheduled = self.unassigned_sessions
+            .iter()
+            .filter(|session| session.session_id.is_some())
+            .map(|session| (session.speaker_id, session.num_votes));
+        for (speaker_id, num_votes) in scheduled.chain(unscheduled) {
+            if let Some(speaker_id) = speaker_id {
+                *speaker_totals.entry(speaker_id).or_default() += num_votes;
+            }
+        }
+
+        // Within each timeslot take each speaker once, sort them by popularity in descending order
+        // and sum the products of adjacent pairs, like the conflicting popular sessions penalty
+        self.schedule_rows
+            .iter()
+            .map(|timeslot| {
+                let mut speakers: Vec<i32> = timeslot.schedule_items
+                    .iter()
+                    .filter(|item| item.is_assigned())
+                    .filter_map(|item| item.speaker_id)
+                    .collect();
+                speakers.sort_unstable();
+                speakers.dedup();
+
+                let mut totals: Vec<i32> = speakers
+                    .iter()
+                    .map(|speaker_id| speaker_totals.get(speaker_id).copied().unwrap_or(0))
+                    .collect();
+                totals.sort_unstable_by(|a, b| b.cmp(a));
+                totals
+                    .windows(2)
+                    .map(|pair| pair[0] * pair[1])
+                    .sum::<i32>()
+            })
+            .sum()
+    }
+
+    fn penalize_wasted_capacity(&self) -> i32 {
+        // Empty slots that are already assigned can't be filled, so they don't count
+        self.schedule_rows
+            .iter()
+            .flat_map(|row| &row.schedule_items)
+            .filter(|item| !item.already_assigned && !item.is_assigned())
+            .map(|item| item.room_capacity)
+            .sum()
+    }
+
+    fn penalize_speaker_slot_fairness(&self) -> i32 {
+        // A slot's desirability is how early it is (the last timeslot counts 1, the one before it 2
+        // and so on) times the size rank of its room within the timeslot (the smallest room counts
+        // 1), so the first timeslot in the biggest room is the most desirable
+        // Each speaker keeps their most desirable slot for free, the desirability of every other
+        // slot they hold is the penalty
+        let num_rows = self.schedule_rows.len() as i32;
+        let mut speaker_slots: HashMap<i32, (i32, i32)> = HashMap::new();
+        for (row_idx, row) in self.schedule_rows.iter().enumerate() {
+            for item in row.schedule_items.iter().filter(|item| item.is_assigned()) {
+                let Some(speaker_id) = item.speaker_id else {
+                    continue;
+                };
+                let size_rank = 1 + row.schedule_items
+                    .iter()
+                    .filter(|other| other.room_capacity < item.room_capacity)
+                    .count() as i32;
+                let desirability = (num_rows - row_idx as i32) * size_rank;
+
+                let (total, best) = speaker_slots.entry(speaker_id).or_default();
+                *total += desirability;
+                *best = (*best).max(desirability);
+            }
+        }
+
+        speaker_slots
+            .values()
+            .map(|(total, best)| total - best)
+            .sum()
+    }
+
+    fn weight_scores(&self, breakdown: &ScoreBreakdown) -> f32 {
+        let weights = &self.options.weights;
+        let scale = |penalty: fn(&ScoreBreakdown) -> i32| match &self.options.normalization {
+            Normalization::Raw => penalty(breakdown) as f32,
+            Normalization::MinMax(ranges) => ranges.scale(penalty, breakdown),
+        };
+
+        weights.conflicting * scale(|b| b.conflicting) +
+            weights.missing * scale(|b| b.missing) +
+            weights.late * scale(|b| b.late) +
+            weights.same_tag * scale(|b| b.same_tag) +
+            weights.speaker_conflict * scale(|b| b.speaker_conflict) +
+            weights.keynote_conflict * scale(|b| b.keynote_conflict) +
+            weights.popular_speaker_conflict * scale(|b| b.popular_speaker_conflict) +
+            weights.wasted_capacity * scale(|b| b.wasted_capacity) +
+            weights.speaker_slot_fairness * scale(|b| b.speaker_slot_fairness) +
+            weights.mutual_exclusion * scale(|b| b.mutual_exclusion) +
+            weights.tag_time_preference * scale(|b| b.tag_time_preference)
+    }
+
+    /// Finds how far each penalty ranges across random fills of the empty slots
+    ///
+    /// Meant to be called on the schedule before it is filled, the result is what
+    /// `Normalization::MinMax` scales the penalties by.
+    ///
+    /// # Parameters
+    /// - `rng`: The random number generator the slots are filled with
+    /// - `samples`: How many random fills to score
+    ///
+    /// # Returns
+    /// The lowest and highest value each penalty took across the samples, all 0 without samples
+    pub fn sample_penalty_ranges<R: Rng + ?Sized>(&self, rng: &mut R, samples: usize) -> PenaltyRanges {
+        let mut ranges: Option<PenaltyRanges> = None;
+        for _ in 0..samples {
+            let mut sample = self.clone();
+            sample.randomly_fill_available_spots_with(rng);
+            let breakdown = sample.score_breakdown();
+            match &mut ranges {
+                Some(ranges) => ranges.widen(&breakdown),
+                None => ranges = Some(PenaltyRanges { min: breakdown.clone(), max: breakdown }),
+            }
+        }
+
+        ranges.unwrap_or_default()
+    }
 
-        weight_conflicting * penalty_conflicting as f32 +
-            weight_missing * penalty_missing as f32 +
-            weight_late * penalty_late as f32 +
-            weight_same_tag * penalty_same_tag as f32 +
-            weight_speaker_conflict * penalty_speaker_conflict as f32
+    /// Scales the penalties by how far they range across random fills of the empty slots, see
+    /// `sample_penalty_ranges`
+    ///
+    /// # Parameters
+    /// - `samples`: How many random fills to score
+    pub fn normalize_min_max(&mut self, samples: usize) {
+        let ranges = self.sample_penalty_ranges(&mut rand::rng(), samples);
+        self.options.normalization = Normalization::MinMax(ranges);
     }
 
     fn apply_action(&mut self, action: &SwapAction) {
@@ -429,6 +1276,24 @@ impl SchedulerData {
         self.apply_action(action);
     }
 
+    /// Checks whether an action only moves sessions into timeslots they are allowed in
+    ///
+    /// Sessions can always be moved off the schedule, so only the sessions moved onto it are
+    /// checked.
+    fn is_allowed(&self, action: &SwapAction) -> bool {
+        match *action {
+            SwapAction::FromSchedule((row1, col1), (row2, col2)) => {
+                let item1 = &self.schedule_rows[row1].schedule_items[col1];
+                let item2 = &self.schedule_rows[row2].schedule_items[col2];
+                item1.allows_slot(item2.time_slot_id) && item2.allows_slot(item1.time_slot_id)
+            }
+            SwapAction::FromUnassigned((row, col), unassigned_idx) => {
+                let item = &self.schedule_rows[row].schedule_items[col];
+                self.unassigned_sessions[unassigned_idx].allows_slot(item.time_slot_id)
+            }
+        }
+    }
+
     pub fn get_swappable_sessions(&self) -> Vec<(usize, usize)> {
         self.schedule_rows
             .iter()
@@ -456,31 +1321,39 @@ impl SchedulerData {
         assert!(self.is_swappable(pos1) && self.is_swappable(pos2));
 
         // Get copies of the current values so we can perform the swap
-        // Cannot do just mem::swap on the whole item since we only want to change the session_id, num_votes, tag_id, speaker_id, and speaker_votes fields
+        // Cannot do just mem::swap on the whole item since we only want to change the session_id, num_votes, tag_id, speaker_id, speaker_votes, is_keynote, and allowed_slots fields
         // Cannot do mem::swap on just these fields either since we'd be holding multiple mutable references
         let session1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].session_id;
         let votes1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].num_votes;
         let tag1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].tag_id;
         let speaker1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].speaker_id;
         let speaker_votes1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].speaker_votes.clone();
+        let keynote1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].is_keynote;
+        let allowed_slots1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].allowed_slots.clone();
 
         let session2 = self.schedule_rows[pos2_row].schedule_items[pos2_col].session_id;
         let votes2 = self.schedule_rows[pos2_row].schedule_items[pos2_col].num_votes;
         let tag2 = self.schedule_rows[pos2_row].schedule_items[pos2_col].tag_id;
         let speaker2 = self.schedule_rows[pos2_row].schedule_items[pos2_col].speaker_id;
         let speaker_votes2 = self.schedule_rows[pos2_row].schedule_items[pos2_col].speaker_votes.clone();
+        let keynote2 = self.schedule_rows[pos2_row].schedule_items[pos2_col].is_keynote;
+        let allowed_slots2 = self.schedule_rows[pos2_row].schedule_items[pos2_col].allowed_slots.clone();
 
         self.schedule_rows[pos1_row].schedule_items[pos1_col].session_id = session2;
         self.schedule_rows[pos1_row].schedule_items[pos1_col].num_votes = votes2;
         self.schedule_rows[pos1_row].schedule_items[pos1_col].tag_id = tag2;
         self.schedule_rows[pos1_row].schedule_items[pos1_col].speaker_id = speaker2;
         self.schedule_rows[pos1_row].schedule_items[pos1_col].speaker_votes = speaker_votes2;
+        self.schedule_rows[pos1_row].schedule_items[pos1_col].is_keynote = keynote2;
+        self.schedule_rows[pos1_row].schedule_items[pos1_col].allowed_slots = allowed_slots2;
 
         self.schedule_rows[pos2_row].schedule_items[pos2_col].session_id = session1;
         self.schedule_rows[pos2_row].schedule_items[pos2_col].num_votes = votes1;
         self.schedule_rows[pos2_row].schedule_items[pos2_col].tag_id = tag1;
         self.schedule_rows[pos2_row].schedule_items[pos2_col].speaker_id = speaker1;
         self.schedule_rows[pos2_row].schedule_items[pos2_col].speaker_votes = speaker_votes1;
+        self.schedule_rows[pos2_row].schedule_items[pos2_col].is_keynote = keynote1;
+        self.schedule_rows[pos2_row].schedule_items[pos2_col].allowed_slots = allowed_slots1;
     }
 
     fn is_swappable(&self, pos1: (usize, usize)) -> bool {
@@ -502,24 +1375,52 @@ impl SchedulerData {
         let tag1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].tag_id;
         let speaker1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].speaker_id;
         let speaker_votes1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].speaker_votes.clone();
+        let keynote1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].is_keynote;
+        let allowed_slots1 = self.schedule_rows[pos1_row].schedule_items[pos1_col].allowed_slots.clone();
 
         let session2 = self.unassigned_sessions[unassigned_idx].session_id;
         let votes2 = self.unassigned_sessions[unassigned_idx].num_votes;
         let tag2 = self.unassigned_sessions[unassigned_idx].tag_id;
         let speaker2 = self.unassigned_sessions[unassigned_idx].speaker_id;
         let speaker_votes2 = self.unassigned_sessions[unassigned_idx].speaker_votes.clone();
+        let keynote2 = self.unassigned_sessions[unassigned_idx].is_keynote;
+        let allowed_slots2 = self.unassigned_sessions[unassigned_idx].allowed_slots.clone();
 
         self.schedule_rows[pos1_row].schedule_items[pos1_col].session_id = session2;
         self.schedule_rows[pos1_row].schedule_items[pos1_col].num_votes = votes2;
         self.schedule_rows[pos1_row].schedule_items[pos1_col].tag_id = tag2;
         self.schedule_rows[pos1_row].schedule_items[pos1_col].speaker_id = speaker2;
         self.schedule_rows[pos1_row].schedule_items[pos1_col].speaker_votes = speaker_votes2;
+        self.schedule_rows[pos1_row].schedule_items[pos1_col].is_keynote = keynote2;
+        self.schedule_rows[pos1_row].schedule_items[pos1_col].allowed_slots = allowed_slots2;
 
         self.unassigned_sessions[unassigned_idx].session_id = session1;
         self.unassigned_sessions[unassigned_idx].num_votes = votes1;
         self.unassigned_sessions[unassigned_idx].tag_id = tag1;
         self.unassigned_sessions[unassigned_idx].speaker_id = speaker1;
         self.unassigned_sessions[unassigned_idx].speaker_votes = speaker_votes1;
+        self.unassigned_sessions[unassigned_idx].is_keynote = keynote1;
+        self.unassigned_sessions[unassigned_idx].allowed_slots = allowed_slots1;
+    }
+
+    /// Counts how many times speakers change rooms between their consecutive sessions
+    ///
+    /// # Returns
+    /// The number of room changes summed across every speaker
+    pub fn speaker_room_switches(&self) -> usize {
+        let mut speaker_rooms: HashMap<i32, Vec<i32>> = HashMap::new();
+        for row in &self.schedule_rows {
+            for item in row.schedule_items.iter().filter(|item| item.is_assigned()) {
+                if let Some(speaker_id) = item.speaker_id {
+                    speaker_rooms.entry(speaker_id).or_default().push(item.room_id);
+                }
+            }
+        }
+
+        speaker_rooms
+            .values()
+            .map(|rooms| rooms.windows(2).filter(|pair| pair[0] != pair[1]).count())
+            .sum()
     }
 
     /// Runs the scheduler with multiple restarts to find the best solution
@@ -531,10 +1432,28 @@ impl SchedulerData {
     /// # Returns
     /// The best score found across all restarts
     pub fn improve_with_restarts(&mut self, restarts: usize, stop_flag: Arc<AtomicBool>) -> f32 {
-        let unmodified_data = self.clone();
-        let mut best_score = f32::MAX;
+        self.improve_with_restarts_stats(restarts, stop_flag).final_score
+    }
+
+    /// Runs the scheduler with multiple restarts and reports what happened during the run
+    ///
+    /// # Parameters
+    /// - `restarts`: Number of times to restart the improvement process
+    /// - `stop_flag`: Signals the function to stop early and return the best results so far
+    ///
+    /// # Returns
+    /// The `RunStats` of the run, the final score being the best score found across all restarts
+    pub fn improve_with_restarts_stats(&mut self, restarts: usize, stop_flag: Arc<AtomicBool>) -> RunStats {
+        let mut unmodified_data = self.clone();
         let mut best_data = self.clone();
+        let mut stats = RunStats {
+            starting_score: unmodified_data.score(),
+            final_score: f32::MAX,
+            ..RunStats::default()
+        };
+        let mut best_objective: Option<Objective> = None;
 
+        let mut rng = rand::rng();
         for i in 0..restarts {
             if stop_flag.load(Ordering::Relaxed) {
                 tracing::info!("Cancelled after {} restarts", i + 1);
@@ -543,23 +1462,124 @@ impl SchedulerData {
 
             tracing::debug!("On iteration {} out of {}", i + 1, restarts);
             *self = unmodified_data.clone();
-            let new_score = self.improve(stop_flag.clone());
-            if new_score < best_score {
-                best_score = new_score;
+            self.randomly_fill_available_spots_with(&mut rng);
+            let (new_score, iterations) = self.local_search(&mut rng, stop_flag.clone());
+            stats.restarts += 1;
+            stats.iterations += iterations;
+            let is_better = best_objective.as_ref().is_none_or(|best| new_score < *best);
+            let breaks_tie = best_objective.as_ref() == Some(&new_score) && self.options.tie_breaker.prefers(self, &best_data);
+            if is_better || breaks_tie {
+                stats.final_score = new_score.total;
+                best_objective = Some(new_score);
                 best_data = self.clone();
             }
+
+            if let Some(ProgressCallback(callback)) = &self.options.progress_callback {
+                callback(Progress {
+                    restart: stats.restarts,
+                    restarts,
+                    best_score: stats.final_score,
+                });
+            }
         }
 
         *self = best_data;
-        best_score
+        stats
     }
-}
-
-pub mod utils {
-    use super::*;
 
-    pub fn make_test_data(num_of_rooms: i32, num_of_time_slots: i32) -> SchedulerData {
-        let mut schedule_rows = Vec::new();
+    /// Runs the scheduler so as many votes as possible end up on the schedule
+    ///
+    /// The empty slots are first filled with the most popular sessions, ignoring the penalties
+    /// between them. The local search restarts that follow only move the scheduled sessions
+    /// between slots, a session that made it onto the schedule is never swapped out for one that
+    /// didn't.
+    ///
+    /// # Parameters
+    /// - `restarts`: Number of times to restart the improvement process
+    /// - `stop_flag`: Signals the function to stop early and return the best results so far
+    ///
+    /// # Returns
+    /// The `RunStats` of the run, the final score being the score of the returned schedule
+    pub fn improve_maximizing_coverage_stats(&mut self, restarts: usize, stop_flag: Arc<AtomicBool>) -> RunStats {
+        let starting_score = self.score();
+        self.fill_greedy_by_votes();
+
+        // Without any unassigned sessions the local search can only rearrange the schedule
+        let left_out = std::mem::take(&mut self.unassigned_sessions);
+        let mut stats = self.improve_with_restarts_stats(restarts, stop_flag);
+        self.unassigned_sessions = left_out;
+
+        stats.starting_score = starting_score;
+        stats.final_score = self.score();
+        stats
+    }
+}
+
+impl SchedulerOptions {
+    /// Checks whether a penalty that can be skipped has to be computed
+    ///
+    /// # Parameters
+    /// - `kind`: The penalty
+    /// - `weight`: The weight of the penalty
+    ///
+    /// # Returns
+    /// `true` if the penalty carries weight or the scoring strategy compares it
+    fn counts(&self, kind: PenaltyKind, weight: f32) -> bool {
+        weight != 0.0 || matches!(&self.scoring_strategy, ScoringStrategy::Lexicographic(kinds) if kinds.contains(&kind))
+    }
+
+    /// Checks whether a session has enough votes to be placed into an empty slot
+    ///
+    /// # Parameters
+    /// - `num_votes`: The number of votes the session has
+    ///
+    /// # Returns
+    /// `true` if the session should be used to fill an empty slot
+    pub fn is_worth_a_slot(&self, num_votes: i32) -> bool {
+        self.empty_slot_tolerance == 0 || num_votes > self.empty_slot_tolerance
+    }
+
+    /// How many iterations a local search runs for
+    ///
+    /// # Parameters
+    /// - `capacity`: The number of slots in the schedule
+    ///
+    /// # Returns
+    /// `max_iterations` if it is set, otherwise `3 * capacity^2`
+    pub fn iterations_for(&self, capacity: i32) -> usize {
+        self.max_iterations.unwrap_or_else(|| {
+            let capacity = usize::try_from(capacity).unwrap_or(0);
+            3 * capacity * capacity
+        })
+    }
+
+    /// The chance of a local search iteration trying a random move, kept between 0 and 1
+    ///
+    /// # Returns
+    /// `random_move_prob` clamped to 0 to 1, or `DEFAULT_RANDOM_MOVE_PROB` if it isn't a number
+    pub fn random_move_probability(&self) -> f64 {
+        if self.random_move_prob.is_nan() {
+            DEFAULT_RANDOM_MOVE_PROB
+        } else {
+            self.random_move_prob.clamp(0.0, 1.0)
+        }
+    }
+}
+
+pub mod utils {
+    use super::*;
+
+    pub fn make_test_data(num_of_rooms: i32, num_of_time_slots: i32) -> SchedulerData {
+        // Let there be 1/3 more sessions than spots on the schedule
+        let num_of_sessions: i32 = (((num_of_rooms * num_of_time_slots) as f32 * (4.0 / 3.0)) as i32) + 1;
+
+        make_test_data_with_sessions(num_of_rooms, num_of_time_slots, num_of_sessions)
+    }
+
+    /// Creates an empty schedule with the given number of unassigned sessions, which can be fewer
+    /// than the number of spots on the schedule
+    pub fn make_test_data_with_sessions(num_of_rooms: i32, num_of_time_slots: i32, num_of_sessions: i32) -> SchedulerData {
+        let mut schedule_rows = Vec::new();
 
         for time_slot in 1..=num_of_time_slots {
             let mut schedule_items = Vec::new();
@@ -574,14 +1594,14 @@ pub mod utils {
                     tag_id: Some(room),
                     speaker_id: None,
                     speaker_votes: Vec::new(),
+                    is_keynote: false,
+                    allowed_slots: None,
+                    room_capacity: 10 * room,
                 });
             }
             schedule_rows.push(ScheduleRow { schedule_items });
         }
 
-        // Let there be 1/3 more sessions than spots on the schedule
-        let num_of_sessions: i32 = (((num_of_rooms * num_of_time_slots) as f32 * (4.0 / 3.0)) as i32) + 1;
-
         let mut unassigned_sessions = Vec::new();
         for i in 0..num_of_sessions {
             unassigned_sessions.push(SessionData {
@@ -590,6 +1610,8 @@ pub mod utils {
                 tag_id: Some((i % 6) + 1),
                 speaker_id: Some((i % 10) + 1),
                 speaker_votes: if i > 5 { vec![i - 1, i - 2] } else { vec![] },
+                is_keynote: false,
+                allowed_slots: None,
             });
         }
 
@@ -597,6 +1619,7 @@ pub mod utils {
             schedule_rows,
             capacity: num_of_rooms * num_of_time_slots,
             unassigned_sessions,
+            options: SchedulerOptions::default(),
         }
     }
 
@@ -679,6 +1702,172 @@ pub(crate) mod tests {
             assert_eq!(data.unassigned_sessions.len(), 0);
         }
 
+        #[test]
+        fn test_more_rooms_than_sessions_leaves_slots_empty() {
+            let mut data = make_test_data_with_sessions(4, 3, 5);
+
+            let stats = data.improve_with_restarts_stats(3, Arc::new(AtomicBool::new(false)));
+
+            let scheduled: Vec<i32> = data.schedule_rows
+                .iter()
+                .flat_map(|schedule_row| &schedule_row.schedule_items)
+                .filter_map(|item| item.session_id)
+                .collect();
+            let unique_scheduled: HashSet<i32> = scheduled.iter().copied().collect();
+            assert_eq!(scheduled.len(), 5);
+            assert_eq!(unique_scheduled.len(), 5);
+            assert!(data.unassigned_sessions.is_empty());
+            assert_eq!(stats.restarts, 3);
+            assert!(stats.final_score <= stats.starting_score);
+        }
+
+        #[test]
+        fn test_local_search_with_nothing_to_move() {
+            // No sessions at all, so every slot stays empty
+            let mut data = make_test_data_with_sessions(3, 2, 0);
+            let stats = data.improve_with_restarts_stats(2, Arc::new(AtomicBool::new(false)));
+            assert_eq!(stats.iterations, 0);
+            assert!(data.schedule_rows.iter().flat_map(|row| &row.schedule_items).all(|item| item.session_id.is_none()));
+
+            // Every slot is already assigned, so there is nowhere to swap a session to
+            let mut data = make_test_data_with_sessions(1, 1, 2);
+            data.schedule_rows[0].schedule_items[0].already_assigned = true;
+            data.schedule_rows[0].schedule_items[0].session_id = Some(999);
+            let stats = data.improve_with_restarts_stats(2, Arc::new(AtomicBool::new(false)));
+            assert_eq!(stats.iterations, 0);
+            assert_eq!(data.schedule_rows[0].schedule_items[0].session_id, Some(999));
+            assert_eq!(data.unassigned_sessions.len(), 2);
+        }
+
+        #[test]
+        fn test_random_move_probability() {
+            let options = |random_move_prob| SchedulerOptions { random_move_prob, ..SchedulerOptions::default() };
+
+            assert_relative_eq!(SchedulerOptions::default().random_move_probability(), DEFAULT_RANDOM_MOVE_PROB);
+            assert_relative_eq!(options(0.2).random_move_probability(), 0.2);
+            assert_relative_eq!(options(-1.0).random_move_probability(), 0.0);
+            assert_relative_eq!(options(3.0).random_move_probability(), 1.0);
+            assert_relative_eq!(options(f64::NAN).random_move_probability(), DEFAULT_RANDOM_MOVE_PROB);
+        }
+
+        #[test]
+        fn test_random_moves_with_seeded_rng() {
+            use rand::{rngs::StdRng, SeedableRng};
+
+            let run = |random_move_prob| {
+                let mut data = make_test_data(3, 4);
+                data.options.random_move_prob = random_move_prob;
+                let mut rng = StdRng::seed_from_u64(7);
+                data.randomly_fill_available_spots_with(&mut rng);
+                let starting_score = data.score();
+                let (objective, iterations) = data.local_search(&mut rng, Arc::new(AtomicBool::new(false)));
+                let score = objective.total;
+                let schedule: Vec<Option<i32>> = data.schedule_rows
+                    .iter()
+                    .flat_map(|row| &row.schedule_items)
+                    .map(|item| item.session_id)
+                    .collect();
+                (starting_score, score, iterations, schedule)
+            };
+
+            // Only random moves, and a mix of random moves and full scans, both run every
+            // iteration and give the same schedule for the same seed
+            for random_move_prob in [1.0, 0.3] {
+                let (starting_score, score, iterations, schedule) = run(random_move_prob);
+                assert_eq!(iterations, 3 * 12 * 12);
+                assert!(score <= starting_score);
+                assert!(schedule.iter().all(Option::is_some));
+                assert_eq!(run(random_move_prob), (starting_score, score, iterations, schedule));
+            }
+        }
+
+        #[test]
+        fn test_max_iterations_caps_the_local_search() {
+            use rand::{rngs::StdRng, SeedableRng};
+
+            let run = |max_iterations| {
+                let mut data = make_test_data(3, 4);
+                data.options.max_iterations = max_iterations;
+                let mut rng = StdRng::seed_from_u64(7);
+                data.randomly_fill_available_spots_with(&mut rng);
+                data.local_search(&mut rng, Arc::new(AtomicBool::new(false))).1
+            };
+
+            assert_eq!(SchedulerOptions::default().iterations_for(12), 3 * 12 * 12);
+            assert_eq!(run(None), 3 * 12 * 12);
+            assert_eq!(run(Some(25)), 25);
+            assert_eq!(run(Some(0)), 0);
+        }
+
+        #[test]
+        fn test_min_max_normalization_stops_the_missing_penalty_dominating() {
+            use rand::{rngs::StdRng, SeedableRng};
+
+            // Two slots for three sessions, conflicts and missing sessions weighed the same
+            let mut data = make_test_data(2, 1);
+            data.unassigned_sessions = [(1, 10), (2, 9), (3, 1)]
+                .into_iter()
+                .map(|(id, num_votes)| SessionData { session_id: Some(id), num_votes, tag_id: Some(id), speaker_id: Some(id), speaker_votes: vec![], is_keynote: false, allowed_slots: None })
+                .collect();
+            data.options.weights = ScoringWeights { conflicting: 1.0, missing: 1.0, late: 0.0, same_tag: 0.0, speaker_conflict: 0.0, ..ScoringWeights::default() };
+            let scheduled = |data: &SchedulerData| -> HashSet<i32> {
+                data.schedule_rows
+                    .iter()
+                    .flat_map(|row| &row.schedule_items)
+                    .filter_map(|item| item.session_id)
+                    .collect()
+            };
+
+            let ranges = data.sample_penalty_ranges(&mut StdRng::seed_from_u64(7), 50);
+            assert_eq!((ranges.min.conflicting, ranges.max.conflicting), (9, 90));
+            assert_eq!((ranges.min.missing, ranges.max.missing), (0, 150));
+
+            // Raw, leaving the 9 vote session out costs 120 against the 90 of the conflict
+            let mut raw = data.clone();
+            raw.improve_with_restarts(10, Arc::new(AtomicBool::new(false)));
+            assert_eq!(scheduled(&raw), HashSet::from([1, 2]));
+
+            // Scaled, both penalties are close to their worst either way and the conflict is avoided
+            let mut normalized = data.clone();
+            normalized.options.normalization = Normalization::MinMax(ranges);
+            normalized.improve_with_restarts(10, Arc::new(AtomicBool::new(false)));
+            assert_eq!(scheduled(&normalized), HashSet::from([1, 3]));
+            assert!(normalized.score() < 1.0);
+        }
+
+        #[test]
+        fn test_maximize_coverage_schedules_more_votes_than_local_search() {
+            // Two slots for three sessions, with clashes between popular sessions weighed heavily
+            let make_data = || {
+                let mut data = make_test_data(2, 1);
+                data.unassigned_sessions = [(1, 10), (2, 9), (3, 1)]
+                    .into_iter()
+                    .map(|(id, num_votes)| SessionData { session_id: Some(id), num_votes, tag_id: Some(id), speaker_id: Some(id), speaker_votes: vec![], is_keynote: false, allowed_slots: None })
+                    .collect();
+                data.options.weights.conflicting = 10.0;
+                data
+            };
+            let scheduled_votes = |data: &SchedulerData| -> i32 {
+                data.schedule_rows
+                    .iter()
+                    .flat_map(|row| &row.schedule_items)
+                    .filter(|item| item.is_assigned())
+                    .map(|item| item.num_votes)
+                    .sum()
+            };
+
+            let mut plain = make_data();
+            plain.improve_with_restarts_stats(10, Arc::new(AtomicBool::new(false)));
+
+            let mut coverage = make_data();
+            let stats = coverage.improve_maximizing_coverage_stats(10, Arc::new(AtomicBool::new(false)));
+
+            assert_eq!(scheduled_votes(&coverage), 19);
+            assert!(scheduled_votes(&coverage) > scheduled_votes(&plain));
+            assert_relative_eq!(stats.final_score, coverage.score());
+            assert!(coverage.unassigned_sessions.iter().any(|session| session.session_id == Some(3)));
+        }
+
         #[test]
         fn test_swap_sessions() {
             let mut data = make_test_data(3, 5);
@@ -721,6 +1910,39 @@ pub(crate) mod tests {
             assert_eq!(data.unassigned_sessions[unassigned_idx].num_votes, votes1_before);
         }
 
+        #[test]
+        fn test_swap_with_unassigned_session_exchanges_speakers() {
+            let mut data = make_test_data(1, 1);
+            let scheduled = &mut data.schedule_rows[0].schedule_items[0];
+            scheduled.session_id = Some(1);
+            scheduled.num_votes = 10;
+            scheduled.tag_id = Some(100);
+            scheduled.speaker_id = Some(1000);
+            scheduled.speaker_votes = vec![10];
+            data.unassigned_sessions = vec![SessionData {
+                session_id: Some(2),
+                num_votes: 20,
+                tag_id: Some(200),
+                speaker_id: Some(2000),
+                speaker_votes: vec![20, 5],
+                is_keynote: false,
+                allowed_slots: None,
+            }];
+
+            data.swap_with_unassigned_session((0, 0), 0);
+
+            let scheduled = &data.schedule_rows[0].schedule_items[0];
+            assert_eq!(
+                (scheduled.session_id, scheduled.num_votes, scheduled.tag_id, scheduled.speaker_id, scheduled.speaker_votes.clone()),
+                (Some(2), 20, Some(200), Some(2000), vec![20, 5]),
+            );
+            let unassigned = &data.unassigned_sessions[0];
+            assert_eq!(
+                (unassigned.session_id, unassigned.num_votes, unassigned.tag_id, unassigned.speaker_id, unassigned.speaker_votes.clone()),
+                (Some(1), 10, Some(100), Some(1000), vec![10]),
+            );
+        }
+
         #[test]
         fn test_is_swappable() {
             let data = make_test_data_with_preassigned(3, 5);
@@ -757,15 +1979,516 @@ pub(crate) mod tests {
             assert_eq!(penalty, 198);
         }
 
+        #[test]
+        fn test_zero_vote_session_is_assigned() {
+            let mut data = make_test_data(2, 1);
+            data.randomly_fill_available_spots();
+            let row = &mut data.schedule_rows[0].schedule_items;
+            row[0].num_votes = 5;
+            row[0].tag_id = Some(1);
+            row[1].num_votes = 0;
+            row[1].tag_id = Some(1);
+
+            assert!(data.schedule_rows[0].schedule_items[1].is_assigned());
+            // The zero vote session shares the timeslot and tag with the popular one
+            assert_eq!(data.penalize_same_topic_time_slots(), 5);
+            assert_eq!(data.penalize_conflicting_popular_sessions(), 0);
+            assert!(data.to_string().starts_with("Row 1: [5, 0]"));
+
+            data.schedule_rows[0].schedule_items[1].session_id = None;
+            assert!(!data.schedule_rows[0].schedule_items[1].is_assigned());
+            assert!(data.to_string().starts_with("Row 1: [5, -]"));
+        }
+
+        #[test]
+        fn test_fill_greedy_by_votes() {
+            let mut data = make_test_data_with_preassigned(3, 5);
+            data.fill_greedy_by_votes();
+
+            // The preassigned slot is untouched and every other slot is filled
+            assert_eq!(data.schedule_rows[0].schedule_items[0].session_id, Some(999));
+            assert!(data.schedule_rows.iter().flat_map(|row| &row.schedule_items).all(|item| item.is_assigned()));
+            assert_eq!(data.unassigned_sessions.len(), 21 - 14);
+
+            // The most popular sessions are spread over the timeslots, earliest first
+            let top_votes = data.unassigned_sessions.iter().map(|session| session.num_votes).max().unwrap();
+            let first_round: Vec<i32> = data.schedule_rows.iter().map(|row| row.schedule_items[1].num_votes).collect();
+            assert!(first_round.windows(2).all(|pair| pair[0] >= pair[1]));
+            assert_eq!(first_round[0], 18);
+            assert!(data.schedule_rows.iter().flat_map(|row| &row.schedule_items).skip(1).all(|item| item.num_votes >= top_votes));
+        }
+
+        #[test]
+        fn test_improve_with_restarts_stats() {
+            // With a tolerance the empty slots count towards the missing penalty, so the filled
+            // schedule scores better than the empty one it started from
+            let mut data = make_test_data(2, 2);
+            data.options.empty_slot_tolerance = 1;
+            let stats = data.improve_with_restarts_stats(3, Arc::new(AtomicBool::new(false)));
+
+            // Each restart runs 3 * capacity^2 iterations when it isn't stopped early
+            assert_eq!(stats.restarts, 3);
+            assert_eq!(stats.iterations, 3 * 3 * 4 * 4);
+            assert!(stats.final_score <= stats.starting_score);
+            assert_relative_eq!(stats.final_score, data.score());
+
+            // A stopped run doesn't start any restarts
+            let mut data = make_test_data(2, 2);
+            let stats = data.improve_with_restarts_stats(3, Arc::new(AtomicBool::new(true)));
+            assert_eq!(stats.restarts, 0);
+            assert_eq!(stats.iterations, 0);
+        }
+
+        #[test]
+        fn test_progress_callback_is_called_after_each_restart() {
+            use std::sync::Mutex;
+
+            let reported = Arc::new(Mutex::new(Vec::new()));
+            let mut data = make_test_data(2, 2);
+            data.options.progress_callback = Some(ProgressCallback::new({
+                let reported = reported.clone();
+                move |progress| reported.lock().unwrap().push(progress)
+            }));
+
+            let stats = data.improve_with_restarts_stats(3, Arc::new(AtomicBool::new(false)));
+
+            let reported = reported.lock().unwrap();
+            let restarts: Vec<(usize, usize)> = reported.iter().map(|progress| (progress.restart, progress.restarts)).collect();
+            assert_eq!(restarts, vec![(1, 3), (2, 3), (3, 3)]);
+            // The best score can only get better as the restarts go on
+            assert!(reported.windows(2).all(|pair| pair[1].best_score <= pair[0].best_score));
+            assert_eq!(reported.last().unwrap().best_score, stats.final_score);
+        }
+
+        #[test]
+        fn test_tie_breaker_prefers_fewer_speaker_room_switches() {
+            // Speaker 1 gives sessions 1 and 2, the other sessions have their own speakers
+            let place = |layout: [[i32; 2]; 2]| {
+                let mut data = make_test_data(2, 2);
+                data.unassigned_sessions.clear();
+                for (row, sessions) in data.schedule_rows.iter_mut().zip(layout) {
+                    for (item, session_id) in row.schedule_items.iter_mut().zip(sessions) {
+                        item.session_id = Some(session_id);
+                        item.speaker_id = Some(if session_id <= 2 { 1 } else { session_id });
+                        item.tag_id = None;
+                    }
+                }
+                data
+            };
+            let mut same_room = place([[1, 3], [2, 4]]);
+            let mut switches_room = place([[1, 3], [4, 2]]);
+
+            assert_relative_eq!(same_room.score(), switches_room.score());
+            assert_eq!(same_room.speaker_room_switches(), 0);
+            assert_eq!(switches_room.speaker_room_switches(), 1);
+
+            let tie_breaker = TieBreaker::MinimizeSpeakerRoomSwitches;
+            assert!(tie_breaker.prefers(&same_room, &switches_room));
+            assert!(!tie_breaker.prefers(&switches_room, &same_room));
+
+            // By default the first schedule found is kept
+            assert!(!TieBreaker::default().prefers(&same_room, &switches_room));
+
+            let prefer_more_switches = TieBreaker::Custom(|candidate, best| {
+                best.speaker_room_switches().cmp(&candidate.speaker_room_switches())
+            });
+            assert!(prefer_more_switches.prefers(&switches_room, &same_room));
+        }
+
+        #[test]
+        fn test_keynote_gets_a_timeslot_to_itself() {
+            // 7 sessions fit in 3 rooms over 3 timeslots with the keynote alone in one of them
+            let mut data = make_test_data(3, 3);
+            data.unassigned_sessions.truncate(7);
+            for (i, session) in data.unassigned_sessions.iter_mut().enumerate() {
+                session.num_votes = i as i32 + 1;
+                session.is_keynote = i == 3;
+            }
+
+            data.improve_with_restarts(10, Arc::new(AtomicBool::new(false)));
+
+            assert_eq!(data.score_breakdown().keynote_conflict, 0);
+            let keynote_row = data.schedule_rows
+                .iter()
+                .find(|row| row.schedule_items.iter().any(|item| item.is_assigned() && item.is_keynote))
+                .expect("the keynote should be scheduled");
+            assert_eq!(keynote_row.schedule_items.iter().filter(|item| item.is_assigned()).count(), 1);
+        }
+
+        #[test]
+        fn test_sessions_stay_in_their_allowed_slots() {
+            // Timeslots 1 and 2 are the morning, the most popular sessions can only present then
+            let mut data = make_test_data(3, 4);
+            let morning = vec![1, 2];
+            for session in data.unassigned_sessions.iter_mut().rev().take(4) {
+                session.allowed_slots = Some(morning.clone());
+            }
+            let morning_only: Vec<i32> = data.unassigned_sessions
+                .iter()
+                .filter(|session| session.allowed_slots.is_some())
+                .filter_map(|session| session.session_id)
+                .collect();
+
+            let assert_in_the_morning = |data: &SchedulerData| {
+                for item in data.schedule_rows.iter().flat_map(|row| &row.schedule_items) {
+                    if let Some(session_id) = item.session_id && morning_only.contains(&session_id) {
+                        assert!(morning.contains(&item.time_slot_id), "session {session_id} is in timeslot {}", item.time_slot_id);
+                    }
+                }
+            };
+
+            for _ in 0..20 {
+                let mut random_start = data.clone();
+                random_start.improve(Arc::new(AtomicBool::new(false)));
+                assert_in_the_morning(&random_start);
+
+                let mut greedy_start = data.clone();
+                greedy_start.improve_greedy_start(Arc::new(AtomicBool::new(false)));
+                assert_in_the_morning(&greedy_start);
+            }
+
+            let mut restarts = data.clone();
+            restarts.improve_with_restarts(20, Arc::new(AtomicBool::new(false)));
+            assert_in_the_morning(&restarts);
+        }
+
+        #[test]
+        fn test_penalize_keynote_conflicts() {
+            let mut data = make_test_data(3, 2);
+            data.randomly_fill_available_spots();
+            assert_eq!(data.penalize_keynote_conflicts(), 0);
+
+            // A keynote against two sessions in the smallest room, and two keynotes against each
+            // other in the two smaller rooms
+            data.schedule_rows[0].schedule_items[0].is_keynote = true;
+            data.schedule_rows[1].schedule_items[0].is_keynote = true;
+            data.schedule_rows[1].schedule_items[1].is_keynote = true;
+            data.schedule_rows[1].schedule_items[2].session_id = None;
+            assert_eq!(data.penalize_keynote_conflicts(), (2 + 2) + (1 + 2) + (1 + 1));
+
+            // A keynote left unscheduled counts as twice a full timeslot
+            data.unassigned_sessions[0].is_keynote = true;
+            assert_eq!(data.penalize_keynote_conflicts(), (2 + 2) + (1 + 2) + (1 + 1) + 2 * 3);
+        }
+
+        #[test]
+        fn test_keynote_stays_scheduled_when_sessions_outnumber_slots() {
+            // 13 sessions compete for 9 slots, the keynote has the fewest votes of them all, so
+            // every timeslot is full and leaving the keynote out would be the only way to avoid
+            // its conflicts
+            let mut data = make_test_data(3, 3);
+            data.unassigned_sessions[0].is_keynote = true;
+            let keynote_id = data.unassigned_sessions[0].session_id;
+
+            data.improve_with_restarts(10, Arc::new(AtomicBool::new(false)));
+
+            let keynote = data.schedule_rows
+                .iter()
+                .flat_map(|row| &row.schedule_items)
+                .find(|item| item.session_id == keynote_id)
+                .expect("the keynote should be scheduled");
+            assert_eq!(keynote.room_capacity, 30);
+            assert_eq!(data.score_breakdown().keynote_conflict, 2);
+        }
+
+        #[test]
+        fn test_penalize_mutual_exclusions() {
+            let mut data = make_test_data(3, 2);
+            data.randomly_fill_available_spots();
+            let row: Vec<i32> = data.schedule_rows[0].schedule_items.iter().filter_map(|item| item.session_id).collect();
+            let other_row: Vec<i32> = data.schedule_rows[1].schedule_items.iter().filter_map(|item| item.session_id).collect();
+            assert_eq!(data.penalize_mutual_exclusions(), 0);
+
+            data.options.mutual_exclusions = vec![(row[0], row[1]), (row[2], row[1]), (row[0], other_row[0])];
+            assert_eq!(data.penalize_mutual_exclusions(), 2);
+        }
+
+        #[test]
+        fn test_penalize_tag_time_preferences() {
+            let mut data = make_test_data(2, 3);
+            data.randomly_fill_available_spots();
+            assert_eq!(data.penalize_tag_time_preferences(), 0);
+
+            // Every session in the first room has tag 1 and in the second room tag 2
+            for (row_idx, row) in data.schedule_rows.iter_mut().enumerate() {
+                for item in &mut row.schedule_items {
+                    item.tag_id = Some(item.room_id);
+                }
+                if row_idx == 1 {
+                    row.schedule_items[0].session_id = None;
+                }
+            }
+            data.options.tag_time_preference = HashMap::from([(1, SlotPreference::Early), (2, SlotPreference::Late), (3, SlotPreference::Late)]);
+            // Tag 1 in timeslots 0 and 2, tag 2 in timeslots 0, 1 and 2
+            assert_eq!(data.penalize_tag_time_preferences(), 2 + (2 + 1));
+
+            data.options.tag_time_preference.insert(2, SlotPreference::Any);
+            assert_eq!(data.penalize_tag_time_preferences(), 2);
+        }
+
+        #[test]
+        fn test_early_preferred_tag_scores_better_in_the_morning() {
+            let mut data = make_test_data(1, 3);
+            data.randomly_fill_available_spots();
+            for row in &mut data.schedule_rows {
+                row.schedule_items[0].tag_id = None;
+            }
+            data.options.tag_time_preference = HashMap::from([(7, SlotPreference::Early)]);
+
+            let mut score_with_beginner_session_in = |row_idx: usize| {
+                for (idx, row) in data.schedule_rows.iter_mut().enumerate() {
+                    row.schedule_items[0].tag_id = if idx == row_idx { Some(7) } else { None };
+                }
+                data.score()
+            };
+            let morning = score_with_beginner_session_in(0);
+            let afternoon = score_with_beginner_session_in(2);
+
+            assert!(morning < afternoon, "morning {morning} should beat afternoon {afternoon}");
+        }
+
+        /// Four popular sessions and one without votes competing for a 2x2 grid, where leaving a
+        /// popular session out makes the schedule less late
+        fn make_lateness_versus_missing_data() -> SchedulerData {
+            let mut data = make_test_data_with_sessions(2, 2, 5);
+            for (i, session) in data.unassigned_sessions.iter_mut().enumerate() {
+                session.num_votes = if i < 4 { 10 } else { 0 };
+                session.tag_id = None;
+                session.speaker_id = None;
+                session.speaker_votes.clear();
+            }
+            data.options.weights = ScoringWeights { missing: 0.001, late: 100.0, ..ScoringWeights::default() };
+            data
+        }
+
+        /// Places the sessions in the order given, a row at a time, leaving the rest unassigned
+        fn place(data: &SchedulerData, session_ids: [i32; 4]) -> SchedulerData {
+            let mut placed = data.clone();
+            let cells = placed.schedule_rows.iter_mut().flat_map(|row| row.schedule_items.iter_mut());
+            for (item, session_id) in cells.zip(session_ids) {
+                let session = data.unassigned_sessions.iter().find(|session| session.session_id == Some(session_id)).unwrap();
+                item.session_id = session.session_id;
+                item.num_votes = session.num_votes;
+            }
+            placed.unassigned_sessions.retain(|session| !session_ids.contains(&session.session_id.unwrap()));
+            placed
+        }
+
+        #[test]
+        fn test_lexicographic_objective_compares_penalties_in_priority_order() {
+            let mut data = make_lateness_versus_missing_data();
+            let everyone_popular = place(&data, [0, 1, 2, 3]);
+            let one_left_out = place(&data, [0, 1, 2, 4]);
+            assert_eq!(everyone_popular.score_breakdown().missing, 0);
+            assert!(one_left_out.score_breakdown().missing > 0);
+            assert!(one_left_out.score_breakdown().late < everyone_popular.score_breakdown().late);
+
+            // Weighted, the lateness saved is worth leaving a popular session out
+            assert!(one_left_out.objective() < everyone_popular.objective());
+
+            data.options.scoring_strategy = ScoringStrategy::Lexicographic(vec![PenaltyKind::Missing, PenaltyKind::Late]);
+            let everyone_popular = place(&data, [0, 1, 2, 3]);
+            let one_left_out = place(&data, [0, 1, 2, 4]);
+            assert!(everyone_popular.objective() < one_left_out.objective());
+            assert_eq!(everyone_popular.objective().penalties, vec![0, 100]);
+        }
+
+        #[test]
+        fn test_lexicographic_random_moves_never_worsen_an_earlier_penalty() {
+            use rand::{rngs::StdRng, SeedableRng};
+
+            let mut data = make_lateness_versus_missing_data();
+            data.options.scoring_strategy = ScoringStrategy::Lexicographic(vec![PenaltyKind::Missing, PenaltyKind::Late]);
+            data.options.random_move_prob = 1.0;
+            data.options.max_iterations = Some(1);
+            let start = place(&data, [0, 1, 2, 3]);
+
+            // A single random move early on, when worse moves are accepted most often
+            for seed in 0..50 {
+                let mut data = start.clone();
+                let (objective, _) = data.local_search(&mut StdRng::seed_from_u64(seed), Arc::new(AtomicBool::new(false)));
+                assert_eq!(objective.penalties[0], 0);
+                assert_eq!(data.score_breakdown().missing, 0);
+            }
+        }
+
+        #[test]
+        fn test_lexicographic_strategy_never_trades_missing_sessions_for_lateness() {
+            let mut weighted = make_lateness_versus_missing_data();
+            weighted.improve_with_restarts(10, Arc::new(AtomicBool::new(false)));
+            assert!(weighted.score_breakdown().missing > 0);
+
+            let mut lexicographic = make_lateness_versus_missing_data();
+            lexicographic.options.scoring_strategy = ScoringStrategy::Lexicographic(vec![PenaltyKind::Missing, PenaltyKind::Late]);
+            lexicographic.improve_with_restarts(10, Arc::new(AtomicBool::new(false)));
+            assert_eq!(lexicographic.score_breakdown().missing, 0);
+            assert_eq!(lexicographic.unassigned_sessions.iter().map(|session| session.num_votes).collect::<Vec<_>>(), vec![0]);
+        }
+
+        #[test]
+        fn test_mutually_exclusive_sessions_end_up_in_different_slots() {
+            // Left to the conflict penalty the sessions with 2 and 3 votes share a timeslot, the
+            // most popular paired with the least popular in the other one
+            let mut data = make_test_data_with_sessions(2, 2, 4);
+            for (i, session) in data.unassigned_sessions.iter_mut().enumerate() {
+                session.num_votes = i as i32 + 1;
+                session.tag_id = None;
+                session.speaker_votes.clear();
+            }
+            data.options.mutual_exclusions = vec![(1, 2)];
+
+            data.improve_with_restarts(10, Arc::new(AtomicBool::new(false)));
+
+            assert_eq!(data.score_breakdown().mutual_exclusion, 0);
+            let row_of = |session_id: i32| data.schedule_rows
+                .iter()
+                .position(|row| row.schedule_items.iter().any(|item| item.session_id == Some(session_id)))
+                .expect("every session fits on the schedule");
+            assert_ne!(row_of(1), row_of(2));
+        }
+
+        #[test]
+        fn test_penalize_wasted_capacity() {
+            let mut data = make_test_data_with_sessions(2, 1, 1);
+            data.schedule_rows[0].schedule_items[0].room_capacity = 10;
+            data.schedule_rows[0].schedule_items[1].room_capacity = 100;
+            data.options.weights.wasted_capacity = 0.1;
+
+            // The popular session is crammed into the small room, leaving the big room empty
+            data.unassigned_sessions[0].num_votes = 50;
+            data.swap_with_unassigned_session((0, 0), 0);
+            assert_eq!(data.penalize_wasted_capacity(), 100);
+            let small_room_score = data.score();
+
+            data.swap_sessions((0, 0), (0, 1));
+            assert_eq!(data.penalize_wasted_capacity(), 10);
+            assert!(data.score() < small_room_score);
+
+            // Without a weight the room the session is in doesn't matter
+            data.options.weights.wasted_capacity = 0.0;
+            let big_room_score = data.score();
+            data.swap_sessions((0, 0), (0, 1));
+            assert_relative_eq!(data.score(), big_room_score);
+        }
+
+        #[test]
+        fn test_penalize_speaker_slot_fairness() {
+            // Two rooms, the second is bigger, and two timeslots
+            let mut data = make_test_data(2, 2);
+            data.options.weights.speaker_slot_fairness = 1.0;
+            data.unassigned_sessions.clear();
+            let layout = [[(1, 2), (2, 1)], [(3, 3), (4, 1)]];
+            for (row, sessions) in data.schedule_rows.iter_mut().zip(layout) {
+                for (item, (session_id, speaker_id)) in row.schedule_items.iter_mut().zip(sessions) {
+                    item.session_id = Some(session_id);
+                    item.speaker_id = Some(speaker_id);
+                    item.num_votes = 10;
+                }
+            }
+
+            // Speaker 1 holds the big room in both timeslots
+            assert_eq!(data.penalize_speaker_slot_fairness(), 2);
+            let front_loaded_score = data.score();
+
+            // Moving their second talk to the small room leaves the big room for someone else
+            data.swap_sessions((1, 0), (1, 1));
+            assert_eq!(data.penalize_speaker_slot_fairness(), 1);
+            assert!(data.score() < front_loaded_score);
+
+            // Without a weight it isn't counted
+            data.options.weights.speaker_slot_fairness = 0.0;
+            assert_eq!(data.score_breakdown().speaker_slot_fairness, 0);
+        }
+
+        #[test]
+        fn test_penalize_popular_speaker_conflicts() {
+            let place = |layout: [[(i32, i32, i32); 2]; 2]| {
+                let mut data = make_test_data(2, 2);
+                data.options.weights.popular_speaker_conflict = 1.0;
+                // Speaker 1 has another session with 5 votes that isn't scheduled
+                data.unassigned_sessions = vec![
+                    SessionData { session_id: Some(5), num_votes: 5, tag_id: None, speaker_id: Some(1), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                ];
+                for (row, sessions) in data.schedule_rows.iter_mut().zip(layout) {
+                    for (item, (session_id, speaker_id, num_votes)) in row.schedule_items.iter_mut().zip(sessions) {
+                        item.session_id = Some(session_id);
+                        item.speaker_id = Some(speaker_id);
+                        item.num_votes = num_votes;
+                    }
+                }
+                data
+            };
+
+            // Speakers 1 and 2 draw 15 and 10 votes, speakers 3 and 4 draw 1 vote each
+            let popular_together = place([[(1, 1, 10), (2, 2, 10)], [(3, 3, 1), (4, 4, 1)]]);
+            let popular_apart = place([[(1, 1, 10), (3, 3, 1)], [(2, 2, 10), (4, 4, 1)]]);
+
+            assert_eq!(popular_together.penalize_popular_speaker_conflicts(), 15 * 10 + 1);
+            assert_eq!(popular_apart.penalize_popular_speaker_conflicts(), 15 + 10);
+            assert!(popular_apart.score_breakdown().total < popular_together.score_breakdown().total);
+
+            // A speaker doesn't conflict with their own sessions
+            let same_speaker = place([[(1, 1, 10), (2, 1, 10)], [(3, 3, 1), (4, 4, 1)]]);
+            assert_eq!(same_speaker.penalize_popular_speaker_conflicts(), 1);
+        }
+
+        #[test]
+        fn test_greedy_start_is_no_worse_than_random_start() {
+            use rand::{rngs::StdRng, SeedableRng};
+
+            let seeds = 0..10u64;
+            let num_runs = seeds.clone().count() as f32;
+            let mut random_total = 0.0;
+            let mut greedy_total = 0.0;
+            for seed in seeds {
+                let mut random_start = make_test_data(3, 5);
+                let mut rng = StdRng::seed_from_u64(seed);
+                random_start.randomly_fill_available_spots_with(&mut rng);
+                random_total += random_start.local_search(&mut rng, Arc::new(AtomicBool::new(false))).0.total;
+
+                let mut greedy_start = make_test_data(3, 5);
+                greedy_start.fill_greedy_by_votes();
+                greedy_total += greedy_start.local_search(&mut StdRng::seed_from_u64(seed), Arc::new(AtomicBool::new(false))).0.total;
+            }
+
+            assert!(greedy_total / num_runs <= random_total / num_runs);
+        }
+
+        #[test]
+        fn test_penalize_conflicting_popular_sessions_with_tied_votes() {
+            let mut data = make_test_data(3, 2);
+            data.randomly_fill_available_spots();
+
+            // Time slot 1 has a three way tie, time slot 2 a tie between two of its sessions
+            for (item, num_votes) in data.schedule_rows[0].schedule_items.iter_mut().zip([6, 6, 6]) {
+                item.num_votes = num_votes;
+            }
+            for (item, num_votes) in data.schedule_rows[1].schedule_items.iter_mut().zip([4, 9, 4]) {
+                item.num_votes = num_votes;
+            }
+
+            let conflicting = data.penalize_conflicting_popular_sessions();
+            let late = data.penalize_late_popular_sessions();
+            assert_eq!(conflicting, 36 + 36 + 36 + 16);
+            assert_eq!(late, 36 + 16);
+
+            // Moving the tied sessions to different rooms doesn't change the result
+            for row in &mut data.schedule_rows {
+                row.schedule_items.reverse();
+            }
+            assert_eq!(data.penalize_conflicting_popular_sessions(), conflicting);
+            assert_eq!(data.penalize_late_popular_sessions(), late);
+        }
+
         #[test]
         fn test_penalize_popular_sessions_missing() {
             let mut data = make_test_data(3, 3);
             data.randomly_fill_available_spots();
             data.unassigned_sessions = vec![
-                SessionData { session_id: Some(1), num_votes: 10, tag_id: Some(1), speaker_id: Some(1), speaker_votes: vec![] },
-                SessionData { session_id: Some(2), num_votes: 8, tag_id: Some(2), speaker_id: Some(2), speaker_votes: vec![] },
-                SessionData { session_id: Some(3), num_votes: 12, tag_id: Some(3), speaker_id: Some(3), speaker_votes: vec![] },
-                SessionData { session_id: Some(4), num_votes: 7, tag_id: Some(4), speaker_id: Some(4), speaker_votes: vec![] },
+                SessionData { session_id: Some(1), num_votes: 10, tag_id: Some(1), speaker_id: Some(1), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                SessionData { session_id: Some(2), num_votes: 8, tag_id: Some(2), speaker_id: Some(2), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                SessionData { session_id: Some(3), num_votes: 12, tag_id: Some(3), speaker_id: Some(3), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                SessionData { session_id: Some(4), num_votes: 7, tag_id: Some(4), speaker_id: Some(4), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
             ];
 
             // Time slot1
@@ -816,7 +2539,7 @@ pub(crate) mod tests {
         #[test]
         fn test_weight_scores() {
             let data = make_test_data(2, 2);
-            let result = data.weight_scores(198, 256, 106, 0, 0);
+            let result = data.weight_scores(&ScoreBreakdown { conflicting: 198, missing: 256, late: 106, ..ScoreBreakdown::default() });
 
             // Expect: 0.3 * 198 + 0.5 * 256 + 0.2 * 106 = 59.4 + 128 + 21.2 = 208.6
             assert_relative_eq!(result, 301.6);
@@ -827,6 +2550,13 @@ pub(crate) mod tests {
             let mut data = make_test_data(3, 2);
             data.randomly_fill_available_spots();
 
+            // Clear the speaker votes of the randomly filled sessions so only the ones below conflict
+            for schedule_row in &mut data.schedule_rows {
+                for schedule_item in &mut schedule_row.schedule_items {
+                    schedule_item.speaker_votes.clear();
+                }
+            }
+
             // Set up specific sessions with speaker conflicts
             // Session 1: Speaker 1 votes for Session 2 and their own session (Session 1)
             data.schedule_rows[0].schedule_items[0].session_id = Some(1);
@@ -846,6 +2576,9 @@ pub(crate) mod tests {
             data.schedule_rows[1].schedule_items[0].speaker_id = Some(3);
             data.schedule_rows[1].schedule_items[0].speaker_votes = vec![];
 
+            // The remaining room in the first time slot could hold a random copy of session 1 or 2
+            data.schedule_rows[0].schedule_items[2].session_id = None;
+
             let penalty = data.penalize_speaker_voting_conflicts();
 
             // Should penalize: Speaker 1 presenting Session 1 while Session 2 (which they voted for) is also in same time slot
@@ -859,10 +2592,10 @@ pub(crate) mod tests {
             let mut data = make_test_data(3, 3);
             data.randomly_fill_available_spots();
             data.unassigned_sessions = vec![
-                SessionData { session_id: Some(1), num_votes: 10, tag_id: Some(1), speaker_id: Some(1), speaker_votes: vec![] },
-                SessionData { session_id: Some(2), num_votes: 8, tag_id: Some(2), speaker_id: Some(2), speaker_votes: vec![] },
-                SessionData { session_id: Some(3), num_votes: 12, tag_id: Some(3), speaker_id: Some(3), speaker_votes: vec![] },
-                SessionData { session_id: Some(4), num_votes: 7, tag_id: Some(4), speaker_id: Some(4), speaker_votes: vec![] },
+                SessionData { session_id: Some(1), num_votes: 10, tag_id: Some(1), speaker_id: Some(1), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                SessionData { session_id: Some(2), num_votes: 8, tag_id: Some(2), speaker_id: Some(2), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                SessionData { session_id: Some(3), num_votes: 12, tag_id: Some(3), speaker_id: Some(3), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                SessionData { session_id: Some(4), num_votes: 7, tag_id: Some(4), speaker_id: Some(4), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
             ];
 
             // Time slot1
@@ -880,18 +2613,56 @@ pub(crate) mod tests {
             data.schedule_rows[2].schedule_items[1].num_votes = 0;
             data.schedule_rows[2].schedule_items[2].num_votes = 7;
 
+            // The randomly filled sessions carry tags and speaker votes, clear them so only the
+            // conflicting, missing, and late penalties contribute to the score
+            for schedule_row in &mut data.schedule_rows {
+                for schedule_item in &mut schedule_row.schedule_items {
+                    schedule_item.tag_id = None;
+                    schedule_item.speaker_votes.clear();
+                }
+            }
+
             let score = data.score();
 
             assert_relative_eq!(score, 1718.35);
         }
 
+        #[test]
+        fn test_score_breakdown() {
+            let mut data = make_test_data(2, 2);
+            data.unassigned_sessions = vec![
+                SessionData { session_id: Some(4), num_votes: 5, tag_id: None, speaker_id: None, speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+            ];
+
+            data.schedule_rows[0].schedule_items[0].session_id = Some(1);
+            data.schedule_rows[0].schedule_items[0].num_votes = 10;
+            data.schedule_rows[0].schedule_items[1].session_id = Some(2);
+            data.schedule_rows[0].schedule_items[1].num_votes = 6;
+            data.schedule_rows[1].schedule_items[0].session_id = Some(3);
+            data.schedule_rows[1].schedule_items[0].num_votes = 4;
+
+            let breakdown = data.score_breakdown();
+
+            // Sessions 1 and 2 share the first time slot: 10 * 6
+            assert_eq!(breakdown.conflicting, 60);
+            // Session 4 beats session 3 by 1, the empty slot only counts with a tolerance: 1 * 15
+            assert_eq!(breakdown.missing, 15);
+            assert_eq!(breakdown.same_tag, 0);
+            assert_eq!(breakdown.speaker_conflict, 0);
+            assert_relative_eq!(
+                breakdown.total,
+                data.weight_scores(&breakdown)
+            );
+            assert_relative_eq!(breakdown.total, data.score());
+        }
+
         #[test]
         fn test_improve_reduces_score() {
             let mut data = make_test_data(3, 5);
             data.randomly_fill_available_spots();
 
             let initial_score = data.score();
-            let final_score = data.improve();
+            let final_score = data.improve(Arc::new(AtomicBool::new(false)));
 
             // Score should be reduced or at least not worse
             assert!(final_score <= initial_score);
@@ -903,7 +2674,7 @@ pub(crate) mod tests {
             let original_session_id = data.schedule_rows[0].schedule_items[0].session_id;
             let original_num_votes = data.schedule_rows[0].schedule_items[0].num_votes;
 
-            data.improve();
+            data.improve(Arc::new(AtomicBool::new(false)));
 
             // The already assigned session remains unchanged
             assert_eq!(data.schedule_rows[0].schedule_items[0].session_id, original_session_id);
@@ -911,12 +2682,82 @@ pub(crate) mod tests {
             assert!(data.schedule_rows[0].schedule_items[0].already_assigned);
         }
 
+        #[test]
+        fn test_penalize_popular_sessions_missing_with_tolerance() {
+            let mut data = make_test_data(1, 2);
+            data.schedule_rows[0].schedule_items[0].session_id = Some(1);
+            data.schedule_rows[0].schedule_items[0].num_votes = 4;
+            data.unassigned_sessions = vec![
+                SessionData { session_id: Some(2), num_votes: 10, tag_id: None, speaker_id: None, speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+            ];
+
+            // Without a tolerance only the scheduled session is compared against
+            assert_eq!(data.penalize_popular_sessions_missing(), (10 - 4) * 15);
+
+            data.options.empty_slot_tolerance = 1;
+
+            // Compared against the scheduled session (10 - 4) and the empty slot (10 - 0)
+            assert_eq!(data.penalize_popular_sessions_missing(), (6 - 1 + 10 - 1) * 15);
+
+            data.options.empty_slot_tolerance = 7;
+
+            // Only the empty slot is beaten by more than the tolerance
+            assert_eq!(data.penalize_popular_sessions_missing(), (10 - 7) * 15);
+        }
+
+        #[test]
+        fn test_default_options_leave_the_missing_penalty_unchanged() {
+            let mut data = make_test_data(2, 3);
+            for (item, num_votes) in data.schedule_rows[0].schedule_items.iter_mut().zip([4, 7]) {
+                item.session_id = Some(num_votes);
+                item.num_votes = num_votes;
+            }
+            data.unassigned_sessions = [(10, 9), (11, 5), (12, 1)]
+                .into_iter()
+                .map(|(id, num_votes)| SessionData { session_id: Some(id), num_votes, tag_id: None, speaker_id: None, speaker_votes: vec![], is_keynote: false, allowed_slots: None })
+                .collect();
+
+            // Each unscheduled session more popular than a scheduled one, empty slots ignored
+            let expected = ((9 - 4) + (9 - 7) + (5 - 4)) * 15;
+            assert_eq!(data.score_breakdown().missing, expected);
+        }
+
+        #[test]
+        fn test_empty_slot_tolerance_leaves_marginal_session_unscheduled() {
+            let make_data = |empty_slot_tolerance| {
+                let mut data = make_test_data(2, 1);
+                data.unassigned_sessions = vec![
+                    SessionData { session_id: Some(1), num_votes: 10, tag_id: Some(1), speaker_id: Some(1), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                    SessionData { session_id: Some(2), num_votes: 3, tag_id: Some(2), speaker_id: Some(2), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                ];
+                data.options.empty_slot_tolerance = empty_slot_tolerance;
+                data
+            };
+
+            // Without a tolerance the marginal session takes the spare slot
+            let mut data = make_data(0);
+            data.improve(Arc::new(AtomicBool::new(false)));
+            assert!(data.unassigned_sessions.iter().all(|session| session.session_id.is_none()));
+
+            // With a high tolerance the slot is left empty instead
+            let mut data = make_data(5);
+            data.improve(Arc::new(AtomicBool::new(false)));
+            let scheduled: Vec<i32> = data.schedule_rows
+                .iter()
+                .flat_map(|row| &row.schedule_items)
+                .filter_map(|item| item.session_id)
+                .collect();
+            assert_eq!(scheduled, vec![1]);
+            assert!(data.unassigned_sessions.iter().any(|session| session.session_id == Some(2)));
+        }
+
         #[test]
         fn test_empty_schedule() {
             let mut data = SchedulerData {
                 schedule_rows: vec![],
                 capacity: 0,
                 unassigned_sessions: vec![],
+                options: SchedulerOptions::default(),
             };
 
             data.randomly_fill_available_spots();
@@ -931,7 +2772,7 @@ pub(crate) mod tests {
             data.randomly_fill_available_spots();
 
             assert!(data.schedule_rows[0].schedule_items[0].session_id.is_some());
-            assert!(data.unassigned_sessions.len() > 0);
+            assert!(!data.unassigned_sessions.is_empty());
         }
     }
 
@@ -946,7 +2787,7 @@ pub(crate) mod tests {
             data.randomly_fill_available_spots();
 
             let initial_score = data.score();
-            let final_score = data.improve();
+            let final_score = data.improve(Arc::new(AtomicBool::new(false)));
 
             assert!(final_score <= initial_score);
         }
@@ -957,35 +2798,134 @@ pub(crate) mod tests {
                 schedule_rows: vec![
                     ScheduleRow {
                         schedule_items: vec![
-                            RoomTimeAssignment { room_id: 1, time_slot_id: 1, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![] },
-                            RoomTimeAssignment { room_id: 2, time_slot_id: 1, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![] },
-                            RoomTimeAssignment { room_id: 3, time_slot_id: 1, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![] },
+                            RoomTimeAssignment { room_id: 1, time_slot_id: 1, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![], is_keynote: false, allowed_slots: None, room_capacity: 0 },
+                            RoomTimeAssignment { room_id: 2, time_slot_id: 1, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![], is_keynote: false, allowed_slots: None, room_capacity: 0 },
+                            RoomTimeAssignment { room_id: 3, time_slot_id: 1, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![], is_keynote: false, allowed_slots: None, room_capacity: 0 },
                         ]
                     },
                     ScheduleRow {
                         schedule_items: vec![
-                            RoomTimeAssignment { room_id: 1, time_slot_id: 2, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![] },
-                            RoomTimeAssignment { room_id: 2, time_slot_id: 2, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![] },
-                            RoomTimeAssignment { room_id: 3, time_slot_id: 2, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![] },
+                            RoomTimeAssignment { room_id: 1, time_slot_id: 2, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![], is_keynote: false, allowed_slots: None, room_capacity: 0 },
+                            RoomTimeAssignment { room_id: 2, time_slot_id: 2, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![], is_keynote: false, allowed_slots: None, room_capacity: 0 },
+                            RoomTimeAssignment { room_id: 3, time_slot_id: 2, session_id: None, id: None, already_assigned: false, num_votes: 0, tag_id: None, speaker_id: None, speaker_votes: vec![], is_keynote: false, allowed_slots: None, room_capacity: 0 },
                         ]
                     },
                 ],
                 capacity: 6,
                 unassigned_sessions: vec![
-                    SessionData { session_id: Some(1), num_votes: 12, tag_id: Some(1), speaker_id: Some(1), speaker_votes: vec![] },
-                    SessionData { session_id: Some(2), num_votes: 10, tag_id: Some(2), speaker_id: Some(2), speaker_votes: vec![] },
-                    SessionData { session_id: Some(3), num_votes: 8, tag_id: Some(3), speaker_id: Some(3), speaker_votes: vec![] },
-                    SessionData { session_id: Some(4), num_votes: 6, tag_id: Some(4), speaker_id: Some(4), speaker_votes: vec![] },
-                    SessionData { session_id: Some(5), num_votes: 4, tag_id: Some(5), speaker_id: Some(5), speaker_votes: vec![] },
-                    SessionData { session_id: Some(6), num_votes: 2, tag_id: Some(6), speaker_id: Some(6), speaker_votes: vec![] },
+                    SessionData { session_id: Some(1), num_votes: 12, tag_id: Some(1), speaker_id: Some(1), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                    SessionData { session_id: Some(2), num_votes: 10, tag_id: Some(2), speaker_id: Some(2), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                    SessionData { session_id: Some(3), num_votes: 8, tag_id: Some(3), speaker_id: Some(3), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                    SessionData { session_id: Some(4), num_votes: 6, tag_id: Some(4), speaker_id: Some(4), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                    SessionData { session_id: Some(5), num_votes: 4, tag_id: Some(5), speaker_id: Some(5), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
+                    SessionData { session_id: Some(6), num_votes: 2, tag_id: Some(6), speaker_id: Some(6), speaker_votes: vec![], is_keynote: false, allowed_slots: None },
                 ],
+                options: SchedulerOptions::default(),
             };
 
-            let final_score = data.improve();
+            // A single local search run can settle in a local minimum, restarts make reaching the
+            // optimum reliable
+            let final_score = data.improve_with_restarts(10, Arc::new(AtomicBool::new(false)));
 
             // All sessions should be scheduled
             assert_eq!(data.unassigned_sessions.len(), 0);
             assert_relative_eq!(final_score, 97.6);
         }
     }
+
+    mod swap_properties {
+        use super::*;
+        use proptest::prelude::*;
+        use proptest::sample::Index;
+
+        /// The session id, votes, tag id, speaker id, speaker votes, keynote flag and allowed slots
+        type SessionFields = (Option<i32>, i32, Option<i32>, Option<i32>, Vec<i32>, bool, Option<Vec<i32>>);
+
+        fn session_fields() -> impl Strategy<Value = SessionFields> {
+            (
+                proptest::option::of(0..100i32),
+                0..50i32,
+                proptest::option::of(0..5i32),
+                proptest::option::of(0..5i32),
+                proptest::collection::vec(0..50i32, 0..4),
+                any::<bool>(),
+                proptest::option::of(proptest::collection::vec(0..5i32, 0..3)),
+            )
+        }
+
+        fn room_time_assignment() -> impl Strategy<Value = RoomTimeAssignment> {
+            (session_fields(), any::<bool>(), 0..100i32).prop_map(
+                |((session_id, num_votes, tag_id, speaker_id, speaker_votes, is_keynote, allowed_slots), already_assigned, room_capacity)| RoomTimeAssignment {
+                    room_id: 0,
+                    time_slot_id: 0,
+                    session_id,
+                    id: None,
+                    already_assigned,
+                    num_votes,
+                    tag_id,
+                    speaker_id,
+                    speaker_votes,
+                    is_keynote,
+                    allowed_slots,
+                    room_capacity,
+                },
+            )
+        }
+
+        fn scheduler_data() -> impl Strategy<Value = SchedulerData> {
+            (1..4usize, 1..4usize)
+                .prop_flat_map(|(rooms, slots)| {
+                    (
+                        proptest::collection::vec(proptest::collection::vec(room_time_assignment(), rooms), slots),
+                        proptest::collection::vec(session_fields(), 0..5),
+                    )
+                })
+                .prop_map(|(rows, unassigned)| SchedulerData {
+                    capacity: rows[0].len() as i32,
+                    schedule_rows: rows
+                        .into_iter()
+                        .map(|schedule_items| ScheduleRow { schedule_items })
+                        .collect(),
+                    unassigned_sessions: unassigned
+                        .into_iter()
+                        .map(|(session_id, num_votes, tag_id, speaker_id, speaker_votes, is_keynote, allowed_slots)| SessionData {
+                            session_id,
+                            num_votes,
+                            tag_id,
+                            speaker_id,
+                            speaker_votes,
+                            is_keynote,
+                            allowed_slots,
+                        })
+                        .collect(),
+                    options: SchedulerOptions::default(),
+                })
+        }
+
+        proptest! {
+            #[test]
+            fn reversing_an_action_restores_the_schedule(
+                data in scheduler_data(),
+                from_unassigned in any::<bool>(),
+                first in any::<Index>(),
+                second in any::<Index>(),
+            ) {
+                let swappable = data.get_swappable_sessions();
+                prop_assume!(!swappable.is_empty());
+
+                let action = if from_unassigned && !data.unassigned_sessions.is_empty() {
+                    SwapAction::FromUnassigned(*first.get(&swappable), second.index(data.unassigned_sessions.len()))
+                } else {
+                    SwapAction::FromSchedule(*first.get(&swappable), *second.get(&swappable))
+                };
+
+                let mut swapped = data.clone();
+                swapped.apply_action(&action);
+                swapped.reverse_action(&action);
+
+                prop_assert_eq!(&swapped.schedule_rows, &data.schedule_rows);
+                prop_assert_eq!(&swapped.unassigned_sessions, &data.unassigned_sessions);
+            }
+        }
+    }
 }
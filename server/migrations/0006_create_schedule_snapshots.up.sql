@@ -0,0 +1,6 @@
+CREATE TABLE schedule_snapshots (
+    id INTEGER GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    label TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    assignments JSONB NOT NULL
+);
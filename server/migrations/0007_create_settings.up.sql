@@ -0,0 +1,6 @@
+CREATE TABLE settings (
+    id INTEGER GENERATED ALWAYS AS (1) STORED UNIQUE,
+    schedule_locked BOOLEAN NOT NULL DEFAULT false
+);
+
+INSERT INTO settings DEFAULT VALUES;
@@ -0,0 +1,6 @@
+CREATE TABLE tag_rules (
+    id INTEGER GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    keyword TEXT NOT NULL,
+    tag_id INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    UNIQUE (keyword, tag_id)
+);
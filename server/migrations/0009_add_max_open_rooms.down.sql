@@ -0,0 +1 @@
+ALTER TABLE time_slots DROP COLUMN max_open_rooms;
@@ -0,0 +1 @@
+ALTER TABLE time_slots ADD COLUMN max_open_rooms INTEGER CHECK (max_open_rooms >= 0);
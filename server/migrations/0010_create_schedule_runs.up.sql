@@ -0,0 +1,10 @@
+CREATE TABLE schedule_runs (
+    id INTEGER GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    restarts INTEGER NOT NULL,
+    iterations BIGINT NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    starting_score REAL NOT NULL,
+    final_score REAL NOT NULL,
+    unscheduled INTEGER NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE settings ADD COLUMN submissions_open BOOLEAN NOT NULL DEFAULT true;
@@ -0,0 +1 @@
+ALTER TABLE sessions DROP COLUMN is_keynote;
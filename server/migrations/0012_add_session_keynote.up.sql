@@ -0,0 +1 @@
+ALTER TABLE sessions ADD COLUMN is_keynote BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,3 @@
+DROP INDEX user_votes_voted_at_idx;
+
+ALTER TABLE user_votes DROP COLUMN voted_at;
@@ -0,0 +1,3 @@
+ALTER TABLE user_votes ADD COLUMN voted_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE INDEX user_votes_voted_at_idx ON user_votes (voted_at);
@@ -0,0 +1,5 @@
+CREATE TABLE session_availability (
+    session_id INTEGER NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+    time_slot_id INTEGER NOT NULL REFERENCES time_slots (id) ON DELETE CASCADE,
+    PRIMARY KEY (session_id, time_slot_id)
+);
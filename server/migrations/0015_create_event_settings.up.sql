@@ -0,0 +1,8 @@
+CREATE TABLE event_settings (
+    id INTEGER GENERATED ALWAYS AS (1) STORED UNIQUE,
+    name TEXT NOT NULL DEFAULT 'Unconference',
+    date DATE,
+    description TEXT NOT NULL DEFAULT ''
+);
+
+INSERT INTO event_settings DEFAULT VALUES;
@@ -0,0 +1,5 @@
+DROP INDEX timeslot_assignments_updated_at_idx;
+
+ALTER TABLE timeslot_assignments
+    DROP COLUMN updated_at,
+    DROP COLUMN created_at;
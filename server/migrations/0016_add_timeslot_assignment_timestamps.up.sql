@@ -0,0 +1,5 @@
+ALTER TABLE timeslot_assignments
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE INDEX timeslot_assignments_updated_at_idx ON timeslot_assignments (updated_at);
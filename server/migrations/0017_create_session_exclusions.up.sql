@@ -0,0 +1,6 @@
+CREATE TABLE session_exclusions (
+    session_id INTEGER NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+    other_session_id INTEGER NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+    PRIMARY KEY (session_id, other_session_id),
+    CHECK (session_id < other_session_id)
+);
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN vote_weight INTEGER NOT NULL DEFAULT 1 CHECK (vote_weight >= 0);
@@ -0,0 +1,3 @@
+DROP TRIGGER timeslot_assignments_record_deletion ON timeslot_assignments;
+DROP FUNCTION record_timeslot_assignment_deletion();
+DROP TABLE timeslot_assignment_deletions;
@@ -0,0 +1,25 @@
+CREATE TABLE timeslot_assignment_deletions (
+    assignment_id INTEGER PRIMARY KEY,
+    time_slot_id INTEGER,
+    room_id INTEGER,
+    session_id INTEGER,
+    created_at TIMESTAMPTZ NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX timeslot_assignment_deletions_deleted_at_idx ON timeslot_assignment_deletions (deleted_at);
+
+-- Assignments are deleted by many queries, so the deletions are recorded by a trigger rather than
+-- by each of them
+CREATE FUNCTION record_timeslot_assignment_deletion() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO timeslot_assignment_deletions (assignment_id, time_slot_id, room_id, session_id, created_at)
+    VALUES (OLD.id, OLD.time_slot_id, OLD.room_id, OLD.session_id, OLD.created_at)
+    ON CONFLICT (assignment_id) DO NOTHING;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER timeslot_assignments_record_deletion
+    AFTER DELETE ON timeslot_assignments
+    FOR EACH ROW EXECUTE FUNCTION record_timeslot_assignment_deletion();
@@ -0,0 +1 @@
+ALTER TABLE settings ADD COLUMN pseudonym_key TEXT NOT NULL DEFAULT replace(gen_random_uuid()::TEXT || gen_random_uuid()::TEXT, '-', '');
@@ -1,6 +1,6 @@
 use crate::{
     controllers::{
-        room_handler, schedule_handler, session_voting_handler, sessions_handler, timeslot_handler,
+        account_handler, bundle_handler, room_handler, schedule_handler, schedule_snapshot_handler, session_voting_handler, sessions_handler, settings_handler, speakers_handler, timeslot_handler,
     },
     models::{
         room_model::Room, schedule_model::Schedule, sessions_model::Session,
@@ -15,20 +15,72 @@ use utoipa::OpenApi;
         // Sessions
         sessions_handler::sessions,
         sessions_handler::get_session,
+        sessions_handler::get_session_full,
+        sessions_handler::similar_sessions,
         sessions_handler::post_session,
         sessions_handler::delete_session,
+        sessions_handler::bulk_delete_sessions,
         sessions_handler::update_session,
+        sessions_handler::duplicate_session,
+        sessions_handler::set_keynote,
+        sessions_handler::exclude_sessions,
+        sessions_handler::merge_sessions,
+        sessions_handler::unvoted_sessions,
+        sessions_handler::sessions_by_tag,
         session_voting_handler::add_vote_for_session,
         session_voting_handler::subtract_vote_for_session,
+        session_voting_handler::vote_stats,
+        session_voting_handler::reconcile_votes,
+        session_voting_handler::trending_sessions,
+        session_voting_handler::session_voters,
+        session_voting_handler::export_votes_csv,
+        // Accounts
+        account_handler::current_user,
+        account_handler::delete_current_user,
+        account_handler::set_vote_weight,
         // Rooms
         room_handler::rooms,
+        room_handler::room_occupancy,
+        room_handler::room_stats,
+        room_handler::room_schedule_ics,
+        room_handler::room_schedule_csv,
         room_handler::post_rooms,
         room_handler::delete_room,
         // Schedules
         schedule_handler::generate,
+        schedule_handler::generate_with_progress,
         schedule_handler::clear,
+        schedule_handler::score,
+        schedule_handler::last_run,
+        schedule_handler::schedule_grid,
+        schedule_handler::schedule_changes,
+        schedule_handler::free_cells,
+        schedule_handler::unschedule_session_from_schedule,
+        schedule_handler::validate,
+        schedule_handler::validate_grid,
+        schedule_handler::commit_grid,
+        schedule_handler::suggestions,
+        schedule_handler::lock_schedule,
+        schedule_handler::unlock_schedule,
+        schedule_snapshot_handler::create_snapshot,
+        schedule_snapshot_handler::snapshots,
+        schedule_snapshot_handler::restore_snapshot,
+        schedule_snapshot_handler::diff_snapshots,
+        // Config
+        settings_handler::event_config,
+        settings_handler::features,
+        settings_handler::set_submissions,
+        settings_handler::event_settings,
+        settings_handler::set_event_settings,
+        // Speakers
+        speakers_handler::speakers,
         // Timeslots
         timeslot_handler::update_timeslot,
+        timeslot_handler::generate_timeslots,
+        timeslot_handler::timeslot_assignments,
+        // Export
+        bundle_handler::export_bundle,
+        bundle_handler::import_bundle,
     ),
     components(
         schemas(Session, Room, Schedule, TimeSlot)
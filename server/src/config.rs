@@ -1,9 +1,19 @@
 use crate::db_config::db_setup;
+use crate::models::account_model::{DeletionStrategy, DEFAULT_ANONYMOUS_USER_EMAIL};
 use crate::models::auth_model::Backend;
+use crate::models::schedule_model::ScoreCache;
+use crate::models::timeslot_assignment_model::{PenaltyNormalization, SchedulingMethod, VoteScaling};
+use chrono::{NaiveDate, Utc};
+use scheduler::{SchedulerOptions, ScoringStrategy, ScoringWeights, DEFAULT_RANDOM_MOVE_PROB};
+use serde::Serialize;
 use sqlx::{Pool, Postgres};
+use std::env::var;
 use std::error::Error;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use utoipa::ToSchema;
 
 /// The application state
 ///
@@ -12,21 +22,27 @@ use tokio::sync::RwLock;
 /// # Fields
 /// - `unconf_data`: Thread-safe storage for the application data
 /// - `auth_backend`: Thread-safe storage for the JWT secret
+/// - `config`: The settings read from the environment at startup
+/// - `score_cache`: The score of the current schedule, invalidated whenever something is changed
 pub struct AppState {
     pub unconf_data: Arc<RwLock<UnconfData>>,
     pub auth_backend: Backend,
+    pub config: Config,
+    pub score_cache: ScoreCache,
 }
 
 impl AppState {
     /// Creates a new `AppState` instance.
     ///
+    /// # Parameters
+    /// - `config`: The settings read from the environment at startup
+    ///
     /// # Returns
     /// `Ok(AppState)`, or an error if unable to initialize `UnconfData`
     ///
     /// # Errors
-    /// This function will return an error if:
-    /// - `UnconfData` cannot be initialized
-    pub async fn new() -> Result<Self, Box<dyn Error>> {
+    /// This function will return an error if `UnconfData` cannot be initialized.
+    pub async fn new(config: Config) -> Result<Self, Box<dyn Error>> {
         let unconf_data = UnconfData::new().await?;
         let db_pool = unconf_data.unconf_db.clone();
         let auth_backend = Backend::new(db_pool);
@@ -34,6 +50,8 @@ impl AppState {
         Ok(Self {
             unconf_data: Arc::new(RwLock::new(unconf_data)),
             auth_backend,
+            config,
+            score_cache: ScoreCache::default(),
         })
     }
 }
@@ -63,3 +81,501 @@ impl UnconfData {
         })
     }
 }
+
+/// Error returned when environment variables have invalid values.
+///
+/// Every invalid variable is listed rather than only the first, so they can all be fixed at once.
+#[derive(Debug, PartialEq, thiserror::Error)]
+#[error("Invalid configuration:\n  {}", .0.join("\n  "))]
+pub struct ConfigError(pub Vec<String>);
+
+/// The settings that change how the local search scheduler runs
+///
+/// # Fields
+/// - `method`: Which scheduler generates schedules, from `SCHEDULING_METHOD`
+/// - `min_votes_to_schedule`: Sessions with fewer votes are left off generated schedules, from
+///   `MIN_VOTES_TO_SCHEDULE`
+/// - `time_budget`: How long the local search scheduler may run for, from
+///   `SCHEDULER_TIME_BUDGET_MS`
+/// - `empty_slot_tolerance`: From `SCHEDULER_EMPTY_SLOT_TOLERANCE`
+/// - `popular_speaker_weight`: From `SCHEDULER_POPULAR_SPEAKER_WEIGHT`
+/// - `wasted_capacity_weight`: From `SCHEDULER_WASTED_CAPACITY_WEIGHT`
+/// - `speaker_fairness_weight`: From `SCHEDULER_SPEAKER_FAIRNESS_WEIGHT`
+/// - `random_move_prob`: From `SCHEDULER_RANDOM_MOVE_PROB`
+/// - `max_iterations`: How many iterations each local search runs for, from
+///   `SCHEDULER_MAX_ITERS`. The scheduler's own `3 * capacity^2` when `None`
+/// - `scoring_strategy`: How the penalties are combined when schedules are compared, from
+///   `SCHEDULER_SCORING`
+/// - `normalization`: How the penalties are scaled before they are weighed when a schedule is
+///   generated, from `SCHEDULER_NORMALIZATION`
+/// - `vote_scaling`: How the sessions' votes are scaled before they are weighed, from
+///   `VOTE_SCALING`
+/// - `keynotes`: Whether keynotes get a timeslot to themselves, follows `FeatureFlags::keynotes`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerConfig {
+    pub method: SchedulingMethod,
+    pub min_votes_to_schedule: i32,
+    pub time_budget: Duration,
+    pub empty_slot_tolerance: i32,
+    pub popular_speaker_weight: f32,
+    pub wasted_capacity_weight: f32,
+    pub speaker_fairness_weight: f32,
+    pub random_move_prob: f64,
+    pub max_iterations: Option<usize>,
+    pub scoring_strategy: ScoringStrategy,
+    pub normalization: PenaltyNormalization,
+    pub vote_scaling: VoteScaling,
+    pub keynotes: bool,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            method: SchedulingMethod::Original,
+            min_votes_to_schedule: 0,
+            time_budget: Duration::from_secs(60),
+            empty_slot_tolerance: 0,
+            popular_speaker_weight: 0.0,
+            wasted_capacity_weight: 0.0,
+            speaker_fairness_weight: 0.0,
+            random_move_prob: DEFAULT_RANDOM_MOVE_PROB,
+            max_iterations: None,
+            scoring_strategy: ScoringStrategy::WeightedSum,
+            normalization: PenaltyNormalization::Raw,
+            vote_scaling: VoteScaling::Linear,
+            keynotes: true,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// Builds the options the local search scheduler is run with.
+    ///
+    /// # Returns
+    /// `SchedulerOptions` with the configured tolerance, weights, random move probability,
+    /// iteration cap and scoring strategy
+    pub fn options(&self) -> SchedulerOptions {
+        SchedulerOptions {
+            empty_slot_tolerance: self.empty_slot_tolerance,
+            weights: ScoringWeights {
+                popular_speaker_conflict: self.popular_speaker_weight,
+                wasted_capacity: self.wasted_capacity_weight,
+                speaker_slot_fairness: self.speaker_fairness_weight,
+                ..ScoringWeights::default()
+            },
+            random_move_prob: self.random_move_prob,
+            max_iterations: self.max_iterations,
+            scoring_strategy: self.scoring_strategy.clone(),
+            ..SchedulerOptions::default()
+        }
+    }
+}
+
+/// The optional features organizers can turn off, every feature is on unless disabled
+///
+/// # Fields
+/// - `keynotes`: Sessions can be marked as keynotes, which the scheduler gives a timeslot to
+///   themselves, from `FEATURE_KEYNOTES`
+/// - `deduplication`: Similar sessions can be listed and merged, from `FEATURE_DEDUPLICATION`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FeatureFlags {
+    pub keynotes: bool,
+    pub deduplication: bool,
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self {
+            keynotes: true,
+            deduplication: true,
+        }
+    }
+}
+
+/// The settings read from environment variables when the server starts
+///
+/// Variables that aren't set (or are empty) get their defaults, see `docs/DOCKER.md`.
+///
+/// # Fields
+/// - `scheduler`: How schedules are generated
+/// - `max_sessions_per_user`: How many sessions each user can submit, `None` for no limit, from
+///   `MAX_SESSIONS_PER_USER`
+/// - `event_date`: The day the unconference takes place on, from `EVENT_DATE`
+/// - `request_timeout`: How long a request may take before it is cancelled, from
+///   `REQUEST_TIMEOUT_SECS`
+/// - `embed_rate_limit_per_minute`: How many requests each IP address can make to the embedded
+///   schedule per minute, from `EMBED_RATE_LIMIT_PER_MINUTE`
+/// - `embed_token`: The token that grants access to the embedded schedule, `None` to disable it,
+///   from `EMBED_TOKEN`
+/// - `account_deletion_strategy`: What happens to a deleted user's sessions, from
+///   `ACCOUNT_DELETION_STRATEGY`
+/// - `anonymous_user_email`: The email of the account that receives reassigned sessions, from
+///   `ANONYMOUS_USER_EMAIL`
+/// - `idempotency_key_ttl`: How long a submitted session's idempotency key is remembered, from
+///   `IDEMPOTENCY_KEY_TTL_SECS`
+/// - `features`: Which optional features are turned on, from the `FEATURE_*` variables
+/// - `log_format`: How log lines are written, from `LOG_FORMAT`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub scheduler: SchedulerConfig,
+    pub max_sessions_per_user: Option<i64>,
+    pub event_date: Option<NaiveDate>,
+    pub request_timeout: Duration,
+    pub embed_rate_limit_per_minute: u32,
+    pub embed_token: Option<String>,
+    pub account_deletion_strategy: DeletionStrategy,
+    pub anonymous_user_email: String,
+    pub idempotency_key_ttl: Duration,
+    pub features: FeatureFlags,
+    pub log_format: LogFormat,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scheduler: SchedulerConfig::default(),
+            max_sessions_per_user: None,
+            event_date: None,
+            request_timeout: Duration::from_secs(30),
+            embed_rate_limit_per_minute: 60,
+            embed_token: None,
+            account_deletion_strategy: DeletionStrategy::ReassignContent,
+            anonymous_user_email: DEFAULT_ANONYMOUS_USER_EMAIL.to_string(),
+            idempotency_key_ttl: Duration::from_secs(24 * 60 * 60),
+            features: FeatureFlags::default(),
+            log_format: LogFormat::default(),
+        }
+    }
+}
+
+/// Reads variables through a lookup function, collecting a problem for each invalid one.
+struct VarReader<F> {
+    lookup: F,
+    problems: Vec<String>,
+}
+
+impl<F: Fn(&str) -> Option<String>> VarReader<F> {
+    /// Parses a variable, checking it with `valid`.
+    ///
+    /// # Returns
+    /// The parsed value, `None` when the variable isn't set or is invalid.
+    fn parse<T: FromStr>(&mut self, name: &str, requirement: &str, valid: impl Fn(&T) -> bool) -> Option<T> {
+        let value = (self.lookup)(name).filter(|value| !value.trim().is_empty())?;
+        match value.trim().parse() {
+            Ok(parsed) if valid(&parsed) => Some(parsed),
+            _ => {
+                self.problems.push(format!("{name} must be {requirement}, got `{value}`"));
+                None
+            }
+        }
+    }
+
+    /// Parses a variable like `parse`, except that setting it to an empty value is invalid rather
+    /// than leaving it unset.
+    fn parse_non_empty<T: FromStr>(&mut self, name: &str, requirement: &str, valid: impl Fn(&T) -> bool) -> Option<T> {
+        match (self.lookup)(name) {
+            Some(value) if value.trim().is_empty() => {
+                self.problems.push(format!("{name} must be {requirement}, got `{value}`"));
+                None
+            }
+            _ => self.parse(name, requirement, valid),
+        }
+    }
+}
+
+impl Config {
+    /// Reads and validates the configuration from the environment.
+    ///
+    /// # Returns
+    /// The `Config`, or a `ConfigError` listing every variable with an invalid value.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(|name| var(name).ok())
+    }
+
+    /// Reads and validates the configuration through a lookup function.
+    ///
+    /// # Parameters
+    /// - `lookup`: Returns the value of a variable, `None` when it isn't set
+    ///
+    /// # Returns
+    /// The `Config`, or a `ConfigError` listing every variable with an invalid value.
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        let mut reader = VarReader { lookup, problems: Vec::new() };
+        let non_negative_weight = |weight: &f32| weight.is_finite() && *weight >= 0.0;
+
+        let scheduler = SchedulerConfig {
+            method: reader
+                .parse("SCHEDULING_METHOD", "`original`, `localsearch` or `maximizecoverage`", |_: &SchedulingMethod| true)
+                .unwrap_or(defaults.scheduler.method),
+            min_votes_to_schedule: reader
+                .parse("MIN_VOTES_TO_SCHEDULE", "a whole number of at least 0", |min_votes: &i32| *min_votes >= 0)
+                .unwrap_or(defaults.scheduler.min_votes_to_schedule),
+            time_budget: reader
+                .parse("SCHEDULER_TIME_BUDGET_MS", "a whole number of milliseconds above 0", |budget: &u64| *budget > 0)
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.scheduler.time_budget),
+            empty_slot_tolerance: reader
+                .parse("SCHEDULER_EMPTY_SLOT_TOLERANCE", "a whole number of at least 0", |tolerance: &i32| *tolerance >= 0)
+                .unwrap_or(defaults.scheduler.empty_slot_tolerance),
+            popular_speaker_weight: reader
+                .parse("SCHEDULER_POPULAR_SPEAKER_WEIGHT", "a number of at least 0", non_negative_weight)
+                .unwrap_or(defaults.scheduler.popular_speaker_weight),
+            wasted_capacity_weight: reader
+                .parse("SCHEDULER_WASTED_CAPACITY_WEIGHT", "a number of at least 0", non_negative_weight)
+                .unwrap_or(defaults.scheduler.wasted_capacity_weight),
+            speaker_fairness_weight: reader
+                .parse("SCHEDULER_SPEAKER_FAIRNESS_WEIGHT", "a number of at least 0", non_negative_weight)
+                .unwrap_or(defaults.scheduler.speaker_fairness_weight),
+            random_move_prob: reader
+                .parse("SCHEDULER_RANDOM_MOVE_PROB", "a number between 0 and 1", |prob: &f64| (0.0..=1.0).contains(prob))
+                .unwrap_or(defaults.scheduler.random_move_prob),
+            max_iterations: reader
+                .parse("SCHEDULER_MAX_ITERS", "a whole number above 0", |max_iterations: &usize| *max_iterations > 0)
+                .or(defaults.scheduler.max_iterations),
+            scoring_strategy: reader
+                .parse(
+                    "SCHEDULER_SCORING",
+                    "`weighted` or `lexicographic:` followed by distinct penalties such as `lexicographic:missing,late`",
+                    distinct_penalties,
+                )
+                .unwrap_or(defaults.scheduler.scoring_strategy),
+            normalization: reader
+                .parse("SCHEDULER_NORMALIZATION", "`raw` or `minmax`", |_: &PenaltyNormalization| true)
+                .unwrap_or(defaults.scheduler.normalization),
+            vote_scaling: reader
+                .parse("VOTE_SCALING", "`linear`, `log` or `sqrt`", |_: &VoteScaling| true)
+                .unwrap_or(defaults.scheduler.vote_scaling),
+            keynotes: defaults.scheduler.keynotes,
+        };
+
+        let mut config = Self {
+            scheduler,
+            max_sessions_per_user: reader
+                .parse("MAX_SESSIONS_PER_USER", "a whole number of at least 0", |max_sessions: &i64| *max_sessions >= 0)
+                .filter(|max_sessions| *max_sessions > 0),
+            event_date: reader.parse("EVENT_DATE", "a date formatted as YYYY-MM-DD", |_: &NaiveDate| true),
+            request_timeout: reader
+                .parse("REQUEST_TIMEOUT_SECS", "a whole number of seconds above 0", |timeout: &u64| *timeout > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.request_timeout),
+            embed_rate_limit_per_minute: reader
+                .parse("EMBED_RATE_LIMIT_PER_MINUTE", "a whole number above 0", |max_requests: &u32| *max_requests > 0)
+                .unwrap_or(defaults.embed_rate_limit_per_minute),
+            embed_token: reader.parse("EMBED_TOKEN", "a token", |_: &String| true),
+            account_deletion_strategy: reader
+                .parse::<AccountDeletionStrategy>("ACCOUNT_DELETION_STRATEGY", "`delete` or `reassign`", |_| true)
+                .map(|strategy| strategy.0)
+                .unwrap_or(defaults.account_deletion_strategy),
+            anonymous_user_email: reader
+                .parse_non_empty("ANONYMOUS_USER_EMAIL", "an email address such as `anonymous@example.com`", |email: &String| is_email(email))
+                .unwrap_or(defaults.anonymous_user_email),
+            idempotency_key_ttl: reader
+                .parse("IDEMPOTENCY_KEY_TTL_SECS", "a whole number of seconds above 0", |ttl: &u64| *ttl > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.idempotency_key_ttl),
+            features: FeatureFlags {
+                keynotes: reader
+                    .parse("FEATURE_KEYNOTES", "`true` or `false`", |_: &bool| true)
+                    .unwrap_or(defaults.features.keynotes),
+                deduplication: reader
+                    .parse("FEATURE_DEDUPLICATION", "`true` or `false`", |_: &bool| true)
+                    .unwrap_or(defaults.features.deduplication),
+            },
+            log_format: reader
+                .parse("LOG_FORMAT", "`pretty` or `json`", |_: &LogFormat| true)
+                .unwrap_or(defaults.log_format),
+        };
+        config.scheduler.keynotes = config.features.keynotes;
+
+        if reader.problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError(reader.problems))
+        }
+    }
+
+    /// The day the unconference takes place on.
+    ///
+    /// # Returns
+    /// `event_date`, or today when it isn't set.
+    pub fn event_date(&self) -> NaiveDate {
+        self.event_date.unwrap_or_else(|| Utc::now().date_naive())
+    }
+}
+
+/// Whether a lexicographic strategy lists at least one penalty and none of them twice.
+fn distinct_penalties(strategy: &ScoringStrategy) -> bool {
+    match strategy {
+        ScoringStrategy::WeightedSum => true,
+        ScoringStrategy::Lexicographic(kinds) => {
+            !kinds.is_empty() && kinds.iter().enumerate().all(|(i, kind)| !kinds[..i].contains(kind))
+        },
+    }
+}
+
+/// Whether an address looks like an email, a single `@` with something on either side and no
+/// whitespace.
+fn is_email(email: &str) -> bool {
+    email.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty() && !domain.is_empty() && !domain.contains('@') && !email.contains(char::is_whitespace)
+    })
+}
+
+/// How log lines are written
+///
+/// # Variants
+/// - `Pretty` - Multi-line human readable logs
+/// - `Json` - One JSON object per line, for log aggregation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown log format {value}")),
+        }
+    }
+}
+
+/// The `ACCOUNT_DELETION_STRATEGY` values, which are shorter than `DeletionStrategy`'s API names.
+struct AccountDeletionStrategy(DeletionStrategy);
+
+impl FromStr for AccountDeletionStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "delete" => Ok(Self(DeletionStrategy::DeleteContent)),
+            "reassign" => Ok(Self(DeletionStrategy::ReassignContent)),
+            _ => Err(format!("unknown account deletion strategy {value}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scheduler::PenaltyKind;
+    use std::collections::HashMap;
+
+    fn config_from(vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        Config::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn unset_variables_get_their_defaults() {
+        assert_eq!(config_from(&[]), Ok(Config::default()));
+        assert_eq!(config_from(&[("MIN_VOTES_TO_SCHEDULE", "")]), Ok(Config::default()));
+    }
+
+    #[test]
+    fn valid_variables_are_parsed() {
+        let config = config_from(&[
+            ("SCHEDULING_METHOD", "LocalSearch"),
+            ("MIN_VOTES_TO_SCHEDULE", "2"),
+            ("SCHEDULER_TIME_BUDGET_MS", "1500"),
+            ("SCHEDULER_WASTED_CAPACITY_WEIGHT", "0.25"),
+            ("SCHEDULER_RANDOM_MOVE_PROB", "1"),
+            ("SCHEDULER_MAX_ITERS", "5000"),
+            ("SCHEDULER_NORMALIZATION", "MinMax"),
+            ("VOTE_SCALING", "log"),
+            ("MAX_SESSIONS_PER_USER", "3"),
+            ("EVENT_DATE", "2026-10-17"),
+            ("REQUEST_TIMEOUT_SECS", "45"),
+            ("ACCOUNT_DELETION_STRATEGY", "delete"),
+            ("ANONYMOUS_USER_EMAIL", "ghost@example.com"),
+            ("EMBED_TOKEN", "secret"),
+            ("IDEMPOTENCY_KEY_TTL_SECS", "600"),
+            ("LOG_FORMAT", "JSON"),
+        ])
+            .unwrap();
+
+        assert_eq!(config.scheduler.method, SchedulingMethod::LocalSearch);
+        assert_eq!(config.scheduler.min_votes_to_schedule, 2);
+        assert_eq!(config.scheduler.time_budget, Duration::from_millis(1500));
+        assert_eq!(config.scheduler.options().weights.wasted_capacity, 0.25);
+        assert_eq!(config.scheduler.options().random_move_prob, 1.0);
+        assert_eq!(config.scheduler.options().max_iterations, Some(5000));
+        assert_eq!(config.scheduler.normalization, PenaltyNormalization::MinMax);
+        assert_eq!(config.scheduler.vote_scaling, VoteScaling::Log);
+        assert_eq!(config.max_sessions_per_user, Some(3));
+        assert_eq!(config.event_date(), NaiveDate::from_ymd_opt(2026, 10, 17).unwrap());
+        assert_eq!(config.request_timeout, Duration::from_secs(45));
+        assert_eq!(config.account_deletion_strategy, DeletionStrategy::DeleteContent);
+        assert_eq!(config.anonymous_user_email, "ghost@example.com");
+        assert_eq!(config.embed_token.as_deref(), Some("secret"));
+        assert_eq!(config.idempotency_key_ttl, Duration::from_secs(600));
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config_from(&[("MAX_SESSIONS_PER_USER", "0")]).unwrap().max_sessions_per_user, None);
+    }
+
+    #[test]
+    fn scoring_strategy_lists_distinct_penalties() {
+        let config = config_from(&[("SCHEDULER_SCORING", "Lexicographic:missing, speaker_conflict")]).unwrap();
+        assert_eq!(
+            config.scheduler.options().scoring_strategy,
+            ScoringStrategy::Lexicographic(vec![PenaltyKind::Missing, PenaltyKind::SpeakerConflict])
+        );
+        assert_eq!(config_from(&[("SCHEDULER_SCORING", "weighted")]).unwrap().scheduler.scoring_strategy, ScoringStrategy::WeightedSum);
+
+        for invalid in ["lexicographic", "lexicographic:", "lexicographic:missing,late,missing", "lexicographic:lateness"] {
+            let err = config_from(&[("SCHEDULER_SCORING", invalid)]).unwrap_err();
+            assert!(err.0[0].starts_with("SCHEDULER_SCORING must be"), "{invalid} was accepted");
+        }
+    }
+
+    #[test]
+    fn features_are_on_unless_turned_off() {
+        let config = config_from(&[]).unwrap();
+        assert_eq!(config.features, FeatureFlags { keynotes: true, deduplication: true });
+        assert!(config.scheduler.keynotes);
+
+        let config = config_from(&[("FEATURE_KEYNOTES", "false"), ("FEATURE_DEDUPLICATION", "true")]).unwrap();
+        assert_eq!(config.features, FeatureFlags { keynotes: false, deduplication: true });
+        assert!(!config.scheduler.keynotes);
+
+        let err = config_from(&[("FEATURE_DEDUPLICATION", "off")]).unwrap_err();
+        assert_eq!(err.0, vec!["FEATURE_DEDUPLICATION must be `true` or `false`, got `off`"]);
+    }
+
+    #[test]
+    fn anonymous_user_email_must_be_an_email_address() {
+        assert_eq!(config_from(&[]).unwrap().anonymous_user_email, DEFAULT_ANONYMOUS_USER_EMAIL);
+        assert_eq!(config_from(&[("EMBED_TOKEN", "")]).unwrap().embed_token, None);
+
+        for invalid in ["", "  ", "anonymous", "@example.com", "anonymous@", "a@b@example.com", "any one@example.com"] {
+            let err = config_from(&[("ANONYMOUS_USER_EMAIL", invalid)]).unwrap_err();
+            assert!(err.0[0].starts_with("ANONYMOUS_USER_EMAIL must be an email address"), "{invalid:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn every_invalid_variable_is_reported() {
+        let err = config_from(&[
+            ("SCHEDULING_METHOD", "fastest"),
+            ("MIN_VOTES_TO_SCHEDULE", "-1"),
+            ("SCHEDULER_RANDOM_MOVE_PROB", "1.5"),
+            ("SCHEDULER_POPULAR_SPEAKER_WEIGHT", "NaN"),
+            ("EVENT_DATE", "17/10/2026"),
+            ("SCHEDULER_TIME_BUDGET_MS", "60000"),
+            ("SCHEDULER_MAX_ITERS", "0"),
+            ("SCHEDULER_NORMALIZATION", "zscore"),
+            ("LOG_FORMAT", "plain"),
+        ])
+            .unwrap_err();
+
+        let names: Vec<&str> = err.0.iter().map(|problem| problem.split_whitespace().next().unwrap()).collect();
+        assert_eq!(names, vec!["SCHEDULING_METHOD", "MIN_VOTES_TO_SCHEDULE", "SCHEDULER_POPULAR_SPEAKER_WEIGHT", "SCHEDULER_RANDOM_MOVE_PROB", "SCHEDULER_MAX_ITERS", "SCHEDULER_NORMALIZATION", "EVENT_DATE", "LOG_FORMAT"]);
+        assert!(err.to_string().contains("MIN_VOTES_TO_SCHEDULE must be a whole number of at least 0, got `-1`"));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::config::AppState;
+use crate::middleware::auth::{AuthInfo, AuthSessionLayer};
+use crate::models::account_model::{
+    delete_account, vote_weight_set, AccountErr, AccountError, CurrentUser, DeleteAccountRequest, VoteWeight,
+};
+use crate::models::auth_model::Credentials;
+use crate::types::ApiStatusCode;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use axum_macros::debug_handler;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/me",
+    responses(
+        (status = 200, description = "The logged in user, or only `is_authenticated: false` when nobody is logged in", body = CurrentUser)
+    )
+)]
+#[debug_handler]
+/// Retrieves the current user
+///
+/// This function is a handler for the route `GET /api/v1/me`. It returns the ID, email, name and
+/// permissions of the logged in user, so a UI can decide what to show when it loads. It doesn't
+/// require a login, when nobody is logged in only `is_authenticated: false` is returned.
+///
+/// # Parameters
+/// - `auth_session` - The authentication session of the user
+/// - `auth_info` - The permissions of the user
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the `CurrentUser`.
+pub(crate) async fn current_user(
+    auth_session: AuthSessionLayer,
+    Extension(auth_info): Extension<AuthInfo>,
+) -> Response {
+    Json(CurrentUser::new(auth_session.user.as_ref(), &auth_info)).into_response()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/me",
+    request_body(
+        content = DeleteAccountRequest,
+        description = "Current password and optional deletion strategy"
+    ),
+    responses(
+        (status = 204, description = "Account deleted", body = ()),
+        (status = 401, description = "Incorrect password", body = AccountError),
+        (status = 409, description = "Account can't be deleted", body = AccountError),
+        (status = 500, description = "Internal server error", body = AccountError)
+    )
+)]
+#[debug_handler]
+/// Deletes the current user's account
+///
+/// This function is a handler for the route `DELETE /api/v1/me`. It re-checks the user's password
+/// and then deletes their account. The user's sessions are deleted or reassigned to the anonymous
+/// account depending on the strategy in the request, falling back to `ACCOUNT_DELETION_STRATEGY`.
+/// The user is logged out afterwards.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `auth_session` - The authentication session of the user
+/// - `request` - The user's password and optional deletion strategy
+///
+/// # Returns
+/// `Response` with a status code of 204 No Content if the account was deleted.
+///
+/// # Errors
+/// If the password is incorrect an account error response with a status code of 401 Unauthorized
+/// is returned. If the account is the anonymous account a 409 Conflict is returned, any other
+/// failure returns a 500 Internal Server Error.
+pub async fn delete_current_user(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    mut auth_session: AuthSessionLayer,
+    Json(request): Json<DeleteAccountRequest>,
+) -> Response {
+    let Some(user) = auth_session.user.clone() else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    let creds = Credentials {
+        email: user.email.clone(),
+        password: request.password,
+    };
+    match auth_session.authenticate(creds).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return AccountError::response(
+                ApiStatusCode::from(StatusCode::UNAUTHORIZED),
+                Box::new(AccountErr::InvalidPassword),
+            );
+        }
+        Err(e) => {
+            return AccountError::response(
+                ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR),
+                Box::new(e),
+            );
+        }
+    }
+
+    let delete_result = {
+        let app_state_lock = app_state.read().await;
+        let strategy = request.strategy.unwrap_or(app_state_lock.config.account_deletion_strategy);
+        let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+        delete_account(write_lock, user.id, strategy, &app_state_lock.config.anonymous_user_email)
+            .await
+            .map_err(|e| match e.downcast_ref::<AccountErr>() {
+                Some(AccountErr::AnonymousAccount) => (StatusCode::CONFLICT, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            })
+    };
+
+    match delete_result {
+        Ok(()) => {
+            if let Err(e) = auth_session.logout().await {
+                tracing::error!("Failed to log out deleted user {}: {:?}", user.id, e);
+            }
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err((status, error)) => AccountError::response(ApiStatusCode::from(status), error.into()),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/{id}/vote_weight",
+    request_body(
+        content = VoteWeight,
+        description = "What each of the user's votes counts as"
+    ),
+    responses(
+        (status = 200, description = "The user's new vote weight", body = VoteWeight),
+        (status = 400, description = "Negative vote weight", body = AccountError),
+        (status = 404, description = "User not found", body = AccountError),
+        (status = 500, description = "Internal server error", body = AccountError)
+    ),
+    params(
+        ("id" = i32, Path, description = "User id")
+    )
+)]
+#[debug_handler]
+/// Sets how much a user's votes count for
+///
+/// This function is a handler for the route `PUT /api/v1/users/{id}/vote_weight`. The scheduler
+/// ranks sessions by the sum of their voters' weights, so program committee members can be given
+/// a weight above the default of 1.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `user_id` - The id of the user
+/// - `request` - The user's new vote weight
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the user's new vote weight.
+///
+/// # Errors
+/// If the weight is negative an account error response with a status code of 400 Bad Request is
+/// returned, and if the user doesn't exist a 404 Not Found. Any other failure returns a 500
+/// Internal Server Error.
+pub async fn set_vote_weight(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(user_id): Path<i32>,
+    Json(request): Json<VoteWeight>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match vote_weight_set(write_lock, user_id, request.vote_weight).await {
+        Ok(vote_weight) => Json(vote_weight).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<AccountErr>() {
+                Some(AccountErr::NegativeVoteWeight(_)) => StatusCode::BAD_REQUEST,
+                Some(AccountErr::DoesNotExist(_)) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            AccountError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
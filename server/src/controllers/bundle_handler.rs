@@ -0,0 +1,120 @@
+use crate::config::AppState;
+use crate::middleware::auth::AuthInfo;
+use crate::models::auth_model::Permission;
+use crate::models::bundle_model::{
+    bundle_export, bundle_import, BundleErr, BundleError, EventBundle, ExportBundleQuery, ImportBundleQuery, ImportSummary,
+};
+use crate::models::schedule_model::{schedule_unlocked, ScheduleError};
+use crate::types::ApiStatusCode;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use axum_macros::debug_handler;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/export/bundle",
+    responses(
+        (status = 200, description = "All event data", body = EventBundle),
+        (status = 500, description = "Internal server error", body = BundleError),
+    ),
+    params(
+        ("anonymize" = Option<bool>, Query, description = "Replace speaker names and emails with pseudonyms, defaults to `false` for admins and is always `true` for staff")
+    )
+)]
+#[debug_handler]
+/// Exports all event data as a single bundle
+///
+/// This function is a handler for the route `GET /api/v1/export/bundle`. It returns the rooms,
+/// timeslots, sessions, tags, vote counts and the current schedule in one versioned JSON document
+/// that can be kept as a backup or imported into another environment.
+///
+/// Speaker names and emails are replaced with pseudonyms such as "Speaker 1" when `anonymize` is
+/// set. Only admins can export them, so exports made by staff are always anonymized.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `auth_info` - The permissions of the user exporting the bundle
+/// - `export_query` - Whether to anonymize the speakers
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the bundle.
+///
+/// # Errors
+/// If an error occurs while reading the event data, a bundle error response with a status code of
+/// 500 Internal Server Error is returned.
+pub(crate) async fn export_bundle(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Extension(auth_info): Extension<AuthInfo>,
+    Query(export_query): Query<ExportBundleQuery>,
+) -> Response {
+    let is_admin = auth_info.permissions.contains(&Permission::from("superuser"));
+    let anonymize = !is_admin || export_query.anonymize.unwrap_or(false);
+
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match bundle_export(read_lock, anonymize).await {
+        Ok(bundle) => Json(bundle).into_response(),
+        Err(e) => BundleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/import/bundle",
+    request_body(
+        content = EventBundle,
+        description = "Bundle produced by the export endpoint"
+    ),
+    responses(
+        (status = 200, description = "Bundle imported", body = ImportSummary),
+        (status = 400, description = "Unsupported version or invalid bundle", body = BundleError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
+        (status = 500, description = "Internal server error", body = BundleError),
+    ),
+    params(
+        ("mode" = Option<String>, Query, description = "`merge` (default) to add to the existing data or `replace` to remove it first")
+    )
+)]
+#[debug_handler]
+/// Imports an event bundle
+///
+/// This function is a handler for the route `POST /api/v1/import/bundle?mode=replace|merge`. It
+/// recreates the rooms, timeslots, sessions, tags and schedule from a bundle exported by
+/// `GET /api/v1/export/bundle`, so an event can be moved between environments.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `import_query` - Whether to merge with or replace the existing data
+/// - `bundle` - The bundle to import
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing how much was imported.
+///
+/// # Errors
+/// If the bundle version isn't supported, the bundle is inconsistent or one of its speakers has no
+/// account here a bundle error response with a status code of 400 Bad Request is returned. If the
+/// schedule is locked a 423 error is returned, otherwise a 500 Internal Server Error is returned.
+/// Nothing is imported when an error occurs.
+pub async fn import_bundle(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Query(import_query): Query<ImportBundleQuery>,
+    Json(bundle): Json<EventBundle>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(write_lock).await {
+        return ScheduleError::locked_response(e);
+    }
+
+    match bundle_import(write_lock, &bundle, import_query.mode.unwrap_or_default()).await {
+        Ok(summary) => Json(summary).into_response(),
+        Err(e @ (BundleErr::VersionMismatch(_) | BundleErr::InvalidBundle(_))) => {
+            BundleError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), Box::new(e))
+        }
+        Err(e) => BundleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
@@ -0,0 +1,68 @@
+use crate::config::AppState;
+use crate::models::embed_model::{embed_schedule_get, embed_token_matches, EmbedFormat, EmbedQuery, EmbedSchedule};
+use askama::Template;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use axum_macros::debug_handler;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// How long browsers and proxies may cache the embedded schedule, in seconds.
+const EMBED_MAX_AGE_SECS: u32 = 30;
+
+#[derive(Template, Debug)]
+#[template(path = "embed_schedule.html")]
+pub(crate) struct EmbedScheduleTemplate {
+    pub(crate) schedule: EmbedSchedule,
+}
+
+#[debug_handler]
+/// Embedded schedule handler
+///
+/// This function is a handler for the route `GET /embed/schedule?token=...`. It returns a
+/// read-only grid of the schedule, without any edit controls, for event websites to embed. It
+/// doesn't need a login or the unconference password, instead the token must match `EMBED_TOKEN`.
+/// The grid only holds rooms, times and session titles.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `embed_query` - The embed token and the format to return, HTML by default or JSON
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and the schedule as an HTML page or JSON.
+///
+/// # Errors
+/// If no `EMBED_TOKEN` is configured a 404 Not Found is returned, if the token is missing or wrong
+/// a 403 Forbidden, and if the schedule can't be loaded or rendered a 500 Internal Server Error.
+pub(crate) async fn embed_schedule(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Query(embed_query): Query<EmbedQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let Some(expected_token) = &app_state_lock.config.embed_token else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !embed_query.token.is_some_and(|token| embed_token_matches(expected_token, &token)) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    let schedule = match embed_schedule_get(read_lock).await {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            tracing::error!("Failed to load the embedded schedule: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let cache_control = [(header::CACHE_CONTROL, format!("public, max-age={EMBED_MAX_AGE_SECS}"))];
+    match embed_query.format.unwrap_or_default() {
+        EmbedFormat::Json => (cache_control, Json(schedule)).into_response(),
+        EmbedFormat::Html => match (EmbedScheduleTemplate { schedule }).render() {
+            Ok(html) => (cache_control, Html(html)).into_response(),
+            Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        },
+    }
+}
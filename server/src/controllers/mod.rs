@@ -1,9 +1,15 @@
+pub mod account_handler;
+pub mod bundle_handler;
+pub mod embed_handler;
 pub mod login_handler;
 pub mod room_handler;
 pub mod schedule_handler;
+pub mod schedule_snapshot_handler;
 pub mod site_handler;
 pub mod timeslot_handler;
 pub mod sessions_handler;
+pub mod settings_handler;
+pub mod speakers_handler;
 pub mod registration_handler;
 pub mod session_voting_handler;
 pub mod session_tags_handler;
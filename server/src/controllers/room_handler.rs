@@ -1,14 +1,17 @@
-use axum::extract::Path;
+use axum::extract::{Path, Query};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::config::AppState;
 use crate::models::room_model::{
-    room_delete, rooms_add, rooms_get, CreateRoomsForm, Room, RoomErr, RoomError,
+    room_delete, room_occupancy_get, room_schedule_get, room_stats_get, rooms_add, rooms_get, CreateRoomsForm, Room, RoomErr, RoomError, RoomOccupancy, RoomOccupancyQuery, RoomStats,
 };
+use crate::config::Config;
+use crate::models::schedule_export_model::{schedule_csv, schedule_ics, ScheduleEntry};
 use crate::types::ApiStatusCode;
-use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use axum::{extract::State, http::{header, StatusCode}, response::{IntoResponse, Response}, Json};
 use axum_macros::debug_handler;
+use chrono::Utc;
 use tracing::debug;
 
 #[utoipa::path(
@@ -46,6 +49,186 @@ pub async fn rooms(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/rooms/occupancy",
+    params(
+        ("time_slot_id" = Option<i32>, Query, description = "Timeslot to show the occupancy of, all timeslots when omitted")
+    ),
+    responses(
+        (status = 200, description = "Occupancy of each room", body = RoomOccupancy),
+        (status = 404, description = "Timeslot not found", body = RoomError),
+        (status = 500, description = "Internal server error", body = RoomError)
+    )
+)]
+#[debug_handler]
+/// Retrieves the occupancy of the rooms
+///
+/// This function is a handler for the route `GET /api/v1/rooms/occupancy`. With a `time_slot_id`
+/// it returns each room, whether it's occupied in that timeslot and by which session. Without one
+/// it returns how many timeslots each room is occupied in, to spot underused rooms.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `query` - The timeslot to show the occupancy of
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the occupancy of each room.
+///
+/// # Errors
+/// If the timeslot doesn't exist, a room error response with a status code of 404 Not Found is
+/// returned. If an error occurs while retrieving the occupancy, a room error response with a status
+/// code of 500 Internal Server Error is returned.
+pub async fn room_occupancy(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Query(query): Query<RoomOccupancyQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match room_occupancy_get(read_lock, query.time_slot_id).await {
+        Ok(occupancy) => Json(occupancy).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<RoomErr>() {
+                Some(RoomErr::TimeslotDoesNotExist(_)) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            RoomError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/rooms",
+    responses(
+        (status = 200, description = "Each room with its sessions and votes, the busiest first", body = Vec<RoomStats>),
+        (status = 500, description = "Internal server error", body = RoomError)
+    )
+)]
+#[debug_handler]
+/// Retrieves how much each room was used
+///
+/// This function is a handler for the route `GET /api/v1/stats/rooms`. It returns each room with
+/// the number of sessions scheduled in it and the total votes across those sessions, the busiest
+/// rooms first, to help plan the venue for the next event.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the stats of each room.
+///
+/// # Errors
+/// If an error occurs while retrieving the stats, a room error response with a status code of 500
+/// Internal Server Error is returned.
+pub async fn room_stats(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match room_stats_get(read_lock).await {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => RoomError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), e),
+    }
+}
+
+/// Responds with a room's schedule built by `build`, or a room error response if it can't be loaded.
+async fn room_schedule_response(
+    app_state: Arc<RwLock<AppState>>,
+    room_id: i32,
+    content_type: &'static str,
+    build: impl FnOnce(&[ScheduleEntry], &Config) -> String,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match room_schedule_get(read_lock, room_id).await {
+        Ok(entries) => ([(header::CONTENT_TYPE, content_type)], build(&entries, &app_state_lock.config)).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<RoomErr>() {
+                Some(RoomErr::DoesNotExist(_)) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            RoomError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/rooms/{id}/schedule.ics",
+    params(
+        ("id" = i32, Path, description = "Room id")
+    ),
+    responses(
+        (status = 200, description = "The room's schedule as an iCalendar document", body = String, content_type = "text/calendar"),
+        (status = 404, description = "Room not found", body = RoomError),
+        (status = 500, description = "Internal server error", body = RoomError)
+    )
+)]
+#[debug_handler]
+/// Exports a room's schedule as an iCalendar document
+///
+/// This function is a handler for the route `GET /api/v1/rooms/{id}/schedule.ics`. It returns an
+/// event for each session scheduled in the room, on the day set by `EVENT_DATE`, so a room host can
+/// import just their room's agenda.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `room_id` - The ID of the room
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and the calendar, which has no events if nothing is
+/// scheduled in the room.
+///
+/// # Errors
+/// If the room doesn't exist, a room error response with a status code of 404 Not Found is
+/// returned. If an error occurs while retrieving the schedule, a room error response with a status
+/// code of 500 Internal Server Error is returned.
+pub async fn room_schedule_ics(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(room_id): Path<i32>,
+) -> Response {
+    room_schedule_response(app_state, room_id, "text/calendar; charset=utf-8", |entries, config| {
+        schedule_ics(entries, config.event_date(), Utc::now())
+    })
+        .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/rooms/{id}/schedule.csv",
+    params(
+        ("id" = i32, Path, description = "Room id")
+    ),
+    responses(
+        (status = 200, description = "The room's schedule as CSV", body = String, content_type = "text/csv"),
+        (status = 404, description = "Room not found", body = RoomError),
+        (status = 500, description = "Internal server error", body = RoomError)
+    )
+)]
+#[debug_handler]
+/// Exports a room's schedule as CSV
+///
+/// This function is a handler for the route `GET /api/v1/rooms/{id}/schedule.csv`. It returns a row
+/// for each session scheduled in the room, ordered by start time.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `room_id` - The ID of the room
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and the CSV, which is just the header row if nothing is
+/// scheduled in the room.
+///
+/// # Errors
+/// If the room doesn't exist, a room error response with a status code of 404 Not Found is
+/// returned. If an error occurs while retrieving the schedule, a room error response with a status
+/// code of 500 Internal Server Error is returned.
+pub async fn room_schedule_csv(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(room_id): Path<i32>,
+) -> Response {
+    room_schedule_response(app_state, room_id, "text/csv; charset=utf-8", |entries, _| schedule_csv(entries)).await
+}
+
 #[utoipa::path(
     post,
     path = "/api/v1/rooms/add",
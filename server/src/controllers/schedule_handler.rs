@@ -2,9 +2,17 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::config::AppState;
-use crate::models::schedule_model::{add_session, remove_session, schedule_clear, schedule_generate, AddSessionReq, RemoveSessionReq, ScheduleErr, ScheduleError};
+use crate::models::schedule_model::{add_session, grid_commit, grid_validate, last_schedule_run_get, remove_session, schedule_clear, schedule_generate, schedule_generate_with_progress, schedule_integrity_check, schedule_setup_get, schedule_suggestions_get, schedule_unlocked, unschedule_session, AddSessionReq, GridValidation, ProposedAssignment, RemoveSessionReq, Schedule, ScheduleErr, GenerationProgress, ScheduleError, ScheduleIntegrityReport, ScheduleRun, ScheduleSuggestion};
+use crate::models::schedule_export_model::{schedule_changes_get, schedule_entries_get, ScheduleChange, ScheduleChangesQuery, ScheduleGrid, ScheduleGridQuery};
+use crate::models::sessions_model::SessionErr;
+use crate::models::settings_model::{schedule_locked_set, ScheduleLock};
+use crate::models::timeslot_assignment_model::{free_cells_get, FreeCell};
 use crate::types::ApiStatusCode;
-use axum::{debug_handler, extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::{debug_handler, extract::{Path, Query, State}, http::StatusCode, response::{IntoResponse, Response}, Json};
+use scheduler::{ProgressCallback, ScoreBreakdown};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
 
 #[utoipa::path(
     post,
@@ -14,6 +22,7 @@ use axum::{debug_handler, extract::State, http::StatusCode, response::{IntoRespo
         (status = 400, description = "Bad request", body = ScheduleError),
         (status = 404, description = "Schedule not found", body = ScheduleError),
         (status = 422, description = "Unprocessable entity", body = ScheduleError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
     )
 )]
 #[debug_handler]
@@ -30,12 +39,17 @@ use axum::{debug_handler, extract::State, http::StatusCode, response::{IntoRespo
 /// error response if the schedule could not be generated.
 ///
 /// # Errors
+/// If the schedule is locked, a schedule error response with a status code of 423 Locked is
+/// returned.
 /// If an error occurs while generating the schedule, a schedule error response with a status code
 /// of 400 Bad Request is returned.
 pub async fn generate(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
     let app_state_lock = app_state.read().await;
     let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
-    let res = schedule_generate(read_lock).await;
+    if let Err(e) = schedule_unlocked(read_lock).await {
+        return ScheduleError::locked_response(e);
+    }
+    let res = schedule_generate(read_lock, &app_state_lock.config.scheduler).await;
     match res {
         Ok(schedule) => Json(schedule).into_response(),
         Err(e) => {
@@ -44,14 +58,82 @@ pub async fn generate(State(app_state): State<Arc<RwLock<AppState>>>) -> Respons
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/sse/schedule-generate",
+    responses(
+        (status = 200, description = "Server-sent `progress` events while the schedule is generated, followed by a `schedule` event with the generated schedule or an `error` event", content_type = "text/event-stream", body = GenerationProgress),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Generates a schedule while streaming its progress
+///
+/// This function is a handler for the route `GET /api/v1/sse/schedule-generate`. It starts
+/// generating a schedule and streams server-sent events as the scheduler runs: a `progress` event
+/// after each restart with the restart number and best score so far, then a final `schedule` event
+/// with the committed schedule, or an `error` event if it couldn't be generated. Progress is only
+/// reported by the local search scheduling method.
+///
+/// The schedule keeps generating and is committed even if the client disconnects.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a stream of server-sent events.
+///
+/// # Errors
+/// If the schedule is locked, a schedule error response with a status code of 423 Locked is
+/// returned before anything is generated.
+pub async fn generate_with_progress(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let (db_pool, config) = {
+        let app_state_lock = app_state.read().await;
+        let db_pool = app_state_lock.unconf_data.read().await.unconf_db.clone();
+        (db_pool, app_state_lock.config.scheduler.clone())
+    };
+    if let Err(e) = schedule_unlocked(&db_pool).await {
+        return ScheduleError::locked_response(e);
+    }
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let progress_callback = ProgressCallback::new({
+        let sender = sender.clone();
+        move |progress| {
+            // Nothing to do if the client has gone away
+            let _ = sender.send(Event::default().event("progress").json_data(GenerationProgress::from(progress)));
+        }
+    });
+
+    tokio::spawn(async move {
+        let event = match schedule_generate_with_progress(&db_pool, &config, Some(progress_callback)).await {
+            Ok(schedule) => Event::default().event("schedule").json_data(schedule),
+            Err(e) => Event::default().event("error").json_data(ScheduleError {
+                status: ApiStatusCode::from(StatusCode::BAD_REQUEST),
+                error: e.to_string(),
+            }),
+        };
+        // The schedule is written after the response has started, too late for the score cache
+        // middleware to notice
+        app_state.read().await.score_cache.invalidate();
+        let _ = sender.send(event);
+    });
+
+    Sse::new(UnboundedReceiverStream::new(receiver))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
 #[utoipa::path(
     post,
     path = "/api/v1/schedules/add_session",
     responses(
         (status = 200, description = "Generating schedule", body = ()),
         (status = 400, description = "Bad request", body = ScheduleError),
-        (status = 404, description = "Schedule not found", body = ScheduleError),
+        (status = 404, description = "Session not found", body = ScheduleError),
         (status = 422, description = "Unprocessable entity", body = ScheduleError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
     )
 )]
 #[debug_handler]
@@ -68,11 +150,18 @@ pub async fn generate(State(app_state): State<Arc<RwLock<AppState>>>) -> Respons
 /// error response if the schedule could not be generated.
 ///
 /// # Errors
+/// If the schedule is locked, a schedule error response with a status code of 423 Locked is
+/// returned.
+/// If the session doesn't exist, a schedule error response with a status code of 404 Not Found is
+/// returned.
 /// If an error occurs while generating the schedule, a schedule error response with a status code
 /// of 400 Bad Request is returned.
 pub async fn add_session_to_schedule(State(app_state): State<Arc<RwLock<AppState>>>, Json(session_req): Json<AddSessionReq>) -> Response {
     let app_state_lock = app_state.read().await;
     let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(read_lock).await {
+        return ScheduleError::locked_response(e);
+    }
     let res = add_session(read_lock, session_req.session_id).await;
     match res {
         Ok(schedule) => Json(schedule).into_response(),
@@ -88,6 +177,12 @@ pub async fn add_session_to_schedule(State(app_state): State<Arc<RwLock<AppState
                 Box::new(res.unwrap_err()),
             )
         },
+        Err(ScheduleErr::SessionError(SessionErr::DoesNotExist(_))) => {
+            ScheduleError::response(
+                ApiStatusCode::from(StatusCode::NOT_FOUND),
+                Box::new(res.unwrap_err()),
+            )
+        },
         Err(e) => {
             ScheduleError::response(
                 ApiStatusCode::from(StatusCode::BAD_REQUEST),
@@ -105,6 +200,7 @@ pub async fn add_session_to_schedule(State(app_state): State<Arc<RwLock<AppState
         (status = 400, description = "Bad request", body = ScheduleError),
         (status = 404, description = "Schedule not found", body = ScheduleError),
         (status = 422, description = "Unprocessable entity", body = ScheduleError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
     )
 )]
 #[debug_handler]
@@ -122,11 +218,16 @@ pub async fn add_session_to_schedule(State(app_state): State<Arc<RwLock<AppState
 /// error response if the session could not be removed
 ///
 /// # Errors
+/// If the schedule is locked, a schedule error response with a status code of 423 Locked is
+/// returned.
 /// If an error occurs while removing the session, a schedule error response with a status code
 /// of 400 Bad Request is returned.
 pub async fn remove_session_from_schedule(State(app_state): State<Arc<RwLock<AppState>>>, Json(session_req): Json<RemoveSessionReq>) -> Response {
     let app_state_lock = app_state.read().await;
     let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(read_lock).await {
+        return ScheduleError::locked_response(e);
+    }
     let res = remove_session(read_lock, session_req.session_id, session_req.timeslot_id, session_req.room_id).await;
     match res {
         Ok(schedule) => Json(schedule).into_response(),
@@ -139,6 +240,64 @@ pub async fn remove_session_from_schedule(State(app_state): State<Arc<RwLock<App
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/schedules/session/{session_id}",
+    responses(
+        (status = 200, description = "Unscheduled the session", body = Schedule),
+        (status = 404, description = "Session isn't scheduled", body = ScheduleError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    ),
+    params(
+        ("session_id" = i32, Path, description = "The ID of the session to unschedule"),
+    )
+)]
+#[debug_handler]
+/// Unschedules a session
+///
+/// This function is a handler for the route `DELETE /api/v1/schedules/session/{session_id}`. It
+/// removes a session from the schedule without the caller needing to know which room and timeslot
+/// it is in.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `session_id` - The ID of the session to unschedule
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and the schedule if the session was unscheduled or an
+/// error response if it could not be unscheduled
+///
+/// # Errors
+/// If the schedule is locked, a schedule error response with a status code of 423 Locked is
+/// returned.
+/// If the session isn't scheduled, a schedule error response with a status code of 404 Not Found
+/// is returned.
+/// If an error occurs while unscheduling the session, a schedule error response with a status code
+/// of 500 Internal Server Error is returned.
+pub async fn unschedule_session_from_schedule(State(app_state): State<Arc<RwLock<AppState>>>, Path(session_id): Path<i32>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(read_lock).await {
+        return ScheduleError::locked_response(e);
+    }
+    match unschedule_session(read_lock, session_id).await {
+        Ok(schedule) => Json(schedule).into_response(),
+        Err(e @ ScheduleErr::DoesNotExist(_)) => {
+            ScheduleError::response(
+                ApiStatusCode::from(StatusCode::NOT_FOUND),
+                Box::new(e),
+            )
+        },
+        Err(e) => {
+            ScheduleError::response(
+                ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR),
+                Box::new(e),
+            )
+        }
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/api/v1/schedules/clear",
@@ -147,6 +306,7 @@ pub async fn remove_session_from_schedule(State(app_state): State<Arc<RwLock<App
         (status = 400, description = "Bad request", body = ScheduleError),
         (status = 404, description = "Schedule not found", body = ScheduleError),
         (status = 422, description = "Unprocessable entity", body = ScheduleError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
     )
 )]
 #[debug_handler]
@@ -163,14 +323,500 @@ pub async fn remove_session_from_schedule(State(app_state): State<Arc<RwLock<App
 /// error response if the schedule could not be cleared.
 ///
 /// # Errors
+/// If the schedule is locked, a schedule error response with a status code of 423 Locked is
+/// returned.
 /// If an error occurs while clearing the schedule, a schedule error response with a status code
 /// of 400 Bad Request is returned.
 pub async fn clear(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
     let app_state_lock = app_state.read().await;
     let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(read_lock).await {
+        return ScheduleError::locked_response(e);
+    }
     let res = schedule_clear(read_lock).await;
     match res {
         Ok(schedule) => Json(schedule).into_response(),
         Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), e),
     }
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/score",
+    responses(
+        (status = 200, description = "Score of the current schedule", body = ScoreBreakdown),
+        (status = 400, description = "Bad request", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Scores the current schedule
+///
+/// This function is a handler for the route `GET /api/v1/schedules/score`. It scores the schedule
+/// as it currently is with the same penalties the scheduler uses, without changing it, so manual
+/// edits can be compared against a generated schedule.
+///
+/// The score is cached until the next change, so it is cheap to poll.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the score breakdown.
+///
+/// # Errors
+/// If an error occurs while scoring the schedule, a schedule error response with a status code of
+/// 400 Bad Request is returned.
+pub async fn score(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match app_state_lock.score_cache.get_or_compute(read_lock, &app_state_lock.config.scheduler).await {
+        Ok(score) => Json(score).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/grid",
+    params(
+        ("tag" = Option<i32>, Query, description = "Only include the sessions with this tag ID")
+    ),
+    responses(
+        (status = 200, description = "The scheduled sessions with their room and timeslot, and what still has to be set up", body = ScheduleGrid),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Retrieves the schedule grid
+///
+/// This function is a handler for the route `GET /api/v1/schedules/grid`. It lists every scheduled
+/// session with its room and timeslot, ordered by start time and room. Given a tag, only the
+/// sessions with that tag are listed so a themed track can be shown on its own. When there are no
+/// rooms or timeslots yet `has_schedule` is `false` and `needs_setup` says what to add.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `query` - The tag to filter the sessions by
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the `ScheduleGrid`, with no
+/// entries if no scheduled session has the tag.
+///
+/// # Errors
+/// If an error occurs while fetching the schedule, a schedule error response with a status code of
+/// 500 Internal Server Error is returned.
+pub async fn schedule_grid(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Query(query): Query<ScheduleGridQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    let needs_setup = match schedule_setup_get(read_lock).await {
+        Ok(needs_setup) => needs_setup,
+        Err(e) => return ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    };
+    match schedule_entries_get(read_lock, None, query.tag).await {
+        Ok(entries) => Json(ScheduleGrid { has_schedule: needs_setup.is_empty(), needs_setup, entries }).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/free-cells",
+    responses(
+        (status = 200, description = "The rooms and timeslots no session is scheduled in", body = Vec<FreeCell>),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Retrieves the free cells of the schedule
+///
+/// This function is a handler for the route `GET /api/v1/schedules/free-cells`. It lists every room
+/// and timeslot a session can still be placed in, ordered by start time and room, so organizers
+/// placing sessions by hand can see where there's space. Rooms a timeslot's `max_open_rooms` keeps
+/// closed aren't listed.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the free cells, an empty list
+/// if the schedule is full.
+///
+/// # Errors
+/// If an error occurs while fetching the free cells, a schedule error response with a status code
+/// of 500 Internal Server Error is returned.
+pub async fn free_cells(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match free_cells_get(read_lock).await {
+        Ok(cells) => Json(cells).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/changes",
+    params(
+        ("since" = String, Query, description = "RFC 3339 timestamp, only list the assignments created or changed after it")
+    ),
+    responses(
+        (status = 200, description = "The assignments created, changed or removed after the timestamp", body = Vec<ScheduleChange>),
+        (status = 400, description = "Missing or invalid timestamp"),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Retrieves the schedule changes since a point in time
+///
+/// This function is a handler for the route `GET /api/v1/schedules/changes`. It lists the timeslot
+/// assignments created, changed or removed after `since`, so clients that can't use server-sent
+/// events can poll with the largest `updated_at` they've seen and only fetch the changes.
+/// Timestamps have microsecond precision. Removed assignments have `removed` set.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `query` - The timestamp to list the changes after
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the changed assignments
+/// ordered by `updated_at`, an empty list if nothing changed.
+///
+/// # Errors
+/// If `since` is missing or isn't an RFC 3339 timestamp, a 400 Bad Request is returned.
+/// If an error occurs while fetching the changes, a schedule error response with a status code of
+/// 500 Internal Server Error is returned.
+pub async fn schedule_changes(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Query(query): Query<ScheduleChangesQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match schedule_changes_get(read_lock, query.since).await {
+        Ok(changes) => Json(changes).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/last-run",
+    responses(
+        (status = 200, description = "Diagnostics of the last schedule generation run", body = ScheduleRun),
+        (status = 404, description = "No schedule has been generated yet", body = ScheduleError),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Retrieves the diagnostics of the last schedule generation run
+///
+/// This function is a handler for the route `GET /api/v1/schedules/last-run`. It returns how many
+/// restarts and iterations the scheduler ran, how long it took, the starting and final scores, and
+/// how many sessions were left unscheduled the last time a schedule was generated.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the run diagnostics.
+///
+/// # Errors
+/// If no schedule has been generated yet, a schedule error response with a status code of 404 Not
+/// Found is returned.
+/// If an error occurs while fetching the run, a schedule error response with a status code of 500
+/// Internal Server Error is returned.
+pub async fn last_run(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match last_schedule_run_get(read_lock).await {
+        Ok(run) => Json(run).into_response(),
+        Err(e @ ScheduleErr::DoesNotExist(_)) => ScheduleError::response(ApiStatusCode::from(StatusCode::NOT_FOUND), Box::new(e)),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/validate",
+    responses(
+        (status = 200, description = "Integrity report of the schedule", body = ScheduleIntegrityReport),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Checks the schedule for integrity violations
+///
+/// This function is a handler for the route `GET /api/v1/schedules/validate`. It checks the live
+/// schedule for sessions scheduled twice, double-booked speakers, assignments pointing at deleted
+/// sessions, rooms or timeslots, and over-capacity placements. This is useful after manual edits
+/// or migrations that bypassed the web UI.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the report of violations.
+///
+/// # Errors
+/// If an error occurs while checking the schedule, a schedule error response with a status code of
+/// 500 Internal Server Error is returned.
+pub async fn validate(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match schedule_integrity_check(read_lock).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/schedules/validate",
+    request_body(
+        content = Vec<ProposedAssignment>,
+        description = "Every cell of the proposed schedule"
+    ),
+    responses(
+        (status = 200, description = "Violations and score of the proposed grid", body = GridValidation),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Checks a proposed grid before it is saved
+///
+/// This function is a handler for the route `POST /api/v1/schedules/validate`. It runs the same
+/// checks as `GET /api/v1/schedules/validate` over a whole grid edited by a client, plus rooms
+/// holding two sessions at once and unknown IDs, and scores it as if it replaced the schedule. The
+/// schedule isn't changed, so problems can be shown before the grid is saved.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `grid` - Every cell of the proposed schedule
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the violations, which refer
+/// to the cells by their position in the grid, and the score.
+///
+/// # Errors
+/// If an error occurs while checking or scoring the grid, a schedule error response with a status
+/// code of 500 Internal Server Error is returned.
+pub async fn validate_grid(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(grid): Json<Vec<ProposedAssignment>>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match grid_validate(read_lock, &app_state_lock.config.scheduler, &grid).await {
+        Ok(validation) => Json(validation).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/schedules/grid",
+    request_body(
+        content = Vec<ProposedAssignment>,
+        description = "Every cell of the new schedule"
+    ),
+    responses(
+        (status = 200, description = "The schedule was replaced", body = ScheduleIntegrityReport),
+        (status = 422, description = "The grid has violations, the schedule wasn't changed", body = ScheduleIntegrityReport),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Replaces the schedule with a proposed grid
+///
+/// This function is a handler for the route `PUT /api/v1/schedules/grid`. It checks a whole grid
+/// edited by a client the same way as `POST /api/v1/schedules/validate` and, if there are no
+/// violations, replaces every assignment with the grid in one transaction, so the grid can be saved
+/// in one go instead of a move at a time.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `grid` - Every cell of the new schedule
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the report of the grid.
+///
+/// # Errors
+/// If the schedule is locked, a schedule error response with a status code of 423 Locked is
+/// returned. If the grid has violations the schedule isn't changed, and a status code of 422
+/// Unprocessable Entity is returned with the report of violations, which refer to the cells by
+/// their position in the grid. If an error occurs while saving the grid, a schedule error response
+/// with a status code of 500 Internal Server Error is returned.
+pub async fn commit_grid(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(grid): Json<Vec<ProposedAssignment>>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(write_lock).await {
+        return ScheduleError::locked_response(e);
+    }
+    match grid_commit(write_lock, &grid).await {
+        Ok(report) if report.valid => Json(report).into_response(),
+        Ok(report) => (StatusCode::UNPROCESSABLE_ENTITY, Json(report)).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/suggestions",
+    responses(
+        (status = 200, description = "Unscheduled sessions suggested for the free slots", body = [ScheduleSuggestion]),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Suggests sessions to add to the schedule
+///
+/// This function is a handler for the route `GET /api/v1/schedules/suggestions`. It pairs the most
+/// voted unscheduled sessions with the free slots in the schedule, proposing a timeslot and room
+/// for each. Sessions are only proposed for timeslots they are available in and that don't hold a
+/// session they conflict with. It doesn't change the schedule.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the suggestions, at most one
+/// per free slot.
+///
+/// # Errors
+/// If an error occurs while loading the schedule, a schedule error response with a status code of
+/// 500 Internal Server Error is returned.
+pub async fn suggestions(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match schedule_suggestions_get(read_lock, app_state_lock.config.scheduler.keynotes).await {
+        Ok(suggestions) => Json(suggestions).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/schedules/lock",
+    responses(
+        (status = 200, description = "Locked schedule", body = ScheduleLock),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Locks the schedule
+///
+/// This function is a handler for the route `POST /api/v1/schedules/lock`. It locks the schedule so
+/// it can't be moved, swapped, added to, removed from, cleared, or regenerated until it is unlocked.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the lock state.
+///
+/// # Errors
+/// If an error occurs while locking the schedule, a schedule error response with a status
+/// code of 500 Internal Server Error is returned.
+pub async fn lock_schedule(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match schedule_locked_set(write_lock, true).await {
+        Ok(lock) => Json(lock).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/schedules/unlock",
+    responses(
+        (status = 200, description = "Unlocked schedule", body = ScheduleLock),
+        (status = 500, description = "Internal server error", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Unlocks the schedule
+///
+/// This function is a handler for the route `POST /api/v1/schedules/unlock`. It unlocks the schedule
+/// so it can be edited again.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the lock state.
+///
+/// # Errors
+/// If an error occurs while unlocking the schedule, a schedule error response with a status
+/// code of 500 Internal Server Error is returned.
+pub async fn unlock_schedule(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match schedule_locked_set(write_lock, false).await {
+        Ok(lock) => Json(lock).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::controllers::timeslot_handler::update_timeslot;
+    use crate::models::timeslot_model::TimeslotUpdateRequest;
+    use crate::test_support::{app_state, insert_room, insert_session, insert_timeslot, insert_user};
+    use axum::extract::Path;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn locked_schedule_rejects_move_until_unlocked(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let session_id = insert_session(&pool, user_id, "Talk").await;
+        let first_room_id = insert_room(&pool, "First", "Here", 10).await;
+        let second_room_id = insert_room(&pool, "Second", "There", 10).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        sqlx::query("INSERT INTO timeslot_assignments (time_slot_id, session_id, room_id) VALUES ($1, $2, $3)")
+            .bind(time_slot_id)
+            .bind(session_id)
+            .bind(first_room_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let app_state = app_state(&pool, Config::default());
+        let move_to_second_room = || Json(TimeslotUpdateRequest {
+            start_time: "09:00".to_string(),
+            end_time: "10:00".to_string(),
+            session_id,
+            room_id: second_room_id,
+            old_room_id: first_room_id,
+        });
+        let session_room = || async {
+            sqlx::query_scalar::<_, i32>("SELECT room_id FROM timeslot_assignments WHERE session_id = $1")
+                .bind(session_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+
+        let response = lock_schedule(State(app_state.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = update_timeslot(State(app_state.clone()), Path(time_slot_id), move_to_second_room()).await;
+        assert_eq!(response.status(), StatusCode::LOCKED);
+        assert_eq!(session_room().await, first_room_id);
+
+        let response = unlock_schedule(State(app_state.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = update_timeslot(State(app_state.clone()), Path(time_slot_id), move_to_second_room()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(session_room().await, second_room_id);
+    }
+}
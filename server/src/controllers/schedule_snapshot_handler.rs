@@ -0,0 +1,177 @@
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::config::AppState;
+use crate::models::schedule_model::{schedule_unlocked, Schedule, ScheduleErr, ScheduleError};
+use crate::models::schedule_snapshot_model::{
+    snapshot_create, snapshot_diff, snapshot_restore, snapshots_get, CreateSnapshotReq, ScheduleDiff,
+    ScheduleSnapshotSummary, SnapshotDiffQuery,
+};
+use crate::types::ApiStatusCode;
+use axum::extract::{Path, Query};
+use axum::{debug_handler, extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/schedules/snapshot",
+    request_body(
+        content = CreateSnapshotReq,
+        description = "Label for the snapshot"
+    ),
+    responses(
+        (status = 201, description = "Snapshot created", body = ScheduleSnapshotSummary),
+        (status = 400, description = "Bad request", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Takes a snapshot of the schedule
+///
+/// This function is a handler for the route `POST /api/v1/schedules/snapshot`. It saves the current
+/// schedule assignments under the given label.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `snapshot_req` - The snapshot request containing the label
+///
+/// # Returns
+/// `Response` with a status code of 201 Created and the snapshot summary.
+///
+/// # Errors
+/// If an error occurs while saving the snapshot, a schedule error response with a status code of
+/// 400 Bad Request is returned.
+pub async fn create_snapshot(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(snapshot_req): Json<CreateSnapshotReq>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match snapshot_create(write_lock, &snapshot_req.label).await {
+        Ok(snapshot) => (StatusCode::CREATED, Json(snapshot)).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/snapshots",
+    responses(
+        (status = 200, description = "List schedule snapshots", body = Vec<ScheduleSnapshotSummary>),
+        (status = 400, description = "Bad request", body = ScheduleError),
+    )
+)]
+#[debug_handler]
+/// Retrieves the list of schedule snapshots
+///
+/// This function is a handler for the route `GET /api/v1/schedules/snapshots`. It lists the saved
+/// snapshots, newest first, without their assignments.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the snapshot summaries.
+///
+/// # Errors
+/// If an error occurs while retrieving the snapshots, a schedule error response with a status code
+/// of 400 Bad Request is returned.
+pub async fn snapshots(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match snapshots_get(read_lock).await {
+        Ok(snapshots) => Json(snapshots).into_response(),
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/schedules/restore/{snapshot_id}",
+    responses(
+        (status = 200, description = "Restored schedule", body = Schedule),
+        (status = 400, description = "Bad request", body = ScheduleError),
+        (status = 404, description = "Snapshot not found", body = ScheduleError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
+    ),
+    params(
+        ("snapshot_id" = i32, Path, description = "Snapshot id")
+    )
+)]
+#[debug_handler]
+/// Restores the schedule from a snapshot
+///
+/// This function is a handler for the route `POST /api/v1/schedules/restore/{snapshot_id}`. It
+/// replaces the current schedule assignments with the ones stored in the snapshot.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `snapshot_id` - The ID of the snapshot to restore
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the restored schedule.
+///
+/// # Errors
+/// If the snapshot doesn't exist a schedule error response with a status code of 404 Not Found is
+/// returned and if the schedule is locked a 423 Locked is returned, otherwise a 400 Bad Request is
+/// returned.
+pub async fn restore_snapshot(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(snapshot_id): Path<i32>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(write_lock).await {
+        return ScheduleError::locked_response(e);
+    }
+    match snapshot_restore(write_lock, snapshot_id).await {
+        Ok(schedule) => Json(schedule).into_response(),
+        Err(e @ ScheduleErr::DoesNotExist(_)) => {
+            ScheduleError::response(ApiStatusCode::from(StatusCode::NOT_FOUND), Box::new(e))
+        }
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), Box::new(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/diff",
+    responses(
+        (status = 200, description = "Differences between the snapshots", body = ScheduleDiff),
+        (status = 400, description = "Bad request", body = ScheduleError),
+        (status = 404, description = "Snapshot not found", body = ScheduleError),
+    ),
+    params(
+        ("from" = i32, Query, description = "Id of the older snapshot"),
+        ("to" = i32, Query, description = "Id of the newer snapshot")
+    )
+)]
+#[debug_handler]
+/// Compares two schedule snapshots
+///
+/// This function is a handler for the route `GET /api/v1/schedules/diff?from={id}&to={id}`. It
+/// reports which sessions moved to a different room or timeslot, which were added, and which were
+/// removed between the two snapshots.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `diff_query` - The IDs of the snapshots to compare
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the differences.
+///
+/// # Errors
+/// If either snapshot doesn't exist a schedule error response with a status code of 404 Not Found
+/// is returned, otherwise a 400 Bad Request is returned.
+pub async fn diff_snapshots(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Query(diff_query): Query<SnapshotDiffQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match snapshot_diff(read_lock, diff_query.from, diff_query.to).await {
+        Ok(diff) => Json(diff).into_response(),
+        Err(e @ ScheduleErr::DoesNotExist(_)) => {
+            ScheduleError::response(ApiStatusCode::from(StatusCode::NOT_FOUND), Box::new(e))
+        }
+        Err(e) => ScheduleError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), Box::new(e)),
+    }
+}
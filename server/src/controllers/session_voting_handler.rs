@@ -1,10 +1,11 @@
 use crate::config::AppState;
 use crate::middleware::auth::AuthSessionLayer;
-use crate::models::session_voting_model::{decrement_vote, increment_vote, SessionVoteError};
+use crate::models::session_voting_model::{decrement_vote, increment_vote, session_voters_get, trending_sessions_get, vote_stats_get, votes_csv_get, votes_reconcile, ExportVotesQuery, SessionVoteErr, SessionVoteError, SessionVoters, SessionVotersQuery, TrendingQuery, TrendingSession, VoteStats, VoteStatsQuery, VotesReconciled, DEFAULT_TRENDING_WINDOW_MINUTES};
 use crate::types::ApiStatusCode;
 use axum::extract::Path;
+use axum::extract::Query;
 use axum::extract::State;
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use axum_macros::debug_handler;
@@ -85,4 +86,224 @@ pub async fn subtract_vote_for_session(
         Ok(sessions_user_voted_for) => (StatusCode::OK, Json(sessions_user_voted_for)).into_response(),
         Err(e) => SessionVoteError::response(ApiStatusCode::from(StatusCode::CONFLICT), e),
     }
-}
\ No newline at end of file
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/votes",
+    responses(
+        (status = 200, description = "Vote statistics", body = VoteStats),
+        (status = 500, description = "Internal server error", body = SessionVoteError),
+    ),
+    params(
+        ("at_least" = Option<i32>, Query, description = "Vote threshold to count sessions against, defaults to 1")
+    )
+)]
+#[debug_handler]
+/// Retrieves statistics on how votes are distributed across sessions
+///
+/// This function is a handler for the route `GET /api/v1/stats/votes`. It returns the fewest, most,
+/// mean and median votes per session, the total votes cast, and how many sessions have at least
+/// the given number of votes.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `stats_query` - The vote threshold to count sessions against
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the vote statistics.
+///
+/// # Errors
+/// If an error occurs while computing the statistics, a session vote error response with a status
+/// code of 500 Internal Server Error is returned.
+pub async fn vote_stats(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Query(stats_query): Query<VoteStatsQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match vote_stats_get(read_lock, stats_query.at_least.unwrap_or(1)).await {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => SessionVoteError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/reconcile-votes",
+    responses(
+        (status = 200, description = "Number of sessions whose vote count was corrected", body = VotesReconciled),
+        (status = 500, description = "Internal server error", body = SessionVoteError),
+    )
+)]
+#[debug_handler]
+/// Recounts the votes of every session
+///
+/// This function is a handler for the route `POST /api/v1/admin/reconcile-votes`. It sets the vote
+/// count of every session to the number of votes users have cast for it, fixing counts that have
+/// drifted without editing the database by hand.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing how many sessions were
+/// corrected.
+///
+/// # Errors
+/// If an error occurs while recounting the votes, a session vote error response with a status code
+/// of 500 Internal Server Error is returned.
+pub async fn reconcile_votes(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match votes_reconcile(write_lock).await {
+        Ok(reconciled) => Json(reconciled).into_response(),
+        Err(e) => SessionVoteError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions/{id}/voters",
+    responses(
+        (status = 200, description = "Voters of the session", body = SessionVoters),
+        (status = 403, description = "Forbidden", body = ()),
+        (status = 404, description = "Session not found", body = SessionVoteError),
+        (status = 500, description = "Internal server error", body = SessionVoteError),
+    ),
+    params(
+        ("id" = i32, Path, description = "Session id"),
+        ("include_contact" = Option<bool>, Query, description = "Include the voters with their names and emails, defaults to only the number of votes")
+    )
+)]
+#[debug_handler]
+/// Retrieves who voted for a session
+///
+/// This function is a handler for the route `GET /api/v1/sessions/{id}/voters`. It returns the
+/// number of votes the session has, and only when `include_contact=true` the user id, name and
+/// email of each voter. It is only available to staff and admins.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `session_id` - The id of the session to get the voters of
+/// - `voters_query` - Whether to include the voters' contact details
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the voters of the session.
+///
+/// # Errors
+/// If the session doesn't exist, a session vote error response with a status code of 404 Not Found
+/// is returned. If an error occurs while retrieving the voters, a session vote error response with
+/// a status code of 500 Internal Server Error is returned.
+pub async fn session_voters(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(session_id): Path<i32>,
+    Query(voters_query): Query<SessionVotersQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match session_voters_get(read_lock, session_id, voters_query.include_contact.unwrap_or(false)).await {
+        Ok(voters) => Json(voters).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<SessionVoteErr>() {
+                Some(SessionVoteErr::SessionDoesNotExist(_)) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            SessionVoteError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions/trending",
+    responses(
+        (status = 200, description = "Sessions with the most recent votes", body = Vec<TrendingSession>),
+        (status = 400, description = "Invalid window", body = SessionVoteError),
+        (status = 500, description = "Internal server error", body = SessionVoteError),
+    ),
+    params(
+        ("window_minutes" = Option<i32>, Query, description = "How many minutes back to count votes over, defaults to 60", minimum = 1)
+    )
+)]
+#[debug_handler]
+/// Retrieves the trending sessions
+///
+/// This function is a handler for the route `GET /api/v1/sessions/trending`. It returns the
+/// sessions that got votes within the last `window_minutes`, most recent votes first, along with
+/// how many votes they got in the window so they can be badged as trending.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `trending_query` - How many minutes back to count votes over
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the trending sessions.
+///
+/// # Errors
+/// If the window isn't positive, a session vote error response with a status code of 400 Bad
+/// Request is returned. If an error occurs while retrieving the sessions, a session vote error
+/// response with a status code of 500 Internal Server Error is returned.
+pub async fn trending_sessions(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Query(trending_query): Query<TrendingQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    let window_minutes = trending_query.window_minutes.unwrap_or(DEFAULT_TRENDING_WINDOW_MINUTES);
+    match trending_sessions_get(read_lock, window_minutes).await {
+        Ok(sessions) => Json(sessions).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<SessionVoteErr>() {
+                Some(SessionVoteErr::InvalidTrendingWindow(_)) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            SessionVoteError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/export/votes.csv",
+    responses(
+        (status = 200, description = "Every vote as CSV", body = String, content_type = "text/csv"),
+        (status = 500, description = "Internal server error", body = SessionVoteError),
+    ),
+    params(
+        ("anonymize" = Option<bool>, Query, description = "Replace the voters with pseudonyms and the sessions with their titles, defaults to `true`")
+    )
+)]
+#[debug_handler]
+/// Exports who voted for what as CSV
+///
+/// This function is a handler for the route `GET /api/v1/export/votes.csv`. It returns a row for
+/// every vote so admins can audit the voting.
+///
+/// The rows are `pseudonym,session_title` unless `anonymize=false` is given, then they are
+/// `user_id,session_id`.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `export_query` - Whether to anonymize the votes
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and the CSV, which is just the header row if there are
+/// no votes.
+///
+/// # Errors
+/// If an error occurs while reading the votes, a session vote error response with a status code of
+/// 500 Internal Server Error is returned.
+pub(crate) async fn export_votes_csv(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Query(export_query): Query<ExportVotesQuery>,
+) -> Response {
+    let anonymize = export_query.anonymize.unwrap_or(true);
+
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match votes_csv_get(read_lock, anonymize).await {
+        Ok(csv) => ([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], csv).into_response(),
+        Err(e) => SessionVoteError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), e),
+    }
+}
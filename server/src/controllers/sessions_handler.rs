@@ -3,48 +3,93 @@ use tokio::sync::RwLock;
 
 use crate::config::AppState;
 use crate::middleware::auth::{AuthInfo, AuthSessionLayer};
-use crate::models::sessions_model::{add, add_for_user, delete, get, get_all_sessions, update, Session, SessionAddedForUser, SessionErr, SessionError};
-use crate::types::ApiStatusCode;
+use crate::models::schedule_model::{schedule_unlocked, ScheduleError};
+use crate::models::settings_model::submissions_open;
+use crate::models::sessions_model::{add, add_for_user, bulk_delete, delete, detail_get, duplicate, get, get_sessions_by_tag, get_sessions_page, get_unvoted_sessions, exclusion_add, keynote_set, merge, similar_get, update, BulkDeleteRequest, IdempotencyKey, BulkDeleteResult, KeynoteUpdate, NewSession, Session, SessionAddedForUser, SessionDetail, SessionErr, SessionError, SessionExclusion, SessionMerge, SessionsByTag, SimilarSession, SimilarSessionsQuery, DEFAULT_SIMILAR_SESSIONS_LIMIT};
+use crate::types::pagination::PaginationError;
+use crate::types::{ApiStatusCode, Pagination};
 use axum::extract::Path;
+use axum::extract::Query;
 use axum::extract::State;
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use axum::{debug_handler, Extension};
 use serde_json::json;
+use sqlx::{Pool, Postgres};
+
+/// The request header a client sets to make resubmitting a session safe.
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Responds to a request for a feature that is turned off as if its endpoint didn't exist.
+///
+/// # Parameters
+/// - `feature` - The name of the feature
+///
+/// # Returns
+/// A session error response with a status code of 404 Not Found.
+fn feature_disabled_response(feature: &str) -> Response {
+    SessionError::response(
+        ApiStatusCode::from(StatusCode::NOT_FOUND),
+        Box::new(SessionErr::FeatureDisabled(feature.to_string())),
+    )
+}
+
+/// Checks whether a user can add a session, staff and admins can add sessions even when
+/// submissions are closed.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `auth_info` - The permissions of the user adding the session
+///
+/// # Returns
+/// `None` if the session can be added, otherwise a session error response with a status code of
+/// 403 Forbidden if submissions are closed, or 500 Internal Server Error if the setting couldn't be
+/// read.
+async fn submissions_closed_response(db_pool: &Pool<Postgres>, auth_info: &AuthInfo) -> Option<Response> {
+    match submissions_open(db_pool).await {
+        Ok(open) if open || auth_info.is_staff_or_admin => None,
+        Ok(_) => Some(SessionError::response(ApiStatusCode::from(StatusCode::FORBIDDEN), Box::new(SessionErr::SubmissionsClosed))),
+        Err(e) => Some(SessionError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e))),
+    }
+}
 
 #[utoipa::path(
     get,
     path = "/api/v1/sessions",
     params(
-        ("page" = i32, Query, description = "Page", minimum = 1),
-        ("limit" = i32, Query, description = "Limit", minimum = 1)
+        ("page" = Option<i64>, Query, description = "Page, defaults to 1", minimum = 1),
+        ("limit" = Option<i64>, Query, description = "Sessions per page, defaults to 100. Every session is listed when neither page nor limit is given", minimum = 1, maximum = 100)
     ),
     responses(
         (status = 200, description = "List sessions", body = Vec<Session>),
+        (status = 400, description = "Invalid page or limit", body = PaginationError),
         (status = 404, description = "No sessions in that range")
     )
 )]
 #[debug_handler]
 /// Retrieves a list of sessions
 ///
-/// This function is a handler for the route `GET /api/v1/sessions`. It retrieves a list of sessions
-/// from the database.
+/// This function is a handler for the route `GET /api/v1/sessions`. It retrieves the sessions
+/// from the database, or a single page of them when the query gives a page or limit.
 ///
 /// # Parameters
 /// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `pagination` - The page of sessions to retrieve, every session when `None`
 ///
 /// # Returns
 /// `Response` with a status code of 200 OK and a JSON body containing the list of sessions or an
 /// error response if no sessions are found.
 ///
 /// # Errors
+/// If the page or limit is invalid, a pagination error response with a status code of 400 Bad
+/// Request is returned.
 /// If an error occurs while retrieving the sessions, a session error response with a status code
 /// of 404 Not Found is returned.
-pub async fn sessions(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+pub async fn sessions(State(app_state): State<Arc<RwLock<AppState>>>, pagination: Option<Pagination>) -> Response {
     let app_state_lock = app_state.read().await;
     let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
-    match get_all_sessions(read_lock).await {
+    match get_sessions_page(read_lock, pagination).await {
         Ok(res) => Json(res).into_response(),
         Err(e) => SessionError::response(
             ApiStatusCode::from(StatusCode::NOT_FOUND),
@@ -90,46 +135,179 @@ pub async fn get_session(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions/{id}/full",
+    responses(
+        (status = 200, description = "Return specified session with its tags, votes and assignment", body = SessionDetail),
+        (status = 404, description = "No session with this id", body = SessionError),
+        (status = 500, description = "Internal server error", body = SessionError),
+    )
+)]
+#[debug_handler]
+/// Retrieves a session with everything its detail view shows
+///
+/// This function is a handler for the route `GET /api/v1/sessions/{id}/full`. It retrieves a
+/// session along with its tags, vote count and the room and timeslot it's in, so the detail view
+/// only needs one request.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `session_id` - The id of the session to retrieve
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the session details or an
+/// error response if the session is not found.
+///
+/// # Errors
+/// If the session doesn't exist, a session error response with a status code of 404 Not Found is
+/// returned.
+/// If an error occurs while retrieving the session details, a session error response with a status
+/// code of 500 Internal Server Error is returned.
+pub async fn get_session_full(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(session_id): Path<i32>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match detail_get(read_lock, session_id).await {
+        Ok(detail) => Json(detail).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<SessionErr>() {
+                Some(SessionErr::DoesNotExist(_)) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            SessionError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions/{id}/similar",
+    params(
+        ("limit" = Option<i64>, Query, description = "How many similar sessions to return, defaults to 5", minimum = 1)
+    ),
+    responses(
+        (status = 200, description = "Sessions most similar to the specified session, with their similarity from 0 to 1", body = Vec<SimilarSession>),
+        (status = 400, description = "Invalid limit", body = SessionError),
+        (status = 404, description = "No session with this id, or deduplication is turned off", body = SessionError),
+        (status = 500, description = "Internal server error", body = SessionError),
+    )
+)]
+#[debug_handler]
+/// Retrieves the sessions most similar to a session
+///
+/// This function is a handler for the route `GET /api/v1/sessions/{id}/similar`. It returns the
+/// sessions whose title or content is closest to the session's, most similar first, so organizers
+/// can spot and merge duplicates before scheduling. Each session comes with its score so the UI
+/// can decide what counts as a duplicate.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `session_id` - The id of the session to find duplicates of
+/// - `similar_query` - How many similar sessions to return
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the similar sessions.
+///
+/// # Errors
+/// If the limit isn't positive, a session error response with a status code of 400 Bad Request is
+/// returned.
+/// If the session doesn't exist or `FEATURE_DEDUPLICATION` is turned off, a session error response
+/// with a status code of 404 Not Found is returned.
+/// If an error occurs while retrieving the sessions, a session error response with a status code
+/// of 500 Internal Server Error is returned.
+pub async fn similar_sessions(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(session_id): Path<i32>,
+    Query(similar_query): Query<SimilarSessionsQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    if !app_state_lock.config.features.deduplication {
+        return feature_disabled_response("deduplication");
+    }
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    let limit = similar_query.limit.unwrap_or(DEFAULT_SIMILAR_SESSIONS_LIMIT);
+    match similar_get(read_lock, session_id, limit).await {
+        Ok(similar) => Json(similar).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<SessionErr>() {
+                Some(SessionErr::InvalidSimilarLimit(_)) => StatusCode::BAD_REQUEST,
+                Some(SessionErr::DoesNotExist(_)) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            SessionError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/api/v1/sessions/add",
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Unique key for the submission, a resubmission with the same key returns the session added the first time")
+    ),
     request_body(
-        content = inline(Session),
-        description = "Session to add"
+        content = inline(NewSession),
+        description = "Session to add, with the IDs of the tags to apply to it"
     ),
     responses(
-        (status = 201, description = "Added session", body = ()),
-        (status = 400, description = "Bad request", body = SessionError)
+        (status = 201, description = "Added session", body = Session),
+        (status = 400, description = "Bad request", body = SessionError),
+        (status = 403, description = "Submissions are closed", body = SessionError),
+        (status = 409, description = "Submission limit reached", body = SessionError)
     )
 )]
 #[debug_handler]
 /// Adds a new session.
 ///
 /// This function is a handler for the route `POST /api/v1/sessions/add`. It adds a new session to the
-/// database.
+/// database and applies the tags in `tag_ids` to it. If the request has an `Idempotency-Key` header
+/// the user already sent within `IDEMPOTENCY_KEY_TTL_SECS`, the session isn't added again.
 ///
 /// # Parameters
 /// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
-/// - `session` - The session to add
+/// - `headers` - The request headers, with the optional `Idempotency-Key`
+/// - `session` - The session to add, with its tags
 ///
 /// # Returns
-/// `Response` with a status code of 201 Created and an empty body if the session was added or an
-/// error response if the session could not be added.
+/// `Response` with a status code of 201 Created and a JSON body containing the new session, with
+/// its ID, if the session was added or an error response if the session could not be added. A
+/// resubmission gets the same response as the first submission.
 ///
 /// # Errors
-/// If an error occurs while adding the session, a session error response with a status code of 400
-/// Bad Request is returned.
+/// If submissions are closed and the user isn't staff or an admin, a session error response with a
+/// status code of 403 Forbidden is returned.
+/// If the user already has `MAX_SESSIONS_PER_USER` sessions, a session error response with a status
+/// code of 409 Conflict is returned.
+/// If an error occurs while adding the session or one of the tags doesn't exist, a session error
+/// response with a status code of 400 Bad Request is returned and the session isn't added.
 pub(crate) async fn post_session(
     State(app_state): State<Arc<RwLock<AppState>>>,
     auth_session: AuthSessionLayer,
     Extension(auth_info): Extension<AuthInfo>,
-    Json(session): Json<Session>,
+    headers: HeaderMap,
+    Json(session): Json<NewSession>,
 ) -> Response {
     let app_state_lock = app_state.read().await;
     let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
-    match add(write_lock, session, auth_session, auth_info).await {
-        Ok(_) => StatusCode::CREATED.into_response(),
-        Err(e) => SessionError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), e),
+    if let Some(response) = submissions_closed_response(write_lock, &auth_info).await {
+        return response;
+    }
+
+    let user_id = auth_session.user.as_ref().unwrap().id;
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|key| key.to_str().ok())
+        .filter(|key| !key.is_empty())
+        .map(|key| IdempotencyKey { key, ttl: app_state_lock.config.idempotency_key_ttl });
+    match add(write_lock, session, user_id, auth_info, app_state_lock.config.max_sessions_per_user, idempotency_key).await {
+        Ok(new_session) => (StatusCode::CREATED, Json(new_session)).into_response(),
+        Err(e) => match e.downcast_ref::<SessionErr>() {
+            Some(SessionErr::SubmissionLimitReached(_)) => SessionError::response(ApiStatusCode::from(StatusCode::CONFLICT), e),
+            _ => SessionError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), e),
+        },
     }
 }
 
@@ -142,7 +320,9 @@ pub(crate) async fn post_session(
     ),
     responses(
         (status = 201, description = "Added session", body = ()),
-        (status = 400, description = "Bad request", body = SessionError)
+        (status = 400, description = "Bad request", body = SessionError),
+        (status = 403, description = "Submissions are closed", body = SessionError),
+        (status = 409, description = "Submission limit reached", body = SessionError)
     )
 )]
 #[debug_handler]
@@ -161,6 +341,10 @@ pub(crate) async fn post_session(
 /// error response if the session could not be added.
 ///
 /// # Errors
+/// If submissions are closed and the user isn't staff or an admin, a session error response with a
+/// status code of 403 Forbidden is returned.
+/// If the user already has `MAX_SESSIONS_PER_USER` sessions, a session error response with a status
+/// code of 409 Conflict is returned.
 /// If an error occurs while adding the session, a session error response with a status code of 400
 /// Bad Request is returned.
 pub(crate) async fn post_session_for_user(
@@ -171,9 +355,16 @@ pub(crate) async fn post_session_for_user(
 ) -> Response {
     let app_state_lock = app_state.read().await;
     let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
-    match add_for_user(write_lock, session, auth_session, auth_info).await {
+    if let Some(response) = submissions_closed_response(write_lock, &auth_info).await {
+        return response;
+    }
+
+    match add_for_user(write_lock, session, auth_session, auth_info, app_state_lock.config.max_sessions_per_user).await {
         Ok(_) => StatusCode::CREATED.into_response(),
-        Err(e) => SessionError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), e),
+        Err(e) => match e.downcast_ref::<SessionErr>() {
+            Some(SessionErr::SubmissionLimitReached(_)) => SessionError::response(ApiStatusCode::from(StatusCode::CONFLICT), e),
+            _ => SessionError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), e),
+        },
     }
 }
 
@@ -222,6 +413,355 @@ pub(crate) async fn delete_session(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions/by-tag",
+    responses(
+        (status = 200, description = "Sessions grouped by tag", body = Vec<SessionsByTag>),
+        (status = 500, description = "Internal server error", body = SessionError),
+    )
+)]
+#[debug_handler]
+/// Retrieves the sessions grouped by tag
+///
+/// This function is a handler for the route `GET /api/v1/sessions/by-tag`. It lists the sessions
+/// under each of their tags so the program can be presented as tracks, with the sessions without
+/// any tags in a final "Untagged" group.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the groups of sessions.
+///
+/// # Errors
+/// If an error occurs while retrieving the sessions, a session error response with a status code
+/// of 500 Internal Server Error is returned.
+pub async fn sessions_by_tag(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match get_sessions_by_tag(read_lock).await {
+        Ok(groups) => Json(groups).into_response(),
+        Err(e) => SessionError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions/unvoted",
+    params(
+        ("page" = Option<i64>, Query, description = "Page, defaults to 1", minimum = 1),
+        ("limit" = Option<i64>, Query, description = "Sessions per page, defaults to 100. Every session is listed when neither page nor limit is given", minimum = 1, maximum = 100)
+    ),
+    responses(
+        (status = 200, description = "Sessions without any votes", body = Vec<Session>),
+        (status = 400, description = "Invalid page or limit", body = PaginationError),
+        (status = 500, description = "Internal server error", body = SessionError),
+    )
+)]
+#[debug_handler]
+/// Retrieves the sessions nobody has voted for
+///
+/// This function is a handler for the route `GET /api/v1/sessions/unvoted`. It lists the sessions
+/// without any votes so they can be pruned before scheduling.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `pagination` - The page of sessions to retrieve, every unvoted session when `None`
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the sessions.
+///
+/// # Errors
+/// If the page or limit is invalid, a pagination error response with a status code of 400 Bad
+/// Request is returned.
+/// If an error occurs while retrieving the sessions, a session error response with a status code
+/// of 500 Internal Server Error is returned.
+pub(crate) async fn unvoted_sessions(State(app_state): State<Arc<RwLock<AppState>>>, pagination: Option<Pagination>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match get_unvoted_sessions(read_lock, pagination).await {
+        Ok(sessions) => Json(sessions).into_response(),
+        Err(e) => SessionError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/sessions/{id}/duplicate",
+    responses(
+        (status = 201, description = "Duplicated session", body = Session),
+        (status = 400, description = "Bad request", body = SessionError),
+        (status = 403, description = "Session belongs to another user or submissions are closed", body = SessionError),
+        (status = 404, description = "Session not found", body = SessionError),
+        (status = 409, description = "Submission limit reached", body = SessionError),
+    ),
+    params(
+        ("id" = i32, Path, description = "Session id")
+    )
+)]
+#[debug_handler]
+/// Duplicates a session
+///
+/// This function is a handler for the route `POST /api/v1/sessions/{id}/duplicate`. It creates a
+/// copy of the session with "(repeat)" added to the title and no votes, so a popular session can be
+/// scheduled twice. Only the session's owner, staff, or admins can duplicate it, and like any
+/// other submission only staff and admins can while submissions are closed.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `session_id` - The id of the session to duplicate
+/// - `auth_session` - The authentication session of the user
+/// - `auth_info` - The permissions of the user
+///
+/// # Returns
+/// `Response` with a status code of 201 Created and a JSON body containing the new session.
+///
+/// # Errors
+/// If the session doesn't exist a session error response with a status code of 404 Not Found is
+/// returned, if the user can't duplicate it or submissions are closed a 403 Forbidden is returned,
+/// if the owner already has `MAX_SESSIONS_PER_USER` sessions a 409 Conflict is returned, and any
+/// other error returns a 400 Bad Request.
+pub(crate) async fn duplicate_session(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(session_id): Path<i32>,
+    auth_session: AuthSessionLayer,
+    Extension(auth_info): Extension<AuthInfo>,
+) -> Response {
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Some(response) = submissions_closed_response(write_lock, &auth_info).await {
+        return response;
+    }
+
+    match duplicate(write_lock, session_id, user.id, auth_info.is_staff_or_admin, app_state_lock.config.max_sessions_per_user).await {
+        Ok(session) => (StatusCode::CREATED, Json(session)).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<SessionErr>() {
+                Some(SessionErr::DoesNotExist(_)) => StatusCode::NOT_FOUND,
+                Some(SessionErr::UnAuthorizedMutableAccess(_)) => StatusCode::FORBIDDEN,
+                Some(SessionErr::SubmissionLimitReached(_)) => StatusCode::CONFLICT,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            SessionError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/sessions/bulk-delete",
+    request_body(
+        content = BulkDeleteRequest,
+        description = "IDs of the sessions to delete"
+    ),
+    responses(
+        (status = 200, description = "Whether each session was deleted", body = Vec<BulkDeleteResult>),
+        (status = 500, description = "Internal server error", body = SessionError),
+    )
+)]
+#[debug_handler]
+/// Deletes several sessions at once
+///
+/// This function is a handler for the route `POST /api/v1/sessions/bulk-delete`. It deletes the
+/// sessions along with their votes, tags and schedule assignments in one transaction, for cleaning
+/// up spam or test submissions. Sessions that don't exist are reported without stopping the rest
+/// from being deleted.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `request` - The IDs of the sessions to delete
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body saying whether each session was deleted.
+///
+/// # Errors
+/// If an error occurs while deleting the sessions none of them are deleted, and a session error
+/// response with a status code of 500 Internal Server Error is returned.
+pub async fn bulk_delete_sessions(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(request): Json<BulkDeleteRequest>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match bulk_delete(write_lock, &request.session_ids).await {
+        Ok(results) => Json(results).into_response(),
+        Err(e) => SessionError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/sessions/{id}/keynote",
+    request_body(
+        content = KeynoteUpdate,
+        description = "Whether the session is a keynote"
+    ),
+    responses(
+        (status = 200, description = "Updated session", body = Session),
+        (status = 400, description = "Bad request", body = SessionError),
+        (status = 404, description = "Session not found, or keynotes are turned off", body = SessionError),
+    ),
+    params(
+        ("id" = i32, Path, description = "Session id")
+    )
+)]
+#[debug_handler]
+/// Marks a session as a keynote
+///
+/// This function is a handler for the route `PUT /api/v1/sessions/{id}/keynote`. The scheduler
+/// heavily penalizes scheduling anything opposite a keynote, so keynotes get a timeslot to
+/// themselves whenever there is room for it.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `session_id` - The id of the session
+/// - `keynote` - Whether the session is a keynote
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the updated session.
+///
+/// # Errors
+/// If the session doesn't exist or `FEATURE_KEYNOTES` is turned off a session error response with
+/// a status code of 404 Not Found is returned, any other error returns a 400 Bad Request.
+pub async fn set_keynote(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(session_id): Path<i32>,
+    Json(keynote): Json<KeynoteUpdate>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    if !app_state_lock.config.features.keynotes {
+        return feature_disabled_response("keynotes");
+    }
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match keynote_set(write_lock, session_id, keynote.is_keynote).await {
+        Ok(session) => Json(session).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<SessionErr>() {
+                Some(SessionErr::DoesNotExist(_)) => StatusCode::NOT_FOUND,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            SessionError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/sessions/exclude",
+    request_body(
+        content = SessionExclusion,
+        description = "The pair of sessions to keep out of the same timeslot"
+    ),
+    responses(
+        (status = 200, description = "The stored pair, lower ID first", body = SessionExclusion),
+        (status = 400, description = "Bad request", body = SessionError),
+        (status = 404, description = "Session not found", body = SessionError),
+        (status = 500, description = "Internal server error", body = SessionError),
+    )
+)]
+#[debug_handler]
+/// Keeps two sessions out of the same timeslot
+///
+/// This function is a handler for the route `POST /api/v1/sessions/exclude`. The scheduler heavily
+/// penalizes scheduling the pair opposite each other, for sessions that share an audience or the
+/// people running them.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `exclusion` - The pair of sessions to keep apart
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the stored pair.
+///
+/// # Errors
+/// If either session doesn't exist a session error response with a status code of 404 Not Found is
+/// returned, a session excluded from itself returns a 400 Bad Request, and any other error returns
+/// a 500 Internal Server Error.
+pub async fn exclude_sessions(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(exclusion): Json<SessionExclusion>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match exclusion_add(write_lock, exclusion).await {
+        Ok(exclusion) => Json(exclusion).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<SessionErr>() {
+                Some(SessionErr::DoesNotExist(_)) => StatusCode::NOT_FOUND,
+                Some(SessionErr::ExcludedFromItself(_)) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            SessionError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/sessions/merge",
+    request_body(
+        content = SessionMerge,
+        description = "The session to keep and the duplicate to merge into it"
+    ),
+    responses(
+        (status = 200, description = "The kept session with its new vote count", body = Session),
+        (status = 400, description = "Bad request", body = SessionError),
+        (status = 404, description = "Session not found, or deduplication is turned off", body = SessionError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
+        (status = 500, description = "Internal server error", body = SessionError),
+    )
+)]
+#[debug_handler]
+/// Merges a duplicate session into another
+///
+/// This function is a handler for the route `POST /api/v1/sessions/merge`. The votes and tags of
+/// the session being removed move to the kept session, without counting users who voted for both
+/// twice, and the removed session is deleted.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `session_merge` - The session to keep and the duplicate to remove
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the kept session.
+///
+/// # Errors
+/// If either session doesn't exist or `FEATURE_DEDUPLICATION` is turned off a session error response
+/// with a status code of 404 Not Found is returned, a session merged into itself returns a 400 Bad
+/// Request, and any other error returns a 500 Internal Server Error.
+///
+/// If the schedule is locked a 423 error is returned, since the removed session may be on it.
+pub async fn merge_sessions(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(session_merge): Json<SessionMerge>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    if !app_state_lock.config.features.deduplication {
+        return feature_disabled_response("deduplication");
+    }
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(write_lock).await {
+        return ScheduleError::locked_response(e);
+    }
+
+    match merge(write_lock, session_merge).await {
+        Ok(session) => Json(session).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<SessionErr>() {
+                Some(SessionErr::DoesNotExist(_)) => StatusCode::NOT_FOUND,
+                Some(SessionErr::MergedIntoItself(_)) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            SessionError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
 #[utoipa::path(
     put,
     path = "/api/v1/sessions/{id}",
@@ -269,4 +809,100 @@ pub(crate) async fn update_session(
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, FeatureFlags};
+    use crate::controllers::settings_handler::features;
+    use crate::models::settings_model::{schedule_locked_set, submissions_open_set};
+    use crate::test_support::{app_state, insert_session_with, insert_user, send};
+    use axum::body::Body;
+    use axum::extract::Request;
+    use axum::http::header::CONTENT_TYPE;
+    use axum::routing;
+    use axum::Router;
+    use sqlx::PgPool;
+    use std::collections::HashSet;
+
+    fn router(pool: &PgPool, features_on: FeatureFlags) -> Router {
+        Router::new()
+            .route("/config/features", routing::get(features))
+            .route("/sessions/{id}/keynote", routing::put(set_keynote))
+            .route("/sessions/{id}/similar", routing::get(similar_sessions))
+            .route("/sessions/merge", routing::post(merge_sessions))
+            .with_state(app_state(pool, Config { features: features_on, ..Config::default() }))
+    }
+
+    async fn send_json(router: &Router, method: &str, uri: &str, body: &str) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        send(router, request).await
+    }
+
+    fn auth_info(is_staff_or_admin: bool) -> AuthInfo {
+        AuthInfo {
+            is_authenticated: true,
+            is_staff_or_admin,
+            permissions: HashSet::new(),
+        }
+    }
+
+    #[sqlx::test]
+    async fn closed_submissions_only_reject_users(pool: PgPool) {
+        assert!(submissions_closed_response(&pool, &auth_info(false)).await.is_none());
+        assert!(submissions_closed_response(&pool, &auth_info(true)).await.is_none());
+
+        submissions_open_set(&pool, false).await.unwrap();
+
+        let response = submissions_closed_response(&pool, &auth_info(false)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(submissions_closed_response(&pool, &auth_info(true)).await.is_none());
+    }
+
+    #[sqlx::test]
+    async fn turned_off_features_hide_their_endpoints(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let keep_id = insert_session_with(&pool, user_id, "Async Rust", "Futures", 0).await;
+        let remove_id = insert_session_with(&pool, user_id, "Async Rust!", "Futures", 0).await;
+        let keynote_uri = format!("/sessions/{keep_id}/keynote");
+        let similar_uri = format!("/sessions/{keep_id}/similar");
+        let merge_body = format!("{{\"keep_id\": {keep_id}, \"remove_id\": {remove_id}}}");
+
+        let off = router(&pool, FeatureFlags { keynotes: false, deduplication: false });
+        let response = send_json(&off, "GET", "/config/features", "").await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap(), json!({"keynotes": false, "deduplication": false}));
+        assert_eq!(send_json(&off, "PUT", &keynote_uri, r#"{"is_keynote": true}"#).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(send_json(&off, "GET", &similar_uri, "").await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(send_json(&off, "POST", "/sessions/merge", &merge_body).await.status(), StatusCode::NOT_FOUND);
+        assert!(!get(&pool, keep_id).await.unwrap().is_keynote);
+        assert!(get(&pool, remove_id).await.is_ok());
+
+        let on = router(&pool, FeatureFlags::default());
+        assert_eq!(send_json(&on, "PUT", &keynote_uri, r#"{"is_keynote": true}"#).await.status(), StatusCode::OK);
+        assert_eq!(send_json(&on, "GET", &similar_uri, "").await.status(), StatusCode::OK);
+        assert_eq!(send_json(&on, "POST", "/sessions/merge", &merge_body).await.status(), StatusCode::OK);
+        assert!(get(&pool, keep_id).await.unwrap().is_keynote);
+        assert!(get(&pool, remove_id).await.is_err());
+    }
 
+    #[sqlx::test]
+    async fn merging_is_refused_while_the_schedule_is_locked(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let keep_id = insert_session_with(&pool, user_id, "Async Rust", "Futures", 0).await;
+        let remove_id = insert_session_with(&pool, user_id, "Async Rust!", "Futures", 0).await;
+        let merge_body = format!("{{\"keep_id\": {keep_id}, \"remove_id\": {remove_id}}}");
+        let router = router(&pool, FeatureFlags::default());
+        schedule_locked_set(&pool, true).await.unwrap();
+
+        assert_eq!(send_json(&router, "POST", "/sessions/merge", &merge_body).await.status(), StatusCode::LOCKED);
+        assert!(get(&pool, remove_id).await.is_ok());
+
+        schedule_locked_set(&pool, false).await.unwrap();
+        assert_eq!(send_json(&router, "POST", "/sessions/merge", &merge_body).await.status(), StatusCode::OK);
+    }
+}
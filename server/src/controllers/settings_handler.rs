@@ -0,0 +1,190 @@
+use crate::config::{AppState, FeatureFlags};
+use crate::models::settings_model::{
+    event_config_get, event_settings_get, event_settings_set, submissions_open_set, EventConfig, EventSettings,
+    EventSettingsUpdate, SettingsErr, SettingsError, SubmissionsStatus,
+};
+use crate::types::ApiStatusCode;
+use axum::{debug_handler, extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/config",
+    responses(
+        (status = 200, description = "Event configuration", body = EventConfig),
+        (status = 500, description = "Internal server error"),
+    )
+)]
+#[debug_handler]
+/// Retrieves the event configuration
+///
+/// This function is a handler for the route `GET /api/v1/config`. It returns the event-level
+/// settings a frontend needs to adapt its UI, such as whether the schedule is locked and how many
+/// rooms and timeslots there are.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the `EventConfig`.
+///
+/// # Errors
+/// If an error occurs while loading the settings, a 500 Internal Server Error is returned.
+pub async fn event_config(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match event_config_get(read_lock).await {
+        Ok(config) => Json(config).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to load the event config: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/config/features",
+    responses(
+        (status = 200, description = "Which optional features are turned on", body = FeatureFlags),
+    )
+)]
+#[debug_handler]
+/// Retrieves the feature flags
+///
+/// This function is a handler for the route `GET /api/v1/config/features`. It returns which of the
+/// optional features the server was started with, set through the `FEATURE_*` environment
+/// variables, so organizers and frontends know what is available.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the `FeatureFlags`.
+pub async fn features(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    Json(app_state.read().await.config.features).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/config/submissions",
+    request_body(
+        content = SubmissionsStatus,
+        description = "Whether users can submit sessions"
+    ),
+    responses(
+        (status = 200, description = "Updated submissions status", body = SubmissionsStatus),
+        (status = 500, description = "Internal server error"),
+    )
+)]
+#[debug_handler]
+/// Opens or closes session submissions
+///
+/// This function is a handler for the route `POST /api/v1/config/submissions`. While submissions
+/// are closed users can't add sessions, staff and admins still can.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `status` - Whether submissions should be open
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the new `SubmissionsStatus`.
+///
+/// # Errors
+/// If an error occurs while saving the setting, a 500 Internal Server Error is returned.
+pub async fn set_submissions(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(status): Json<SubmissionsStatus>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match submissions_open_set(write_lock, status.open).await {
+        Ok(status) => Json(status).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to update the submissions status: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/config/event",
+    responses(
+        (status = 200, description = "Event name, date and description", body = EventSettings),
+        (status = 500, description = "Internal server error"),
+    )
+)]
+#[debug_handler]
+/// Retrieves the event settings
+///
+/// This function is a handler for the route `GET /api/v1/config/event`. It returns the name, date
+/// and description the event is presented with.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the `EventSettings`.
+///
+/// # Errors
+/// If an error occurs while loading the settings, a 500 Internal Server Error is returned.
+pub async fn event_settings(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match event_settings_get(read_lock).await {
+        Ok(settings) => Json(settings).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to load the event settings: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/config/event",
+    request_body(
+        content = EventSettingsUpdate,
+        description = "The event name, date (YYYY-MM-DD) and description"
+    ),
+    responses(
+        (status = 200, description = "Updated event settings", body = EventSettings),
+        (status = 400, description = "Blank name or invalid date", body = SettingsError),
+        (status = 500, description = "Internal server error", body = SettingsError),
+    )
+)]
+#[debug_handler]
+/// Updates the event settings
+///
+/// This function is a handler for the route `PUT /api/v1/config/event`. It replaces the name,
+/// date and description the event is presented with.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `update` - The new event settings
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the saved `EventSettings`.
+///
+/// # Errors
+/// If the name is blank or the date isn't a `YYYY-MM-DD` date, an error response with a status
+/// code of 400 Bad Request is returned.
+/// If an error occurs while saving the settings, an error response with a status code of 500
+/// Internal Server Error is returned.
+pub async fn set_event_settings(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(update): Json<EventSettingsUpdate>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match event_settings_set(write_lock, update).await {
+        Ok(settings) => Json(settings).into_response(),
+        Err(e @ SettingsErr::IoError(_)) => {
+            tracing::error!("Failed to update the event settings: {}", e);
+            SettingsError::response(ApiStatusCode::from(StatusCode::INTERNAL_SERVER_ERROR), Box::new(e))
+        }
+        Err(e) => SettingsError::response(ApiStatusCode::from(StatusCode::BAD_REQUEST), Box::new(e)),
+    }
+}
@@ -3,7 +3,8 @@ use crate::middleware::auth::{AuthInfo, AuthSessionLayer};
 use crate::models::auth_model::Permission;
 use crate::models::index_model::IndexContent;
 use crate::models::room_model::{rooms_get, Room};
-use crate::models::schedule_model::{schedules_get, Schedule};
+use crate::models::schedule_export_model::{print_schedule_get, PrintSchedule};
+use crate::models::schedule_model::{schedules_get, Schedule, SetupStep};
 use crate::models::session_voting_model::get_sessions_user_voted_for;
 use crate::models::sessions_model::{get_all_sessions, Session};
 use crate::models::tags_model::{get_all_tags, Tag};
@@ -127,6 +128,8 @@ pub(crate) struct ScheduleTemplate {
     pub(crate) schedule: Option<Schedule>,
     pub(crate) rooms: Option<Vec<Room>>,
     pub(crate) events: Vec<Event>,
+    pub(crate) has_schedule: bool,
+    pub(crate) needs_setup: Vec<SetupStep>,
     is_authenticated: bool,
     permissions: HashSet<Permission>,
     pub(crate) unpopulated_sessions: Vec<Session>,
@@ -211,17 +214,21 @@ pub(crate) async fn schedule_handler(State(app_state): State<Arc<RwLock<AppState
         let populated_session_ids: Vec<i32> = events.iter().map(|event| event.session_id).collect();
         let unpopulated_sessions = query_as!(
             Session,
-            "SELECT id, user_id, title, content, votes, NULL::INTEGER as tag_id FROM sessions WHERE NOT (id = ANY($1))",
+            "SELECT id, user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id FROM sessions WHERE NOT (id = ANY($1))",
             &populated_session_ids,
         )
             .fetch_all(read_lock)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
 
+        let needs_setup = SetupStep::missing(rooms.is_some(), !timeslots.is_empty());
+
         let template = ScheduleTemplate {
             schedule,
             rooms,
             events,
+            has_schedule: needs_setup.is_empty(),
+            needs_setup,
             is_authenticated,
             permissions,
             unpopulated_sessions,
@@ -238,6 +245,47 @@ pub(crate) async fn schedule_handler(State(app_state): State<Arc<RwLock<AppState
         Err(response) => response,
     }
 }
+#[derive(Template, Debug)]
+#[template(path = "print_schedule.html")]
+/// Printable schedule template
+pub(crate) struct PrintScheduleTemplate {
+    pub(crate) schedule: PrintSchedule,
+}
+
+#[debug_handler]
+/// Printable schedule handler
+///
+/// This function is a handler for the route `GET /schedule/print`. It renders the schedule as a
+/// plain table, timeslots down and rooms across with the title and speaker of each session, without
+/// any navigation or edit controls so organizers can print it and post it on walls.
+///
+/// # Returns
+/// `Response` with the rendered HTML page or an error status code.
+///
+/// # Errors
+/// If the schedule can't be loaded or the template fails to render, an internal server error
+/// status code is returned.
+pub(crate) async fn print_schedule_handler(State(app_state): State<Arc<RwLock<AppState>>>) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+
+    let schedule = match print_schedule_get(read_lock).await {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            tracing::error!("Failed to load the printable schedule: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match (PrintScheduleTemplate { schedule }).render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Error rendering template: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
 #[derive(Template, Debug)]
 #[template(path = "sessions.html")]
 /// Sessions template
@@ -295,7 +343,7 @@ pub struct SessionAndUser {
 /// - `db_pool` - The database connection pool
 ///
 /// # Returns
-/// A vector containing the sessions and users or an error if the query fails.
+/// A vector containing the sessions and users, ordered by session ID, or an error if the query fails.
 ///
 /// # Errors
 /// An error is returned if the query fails.
@@ -318,7 +366,8 @@ pub async fn combine_session_and_user(
         FROM sessions s \
         JOIN users u ON u.id = s.user_id
         LEFT JOIN session_tags st on s.id = st.session_id
-        GROUP BY s.id, u.id",
+        GROUP BY s.id, u.id
+        ORDER BY s.id",
     )
         .fetch_all(db_pool)
         .await?;
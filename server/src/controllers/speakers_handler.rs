@@ -0,0 +1,60 @@
+use crate::config::AppState;
+use crate::models::speakers_model::{speakers_get_paginated, Speaker, SpeakerQuery};
+use crate::types::pagination::PaginationError;
+use crate::types::Pagination;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{debug_handler, Json};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/speakers",
+    params(
+        ("page" = Option<i64>, Query, description = "Page, defaults to 1", minimum = 1),
+        ("limit" = Option<i64>, Query, description = "Speakers per page, defaults to 100. Every speaker is listed when neither page nor limit is given", minimum = 1, maximum = 100),
+        ("q" = Option<String>, Query, description = "Only list speakers whose name or email contains this, ignoring case")
+    ),
+    responses(
+        (status = 200, description = "List speakers", body = Vec<Speaker>),
+        (status = 400, description = "Invalid page or limit", body = PaginationError),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[debug_handler]
+/// Retrieves a list of speakers
+///
+/// This function is a handler for the route `GET /api/v1/speakers`. It retrieves the users who
+/// submitted sessions, optionally only the ones whose name or email matches a search, and only a
+/// single page of them when the query gives a page or limit.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `pagination` - The page of speakers to retrieve, every speaker when `None`
+/// - `query` - The search to filter the speakers by
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the speakers,
+/// an empty list if none match.
+///
+/// # Errors
+/// If the page or limit is invalid, a pagination error response with a status code of 400 Bad
+/// Request is returned.
+/// If an error occurs while retrieving the speakers, a 500 Internal Server Error is returned.
+pub async fn speakers(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    pagination: Option<Pagination>,
+    Query(query): Query<SpeakerQuery>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match speakers_get_paginated(read_lock, pagination, query.q.as_deref()).await {
+        Ok(speakers) => Json(speakers).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to load the speakers: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::config::AppState;
-use crate::middleware::auth::AuthInfo;
-use crate::models::tags_model::{self, Tag, TagError};
+use crate::middleware::auth::{AuthInfo, AuthSessionLayer};
+use crate::models::tags_model::{self, Tag, TagError, TagRule, TagRulesApplied};
 use crate::types::ApiStatusCode;
 use axum::extract::Path;
 use axum::extract::State;
@@ -23,6 +23,12 @@ pub struct UpdateTagRequest {
     pub tag_name: String,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateTagRuleRequest {
+    pub keyword: String,
+    pub tag_id: i32,
+}
+
 #[utoipa::path(
     get,
     path = "/api/v1/tags",
@@ -245,4 +251,136 @@ pub(crate) async fn delete_tag(
             TagError::response(ApiStatusCode::from(status), e)
         }
     }
-}
\ No newline at end of file
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/tags/rules",
+    request_body = CreateTagRuleRequest,
+    responses(
+        (status = 201, description = "Tag rule created", body = TagRule),
+        (status = 404, description = "Tag not found", body = TagError),
+        (status = 400, description = "Bad request", body = TagError),
+    )
+)]
+#[debug_handler]
+/// Creates a keyword rule for automatically tagging sessions
+///
+/// This function is a handler for the route `POST /api/v1/tags/rules`.
+/// Sessions whose title contains the keyword get the tag when the rules are applied.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `request` - JSON body containing the keyword and tag ID
+///
+/// # Returns
+/// `Response` with a status code of 201 Created and the new tag rule.
+///
+/// # Errors
+/// If the keyword is blank a 400 Bad Request is returned. If the tag is not found or an error
+/// occurs, an error response is returned.
+pub(crate) async fn create_tag_rule(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(request): Json<CreateTagRuleRequest>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let db_pool = &app_state_lock.unconf_data.read().await.unconf_db;
+
+    match tags_model::create_tag_rule(db_pool, &request.keyword, request.tag_id).await {
+        Ok(rule) => (StatusCode::CREATED, Json(rule)).into_response(),
+        Err(e) => {
+            let status = if e.to_string().contains("not found") {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::BAD_REQUEST
+            };
+            TagError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/tags/apply-rules",
+    responses(
+        (status = 200, description = "Number of tags applied", body = TagRulesApplied),
+        (status = 403, description = "Unauthorized", body = TagError),
+        (status = 400, description = "Bad request", body = TagError),
+    )
+)]
+#[debug_handler]
+/// Applies the keyword tag rules to all sessions
+///
+/// This function is a handler for the route `POST /api/v1/tags/apply-rules`.
+/// It scans every session and tags the ones whose title matches a rule, skipping sessions that
+/// already have the tag.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `auth_session` - Authentication session for authorization
+/// - `auth_info` - An instance of `AuthInfo`
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and the number of tags applied.
+///
+/// # Errors
+/// If the user is unauthorized or a tag can't be applied, an error response is returned.
+pub(crate) async fn apply_tag_rules(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    auth_session: AuthSessionLayer,
+    Extension(auth_info): Extension<AuthInfo>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let db_pool = &app_state_lock.unconf_data.read().await.unconf_db;
+
+    match tags_model::apply_tag_rules(db_pool, auth_session, auth_info).await {
+        Ok(applied) => (StatusCode::OK, Json(applied)).into_response(),
+        Err(e) => {
+            let status = if e.to_string().contains("does not have access") {
+                StatusCode::FORBIDDEN
+            } else {
+                StatusCode::BAD_REQUEST
+            };
+            TagError::response(ApiStatusCode::from(status), e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_support::{app_state, insert_tag, send};
+    use axum::body::Body;
+    use axum::extract::Request;
+    use axum::http::header::CONTENT_TYPE;
+    use axum::routing;
+    use axum::Router;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn blank_tag_rule_keywords_are_rejected(pool: PgPool) {
+        let tag_id = insert_tag(&pool, "Rust").await;
+        let router = Router::new()
+            .route("/tags/rules", routing::post(create_tag_rule))
+            .with_state(app_state(&pool, Config::default()));
+        let create = |keyword: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/tags/rules")
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::json!({ "keyword": keyword, "tag_id": tag_id }).to_string()))
+                .unwrap()
+        };
+
+        for keyword in ["", "   ", "\t\n"] {
+            assert_eq!(send(&router, create(keyword)).await.status(), StatusCode::BAD_REQUEST);
+        }
+        let rules: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tag_rules").fetch_one(&pool).await.unwrap();
+        assert_eq!(rules, 0);
+
+        assert_eq!(send(&router, create(" rust ")).await.status(), StatusCode::CREATED);
+        let keyword: String = sqlx::query_scalar("SELECT keyword FROM tag_rules").fetch_one(&pool).await.unwrap();
+        assert_eq!(keyword, "rust");
+    }
+}
@@ -3,12 +3,14 @@ use tokio::sync::RwLock;
 
 use crate::config::AppState;
 use crate::models::{
+    schedule_model::{schedule_unlocked, ScheduleError},
     timeslot_assignment_model::{
         timeslot_assignment_swap, timeslot_assignment_update, TimeslotSwapRequest,
     },
     timeslot_model::{
-        timeslots_add, TimeSlot, TimeSlotError, TimeslotAssignmentForm, TimeslotForm,
-        TimeslotRequest, TimeslotRequestWrapper, TimeslotUpdateRequest,
+        timeslot_assignments_get, timeslots_add, timeslots_generate, TimeSlot, TimeSlotErr,
+        TimeSlotError, TimeslotAssignmentForm, TimeslotForm, TimeslotGenerateRequest,
+        TimeslotRequest, TimeslotRequestWrapper, TimeslotRoomAssignment, TimeslotUpdateRequest,
     },
 };
 use axum::debug_handler;
@@ -71,6 +73,58 @@ pub async fn add_timeslots(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/timeslots/generate",
+    request_body(
+        content = TimeslotGenerateRequest,
+        description = "Start time, slot length, and either a count or an end time"
+    ),
+    responses(
+        (status = 201, description = "Created timeslots", body = Vec<TimeSlot>),
+        (status = 400, description = "Bad request", body = TimeSlotError),
+        (status = 409, description = "Timeslots overlap existing timeslots", body = TimeSlotError),
+        (status = 500, description = "Internal server error", body = TimeSlotError),
+    )
+)]
+#[debug_handler]
+/// Creates a run of back to back timeslots
+///
+/// This function is a handler for the route `POST /api/v1/timeslots/generate`. It creates
+/// `count` timeslots of `slot_minutes` each starting at `start_time`, or as many as fit before
+/// `end_time`.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `request` - The start time, slot length, and count or end time of the timeslots
+///
+/// # Returns
+/// `Response` with a status code of 201 Created and a JSON body containing the created timeslots.
+///
+/// # Errors
+/// This function returns a 400 error if the request is invalid, a 409 error if the timeslots would
+/// overlap existing timeslots, and a 500 error if they couldn't be saved.
+pub async fn generate_timeslots(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Json(request): Json<TimeslotGenerateRequest>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+
+    match timeslots_generate(write_lock, request).await {
+        Ok(timeslots) => (StatusCode::CREATED, Json(timeslots)).into_response(),
+        Err(e) => {
+            let status = match e {
+                TimeSlotErr::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+                TimeSlotErr::Overlaps(_) => StatusCode::CONFLICT,
+                TimeSlotErr::DoesNotExist(_) => StatusCode::NOT_FOUND,
+                TimeSlotErr::IoError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            TimeSlotError::response(status.into(), Box::new(e))
+        }
+    }
+}
+
 #[utoipa::path(
     put,
     path = "/api/v1/timeslot/{id}",
@@ -83,6 +137,7 @@ pub async fn add_timeslots(
         (status = 400, description = "Bad request", body = TimeSlotError),
         (status = 404, description = "Timeslot not found", body = TimeSlotError),
         (status = 422, description = "Unprocessable entity", body = TimeSlotError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
     )
 )]
 #[debug_handler]
@@ -105,6 +160,8 @@ pub async fn add_timeslots(
 /// - The timeslot could not be updated
 /// - The timeslot does not exist
 /// - The timeslot is invalid
+///
+/// If the schedule is locked a 423 error is returned.
 pub async fn update_timeslot(
     State(app_state): State<Arc<RwLock<AppState>>>,
     Path(timeslot_id): Path<i32>,
@@ -112,6 +169,9 @@ pub async fn update_timeslot(
 ) -> Response {
     let app_state_lock = app_state.read().await;
     let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(write_lock).await {
+        return ScheduleError::locked_response(e);
+    }
 
     let start_time = match NaiveTime::parse_from_str(&request.start_time, "%H:%M") {
         Ok(time) => time,
@@ -132,6 +192,7 @@ pub async fn update_timeslot(
             let timeslot = TimeslotForm {
                 start_time: request.start_time,
                 duration,
+                max_open_rooms: None,
                 assignments: vec![TimeslotAssignmentForm {
                     session_id: request.session_id,
                     room_id: request.room_id,
@@ -174,6 +235,7 @@ pub async fn update_timeslot(
         (status = 400, description = "Bad request", body = TimeSlotError),
         (status = 404, description = "Timeslot not found", body = TimeSlotError),
         (status = 422, description = "Unprocessable entity", body = TimeSlotError),
+        (status = 423, description = "Schedule is locked", body = ScheduleError),
     )
 )]
 #[debug_handler]
@@ -195,15 +257,62 @@ pub async fn update_timeslot(
 /// - The timeslots could not be updated
 /// - The timeslot does not exist
 /// - The timeslot is invalid
+///
+/// If the schedule is locked a 423 error is returned.
 pub async fn swap_timeslots(
     State(app_state): State<Arc<RwLock<AppState>>>,
     Json(request): Json<TimeslotSwapRequest>,
 ) -> Response {
     let app_state_lock = app_state.read().await;
     let write_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    if let Err(e) = schedule_unlocked(write_lock).await {
+        return ScheduleError::locked_response(e);
+    }
 
     match timeslot_assignment_swap(write_lock, request).await {
         Ok(_) => Json(()).into_response(),
         Err(e) => TimeSlotError::response(StatusCode::INTERNAL_SERVER_ERROR.into(), e),
     }
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/timeslots/{id}/assignments",
+    responses(
+        (status = 200, description = "Rooms and sessions in the timeslot", body = Vec<TimeslotRoomAssignment>),
+        (status = 404, description = "Timeslot not found", body = TimeSlotError),
+        (status = 500, description = "Internal server error", body = TimeSlotError),
+    ),
+    params(
+        ("id" = i32, Path, description = "Timeslot id")
+    )
+)]
+#[debug_handler]
+/// Retrieves the assignments of a single timeslot
+///
+/// This function is a handler for the route `GET /api/v1/timeslots/{id}/assignments`. It lists
+/// every room with the session, speaker and vote count held in it during the timeslot. Empty rooms
+/// are included with a `null` session.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `timeslot_id` - The id of the timeslot
+///
+/// # Returns
+/// `Response` with a status code of 200 OK and a JSON body containing the rooms of the timeslot.
+///
+/// # Errors
+/// If the timeslot doesn't exist a timeslot error response with a status code of 404 Not Found is
+/// returned, otherwise a 500 Internal Server Error is returned.
+pub async fn timeslot_assignments(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    Path(timeslot_id): Path<i32>,
+) -> Response {
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    match timeslot_assignments_get(read_lock, timeslot_id).await {
+        Ok(assignments) => Json(assignments).into_response(),
+        Err(e @ TimeSlotErr::DoesNotExist(_)) => TimeSlotError::response(StatusCode::NOT_FOUND.into(), Box::new(e)),
+        Err(e) => TimeSlotError::response(StatusCode::INTERNAL_SERVER_ERROR.into(), Box::new(e)),
+    }
+}
@@ -6,4 +6,7 @@ pub mod init;
 pub mod middleware;
 pub mod models;
 pub mod routes;
-pub mod types;
\ No newline at end of file
+pub mod types;
+
+#[cfg(test)]
+mod test_support;
@@ -6,8 +6,8 @@ pub use server::*;
 use std::env::var;
 
 use axum::Router;
-use config::AppState;
-use tracing_subscriber::{fmt, EnvFilter};
+use config::{AppState, Config, LogFormat};
+use tracing_subscriber::{fmt, EnvFilter, Layer};
 
 use crate::controllers::site_handler::handler_404;
 use crate::routes::middleware::configure_middleware;
@@ -28,11 +28,25 @@ async fn main() {
         dotenv().ok();
     }
 
-    // Setup formatting and environment for trace
-    setup_tracing().await;
+    // Read the settings, then setup formatting and environment for trace
+    let config = Config::from_env();
+    setup_tracing(config.as_ref().map_or(LogFormat::default(), |config| config.log_format)).await;
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("Failed to start: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     // Connect to database and setup app state
-    let app_state = Arc::new(RwLock::new(AppState::new().await.unwrap()));
+    let app_state = match AppState::new(config).await {
+        Ok(app_state) => Arc::new(RwLock::new(app_state)),
+        Err(e) => {
+            tracing::error!("Failed to start: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     // Initialize defaults from environment variables
     {
@@ -55,7 +69,7 @@ async fn main() {
 
     let listener = tokio::net::TcpListener::bind(ip).await.unwrap();
     tracing::info!("serving {}", listener.local_addr().unwrap());
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap();
@@ -64,16 +78,23 @@ async fn main() {
 /// Set up a tracing subscriber with formatting and filtering
 ///
 /// This functions sets up a tracing subscriber with two layers:
-/// - A formatting layer that includes the file and line number
+/// - A formatting layer that includes the file and line number, either pretty printed or as JSON
 /// - A filter layer that uses the `RUST_LOG` environment variable
 ///
+/// # Parameters
+/// - `log_format` - Whether to pretty print the logs or write them as JSON, from `LOG_FORMAT`
+///
 /// # Environment Variables
 /// - `RUST_LOG` - The log level for the application. If not set, defaults to `info`
 ///
 /// # Panics
 /// This function will panic if the tracing subscriber cannot be initialized
-async fn setup_tracing() {
-    let fmt_layer = fmt::layer().with_file(true).with_line_number(true).pretty();
+async fn setup_tracing(log_format: LogFormat) {
+    let fmt_layer = fmt::layer().with_file(true).with_line_number(true);
+    let fmt_layer = match log_format {
+        LogFormat::Json => fmt_layer.json().boxed(),
+        LogFormat::Pretty => fmt_layer.pretty().boxed(),
+    };
     let filter_layer = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new("info"))
         .unwrap();
@@ -96,8 +117,10 @@ async fn setup_tracing() {
 /// A configured Router with all routes and middleware
 async fn configure_app_router(app_state: Arc<RwLock<AppState>>) -> Router {
     // Get route modules
-    let site_routes = site_routes::get_routes(app_state.clone());
+    let config = app_state.read().await.config.clone();
+    let site_routes = site_routes::get_routes(app_state.clone(), &config);
     let api_routes = api_routes::get_routes(&app_state.clone());
+    let long_running_api_routes = api_routes::get_long_running_routes(&app_state.clone());
     let docs_routes = docs_routes::get_routes(app_state.clone());
 
     // Combine routes
@@ -107,9 +130,12 @@ async fn configure_app_router(app_state: Arc<RwLock<AppState>>) -> Router {
         .merge(docs_routes)
         .with_state(app_state.clone())
         .fallback(handler_404);
+    let long_running_app = Router::new()
+        .nest("/api/v1", long_running_api_routes)
+        .with_state(app_state.clone());
 
     // Add middleware
-    configure_middleware(app, app_state).await
+    configure_middleware(app, long_running_app, app_state).await
 }
 
 async fn shutdown_signal() {
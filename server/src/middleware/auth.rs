@@ -122,6 +122,7 @@ pub async fn current_user_handler(auth_session: AuthSessionLayer) -> Response {
                 lname: user.lname,
                 email: user.email,
                 password: String::new(),
+                vote_weight: user.vote_weight,
             };
             (StatusCode::OK, Json(user_info)).into_response()
         }
@@ -1,3 +1,5 @@
 pub mod auth;
+pub mod rate_limit;
+pub mod score_cache;
 pub mod unauth;
 pub mod unconference_password;
@@ -0,0 +1,212 @@
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Limits how many requests each client can make within a window of time
+///
+/// Every client IP address gets `max_requests` requests per window, counted from its first request
+/// in the window.
+///
+/// # Fields
+/// - `max_requests` - How many requests a client can make per window
+/// - `window` - How long a window lasts
+/// - `clients` - The clients seen in their current window
+#[derive(Debug)]
+pub struct RateLimiter {
+    max_requests: u32,
+    window: Duration,
+    clients: Mutex<Clients>,
+}
+
+/// The clients a `RateLimiter` is counting requests for
+///
+/// # Fields
+/// - `windows` - When each client's current window started and how many requests it has made
+/// - `last_sweep` - When the clients whose window had ended were last forgotten
+#[derive(Debug)]
+struct Clients {
+    windows: HashMap<IpAddr, (Instant, u32)>,
+    last_sweep: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a new `RateLimiter`.
+    ///
+    /// # Parameters
+    /// - `max_requests` - How many requests a client can make per window
+    /// - `window` - How long a window lasts
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+            clients: Mutex::new(Clients { windows: HashMap::new(), last_sweep: Instant::now() }),
+        }
+    }
+
+    /// Creates a `RateLimiter` that allows a number of requests per minute.
+    ///
+    /// # Parameters
+    /// - `max_requests` - How many requests a client can make per minute
+    pub fn per_minute(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(60))
+    }
+
+    /// Records a request from a client and checks whether it is within the limit.
+    ///
+    /// Once per window the clients whose window has ended are forgotten, so the map doesn't grow
+    /// with every client ever seen without every request paying for a pass over it.
+    ///
+    /// # Parameters
+    /// - `client` - The IP address of the client
+    /// - `now` - When the request was made
+    ///
+    /// # Returns
+    /// `Ok(())` if the request is allowed, otherwise `Err` with how long until the client's window
+    /// ends.
+    pub fn check(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut clients = self.clients.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if now.duration_since(clients.last_sweep) >= self.window {
+            clients.windows.retain(|_, (window_start, _)| now.duration_since(*window_start) < self.window);
+            clients.last_sweep = now;
+        }
+
+        let (window_start, requests) = clients.windows.entry(client).or_insert((now, 0));
+        if now.duration_since(*window_start) >= self.window {
+            *window_start = now;
+            *requests = 0;
+        }
+        if *requests >= self.max_requests {
+            return Err(self.window.saturating_sub(now.duration_since(*window_start)));
+        }
+
+        *requests += 1;
+        Ok(())
+    }
+}
+
+/// Works out which client a request came from
+///
+/// Requests passed on by a reverse proxy on the same machine all come from a loopback address, so
+/// for those the client is taken from the `X-Real-IP` header the proxy sets, or failing that the
+/// last address in `X-Forwarded-For`, which the proxy appended. The headers of any other peer are
+/// ignored, since a client could send whatever it likes in them.
+///
+/// # Parameters
+/// - `peer` - The address the request was received from
+/// - `headers` - The request headers
+///
+/// # Returns
+/// The IP address of the client
+fn client_ip(peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+    if !peer.is_loopback() {
+        return peer;
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let real_ip = header("x-real-ip").and_then(|ip| ip.trim().parse().ok());
+    let forwarded_for = || {
+        header("x-forwarded-for")
+            .and_then(|forwarded| forwarded.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok())
+    };
+
+    real_ip
+        .or_else(forwarded_for)
+        .unwrap_or(peer)
+}
+
+/// Rejects clients that have gone over the rate limit
+///
+/// Requests over the limit get a 429 Too Many Requests response with a `Retry-After` header.
+///
+/// # Parameters
+/// - `limiter` - The rate limiter to count the request against
+/// - `addr` - The address the request was received from, see `client_ip`
+/// - `req` - The request object
+/// - `next` - The rest of the middleware stack
+///
+/// # Returns
+/// A `Response`
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match limiter.check(client_ip(addr.ip(), req.headers()), Instant::now()) {
+        Ok(()) => next.run(req).await,
+        Err(retry_after) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+        ).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn rate_limiter_limits_each_client_per_window() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let other_client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let start = Instant::now();
+
+        assert_eq!(limiter.check(client, start), Ok(()));
+        assert_eq!(limiter.check(client, start + Duration::from_secs(1)), Ok(()));
+        assert_eq!(limiter.check(client, start + Duration::from_secs(20)), Err(Duration::from_secs(40)));
+        assert_eq!(limiter.check(other_client, start + Duration::from_secs(20)), Ok(()));
+
+        // A new window starts once the old one has ended
+        assert_eq!(limiter.check(client, start + Duration::from_secs(60)), Ok(()));
+    }
+
+    #[test]
+    fn rate_limiter_forgets_ended_windows_once_per_window() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let start = Instant::now();
+        let seen = |limiter: &RateLimiter| limiter.clients.lock().unwrap().windows.len();
+        let check = |client: u8, secs: u64| limiter.check(IpAddr::V4(Ipv4Addr::new(10, 0, 0, client)), start + Duration::from_secs(secs));
+
+        assert_eq!(check(1, 0), Ok(()));
+        assert_eq!(check(2, 20), Ok(()));
+        assert_eq!(seen(&limiter), 2);
+
+        // A window after the limiter started, the first client's ended window is forgotten
+        assert_eq!(check(3, 70), Ok(()));
+        assert_eq!(seen(&limiter), 2);
+
+        // The second client's window has ended too, but it waits for the next sweep
+        assert_eq!(check(4, 90), Ok(()));
+        assert_eq!(seen(&limiter), 3);
+
+        assert_eq!(check(5, 130), Ok(()));
+        assert_eq!(seen(&limiter), 2);
+    }
+
+    #[test]
+    fn client_ip_trusts_forwarded_headers_only_from_the_proxy() {
+        let proxy = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let direct = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1));
+        let client = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7));
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, value.parse().unwrap());
+            }
+            headers
+        };
+
+        assert_eq!(client_ip(proxy, &headers(&[("x-real-ip", "198.51.100.7")])), client);
+        assert_eq!(client_ip(proxy, &headers(&[("x-forwarded-for", "10.9.9.9, 198.51.100.7")])), client);
+        assert_eq!(client_ip(proxy, &headers(&[])), proxy);
+        assert_eq!(client_ip(direct, &headers(&[("x-real-ip", "198.51.100.7")])), direct);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::config::AppState;
+use axum::extract::{Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Invalidates the cached schedule score after every change
+///
+/// Any request other than a `GET`, `HEAD` or `OPTIONS` that succeeds may have changed the schedule
+/// or the votes it is scored on, so the score is worked out again the next time it is asked for.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `req` - The request object
+/// - `next` - The rest of the middleware stack
+///
+/// # Returns
+/// The `Response` of the rest of the middleware stack
+pub async fn score_cache_middleware(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let read_only = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let response = next.run(req).await;
+
+    if !read_only && response.status().is_success() {
+        app_state.read().await.score_cache.invalidate();
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::controllers::schedule_handler::{add_session_to_schedule, score, unschedule_session_from_schedule};
+    use crate::controllers::timeslot_handler::{swap_timeslots, update_timeslot};
+    use crate::test_support::{app_state, assign_session, insert_room, insert_session, insert_timeslot, insert_user, insert_vote, send};
+    use axum::body::Body;
+    use axum::http::header::CONTENT_TYPE;
+    use axum::http::StatusCode;
+    use axum::middleware::from_fn_with_state;
+    use axum::routing::{delete, get, post, put};
+    use axum::Router;
+    use sqlx::PgPool;
+
+    fn router(pool: &PgPool) -> Router {
+        let app_state = app_state(pool, Config::default());
+
+        Router::new()
+            .route("/schedules/score", get(score))
+            .route("/schedules/add_session", post(add_session_to_schedule))
+            .route("/schedules/session/{session_id}", delete(unschedule_session_from_schedule))
+            .route("/timeslots/{id}", put(update_timeslot))
+            .route("/timeslots/swap", put(swap_timeslots))
+            .layer(from_fn_with_state(app_state.clone(), score_cache_middleware))
+            .with_state(app_state)
+    }
+
+    async fn penalty(router: &Router, name: &str) -> i64 {
+        let response = send(router, Request::get("/schedules/score").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        json[name].as_i64().unwrap()
+    }
+
+    fn add_session(session_id: i32) -> Request {
+        Request::post("/schedules/add_session")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(format!("{{\"session_id\": {session_id}}}")))
+            .unwrap()
+    }
+
+    fn put_json(uri: String, body: serde_json::Value) -> Request {
+        Request::put(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    /// Schedules an unvoted session at 09:00 and two voted ones side by side at 10:00
+    ///
+    /// Returns the IDs of the timeslots, the rooms and the session in the second room at 10:00.
+    async fn late_schedule(pool: &PgPool) -> ([i32; 2], [i32; 2], i32) {
+        let user_id = insert_user(pool, "A", "User", "a@example.com").await;
+        let unvoted_id = insert_session(pool, user_id, "Unvoted").await;
+        let first_voted_id = insert_session(pool, user_id, "First voted").await;
+        let second_voted_id = insert_session(pool, user_id, "Second voted").await;
+        insert_vote(pool, user_id, first_voted_id).await;
+        insert_vote(pool, user_id, second_voted_id).await;
+        let room_ids = [insert_room(pool, "Room", "Here", 10).await, insert_room(pool, "Other room", "There", 10).await];
+        let time_slot_ids = [insert_timeslot(pool, "09:00", "10:00").await, insert_timeslot(pool, "10:00", "11:00").await];
+        assign_session(pool, time_slot_ids[0], room_ids[0], unvoted_id).await;
+        assign_session(pool, time_slot_ids[1], room_ids[0], first_voted_id).await;
+        assign_session(pool, time_slot_ids[1], room_ids[1], second_voted_id).await;
+
+        (time_slot_ids, room_ids, second_voted_id)
+    }
+
+    #[sqlx::test]
+    async fn schedule_edits_invalidate_the_cached_score(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let voted_id = insert_session(&pool, user_id, "Voted").await;
+        let unvoted_id = insert_session(&pool, user_id, "Unvoted").await;
+        let filler_id = insert_session(&pool, user_id, "Filler").await;
+        insert_vote(&pool, user_id, voted_id).await;
+        let room_id = insert_room(&pool, "Room", "Here", 10).await;
+        let filler_room_id = insert_room(&pool, "Other room", "There", 10).await;
+        insert_room(&pool, "Third room", "Elsewhere", 10).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        assign_session(&pool, time_slot_id, filler_room_id, filler_id).await;
+        let router = router(&pool);
+
+        // The voted session is left out for the unvoted filler
+        assert_eq!(penalty(&router, "missing").await, 15);
+
+        // Changes that don't go through the API aren't noticed until the cache is invalidated
+        assign_session(&pool, time_slot_id, room_id, voted_id).await;
+        assert_eq!(penalty(&router, "missing").await, 15);
+
+        // A failed edit doesn't invalidate the cache
+        let response = send(&router, Request::delete(format!("/schedules/session/{unvoted_id}")).body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(penalty(&router, "missing").await, 15);
+
+        let response = send(&router, add_session(unvoted_id)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(penalty(&router, "missing").await, 0);
+
+        // Now left out for both unvoted sessions
+        let response = send(&router, Request::delete(format!("/schedules/session/{voted_id}")).body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(penalty(&router, "missing").await, 30);
+    }
+
+    #[sqlx::test]
+    async fn moving_a_session_invalidates_the_cached_score(pool: PgPool) {
+        let (time_slot_ids, room_ids, session_id) = late_schedule(&pool).await;
+        let router = router(&pool);
+
+        // The two voted sessions clash in the later timeslot
+        assert_eq!(penalty(&router, "late").await, 1);
+
+        let response = send(&router, put_json(
+            format!("/timeslots/{}", time_slot_ids[1]),
+            serde_json::json!({
+                "start_time": "09:00",
+                "end_time": "10:00",
+                "session_id": session_id,
+                "room_id": room_ids[1],
+                "old_room_id": room_ids[1],
+            }),
+        )).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(penalty(&router, "late").await, 0);
+    }
+
+    #[sqlx::test]
+    async fn swapping_sessions_invalidates_the_cached_score(pool: PgPool) {
+        let (time_slot_ids, room_ids, _) = late_schedule(&pool).await;
+        let router = router(&pool);
+
+        assert_eq!(penalty(&router, "late").await, 1);
+
+        let response = send(&router, put_json(
+            "/timeslots/swap".to_string(),
+            serde_json::json!({
+                "timeslot_id_1": time_slot_ids[0],
+                "room_id_1": room_ids[0],
+                "timeslot_id_2": time_slot_ids[1],
+                "room_id_2": room_ids[0],
+            }),
+        )).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(penalty(&router, "late").await, 0);
+    }
+}
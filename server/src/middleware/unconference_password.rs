@@ -1,27 +1,206 @@
-use axum::extract::Request;
+use crate::config::AppState;
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Redirect, Response};
+use axum::Json;
+use std::sync::Arc;
+use tokio::sync::RwLock;
 use tower_sessions::Session;
 
+/// Checks whether the visitor can access the unconference
+///
+/// Access is granted when no unconference password has been configured, or when the visitor has
+/// entered it on the unconference login page. If the password can't be looked up access is denied.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `session` - The site session
+///
+/// # Returns
+/// `true` if the visitor can access the unconference
+async fn unconference_access_granted(app_state: &Arc<RwLock<AppState>>, session: &Session) -> bool {
+    if let Ok(Some(true)) = session.get::<bool>("unconference_authenticated").await {
+        return true;
+    }
+
+    let app_state_lock = app_state.read().await;
+    let read_lock = &app_state_lock.unconf_data.read().await.unconf_db;
+    let password_configured = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM conference_password) as "exists!""#
+    )
+        .fetch_one(read_lock)
+        .await;
+
+    match password_configured {
+        Ok(password_configured) => !password_configured,
+        Err(e) => {
+            tracing::error!("Failed to check for an unconference password: {:?}", e);
+            false
+        }
+    }
+}
+
 /// Configures middleware unconference access
 ///
 /// This function ensures only those who have logged in to the general unconference login can
-/// access the site
+/// access the site. When no unconference password is configured the site is open.
 ///
 /// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
 /// - `session` - The site session
 /// - `req` - The request object
 /// - `next` - The rest of the middleware stack
 ///
 /// # Returns
-/// A `Response`
-pub async fn unconference_password_middleware(session: Session, req: Request, next: Next) -> Response {
-    match session.get::<bool>("unconference_authenticated").await {
-        Ok(Some(true)) => {
-            next.run(req).await
-        }
-        _ => {
-            Redirect::to("/unconference_login").into_response()
+/// A `Response`, redirecting to the unconference login page if access isn't granted
+pub async fn unconference_password_middleware(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    session: Session,
+    req: Request,
+    next: Next,
+) -> Response {
+    if unconference_access_granted(&app_state, &session).await {
+        next.run(req).await
+    } else {
+        Redirect::to("/unconference_login").into_response()
+    }
+}
+
+/// Configures middleware unconference access for the API
+///
+/// This function is the API counterpart of `unconference_password_middleware`, instead of
+/// redirecting it responds with 401 Unauthorized so API clients can tell why they were turned away.
+///
+/// # Parameters
+/// - `app_state` - Thread-safe shared state wrapped in an Arc and RwLock
+/// - `session` - The site session
+/// - `req` - The request object
+/// - `next` - The rest of the middleware stack
+///
+/// # Returns
+/// A `Response`, with a status code of 401 Unauthorized if access isn't granted
+pub async fn unconference_password_api_middleware(
+    State(app_state): State<Arc<RwLock<AppState>>>,
+    session: Session,
+    req: Request,
+    next: Next,
+) -> Response {
+    if unconference_access_granted(&app_state, &session).await {
+        next.run(req).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "success": "false",
+                "message": "Unconference password required"
+            })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::controllers::login_handler::unconference_password_submit_handler;
+    use crate::test_support::{app_state, send};
+    use axum::body::Body;
+    use axum::http::header::{CONTENT_TYPE, COOKIE, LOCATION, SET_COOKIE};
+    use axum::middleware::from_fn_with_state;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+    use tower_sessions::{MemoryStore, SessionManagerLayer};
+
+    fn router(pool: &PgPool) -> Router {
+        let app_state = app_state(pool, Config::default());
+
+        let open_routes = Router::new()
+            .route("/open", get(|| async { "open" }))
+            .route("/unconference_login", post(unconference_password_submit_handler));
+        let gated_routes = Router::new()
+            .route("/gated", get(|| async { "gated" }))
+            .route_layer(from_fn_with_state(app_state.clone(), unconference_password_middleware));
+        let gated_api_routes = Router::new()
+            .route("/api/gated", get(|| async { "gated" }))
+            .route_layer(from_fn_with_state(app_state.clone(), unconference_password_api_middleware));
+
+        open_routes
+            .merge(gated_routes)
+            .merge(gated_api_routes)
+            .with_state(app_state)
+            .layer(SessionManagerLayer::new(MemoryStore::default()))
+    }
+
+    async fn get_with_cookie(router: &Router, uri: &str, cookie: Option<&str>) -> Response {
+        let mut request = Request::get(uri);
+        if let Some(cookie) = cookie {
+            request = request.header(COOKIE, cookie);
         }
+
+        send(router, request.body(Body::empty()).unwrap()).await
+    }
+
+    async fn set_password(pool: &PgPool, password: &str) {
+        sqlx::query("INSERT INTO conference_password (password) VALUES ($1)")
+            .bind(bcrypt::hash(password, 4).unwrap())
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn routes_are_open_without_a_password(pool: PgPool) {
+        let router = router(&pool);
+
+        assert_eq!(get_with_cookie(&router, "/open", None).await.status(), StatusCode::OK);
+        assert_eq!(get_with_cookie(&router, "/gated", None).await.status(), StatusCode::OK);
+        assert_eq!(get_with_cookie(&router, "/api/gated", None).await.status(), StatusCode::OK);
+    }
+
+    #[sqlx::test]
+    async fn gated_routes_require_the_password(pool: PgPool) {
+        set_password(&pool, "letmein").await;
+        let router = router(&pool);
+
+        assert_eq!(get_with_cookie(&router, "/open", None).await.status(), StatusCode::OK);
+
+        let response = get_with_cookie(&router, "/gated", None).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(response.headers()[LOCATION], "/unconference_login");
+
+        let response = get_with_cookie(&router, "/api/gated", None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[sqlx::test]
+    async fn entering_the_password_opens_gated_routes(pool: PgPool) {
+        set_password(&pool, "letmein").await;
+        let router = router(&pool);
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post("/unconference_login")
+                    .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(Body::from("password=letmein"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let cookie = response.headers()[SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(get_with_cookie(&router, "/gated", Some(&cookie)).await.status(), StatusCode::OK);
+        assert_eq!(get_with_cookie(&router, "/api/gated", Some(&cookie)).await.status(), StatusCode::OK);
     }
-}
\ No newline at end of file
+}
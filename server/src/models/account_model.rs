@@ -0,0 +1,456 @@
+use crate::middleware::auth::AuthInfo;
+use crate::models::auth_model::User;
+use crate::types::ApiStatusCode;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use sqlx::{Pool, Postgres};
+use std::error::Error;
+use utoipa::ToSchema;
+
+/// Email address used for the placeholder account that receives reassigned sessions when the
+/// `ANONYMOUS_USER_EMAIL` environment variable is not set
+pub const DEFAULT_ANONYMOUS_USER_EMAIL: &str = "anonymous@unconfrs.local";
+
+#[derive(Debug, thiserror::Error, ToSchema, Serialize)]
+/// An enumeration of possible errors that can occur when working with accounts.
+///
+/// # Variants
+/// - `InvalidPassword` - The password supplied to confirm the action was incorrect
+/// - `DoesNotExist` - The account does not exist
+/// - `AnonymousAccount` - The anonymous account that holds reassigned sessions can't be deleted
+/// - `NegativeVoteWeight` - A user's votes can't count against the sessions they voted for
+pub enum AccountErr {
+    #[error("Password is incorrect")]
+    InvalidPassword,
+    #[error("Account {0} doesn't exist")]
+    DoesNotExist(String),
+    #[error("The anonymous account cannot be deleted")]
+    AnonymousAccount,
+    #[error("Vote weight {0} can't be negative")]
+    NegativeVoteWeight(i32),
+}
+
+/// Struct representing an error that occurred when working with accounts.
+///
+/// # Fields
+/// - `status` - The HTTP status code associated with the error
+/// - `error` - A string describing the specific error that occurred
+#[derive(Debug, ToSchema)]
+pub struct AccountError {
+    pub status: ApiStatusCode,
+    pub error: String,
+}
+
+/// Implements the `Serialize` trait for `AccountError`
+///
+/// This implementation serializes an `AccountError` into a JSON object with two properties:
+/// `status` and `error`.
+impl Serialize for AccountError {
+    /// Serializes an `AccountError`
+    ///
+    /// The serialized JSON object will have two properties:
+    /// - `status`: A string for the HTTP status code
+    /// - `error`: A string describing the error
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let status: String = self.status.to_string();
+        let mut state = serializer.serialize_struct("AccountError", 2)?;
+        state.serialize_field("status", &status)?;
+        state.serialize_field("error", &self.error)?;
+        state.end()
+    }
+}
+
+impl AccountError {
+    /// Creates a `Response` instance from a `StatusCode` and `AccountErr`.
+    ///
+    /// # Parameters
+    /// - `status`: The HTTP status code.
+    /// - `error`: The `AccountErr` instance.
+    ///
+    /// # Returns
+    /// `Response` instance with the status code and JSON body containing the error.
+    pub fn response(status: ApiStatusCode, error: Box<dyn Error>) -> Response {
+        let error = AccountError {
+            status,
+            error: error.to_string(),
+        };
+
+        let http_status = StatusCode::from_u16(status.0)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        (http_status, Json(error)).into_response()
+    }
+}
+
+/// What happens to the sessions a user submitted when their account is deleted.
+///
+/// # Variants
+/// - `DeleteContent` - The user's sessions are deleted along with any votes and tags on them
+/// - `ReassignContent` - The user's sessions are handed over to the anonymous account so the
+///   schedule stays intact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionStrategy {
+    DeleteContent,
+    ReassignContent,
+}
+
+/// Struct representing a request to delete the current user's account.
+///
+/// # Fields
+/// - `password` - The user's current password, required to confirm the deletion
+/// - `strategy` - Overrides the configured `DeletionStrategy` (optional)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+    pub strategy: Option<DeletionStrategy>,
+}
+
+/// Struct representing how much a user's votes count for when scheduling.
+///
+/// # Fields
+/// - `user_id` - The ID of the user, ignored in requests
+/// - `vote_weight` - What each of the user's votes counts as, 1 for everyone by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct VoteWeight {
+    #[serde(default, skip_deserializing)]
+    pub user_id: i32,
+    pub vote_weight: i32,
+}
+
+/// Struct representing who is logged in, so a UI can show what the user may do.
+///
+/// Only `is_authenticated` is included when nobody is logged in.
+///
+/// # Fields
+/// - `user_id` - The ID of the user
+/// - `email` - The user's email address
+/// - `name` - The user's first and last name
+/// - `permissions` - The names of the user's permissions, sorted
+/// - `is_authenticated` - Whether a user is logged in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct CurrentUser {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Vec<String>>,
+    pub is_authenticated: bool,
+}
+
+impl CurrentUser {
+    /// Describes the logged in user, if there is one
+    ///
+    /// # Parameters
+    /// - `user`: The logged in user, `None` when nobody is logged in
+    /// - `auth_info`: The permissions of the user
+    ///
+    /// # Returns
+    /// The `CurrentUser`
+    pub(crate) fn new(user: Option<&User>, auth_info: &AuthInfo) -> Self {
+        let Some(user) = user.filter(|_| auth_info.is_authenticated) else {
+            return Self { user_id: None, email: None, name: None, permissions: None, is_authenticated: false };
+        };
+
+        let mut permissions: Vec<String> = auth_info.permissions
+            .iter()
+            .map(|permission| permission.name.clone())
+            .collect();
+        permissions.sort();
+
+        Self {
+            user_id: Some(user.id),
+            email: Some(user.email.clone()),
+            name: Some(format!("{} {}", user.fname, user.lname)),
+            permissions: Some(permissions),
+            is_authenticated: true,
+        }
+    }
+}
+
+/// Deletes a user's account
+///
+/// This function removes the user along with their votes and group memberships in a single
+/// transaction. Depending on the `strategy` the user's sessions are either deleted or reassigned to
+/// the anonymous account, which is created if it doesn't exist yet. The anonymous account has no
+/// usable password so it can't be logged into.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool.
+/// - `user_id`: The ID of the user to delete.
+/// - `strategy`: What to do with the user's sessions.
+/// - `anonymous_email`: The email address of the anonymous account, from `Config`.
+///
+/// # Returns
+/// An empty `Result` if the account was deleted or an error if the query fails.
+///
+/// # Errors
+/// If the user doesn't exist, is the anonymous account, or a query fails, a boxed error is
+/// returned and no changes are made.
+pub async fn delete_account(
+    db_pool: &Pool<Postgres>,
+    user_id: i32,
+    strategy: DeletionStrategy,
+    anonymous_email: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut tx = db_pool.begin().await?;
+
+    let email = sqlx::query_scalar!("SELECT email FROM users WHERE id = $1", user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AccountErr::DoesNotExist(user_id.to_string()))?;
+
+    if email == anonymous_email {
+        return Err(Box::new(AccountErr::AnonymousAccount));
+    }
+
+    match strategy {
+        DeletionStrategy::DeleteContent => {
+            // Votes and tags on the sessions cascade, schedule assignments have to be removed first
+            sqlx::query!(
+                "DELETE FROM timeslot_assignments WHERE session_id IN (SELECT id FROM sessions WHERE user_id = $1)",
+                user_id,
+            )
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query!("DELETE FROM sessions WHERE user_id = $1", user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        DeletionStrategy::ReassignContent => {
+            let anonymous_user_id = sqlx::query_scalar!(
+                r"INSERT INTO users (fname, lname, email, password) VALUES ('Anonymous', 'User', $1, '!')
+                ON CONFLICT (email) DO UPDATE SET email = EXCLUDED.email
+                RETURNING id",
+                anonymous_email,
+            )
+                .fetch_one(&mut *tx)
+                .await?;
+
+            sqlx::query!(
+                "UPDATE sessions SET user_id = $1 WHERE user_id = $2",
+                anonymous_user_id,
+                user_id,
+            )
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    sqlx::query!(
+        "UPDATE timeslot_assignments SET creator = NULL WHERE creator = $1",
+        user_id,
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!("DELETE FROM users_groups WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // The user's votes are removed by the cascade on user_votes
+    sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Sets how much a user's votes count for when scheduling, e.g. to let program committee members'
+/// votes count more than attendees'.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool.
+/// - `user_id`: The ID of the user.
+/// - `vote_weight`: What each of the user's votes counts as, 0 to leave them out.
+///
+/// # Returns
+/// A `Result` containing the user's new `VoteWeight` or an error if the query fails.
+///
+/// # Errors
+/// If the weight is negative an `AccountErr::NegativeVoteWeight` error is returned, and if the user
+/// doesn't exist an `AccountErr::DoesNotExist` error. If the query fails, a boxed error is returned.
+pub async fn vote_weight_set(
+    db_pool: &Pool<Postgres>,
+    user_id: i32,
+    vote_weight: i32,
+) -> Result<VoteWeight, Box<dyn Error>> {
+    if vote_weight < 0 {
+        return Err(Box::new(AccountErr::NegativeVoteWeight(vote_weight)));
+    }
+
+    sqlx::query_as!(
+        VoteWeight,
+        r#"UPDATE users SET vote_weight = $2 WHERE id = $1
+        RETURNING id as user_id, vote_weight"#,
+        user_id,
+        vote_weight,
+    )
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| Box::new(AccountErr::DoesNotExist(user_id.to_string())) as Box<dyn Error>)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::auth_model::Permission;
+    use sqlx::PgPool;
+    use std::collections::HashSet;
+
+    #[test]
+    fn current_user_is_only_described_when_logged_in() {
+        let user = User {
+            id: 7,
+            fname: "Ada".to_string(),
+            lname: "Lovelace".to_string(),
+            email: "ada@example.com".to_string(),
+            password: "hash".to_string(),
+            vote_weight: 1,
+        };
+        let logged_in = AuthInfo {
+            is_authenticated: true,
+            is_staff_or_admin: true,
+            permissions: HashSet::from([Permission::from("staff"), Permission::from("default")]),
+        };
+
+        let current_user = CurrentUser::new(Some(&user), &logged_in);
+        assert_eq!(current_user, CurrentUser {
+            user_id: Some(7),
+            email: Some("ada@example.com".to_string()),
+            name: Some("Ada Lovelace".to_string()),
+            permissions: Some(vec!["default".to_string(), "staff".to_string()]),
+            is_authenticated: true,
+        });
+        assert!(!serde_json::to_string(&current_user).unwrap().contains("hash"));
+
+        let anonymous = AuthInfo {
+            is_authenticated: false,
+            is_staff_or_admin: false,
+            permissions: HashSet::from([Permission::from("default")]),
+        };
+        let json = serde_json::to_value(CurrentUser::new(None, &anonymous)).unwrap();
+        assert_eq!(json, serde_json::json!({ "is_authenticated": false }));
+    }
+
+    /// Creates a user with a scheduled session that another user voted for, and has them vote for
+    /// the other user's session
+    ///
+    /// Returns the ids of the user to delete, the other user, and the session to delete
+    async fn seed_user_with_content(pool: &PgPool) -> (i32, i32, i32) {
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (fname, lname, email, password) VALUES ('Leaving', 'User', 'leaving@example.com', 'x') RETURNING id",
+        )
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        let other_user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (fname, lname, email, password) VALUES ('Staying', 'User', 'staying@example.com', 'x') RETURNING id",
+        )
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO users_groups (user_id, group_id) VALUES ($1, (SELECT id FROM groups WHERE name = 'user'))")
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let session_id: i32 = sqlx::query_scalar(
+            "INSERT INTO sessions (user_id, title, content, votes) VALUES ($1, 'Leaving talk', 'content', 0) RETURNING id",
+        )
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        let other_session_id: i32 = sqlx::query_scalar(
+            "INSERT INTO sessions (user_id, title, content, votes) VALUES ($1, 'Staying talk', 'content', 0) RETURNING id",
+        )
+            .bind(other_user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $2), ($3, $4)")
+            .bind(other_user_id)
+            .bind(session_id)
+            .bind(user_id)
+            .bind(other_session_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let room_id: i32 = sqlx::query_scalar(
+            "INSERT INTO rooms (name, location, available_spots) VALUES ('Room', 'Here', 10) RETURNING id",
+        )
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        let time_slot_id: i32 = sqlx::query_scalar(
+            "INSERT INTO time_slots (start_time, end_time, duration) VALUES ('09:00', '10:00', '1 hour') RETURNING id",
+        )
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO timeslot_assignments (time_slot_id, creator, session_id, room_id) VALUES ($1, $2, $3, $4)")
+            .bind(time_slot_id)
+            .bind(user_id)
+            .bind(session_id)
+            .bind(room_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        (user_id, other_user_id, session_id)
+    }
+
+    async fn count(pool: &PgPool, query: &str, id: i32) -> i64 {
+        sqlx::query_scalar(query).bind(id).fetch_one(pool).await.unwrap()
+    }
+
+    #[sqlx::test]
+    async fn delete_content_removes_sessions_and_votes(pool: PgPool) {
+        let (user_id, other_user_id, session_id) = seed_user_with_content(&pool).await;
+
+        delete_account(&pool, user_id, DeletionStrategy::DeleteContent, DEFAULT_ANONYMOUS_USER_EMAIL).await.unwrap();
+
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM users WHERE id = $1", user_id).await, 0);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM sessions WHERE id = $1", session_id).await, 0);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM timeslot_assignments WHERE session_id = $1", session_id).await, 0);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM user_votes WHERE user_id = $1", user_id).await, 0);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM user_votes WHERE user_id = $1", other_user_id).await, 0);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM sessions WHERE user_id = $1", other_user_id).await, 1);
+    }
+
+    #[sqlx::test]
+    async fn reassign_content_moves_sessions_to_anonymous_user(pool: PgPool) {
+        let (user_id, other_user_id, session_id) = seed_user_with_content(&pool).await;
+
+        delete_account(&pool, user_id, DeletionStrategy::ReassignContent, DEFAULT_ANONYMOUS_USER_EMAIL).await.unwrap();
+
+        let owner_email: String = sqlx::query_scalar(
+            "SELECT users.email FROM sessions JOIN users ON users.id = sessions.user_id WHERE sessions.id = $1",
+        )
+            .bind(session_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(owner_email, DEFAULT_ANONYMOUS_USER_EMAIL);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM users WHERE id = $1", user_id).await, 0);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM timeslot_assignments WHERE session_id = $1", session_id).await, 1);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM user_votes WHERE user_id = $1", user_id).await, 0);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM user_votes WHERE user_id = $1", other_user_id).await, 1);
+    }
+}
@@ -82,6 +82,7 @@ pub struct User {
     pub(crate) email: String,
     #[serde(skip_serializing)]
     pub(crate) password: String,
+    pub(crate) vote_weight: i32,
 }
 
 
@@ -0,0 +1,860 @@
+use crate::models::room_model::rooms_get;
+use crate::models::schedule_snapshot_model::SnapshotAssignment;
+use crate::models::settings_model::{pseudonym_key_get, user_pseudonym};
+use crate::models::tags_model::{get_all_tags, Tag};
+use crate::models::timeslot_model::{timeslot_get, ExistingTimeslot};
+use crate::types::ApiStatusCode;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use sqlx::{FromRow, PgConnection, Pool, Postgres};
+use std::collections::HashMap;
+use std::error::Error;
+use utoipa::ToSchema;
+
+/// The version of the event bundle format written by `bundle_export`.
+pub const BUNDLE_VERSION: i32 = 1;
+
+/// How many rows are read at a time from the tables that grow with the event.
+const BUNDLE_PAGE_SIZE: i64 = 500;
+
+/// An enumeration of errors that may occur when exporting or importing an event bundle.
+///
+/// # Variants
+/// - `IoError` - Reading or writing the event data failed
+/// - `VersionMismatch` - The bundle was written in a format this server can't read
+/// - `InvalidBundle` - The bundle refers to data it doesn't contain
+#[derive(Debug, thiserror::Error, ToSchema, Serialize)]
+pub enum BundleErr {
+    #[error("Bundle io failed: {0}")]
+    IoError(String),
+    #[error("Bundle version {0} is not supported, expected version {BUNDLE_VERSION}")]
+    VersionMismatch(i32),
+    #[error("Invalid bundle: {0}")]
+    InvalidBundle(String),
+}
+
+impl From<sqlx::Error> for BundleErr {
+    fn from(e: sqlx::Error) -> Self {
+        BundleErr::IoError(e.to_string())
+    }
+}
+
+/// Struct representing an error that occurred when working with event bundles.
+///
+/// # Fields
+/// - `status` - The HTTP status code associated with the error
+/// - `error` - A string describing the specific error that occurred
+#[derive(Debug, ToSchema)]
+pub struct BundleError {
+    pub status: ApiStatusCode,
+    pub error: String,
+}
+
+/// Implements the `Serialize` trait for `BundleError`
+///
+/// This implementation serializes a `BundleError` into a JSON object with two properties:
+/// `status` and `error`.
+impl Serialize for BundleError {
+    /// Serializes a `BundleError`
+    ///
+    /// The serialized JSON object will have two properties:
+    /// - `status`: A string for the HTTP status code
+    /// - `error`: A string describing the error
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let status: String = self.status.to_string();
+        let mut state = serializer.serialize_struct("BundleError", 2)?;
+        state.serialize_field("status", &status)?;
+        state.serialize_field("error", &self.error)?;
+        state.end()
+    }
+}
+
+impl BundleError {
+    /// Creates a `Response` instance from a `StatusCode` and error.
+    ///
+    /// # Parameters
+    /// - `status`: The HTTP status code.
+    /// - `error`: The error that occurred.
+    ///
+    /// # Returns
+    /// `Response` instance with the status code and JSON body containing the error.
+    pub fn response(status: ApiStatusCode, error: Box<dyn Error>) -> Response {
+        let error = BundleError {
+            status,
+            error: error.to_string(),
+        };
+
+        let http_status = StatusCode::from_u16(status.0)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        (http_status, Json(error)).into_response()
+    }
+}
+
+/// Struct representing a room in an event bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BundleRoom {
+    pub id: i32,
+    pub name: String,
+    pub location: String,
+    pub available_spots: i32,
+}
+
+/// Struct representing a session in an event bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema, FromRow)]
+pub struct BundleSession {
+    pub id: i32,
+    pub user_id: i32,
+    pub title: String,
+    pub content: String,
+    pub votes: i32,
+}
+
+/// Struct representing the user who submitted sessions in an event bundle.
+///
+/// # Fields
+/// - `user_id` - The ID of the user, matching `BundleSession::user_id`
+/// - `name` - The user's name, or a pseudonym such as "Speaker 1" when anonymized
+/// - `email` - The user's email, left out when anonymized
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema, FromRow)]
+pub struct BundleSpeaker {
+    pub user_id: i32,
+    pub name: String,
+    pub email: Option<String>,
+}
+
+/// Struct representing a tag applied to a session in an event bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, FromRow)]
+pub struct BundleSessionTag {
+    pub session_id: i32,
+    pub tag_id: i32,
+}
+
+/// Struct representing the number of votes a session received, without who cast them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, FromRow)]
+pub struct BundleVoteCount {
+    pub session_id: i32,
+    pub votes: i64,
+}
+
+/// Struct representing all the data of an event, used to back it up or move it between
+/// environments.
+///
+/// # Fields
+/// - `version` - The version of the bundle format
+/// - `exported_at` - When the bundle was exported
+/// - `rooms` - The rooms of the event
+/// - `timeslots` - The timeslots of the event
+/// - `sessions` - The sessions submitted to the event
+/// - `speakers` - The users who submitted the sessions
+/// - `tags` - The available tags
+/// - `session_tags` - The tags applied to each session
+/// - `votes` - The number of votes each voted-for session received
+/// - `assignments` - The current schedule
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EventBundle {
+    pub version: i32,
+    pub exported_at: DateTime<Utc>,
+    pub rooms: Vec<BundleRoom>,
+    pub timeslots: Vec<ExistingTimeslot>,
+    pub sessions: Vec<BundleSession>,
+    #[serde(default)]
+    pub speakers: Vec<BundleSpeaker>,
+    pub tags: Vec<Tag>,
+    pub session_tags: Vec<BundleSessionTag>,
+    pub votes: Vec<BundleVoteCount>,
+    pub assignments: Vec<SnapshotAssignment>,
+}
+
+/// How an imported bundle is combined with the event data already in the database.
+///
+/// # Variants
+/// - `Merge` - The bundle is added alongside the existing data
+/// - `Replace` - The existing event data is removed before the bundle is added
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    #[default]
+    Merge,
+    Replace,
+}
+
+/// Struct representing the query parameters of a bundle import.
+///
+/// # Fields
+/// - `mode` - How to combine the bundle with the existing data, defaults to merging
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportBundleQuery {
+    pub mode: Option<ImportMode>,
+}
+
+/// Struct representing the query parameters for exporting a bundle.
+///
+/// # Fields
+/// - `anonymize` - Whether to replace speaker names and emails with pseudonyms
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExportBundleQuery {
+    pub anonymize: Option<bool>,
+}
+
+/// Struct representing how much of a bundle was imported.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ImportSummary {
+    pub rooms: i32,
+    pub timeslots: i32,
+    pub sessions: i32,
+    pub tags: i32,
+    pub session_tags: i32,
+    pub votes: i32,
+    pub assignments: i32,
+}
+
+/// Reads the sessions a page at a time.
+///
+/// # Parameters
+/// - `conn` - The connection of the export's transaction
+///
+/// # Returns
+/// A `Result` containing every session ordered by ID or a `BundleErr` error.
+///
+/// # Errors
+/// If a page can't be read, a `BundleErr` error is returned.
+async fn bundle_sessions_get(conn: &mut PgConnection) -> Result<Vec<BundleSession>, BundleErr> {
+    let mut sessions = Vec::new();
+    let mut last_id = 0;
+    loop {
+        let page = sqlx::query_as!(
+            BundleSession,
+            "SELECT id, user_id, title, content, votes FROM sessions WHERE id > $1 ORDER BY id LIMIT $2",
+            last_id,
+            BUNDLE_PAGE_SIZE,
+        )
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| BundleErr::IoError(e.to_string()))?;
+
+        let Some(last) = page.last() else {
+            break;
+        };
+        last_id = last.id;
+        sessions.extend(page);
+    }
+
+    Ok(sessions)
+}
+
+/// Reads the session tags a page at a time.
+///
+/// # Parameters
+/// - `conn` - The connection of the export's transaction
+///
+/// # Returns
+/// A `Result` containing every session tag ordered by session and tag or a `BundleErr` error.
+///
+/// # Errors
+/// If a page can't be read, a `BundleErr` error is returned.
+async fn bundle_session_tags_get(conn: &mut PgConnection) -> Result<Vec<BundleSessionTag>, BundleErr> {
+    let mut session_tags = Vec::new();
+    let mut last = (0, 0);
+    loop {
+        let page = sqlx::query_as!(
+            BundleSessionTag,
+            r#"SELECT session_id as "session_id!", tag_id as "tag_id!"
+            FROM session_tags
+            WHERE (session_id, tag_id) > ($1, $2)
+            ORDER BY session_id, tag_id
+            LIMIT $3"#,
+            last.0,
+            last.1,
+            BUNDLE_PAGE_SIZE,
+        )
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| BundleErr::IoError(e.to_string()))?;
+
+        let Some(last_tag) = page.last() else {
+            break;
+        };
+        last = (last_tag.session_id, last_tag.tag_id);
+        session_tags.extend(page);
+    }
+
+    Ok(session_tags)
+}
+
+/// Retrieves the users who submitted sessions, in user ID order.
+///
+/// When anonymized each speaker is named by a pseudonym derived from their user ID, so the same
+/// speaker keeps the same pseudonym across exports even as other speakers come and go, and their
+/// email is left out.
+///
+/// # Parameters
+/// - `conn` - The connection of the export's transaction
+/// - `anonymize` - Whether to replace names and emails with pseudonyms
+///
+/// # Returns
+/// A `Result` containing the speakers or a `BundleErr` error.
+///
+/// # Errors
+/// If the speakers can't be read, a `BundleErr` error is returned.
+async fn bundle_speakers_get(conn: &mut PgConnection, anonymize: bool) -> Result<Vec<BundleSpeaker>, BundleErr> {
+    let speakers = sqlx::query_as!(
+        BundleSpeaker,
+        r#"SELECT u.id as user_id, u.fname || ' ' || u.lname as "name!", u.email as "email?"
+        FROM users u
+        WHERE EXISTS (SELECT 1 FROM sessions s WHERE s.user_id = u.id)
+        ORDER BY u.id"#
+    )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?;
+
+    if !anonymize {
+        return Ok(speakers);
+    }
+
+    let key = pseudonym_key_get(&mut *conn)
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?;
+
+    Ok(speakers
+        .into_iter()
+        .map(|speaker| BundleSpeaker {
+            user_id: speaker.user_id,
+            name: user_pseudonym(&key, "Speaker", speaker.user_id),
+            email: None,
+        })
+        .collect())
+}
+
+/// Assembles all the data of the event into a bundle.
+///
+/// Everything is read in a single read-only `REPEATABLE READ` transaction, so edits made during
+/// the export can't leave a tag, vote or assignment pointing at a session missing from the bundle.
+/// Sessions and session tags are read a page at a time to keep each query small, but the whole
+/// bundle is still built in memory before it is returned. Votes are exported as counts per session
+/// so voters can't be identified.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `anonymize` - Whether to replace speaker names and emails with pseudonyms
+///
+/// # Returns
+/// A `Result` containing the `EventBundle` or a `BundleErr` error.
+///
+/// # Errors
+/// If any of the event data can't be read, a `BundleErr` error is returned.
+pub async fn bundle_export(db_pool: &Pool<Postgres>, anonymize: bool) -> Result<EventBundle, BundleErr> {
+    let mut tx = db_pool
+        .begin()
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?;
+    sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?;
+
+    let rooms = rooms_get(&mut *tx)
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|room| {
+            Some(BundleRoom {
+                id: room.id?,
+                name: room.name,
+                location: room.location,
+                available_spots: room.available_spots,
+            })
+        })
+        .collect();
+
+    let mut timeslots = timeslot_get(&mut *tx)
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?;
+    timeslots.sort_by_key(|timeslot| timeslot.id);
+
+    let mut tags = get_all_tags(&mut *tx)
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?;
+    tags.sort_by_key(|tag| tag.id);
+
+    let sessions = bundle_sessions_get(&mut tx).await?;
+    let speakers = bundle_speakers_get(&mut tx, anonymize).await?;
+    let session_tags = bundle_session_tags_get(&mut tx).await?;
+
+    let votes = sqlx::query_as!(
+        BundleVoteCount,
+        r#"SELECT session_id as "session_id!", COUNT(*) as "votes!"
+        FROM user_votes
+        GROUP BY session_id
+        ORDER BY session_id"#
+    )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?;
+
+    let assignments = sqlx::query_as!(
+        SnapshotAssignment,
+        r#"SELECT time_slot_id as "time_slot_id!", room_id as "room_id!", session_id as "session_id!"
+        FROM timeslot_assignments
+        WHERE time_slot_id IS NOT NULL AND room_id IS NOT NULL AND session_id IS NOT NULL
+        ORDER BY time_slot_id, room_id"#
+    )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| BundleErr::IoError(e.to_string()))?;
+
+    Ok(EventBundle {
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now(),
+        rooms,
+        timeslots,
+        sessions,
+        speakers,
+        tags,
+        session_tags,
+        votes,
+        assignments,
+    })
+}
+
+/// Looks up the new ID of a row from the bundle.
+///
+/// # Parameters
+/// - `ids` - The mapping from bundle IDs to new IDs
+/// - `kind` - What the ID refers to, used in the error message
+/// - `id` - The ID from the bundle
+///
+/// # Returns
+/// A `Result` containing the new ID or a `BundleErr` error.
+///
+/// # Errors
+/// If the bundle doesn't contain the referenced row, an `InvalidBundle` error is returned.
+fn remapped_id(ids: &HashMap<i32, i32>, kind: &str, id: i32) -> Result<i32, BundleErr> {
+    ids.get(&id)
+        .copied()
+        .ok_or_else(|| BundleErr::InvalidBundle(format!("{kind} {id} is referenced but not in the bundle")))
+}
+
+/// Finds the user of this database each speaker of the bundle is, matching them by email.
+///
+/// # Parameters
+/// - `tx` - The transaction the import runs in
+/// - `bundle` - The bundle being imported
+///
+/// # Returns
+/// A `Result` containing the mapping from bundle user IDs to user IDs or a `BundleErr` error.
+///
+/// # Errors
+/// If a session's speaker isn't in the bundle, has no email or has no account with that email, an
+/// `InvalidBundle` error is returned.
+async fn bundle_speaker_ids(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    bundle: &EventBundle,
+) -> Result<HashMap<i32, i32>, BundleErr> {
+    let mut user_ids = HashMap::new();
+    for session in &bundle.sessions {
+        if user_ids.contains_key(&session.user_id) {
+            continue;
+        }
+
+        let email = bundle.speakers
+            .iter()
+            .find(|speaker| speaker.user_id == session.user_id)
+            .and_then(|speaker| speaker.email.as_deref())
+            .ok_or_else(|| BundleErr::InvalidBundle(format!("Speaker {} has no email to match", session.user_id)))?;
+        let user_id = sqlx::query_scalar!("SELECT id FROM users WHERE email = $1", email)
+            .fetch_optional(&mut **tx)
+            .await?
+            .ok_or_else(|| BundleErr::InvalidBundle(format!("No user has the email {email}")))?;
+        user_ids.insert(session.user_id, user_id);
+    }
+
+    Ok(user_ids)
+}
+
+/// Recreates the event data from a bundle.
+///
+/// Everything is inserted in dependency order inside a single transaction, so either the whole
+/// bundle is imported or nothing is. Rows get new IDs and references between them are remapped.
+/// Tags are matched by name so importing doesn't duplicate a tag that already exists. Speakers are
+/// matched to users by email, so an anonymized bundle can't be imported. Individual votes aren't
+/// part of a bundle, so each session's vote counter is restored from the bundle but not who voted
+/// for it.
+///
+/// Replacing removes the rooms, timeslots and sessions along with everything that hangs off them.
+/// Tags with rules are kept so the rules survive, the other tags are removed.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `bundle` - The bundle to import
+/// - `mode` - Whether to merge with or replace the existing event data
+///
+/// # Returns
+/// A `Result` containing an `ImportSummary` or a `BundleErr` error.
+///
+/// # Errors
+/// If the bundle version isn't supported a `VersionMismatch` error is returned, if the bundle
+/// refers to data it doesn't contain or a speaker can't be matched an `InvalidBundle` error is
+/// returned, otherwise any database
+/// failure returns an `IoError`.
+pub async fn bundle_import(
+    db_pool: &Pool<Postgres>,
+    bundle: &EventBundle,
+    mode: ImportMode,
+) -> Result<ImportSummary, BundleErr> {
+    if bundle.version != BUNDLE_VERSION {
+        return Err(BundleErr::VersionMismatch(bundle.version));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    let user_ids = bundle_speaker_ids(&mut tx, bundle).await?;
+
+    if mode == ImportMode::Replace {
+        sqlx::query!("DELETE FROM timeslot_assignments").execute(&mut *tx).await?;
+        sqlx::query!("DELETE FROM sessions").execute(&mut *tx).await?;
+        sqlx::query!("DELETE FROM time_slots").execute(&mut *tx).await?;
+        sqlx::query!("DELETE FROM rooms").execute(&mut *tx).await?;
+        sqlx::query!("DELETE FROM tags t WHERE NOT EXISTS (SELECT 1 FROM tag_rules tr WHERE tr.tag_id = t.id)")
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let mut summary = ImportSummary::default();
+
+    let mut room_ids = HashMap::new();
+    for room in &bundle.rooms {
+        let id = sqlx::query_scalar!(
+            "INSERT INTO rooms (name, location, available_spots) VALUES ($1, $2, $3) RETURNING id",
+            room.name,
+            room.location,
+            room.available_spots,
+        )
+            .fetch_one(&mut *tx)
+            .await?;
+        room_ids.insert(room.id, id);
+        summary.rooms += 1;
+    }
+
+    let mut timeslot_ids = HashMap::new();
+    for timeslot in &bundle.timeslots {
+        let id = sqlx::query_scalar!(
+            "INSERT INTO time_slots (start_time, end_time, duration, max_open_rooms) VALUES ($1, $2, make_interval(mins => $3), $4) RETURNING id",
+            timeslot.start_time,
+            timeslot.end_time,
+            timeslot.duration,
+            timeslot.max_open_rooms,
+        )
+            .fetch_one(&mut *tx)
+            .await?;
+        timeslot_ids.insert(timeslot.id, id);
+        summary.timeslots += 1;
+    }
+
+    let mut tag_ids = HashMap::new();
+    for tag in &bundle.tags {
+        let id = sqlx::query_scalar!(
+            r#"INSERT INTO tags (tag_name) VALUES ($1)
+            ON CONFLICT (tag_name) DO UPDATE SET tag_name = EXCLUDED.tag_name
+            RETURNING id"#,
+            tag.tag_name,
+        )
+            .fetch_one(&mut *tx)
+            .await?;
+        tag_ids.insert(tag.id, id);
+        summary.tags += 1;
+    }
+
+    let mut session_ids = HashMap::new();
+    for session in &bundle.sessions {
+        let id = sqlx::query_scalar!(
+            "INSERT INTO sessions (user_id, title, content, votes) VALUES ($1, $2, $3, $4) RETURNING id",
+            user_ids[&session.user_id],
+            session.title,
+            session.content,
+            session.votes,
+        )
+            .fetch_one(&mut *tx)
+            .await?;
+        session_ids.insert(session.id, id);
+        summary.sessions += 1;
+    }
+
+    for session_tag in &bundle.session_tags {
+        let session_id = remapped_id(&session_ids, "Session", session_tag.session_id)?;
+        let tag_id = remapped_id(&tag_ids, "Tag", session_tag.tag_id)?;
+        sqlx::query!(
+            "INSERT INTO session_tags (session_id, tag_id) VALUES ($1, $2)",
+            session_id,
+            tag_id,
+        )
+            .execute(&mut *tx)
+            .await?;
+        summary.session_tags += 1;
+    }
+
+    for vote_count in &bundle.votes {
+        let session_id = remapped_id(&session_ids, "Session", vote_count.session_id)?;
+        let votes = i32::try_from(vote_count.votes)
+            .map_err(|_| BundleErr::InvalidBundle(format!("Session {} has too many votes", vote_count.session_id)))?;
+        sqlx::query!("UPDATE sessions SET votes = $1 WHERE id = $2", votes, session_id)
+            .execute(&mut *tx)
+            .await?;
+        summary.votes += 1;
+    }
+
+    for assignment in &bundle.assignments {
+        let time_slot_id = remapped_id(&timeslot_ids, "Timeslot", assignment.time_slot_id)?;
+        let room_id = remapped_id(&room_ids, "Room", assignment.room_id)?;
+        let session_id = remapped_id(&session_ids, "Session", assignment.session_id)?;
+        sqlx::query!(
+            "INSERT INTO timeslot_assignments (time_slot_id, room_id, session_id) VALUES ($1, $2, $3)",
+            time_slot_id,
+            room_id,
+            session_id,
+        )
+            .execute(&mut *tx)
+            .await?;
+        summary.assignments += 1;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!("Imported event bundle ({:?}): {:?}", mode, summary);
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assign_session, insert_room, insert_session, insert_session_with, insert_tag, insert_timeslot, insert_user, insert_vote, tag_session};
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn anonymized_bundle_export_has_no_names_or_emails(pool: PgPool) {
+        let mut user_ids = vec![];
+        for (fname, email) in [("Alice", "alice@example.com"), ("Bob", "bob@example.com")] {
+            let user_id = insert_user(&pool, fname, "Speaker", email).await;
+            insert_session(&pool, user_id, "Talk").await;
+            user_ids.push(user_id);
+        }
+
+        let raw = serde_json::to_string(&bundle_export(&pool, false).await.unwrap()).unwrap();
+        assert!(raw.contains("alice@example.com") && raw.contains("Bob Speaker"));
+
+        let bundle = bundle_export(&pool, true).await.unwrap();
+        let anonymized = serde_json::to_string(&bundle).unwrap();
+        for identifying in ["alice@example.com", "bob@example.com", "Alice", "Bob"] {
+            assert!(!anonymized.contains(identifying), "{identifying} in anonymized export");
+        }
+        let key = pseudonym_key_get(&pool).await.unwrap();
+        assert_eq!(bundle.speakers, vec![
+            BundleSpeaker { user_id: user_ids[0], name: user_pseudonym(&key, "Speaker", user_ids[0]), email: None },
+            BundleSpeaker { user_id: user_ids[1], name: user_pseudonym(&key, "Speaker", user_ids[1]), email: None },
+        ]);
+    }
+
+    #[sqlx::test]
+    async fn removing_a_speaker_keeps_the_other_pseudonyms(pool: PgPool) {
+        let mut user_ids = vec![];
+        for (fname, email) in [("Alice", "alice@example.com"), ("Bob", "bob@example.com"), ("Carol", "carol@example.com")] {
+            let user_id = insert_user(&pool, fname, "Speaker", email).await;
+            insert_session(&pool, user_id, "Talk").await;
+            user_ids.push(user_id);
+        }
+        let pseudonyms = |bundle: EventBundle| -> Vec<(i32, String)> {
+            bundle.speakers.into_iter().map(|speaker| (speaker.user_id, speaker.name)).collect()
+        };
+        let before = pseudonyms(bundle_export(&pool, true).await.unwrap());
+
+        sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+            .bind(user_ids[0])
+            .execute(&pool)
+            .await
+            .unwrap();
+        let after = pseudonyms(bundle_export(&pool, true).await.unwrap());
+
+        assert_eq!(after, before[1..]);
+    }
+
+    #[sqlx::test]
+    async fn bundle_export_collects_event_data(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let room_id = insert_room(&pool, "Room A", "Floor 1", 20).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        let tag_id = insert_tag(&pool, "Rust").await;
+        let session_id = insert_session_with(&pool, user_id, "Talk", "content", 1).await;
+        tag_session(&pool, session_id, tag_id).await;
+        insert_vote(&pool, user_id, session_id).await;
+        assign_session(&pool, time_slot_id, room_id, session_id).await;
+
+        let bundle = bundle_export(&pool, false).await.unwrap();
+
+        assert_eq!(bundle.version, BUNDLE_VERSION);
+        assert_eq!(bundle.rooms.iter().map(|room| room.id).collect::<Vec<_>>(), vec![room_id]);
+        assert_eq!(bundle.timeslots.iter().map(|timeslot| timeslot.id).collect::<Vec<_>>(), vec![time_slot_id]);
+        assert_eq!(bundle.sessions.iter().map(|session| session.id).collect::<Vec<_>>(), vec![session_id]);
+        assert_eq!(bundle.tags.iter().map(|tag| tag.id).collect::<Vec<_>>(), vec![tag_id]);
+        assert_eq!(bundle.session_tags, vec![BundleSessionTag { session_id, tag_id }]);
+        assert_eq!(bundle.votes, vec![BundleVoteCount { session_id, votes: 1 }]);
+        assert_eq!(bundle.assignments, vec![SnapshotAssignment { time_slot_id, room_id, session_id }]);
+    }
+
+    #[sqlx::test]
+    async fn bundle_sessions_get_reads_every_page(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let num_sessions = BUNDLE_PAGE_SIZE + 1;
+        sqlx::query("INSERT INTO sessions (user_id, title, content, votes) SELECT $1, 'Talk ' || n, 'content', 0 FROM generate_series(1, $2) n")
+            .bind(user_id)
+            .bind(num_sessions)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let sessions = bundle_sessions_get(&mut pool.acquire().await.unwrap()).await.unwrap();
+
+        assert_eq!(sessions.len() as i64, num_sessions);
+    }
+
+    #[sqlx::test]
+    async fn bundle_import_round_trips_with_new_ids(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let room_id = insert_room(&pool, "Room A", "Floor 1", 20).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        let tag_id = insert_tag(&pool, "Rust").await;
+        let session_id = insert_session_with(&pool, user_id, "Talk", "content", 3).await;
+        tag_session(&pool, session_id, tag_id).await;
+        assign_session(&pool, time_slot_id, room_id, session_id).await;
+        let bundle = bundle_export(&pool, false).await.unwrap();
+
+        let summary = bundle_import(&pool, &bundle, ImportMode::Replace).await.unwrap();
+
+        assert_eq!(summary, ImportSummary { rooms: 1, timeslots: 1, sessions: 1, tags: 1, session_tags: 1, votes: 0, assignments: 1 });
+        let reimported = bundle_export(&pool, false).await.unwrap();
+        let new_session = &reimported.sessions[0];
+        assert_ne!(new_session.id, session_id);
+        assert_eq!((new_session.user_id, new_session.title.as_str(), new_session.votes), (user_id, "Talk", 3));
+        assert_eq!(reimported.timeslots[0].duration, 60);
+        assert_eq!(reimported.session_tags, vec![BundleSessionTag { session_id: new_session.id, tag_id: reimported.tags[0].id }]);
+        assert_eq!(reimported.assignments, vec![SnapshotAssignment {
+            time_slot_id: reimported.timeslots[0].id,
+            room_id: reimported.rooms[0].id,
+            session_id: new_session.id,
+        }]);
+    }
+
+    #[sqlx::test]
+    async fn bundle_import_merge_keeps_existing_data(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        insert_tag(&pool, "Rust").await;
+        insert_session(&pool, user_id, "Talk").await;
+        let bundle = bundle_export(&pool, false).await.unwrap();
+
+        bundle_import(&pool, &bundle, ImportMode::Merge).await.unwrap();
+
+        let merged = bundle_export(&pool, false).await.unwrap();
+        assert_eq!(merged.sessions.len(), 2);
+        assert_eq!(merged.tags.len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn bundle_import_rejects_other_versions(pool: PgPool) {
+        let mut bundle = bundle_export(&pool, false).await.unwrap();
+        bundle.version = BUNDLE_VERSION + 1;
+
+        let result = bundle_import(&pool, &bundle, ImportMode::Merge).await;
+
+        assert!(matches!(result, Err(BundleErr::VersionMismatch(version)) if version == BUNDLE_VERSION + 1));
+    }
+
+    #[sqlx::test]
+    async fn bundle_import_rolls_back_on_missing_reference(pool: PgPool) {
+        let mut bundle = bundle_export(&pool, false).await.unwrap();
+        bundle.rooms.push(BundleRoom { id: 1, name: "Room A".to_string(), location: "Floor 1".to_string(), available_spots: 20 });
+        bundle.session_tags.push(BundleSessionTag { session_id: 42, tag_id: 7 });
+
+        let result = bundle_import(&pool, &bundle, ImportMode::Merge).await;
+
+        assert!(matches!(result, Err(BundleErr::InvalidBundle(_))));
+        assert!(bundle_export(&pool, false).await.unwrap().rooms.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn bundle_import_matches_speakers_by_email(pool: PgPool) {
+        let exporter_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let other_id = insert_user(&pool, "B", "User", "b@example.com").await;
+        insert_session(&pool, exporter_id, "Talk").await;
+        let mut bundle = bundle_export(&pool, false).await.unwrap();
+        bundle.speakers[0].email = Some("b@example.com".to_string());
+
+        bundle_import(&pool, &bundle, ImportMode::Replace).await.unwrap();
+
+        let reimported = bundle_export(&pool, false).await.unwrap();
+        assert_eq!(reimported.sessions.iter().map(|session| session.user_id).collect::<Vec<_>>(), vec![other_id]);
+    }
+
+    #[sqlx::test]
+    async fn bundle_import_rejects_unknown_speakers(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        insert_session(&pool, user_id, "Talk").await;
+        let anonymized = bundle_export(&pool, true).await.unwrap();
+        let mut unknown = bundle_export(&pool, false).await.unwrap();
+        unknown.speakers[0].email = Some("nobody@example.com".to_string());
+
+        for bundle in [anonymized, unknown] {
+            let result = bundle_import(&pool, &bundle, ImportMode::Merge).await;
+
+            assert!(matches!(result, Err(BundleErr::InvalidBundle(_))), "{result:?}");
+        }
+        assert_eq!(bundle_export(&pool, false).await.unwrap().sessions.len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn bundle_import_replace_keeps_tags_with_rules(pool: PgPool) {
+        let ruled_id = insert_tag(&pool, "Rust").await;
+        insert_tag(&pool, "Unused").await;
+        sqlx::query("INSERT INTO tag_rules (keyword, tag_id) VALUES ('cargo', $1)")
+            .bind(ruled_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mut bundle = bundle_export(&pool, false).await.unwrap();
+        bundle.tags.clear();
+
+        bundle_import(&pool, &bundle, ImportMode::Replace).await.unwrap();
+
+        let tags = get_all_tags(&pool).await.unwrap();
+        assert_eq!(tags.iter().map(|tag| tag.tag_name.as_str()).collect::<Vec<_>>(), vec!["Rust"]);
+        let rules: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tag_rules WHERE tag_id = $1")
+            .bind(ruled_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rules, 1);
+    }
+
+    #[sqlx::test]
+    async fn bundle_import_restores_vote_counts(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let session_id = insert_session(&pool, user_id, "Talk").await;
+        let mut bundle = bundle_export(&pool, false).await.unwrap();
+        bundle.votes.push(BundleVoteCount { session_id, votes: 5 });
+
+        let summary = bundle_import(&pool, &bundle, ImportMode::Replace).await.unwrap();
+
+        assert_eq!(summary.votes, 1);
+        assert_eq!(bundle_export(&pool, false).await.unwrap().sessions[0].votes, 5);
+    }
+}
@@ -0,0 +1,173 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+use std::error::Error;
+use utoipa::ToSchema;
+
+/// Struct representing a room in the embedded schedule.
+///
+/// # Fields
+/// - `id` - The ID of the room
+/// - `name` - The name of the room
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct EmbedRoom {
+    pub id: i32,
+    pub name: String,
+}
+
+/// Struct representing a timeslot in the embedded schedule.
+///
+/// # Fields
+/// - `start_time` - When the timeslot starts
+/// - `end_time` - When the timeslot ends
+/// - `sessions` - The title of the session in each room, in the same order as the rooms, `None`
+///   when the room is free
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct EmbedTimeslot {
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub sessions: Vec<Option<String>>,
+}
+
+/// Struct representing the read-only schedule shown on other websites.
+///
+/// Only the rooms, times and session titles are included, nothing about who submitted or voted
+/// for a session.
+///
+/// # Fields
+/// - `rooms` - The rooms, ordered by ID
+/// - `timeslots` - The timeslots, ordered by start time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct EmbedSchedule {
+    pub rooms: Vec<EmbedRoom>,
+    pub timeslots: Vec<EmbedTimeslot>,
+}
+
+/// The formats the embedded schedule can be returned in.
+///
+/// # Variants
+/// - `Html` - A standalone HTML page to show in an iframe
+/// - `Json` - The `EmbedSchedule` as JSON
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedFormat {
+    #[default]
+    Html,
+    Json,
+}
+
+/// Struct representing the query parameters of the embedded schedule.
+///
+/// # Fields
+/// - `token` - The embed token
+/// - `format` - The format to return the schedule in, defaults to HTML
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EmbedQuery {
+    pub token: Option<String>,
+    pub format: Option<EmbedFormat>,
+}
+
+/// Checks a token against the configured embed token.
+///
+/// Every byte is compared so how long the check takes doesn't reveal how much of the token was
+/// right.
+///
+/// # Parameters
+/// - `expected` - The configured embed token
+/// - `given` - The token sent with the request
+///
+/// # Returns
+/// `true` if the tokens match
+pub fn embed_token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Retrieves the schedule as a grid of rooms and timeslots.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the `EmbedSchedule` or an error if the query fails.
+///
+/// # Errors
+/// If the query fails, a boxed error is returned.
+pub async fn embed_schedule_get(db_pool: &Pool<Postgres>) -> Result<EmbedSchedule, Box<dyn Error>> {
+    let rooms = sqlx::query_as!(
+        EmbedRoom,
+        "SELECT id, name FROM rooms ORDER BY id"
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    let cells = sqlx::query!(
+        r#"SELECT ts.id, ts.start_time, ts.end_time, s.title as "title?"
+        FROM time_slots ts
+        CROSS JOIN rooms r
+        LEFT JOIN timeslot_assignments ta ON ta.time_slot_id = ts.id AND ta.room_id = r.id
+        LEFT JOIN sessions s ON s.id = ta.session_id
+        ORDER BY ts.start_time, ts.id, r.id"#
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    let mut timeslots: Vec<EmbedTimeslot> = Vec::new();
+    let mut current_timeslot_id = None;
+    for cell in cells {
+        if current_timeslot_id != Some(cell.id) {
+            current_timeslot_id = Some(cell.id);
+            timeslots.push(EmbedTimeslot {
+                start_time: cell.start_time,
+                end_time: cell.end_time,
+                sessions: Vec::with_capacity(rooms.len()),
+            });
+        }
+
+        if let Some(timeslot) = timeslots.last_mut() {
+            timeslot.sessions.push(cell.title);
+        }
+    }
+
+    Ok(EmbedSchedule { rooms, timeslots })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assign_session, insert_room, insert_session, insert_timeslot, insert_user};
+    use sqlx::PgPool;
+
+    #[test]
+    fn embed_token_matches_only_the_exact_token() {
+        assert!(embed_token_matches("secret", "secret"));
+        assert!(!embed_token_matches("secret", "secreT"));
+        assert!(!embed_token_matches("secret", "secret2"));
+        assert!(!embed_token_matches("secret", ""));
+    }
+
+    #[sqlx::test]
+    async fn embed_schedule_has_titles_but_no_speaker_details(pool: PgPool) {
+        let user_id = insert_user(&pool, "Ada", "Speaker", "ada@example.com").await;
+        let room_a = insert_room(&pool, "Room A", "Here", 10).await;
+        insert_room(&pool, "Room B", "There", 10).await;
+        let late_slot = insert_timeslot(&pool, "10:00", "11:00").await;
+        insert_timeslot(&pool, "09:00", "10:00").await;
+        let session_id = insert_session(&pool, user_id, "Rust").await;
+        assign_session(&pool, late_slot, room_a, session_id).await;
+
+        let schedule = embed_schedule_get(&pool).await.unwrap();
+
+        let room_names: Vec<&str> = schedule.rooms.iter().map(|room| room.name.as_str()).collect();
+        assert_eq!(room_names, vec!["Room A", "Room B"]);
+        let grid: Vec<Vec<Option<String>>> = schedule.timeslots.iter().map(|timeslot| timeslot.sessions.clone()).collect();
+        assert_eq!(grid, vec![vec![None, None], vec![Some("Rust".to_string()), None]]);
+
+        let json = serde_json::to_string(&schedule).unwrap();
+        assert!(!json.contains("ada@example.com") && !json.contains("Ada"));
+    }
+}
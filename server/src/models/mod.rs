@@ -1,10 +1,17 @@
+pub mod account_model;
 pub mod auth_model;
+pub mod bundle_model;
+pub mod embed_model;
 pub mod room_model;
 pub mod schedule_model;
+pub mod schedule_export_model;
+pub mod schedule_snapshot_model;
 pub mod timeslot_assignment_model;
 pub mod timeslot_model;
 pub mod sessions_model;
 pub mod session_voting_model;
 pub mod session_tags_model;
+pub mod settings_model;
+pub mod speakers_model;
 pub mod tags_model;
 pub mod index_model;
@@ -1,8 +1,9 @@
+use crate::models::schedule_export_model::{schedule_entries_get, ScheduleEntry};
 use crate::types::ApiStatusCode;
 use axum::response::IntoResponse;
 use axum::{http::StatusCode, response::Response, Json};
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
-use sqlx::{FromRow, Pool, Postgres};
+use sqlx::{FromRow, PgExecutor, Pool, Postgres};
 use std::error::Error;
 use utoipa::ToSchema;
 
@@ -20,12 +21,15 @@ type BoxedError = Box<dyn Error + Send + Sync>;
 /// # Variants:
 /// - `IoError(String)`: An I/O error occurred.
 /// - `DoesNotExist(String)`: The room does not exist.
+/// - `TimeslotDoesNotExist(String)`: The timeslot the rooms were asked about does not exist.
 #[derive(Debug, thiserror::Error, ToSchema, Serialize)]
 pub enum RoomErr {
     #[error("Room io failed: {0}")]
     IoError(String),
     #[error("Room {0} doesn't exist")]
     DoesNotExist(String),
+    #[error("Timeslot {0} doesn't exist")]
+    TimeslotDoesNotExist(String),
 }
 
 impl From<std::io::Error> for RoomErr {
@@ -39,6 +43,8 @@ impl From<std::io::Error> for RoomErr {
     /// # Example
     ///
     /// ```
+    /// use server::models::room_model::RoomErr;
+    ///
     /// let io_err = std::io::Error::new(std::io::ErrorKind::Other, "IO error");
     /// let room_err: RoomErr = io_err.into();
     /// ```
@@ -84,23 +90,21 @@ impl Serialize for RoomError {
 }
 
 impl RoomError {
-    /// Creates a new `RoomError` instance.
-    ///
-    /// This function takes an HTTP status code and an error message and returns a new `RoomError`
-    /// instance.
+    /// Creates a `Response` instance from a `StatusCode` and `RoomErr`.
     ///
     /// # Parameters
-    /// - `status`: The HTTP status code associated with the error.
-    /// - `error`: A string describing the specific error that occurred.
+    /// - `status`: The HTTP status code to return
+    /// - `error`: The `RoomErr` to return
     ///
     /// # Returns
-    /// A new `RoomError` instance with the provided status code and error message.
+    /// A `Response` instance with the HTTP status code and the serialized `RoomError`.
     pub fn response(status: ApiStatusCode, error: BoxedError) -> Response {
         let error = RoomError {
             status,
             error: error.to_string(),
         };
-        (status, Json(error)).into_response()
+
+        (status.as_status_code(), Json(error)).into_response()
     }
 }
 
@@ -172,6 +176,74 @@ pub struct CreateRoomsForm {
     pub rooms: Vec<Room>,
 }
 
+/// Struct representing the query parameters for room occupancy.
+///
+/// Fields:
+/// - `time_slot_id`: The timeslot to show the occupancy of, all timeslots when omitted.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RoomOccupancyQuery {
+    pub time_slot_id: Option<i32>,
+}
+
+/// Struct representing whether a room is occupied in a timeslot.
+///
+/// Fields:
+/// - `room_id`: The ID of the room.
+/// - `name`: The name of the room.
+/// - `occupied`: Whether a session is scheduled in the room.
+/// - `session_id`: The ID of the session in the room, if any.
+/// - `session_title`: The title of the session in the room, if any.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RoomSlotOccupancy {
+    pub room_id: i32,
+    pub name: String,
+    pub occupied: bool,
+    pub session_id: Option<i32>,
+    pub session_title: Option<String>,
+}
+
+/// Struct representing how many timeslots a room is occupied in.
+///
+/// Fields:
+/// - `room_id`: The ID of the room.
+/// - `name`: The name of the room.
+/// - `occupied_slots`: The number of timeslots with a session scheduled in the room.
+/// - `total_slots`: The number of timeslots.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RoomOccupancyCount {
+    pub room_id: i32,
+    pub name: String,
+    pub occupied_slots: i64,
+    pub total_slots: i64,
+}
+
+/// Enum representing the occupancy of the rooms.
+///
+/// Variants:
+/// - `Slot`: Each room and the session in it during a single timeslot.
+/// - `AllSlots`: Each room and how many timeslots it is occupied in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum RoomOccupancy {
+    Slot(Vec<RoomSlotOccupancy>),
+    AllSlots(Vec<RoomOccupancyCount>),
+}
+
+/// Struct representing how much a room was used.
+///
+/// Fields:
+/// - `room_id`: The ID of the room.
+/// - `name`: The name of the room.
+/// - `sessions`: The number of sessions scheduled in the room.
+/// - `votes`: The total votes across the sessions scheduled in the room.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RoomStats {
+    pub room_id: i32,
+    pub name: String,
+    pub sessions: i64,
+    pub votes: i64,
+}
+
 /// Gets all rooms.
 ///
 /// This function retrieves all rooms from the database.
@@ -185,7 +257,7 @@ pub struct CreateRoomsForm {
 ///
 /// # Errors
 /// If an error occurs while fetching the rooms from the database, a `BoxedError` is returned.
-pub(crate) async fn rooms_get(db_pool: &Pool<Postgres>) -> Result<Option<Vec<Room>>, BoxedError> {
+pub(crate) async fn rooms_get(db_pool: impl PgExecutor<'_>) -> Result<Option<Vec<Room>>, BoxedError> {
     let rooms = Some(
         sqlx::query_as::<Postgres, Room>(
             r"
@@ -285,3 +357,222 @@ pub async fn get_num_rooms(db_pool: &Pool<Postgres>) -> Result<i32, BoxedError>
     // This is safe to unwrap since it should always return a number
     Ok(num_rooms.unwrap())
 }
+
+/// Gets the occupancy of the rooms.
+///
+/// With a timeslot each room is listed with the session scheduled in it during that timeslot,
+/// otherwise each room is listed with how many timeslots it has a session in.
+///
+/// # Parameters
+/// - `db_pool`: A reference to the database connection pool.
+/// - `time_slot_id`: The timeslot to get the occupancy of, all timeslots when `None`.
+///
+/// # Returns
+/// A `Result` containing the `RoomOccupancy` of every room, ordered by room ID.
+///
+/// # Errors
+/// If the timeslot doesn't exist a `RoomErr::TimeslotDoesNotExist` error is returned. If an error
+/// occurs while querying the database, a `BoxedError` is returned.
+pub async fn room_occupancy_get(db_pool: &Pool<Postgres>, time_slot_id: Option<i32>) -> Result<RoomOccupancy, BoxedError> {
+    let Some(time_slot_id) = time_slot_id else {
+        let counts = sqlx::query_as!(
+            RoomOccupancyCount,
+            r#"SELECT
+                r.id as room_id,
+                r.name,
+                COUNT(ta.session_id) as "occupied_slots!",
+                (SELECT COUNT(*) FROM time_slots) as "total_slots!"
+            FROM rooms r
+            LEFT JOIN timeslot_assignments ta ON ta.room_id = r.id
+            GROUP BY r.id
+            ORDER BY r.id"#
+        )
+            .fetch_all(db_pool)
+            .await?;
+
+        return Ok(RoomOccupancy::AllSlots(counts));
+    };
+
+    let timeslot_exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM time_slots WHERE id = $1) as "exists!""#,
+        time_slot_id,
+    )
+        .fetch_one(db_pool)
+        .await?;
+    if !timeslot_exists {
+        return Err(Box::new(RoomErr::TimeslotDoesNotExist(time_slot_id.to_string())));
+    }
+
+    let rooms = sqlx::query_as!(
+        RoomSlotOccupancy,
+        r#"SELECT
+            r.id as room_id,
+            r.name,
+            s.id IS NOT NULL as "occupied!",
+            s.id as "session_id?",
+            s.title as "session_title?"
+        FROM rooms r
+        LEFT JOIN timeslot_assignments ta ON ta.room_id = r.id AND ta.time_slot_id = $1
+        LEFT JOIN sessions s ON s.id = ta.session_id
+        ORDER BY r.id"#,
+        time_slot_id,
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    Ok(RoomOccupancy::Slot(rooms))
+}
+
+/// Gets the sessions scheduled in a room, to export the room's agenda.
+///
+/// # Parameters
+/// - `db_pool`: A reference to the database connection pool.
+/// - `room_id`: The ID of the room.
+///
+/// # Returns
+/// A `Result` containing the sessions scheduled in the room, ordered by start time.
+///
+/// # Errors
+/// If the room doesn't exist a `RoomErr::DoesNotExist` error is returned. If an error occurs while
+/// querying the database, a `BoxedError` is returned.
+pub async fn room_schedule_get(db_pool: &Pool<Postgres>, room_id: i32) -> Result<Vec<ScheduleEntry>, BoxedError> {
+    let room_exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM rooms WHERE id = $1) as "exists!""#,
+        room_id,
+    )
+        .fetch_one(db_pool)
+        .await?;
+    if !room_exists {
+        return Err(Box::new(RoomErr::DoesNotExist(room_id.to_string())));
+    }
+
+    Ok(schedule_entries_get(db_pool, Some(room_id), None).await?)
+}
+
+/// Gets how much each room was used.
+///
+/// Rooms without any sessions scheduled in them are listed with zeros.
+///
+/// # Parameters
+/// - `db_pool`: A reference to the database connection pool.
+///
+/// # Returns
+/// A `Result` containing the `RoomStats` of every room, the busiest first. Rooms are ordered by the
+/// number of sessions scheduled in them, then by their votes, then by room ID.
+///
+/// # Errors
+/// If an error occurs while querying the database, a `BoxedError` is returned.
+pub async fn room_stats_get(db_pool: &Pool<Postgres>) -> Result<Vec<RoomStats>, BoxedError> {
+    let stats = sqlx::query_as!(
+        RoomStats,
+        r#"SELECT
+            r.id as room_id,
+            r.name,
+            COUNT(DISTINCT ta.id) as "sessions!",
+            COUNT(uv.user_id) as "votes!"
+        FROM rooms r
+        LEFT JOIN timeslot_assignments ta ON ta.room_id = r.id AND ta.session_id IS NOT NULL
+        LEFT JOIN user_votes uv ON uv.session_id = ta.session_id
+        GROUP BY r.id
+        ORDER BY "sessions!" DESC, "votes!" DESC, r.id"#
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assign_session, insert_room, insert_session, insert_timeslot, insert_user, insert_vote};
+    use sqlx::PgPool;
+
+    #[tokio::test]
+    async fn error_response_has_the_status_and_the_api_error_shape() {
+        let response = RoomError::response(
+            ApiStatusCode::from(StatusCode::NOT_FOUND),
+            Box::new(RoomErr::DoesNotExist("3".to_string())),
+        );
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json, serde_json::json!({ "status": "404", "error": "Room 3 doesn't exist" }));
+    }
+
+    #[sqlx::test]
+    async fn room_schedule_of_a_missing_room_is_an_error(pool: PgPool) {
+        let room_id = insert_room(&pool, "Room A", "Here", 10).await;
+
+        assert!(room_schedule_get(&pool, room_id).await.unwrap().is_empty());
+        let err = room_schedule_get(&pool, room_id + 1).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<RoomErr>(), Some(RoomErr::DoesNotExist(_))));
+    }
+
+    #[sqlx::test]
+    async fn room_occupancy_per_slot_and_across_slots(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let used_room = insert_room(&pool, "Used", "Here", 10).await;
+        let empty_room = insert_room(&pool, "Empty", "There", 10).await;
+        let first_slot = insert_timeslot(&pool, "09:00", "10:00").await;
+        let second_slot = insert_timeslot(&pool, "10:00", "11:00").await;
+        let session_id = insert_session(&pool, user_id, "Rust").await;
+        assign_session(&pool, first_slot, used_room, session_id).await;
+
+        let first_slot_occupancy = room_occupancy_get(&pool, Some(first_slot)).await.unwrap();
+        assert_eq!(first_slot_occupancy, RoomOccupancy::Slot(vec![
+            RoomSlotOccupancy { room_id: used_room, name: "Used".to_string(), occupied: true, session_id: Some(session_id), session_title: Some("Rust".to_string()) },
+            RoomSlotOccupancy { room_id: empty_room, name: "Empty".to_string(), occupied: false, session_id: None, session_title: None },
+        ]));
+
+        let RoomOccupancy::Slot(second_slot_occupancy) = room_occupancy_get(&pool, Some(second_slot)).await.unwrap() else {
+            panic!("expected the occupancy of a single timeslot");
+        };
+        assert!(second_slot_occupancy.iter().all(|room| !room.occupied));
+
+        let all_slots_occupancy = room_occupancy_get(&pool, None).await.unwrap();
+        assert_eq!(all_slots_occupancy, RoomOccupancy::AllSlots(vec![
+            RoomOccupancyCount { room_id: used_room, name: "Used".to_string(), occupied_slots: 1, total_slots: 2 },
+            RoomOccupancyCount { room_id: empty_room, name: "Empty".to_string(), occupied_slots: 0, total_slots: 2 },
+        ]));
+
+        let missing = room_occupancy_get(&pool, Some(second_slot + 1)).await.unwrap_err();
+        assert!(matches!(missing.downcast_ref::<RoomErr>(), Some(RoomErr::TimeslotDoesNotExist(_))));
+    }
+
+    #[sqlx::test]
+    async fn room_stats_list_the_busiest_rooms_first(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let voter_id = insert_user(&pool, "B", "User", "b@example.com").await;
+        let empty_room = insert_room(&pool, "Empty", "Here", 10).await;
+        let quiet_room = insert_room(&pool, "Quiet", "Here", 10).await;
+        let popular_room = insert_room(&pool, "Popular", "Here", 10).await;
+        let busy_room = insert_room(&pool, "Busy", "Here", 10).await;
+        let first_slot = insert_timeslot(&pool, "09:00", "10:00").await;
+        let second_slot = insert_timeslot(&pool, "10:00", "11:00").await;
+
+        // (room, timeslot, voters)
+        for (i, (room_id, time_slot_id, voters)) in [
+            (busy_room, first_slot, vec![user_id]),
+            (busy_room, second_slot, vec![]),
+            (popular_room, first_slot, vec![user_id, voter_id]),
+            (quiet_room, first_slot, vec![]),
+        ].into_iter().enumerate() {
+            let session_id = insert_session(&pool, user_id, &format!("Talk {i}")).await;
+            assign_session(&pool, time_slot_id, room_id, session_id).await;
+            for voter in voters {
+                insert_vote(&pool, voter, session_id).await;
+            }
+        }
+
+        let stats = room_stats_get(&pool).await.unwrap();
+
+        assert_eq!(stats, vec![
+            RoomStats { room_id: busy_room, name: "Busy".to_string(), sessions: 2, votes: 1 },
+            RoomStats { room_id: popular_room, name: "Popular".to_string(), sessions: 1, votes: 2 },
+            RoomStats { room_id: quiet_room, name: "Quiet".to_string(), sessions: 1, votes: 0 },
+            RoomStats { room_id: empty_room, name: "Empty".to_string(), sessions: 0, votes: 0 },
+        ]);
+    }
+}
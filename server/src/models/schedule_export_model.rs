@@ -0,0 +1,480 @@
+use crate::models::schedule_model::SetupStep;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Postgres};
+use std::fmt::Write;
+use utoipa::ToSchema;
+
+/// The header row of a schedule exported as CSV.
+const CSV_HEADER: &str = "start_time,end_time,room,location,session_id,title";
+
+/// Struct representing a scheduled session in an exported schedule.
+///
+/// # Fields
+/// - `session_id` - The ID of the session
+/// - `title` - The title of the session
+/// - `room_id` - The ID of the room the session is in
+/// - `room_name` - The name of the room the session is in
+/// - `location` - Where the room is
+/// - `time_slot_id` - The ID of the session's timeslot
+/// - `start_time` - When the session's timeslot starts
+/// - `end_time` - When the session's timeslot ends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema, FromRow)]
+pub struct ScheduleEntry {
+    pub session_id: i32,
+    pub title: String,
+    pub room_id: i32,
+    pub room_name: String,
+    pub location: String,
+    pub time_slot_id: i32,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+}
+
+/// Struct representing the filter of the schedule grid.
+///
+/// # Fields
+/// - `tag` - Only include the sessions with this tag ID, every session when `None`
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+pub struct ScheduleGridQuery {
+    pub tag: Option<i32>,
+}
+
+/// Struct representing the schedule grid, with what still has to be set up when there's no
+/// schedule yet.
+///
+/// # Fields
+/// - `has_schedule` - Whether there are rooms and timeslots to schedule sessions in
+/// - `needs_setup` - What organizers have to add before there can be a schedule
+/// - `entries` - The scheduled sessions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ScheduleGrid {
+    pub has_schedule: bool,
+    pub needs_setup: Vec<SetupStep>,
+    pub entries: Vec<ScheduleEntry>,
+}
+
+/// Retrieves the scheduled sessions to export.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `room_id` - Only export the sessions in this room, every room when `None`
+/// - `tag_id` - Only export the sessions with this tag, every session when `None`
+///
+/// # Returns
+/// A `Result` containing the scheduled sessions ordered by start time and room, or an error if the
+/// query fails.
+///
+/// # Errors
+/// If the query fails, a `sqlx::Error` is returned.
+pub async fn schedule_entries_get(db_pool: &Pool<Postgres>, room_id: Option<i32>, tag_id: Option<i32>) -> Result<Vec<ScheduleEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        ScheduleEntry,
+        r#"SELECT
+            s.id as session_id,
+            s.title,
+            r.id as room_id,
+            r.name as room_name,
+            r.location,
+            ts.id as time_slot_id,
+            ts.start_time,
+            ts.end_time
+        FROM timeslot_assignments ta
+        JOIN sessions s ON s.id = ta.session_id
+        JOIN rooms r ON r.id = ta.room_id
+        JOIN time_slots ts ON ts.id = ta.time_slot_id
+        WHERE ($1::INTEGER IS NULL OR ta.room_id = $1)
+            AND ($2::INTEGER IS NULL OR EXISTS (SELECT 1 FROM session_tags st WHERE st.session_id = s.id AND st.tag_id = $2))
+        ORDER BY ts.start_time, ts.id, r.id"#,
+        room_id,
+        tag_id,
+    )
+        .fetch_all(db_pool)
+        .await
+}
+
+/// Struct representing a timeslot assignment that was created, changed or removed.
+///
+/// # Fields
+/// - `id` - The ID of the assignment
+/// - `time_slot_id` - The ID of the assignment's timeslot
+/// - `room_id` - The ID of the assignment's room
+/// - `session_id` - The ID of the session in the room, `None` if the cell is empty. For a removed
+///   assignment, the session it held when it was removed
+/// - `created_at` - When the assignment was created
+/// - `updated_at` - When the assignment was last changed or removed, the same as `created_at` if it
+///   never was
+/// - `removed` - Whether the assignment was removed from the schedule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema, FromRow)]
+pub struct ScheduleChange {
+    pub id: i32,
+    pub time_slot_id: Option<i32>,
+    pub room_id: Option<i32>,
+    pub session_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub removed: bool,
+}
+
+/// Struct representing the point in time to list the schedule changes after.
+///
+/// # Fields
+/// - `since` - Only list the assignments created or changed after this RFC 3339 timestamp
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+pub struct ScheduleChangesQuery {
+    pub since: DateTime<Utc>,
+}
+
+/// Retrieves the timeslot assignments created, changed or removed after a point in time.
+///
+/// Timestamps are stored with microsecond precision, so a client polling with the largest
+/// `updated_at` it has seen gets every later change exactly once. Removed assignments are listed
+/// with `removed` set, from the deletions recorded by the `timeslot_assignments_record_deletion`
+/// trigger.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `since` - Only list the assignments whose `updated_at` is strictly after this
+///
+/// # Returns
+/// A `Result` containing the changed assignments ordered by `updated_at` and ID, empty if nothing
+/// changed, or an error if the query fails.
+///
+/// # Errors
+/// If the query fails, a `sqlx::Error` is returned.
+pub async fn schedule_changes_get(db_pool: &Pool<Postgres>, since: DateTime<Utc>) -> Result<Vec<ScheduleChange>, sqlx::Error> {
+    sqlx::query_as!(
+        ScheduleChange,
+        r#"SELECT id as "id!", time_slot_id, room_id, session_id, created_at as "created_at!", updated_at as "updated_at!", removed as "removed!"
+        FROM (
+            SELECT id, time_slot_id, room_id, session_id, created_at, updated_at, false AS removed
+            FROM timeslot_assignments
+            WHERE updated_at > $1
+            UNION ALL
+            SELECT assignment_id, time_slot_id, room_id, session_id, created_at, deleted_at, true
+            FROM timeslot_assignment_deletions
+            WHERE deleted_at > $1
+        ) changes
+        ORDER BY updated_at, id"#,
+        since,
+    )
+        .fetch_all(db_pool)
+        .await
+}
+
+/// Struct representing a session on the printable schedule.
+///
+/// # Fields
+/// - `title` - The title of the session
+/// - `speaker` - The full name of the user who submitted the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintSession {
+    pub title: String,
+    pub speaker: String,
+}
+
+/// Struct representing a timeslot on the printable schedule.
+///
+/// # Fields
+/// - `start_time` - When the timeslot starts
+/// - `end_time` - When the timeslot ends
+/// - `sessions` - The session in each room, in the same order as the rooms, `None` when the room is
+///   free
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintTimeslot {
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub sessions: Vec<Option<PrintSession>>,
+}
+
+/// Struct representing the schedule as a grid to print and post on walls.
+///
+/// # Fields
+/// - `rooms` - The names of the rooms, ordered by ID
+/// - `timeslots` - The timeslots, ordered by start time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintSchedule {
+    pub rooms: Vec<String>,
+    pub timeslots: Vec<PrintTimeslot>,
+}
+
+/// Retrieves the schedule as a grid of rooms and timeslots, with the speaker of each session.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the `PrintSchedule` or an error if the query fails.
+///
+/// # Errors
+/// If the query fails, a `sqlx::Error` is returned.
+pub async fn print_schedule_get(db_pool: &Pool<Postgres>) -> Result<PrintSchedule, sqlx::Error> {
+    let rooms = sqlx::query_scalar!("SELECT name FROM rooms ORDER BY id")
+        .fetch_all(db_pool)
+        .await?;
+
+    let cells = sqlx::query!(
+        r#"SELECT ts.id, ts.start_time, ts.end_time, s.title as "title?", u.fname || ' ' || u.lname as "speaker?"
+        FROM time_slots ts
+        CROSS JOIN rooms r
+        LEFT JOIN timeslot_assignments ta ON ta.time_slot_id = ts.id AND ta.room_id = r.id
+        LEFT JOIN sessions s ON s.id = ta.session_id
+        LEFT JOIN users u ON u.id = s.user_id
+        ORDER BY ts.start_time, ts.id, r.id"#
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    let mut timeslots: Vec<PrintTimeslot> = Vec::new();
+    let mut current_timeslot_id = None;
+    for cell in cells {
+        if current_timeslot_id != Some(cell.id) {
+            current_timeslot_id = Some(cell.id);
+            timeslots.push(PrintTimeslot {
+                start_time: cell.start_time,
+                end_time: cell.end_time,
+                sessions: Vec::with_capacity(rooms.len()),
+            });
+        }
+
+        if let Some(timeslot) = timeslots.last_mut() {
+            let session = cell.title.map(|title| PrintSession { title, speaker: cell.speaker.unwrap_or_default() });
+            timeslot.sessions.push(session);
+        }
+    }
+
+    Ok(PrintSchedule { rooms, timeslots })
+}
+
+/// Quotes a CSV field if it contains a comma, quote or line break.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Builds a CSV document of the scheduled sessions.
+///
+/// # Parameters
+/// - `entries` - The scheduled sessions, in the order they should be listed
+///
+/// # Returns
+/// The CSV document, just the header row when there are no sessions.
+pub fn schedule_csv(entries: &[ScheduleEntry]) -> String {
+    let mut csv = format!("{CSV_HEADER}\r\n");
+    for entry in entries {
+        let _ = write!(
+            csv,
+            "{},{},{},{},{},{}\r\n",
+            entry.start_time.format("%H:%M"),
+            entry.end_time.format("%H:%M"),
+            csv_field(&entry.room_name),
+            csv_field(&entry.location),
+            entry.session_id,
+            csv_field(&entry.title),
+        );
+    }
+    csv
+}
+
+/// Escapes the characters iCalendar gives a special meaning to in text values.
+fn ics_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Builds an iCalendar document of the scheduled sessions.
+///
+/// Timeslots only have times, so every session is placed on `date` in the calendar user's local
+/// time.
+///
+/// # Parameters
+/// - `entries` - The scheduled sessions
+/// - `date` - The day the event takes place on
+/// - `generated_at` - When the document was generated, used as each event's timestamp
+///
+/// # Returns
+/// The iCalendar document, a calendar without events when there are no sessions.
+pub fn schedule_ics(entries: &[ScheduleEntry], date: NaiveDate, generated_at: DateTime<Utc>) -> String {
+    let mut ics = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//UnconfRS//Schedule//EN\r\nCALSCALE:GREGORIAN\r\n");
+    let dtstamp = generated_at.format("%Y%m%dT%H%M%SZ");
+    for entry in entries {
+        let _ = write!(
+            ics,
+            "BEGIN:VEVENT\r\nUID:session-{}-room-{}-{}@unconfrs\r\nDTSTAMP:{dtstamp}\r\nDTSTART:{}\r\nDTEND:{}\r\nSUMMARY:{}\r\nLOCATION:{}\r\nEND:VEVENT\r\n",
+            entry.session_id,
+            entry.room_id,
+            entry.start_time.format("%H%M"),
+            date.and_time(entry.start_time).format("%Y%m%dT%H%M%S"),
+            date.and_time(entry.end_time).format("%Y%m%dT%H%M%S"),
+            ics_text(&entry.title),
+            ics_text(&format!("{} ({})", entry.room_name, entry.location)),
+        );
+    }
+    ics.push_str("END:VCALENDAR\r\n");
+    ics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assign_session, insert_room, insert_session, insert_tag, insert_timeslot, insert_user, tag_session};
+    use sqlx::PgPool;
+
+    fn entry(title: &str) -> ScheduleEntry {
+        ScheduleEntry {
+            session_id: 1,
+            title: title.to_string(),
+            room_id: 2,
+            room_name: "Room A".to_string(),
+            location: "First floor".to_string(),
+            time_slot_id: 3,
+            start_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        assert_eq!(schedule_csv(&[]), format!("{CSV_HEADER}\r\n"));
+        assert_eq!(
+            schedule_csv(&[entry("Rust, \"fast\"")]),
+            format!("{CSV_HEADER}\r\n09:00,10:00,Room A,First floor,1,\"Rust, \"\"fast\"\"\"\r\n"),
+        );
+    }
+
+    #[test]
+    fn ics_has_an_event_per_session_on_the_event_date() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let generated_at = date.and_hms_opt(8, 0, 0).unwrap().and_utc();
+
+        let empty = schedule_ics(&[], date, generated_at);
+        assert!(empty.starts_with("BEGIN:VCALENDAR\r\n") && empty.ends_with("END:VCALENDAR\r\n"));
+        assert!(!empty.contains("BEGIN:VEVENT"));
+
+        let ics = schedule_ics(&[entry("Rust; Go")], date, generated_at);
+        assert!(ics.contains("DTSTART:20261017T090000\r\nDTEND:20261017T100000\r\n"));
+        assert!(ics.contains("SUMMARY:Rust\\; Go\r\n"));
+        assert!(ics.contains("DTSTAMP:20261017T080000Z\r\n"));
+    }
+
+    #[sqlx::test]
+    async fn print_schedule_has_titles_and_speakers_in_a_grid(pool: PgPool) {
+        let user_id = insert_user(&pool, "Ada", "Lovelace", "ada@example.com").await;
+        insert_room(&pool, "Room A", "Here", 10).await;
+        let room_b = insert_room(&pool, "Room B", "There", 10).await;
+        insert_timeslot(&pool, "10:00", "11:00").await;
+        let early_slot = insert_timeslot(&pool, "09:00", "10:00").await;
+        let session_id = insert_session(&pool, user_id, "Engines").await;
+        assign_session(&pool, early_slot, room_b, session_id).await;
+
+        let schedule = print_schedule_get(&pool).await.unwrap();
+
+        assert_eq!(schedule.rooms, vec!["Room A", "Room B"]);
+        let start_times: Vec<String> = schedule.timeslots.iter().map(|timeslot| timeslot.start_time.format("%H:%M").to_string()).collect();
+        assert_eq!(start_times, vec!["09:00", "10:00"]);
+        let engines = PrintSession { title: "Engines".to_string(), speaker: "Ada Lovelace".to_string() };
+        let grid: Vec<Vec<Option<PrintSession>>> = schedule.timeslots.into_iter().map(|timeslot| timeslot.sessions).collect();
+        assert_eq!(grid, vec![vec![None, Some(engines)], vec![None, None]]);
+    }
+
+    #[sqlx::test]
+    async fn entries_can_be_filtered_by_room(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let room_a = insert_room(&pool, "Room A", "Here", 10).await;
+        let room_b = insert_room(&pool, "Room B", "There", 10).await;
+        let late_slot = insert_timeslot(&pool, "10:00", "11:00").await;
+        let early_slot = insert_timeslot(&pool, "09:00", "10:00").await;
+        for (title, slot, room) in [("Late A", late_slot, room_a), ("Early A", early_slot, room_a), ("Early B", early_slot, room_b)] {
+            let session_id = insert_session(&pool, user_id, title).await;
+            assign_session(&pool, slot, room, session_id).await;
+        }
+
+        let titles = |entries: Vec<ScheduleEntry>| entries.into_iter().map(|entry| entry.title).collect::<Vec<_>>();
+        assert_eq!(titles(schedule_entries_get(&pool, None, None).await.unwrap()), vec!["Early A", "Early B", "Late A"]);
+        assert_eq!(titles(schedule_entries_get(&pool, Some(room_a), None).await.unwrap()), vec!["Early A", "Late A"]);
+    }
+
+    #[sqlx::test]
+    async fn entries_can_be_filtered_by_tag(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let room_id = insert_room(&pool, "Room A", "Here", 10).await;
+        let rust_tag = insert_tag(&pool, "Rust").await;
+        let go_tag = insert_tag(&pool, "Go").await;
+        let unused_tag = insert_tag(&pool, "Unused").await;
+        for (title, start, end, tag) in [("Ownership", "09:00", "10:00", rust_tag), ("Goroutines", "10:00", "11:00", go_tag), ("Lifetimes", "11:00", "12:00", rust_tag)] {
+            let slot = insert_timeslot(&pool, start, end).await;
+            let session_id = insert_session(&pool, user_id, title).await;
+            tag_session(&pool, session_id, tag).await;
+            assign_session(&pool, slot, room_id, session_id).await;
+        }
+
+        let titles = |entries: Vec<ScheduleEntry>| entries.into_iter().map(|entry| entry.title).collect::<Vec<_>>();
+        assert_eq!(titles(schedule_entries_get(&pool, None, Some(rust_tag)).await.unwrap()), vec!["Ownership", "Lifetimes"]);
+        assert_eq!(titles(schedule_entries_get(&pool, None, None).await.unwrap()).len(), 3);
+        assert!(schedule_entries_get(&pool, None, Some(unused_tag)).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn changes_are_the_assignments_updated_after_the_timestamp(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let room_id = insert_room(&pool, "Room A", "Here", 10).await;
+        let mut assignments = Vec::new();
+        for (start, end, updated_at) in [("09:00", "10:00", "2026-10-17 09:00:00+00"), ("10:00", "11:00", "2026-10-17 10:00:00.000002+00")] {
+            let slot = insert_timeslot(&pool, start, end).await;
+            let session_id = insert_session(&pool, user_id, "Talk").await;
+            let assignment_id: i32 = sqlx::query_scalar(
+                "INSERT INTO timeslot_assignments (time_slot_id, room_id, session_id, created_at, updated_at)
+                VALUES ($1, $2, $3, $4::TIMESTAMPTZ, $4::TIMESTAMPTZ)
+                RETURNING id",
+            )
+                .bind(slot)
+                .bind(room_id)
+                .bind(session_id)
+                .bind(updated_at)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assignments.push(assignment_id);
+        }
+
+        let ids = |changes: Vec<ScheduleChange>| changes.into_iter().map(|change| change.id).collect::<Vec<_>>();
+        let at = |timestamp: &str| DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc);
+        assert_eq!(ids(schedule_changes_get(&pool, at("2026-10-17T08:00:00Z")).await.unwrap()), assignments);
+        assert_eq!(ids(schedule_changes_get(&pool, at("2026-10-17T10:00:00.000001Z")).await.unwrap()), vec![assignments[1]]);
+        assert!(schedule_changes_get(&pool, at("2026-10-17T10:00:00.000002Z")).await.unwrap().is_empty());
+
+        sqlx::query("UPDATE timeslot_assignments SET session_id = NULL, updated_at = '2026-10-17 11:00:00+00' WHERE id = $1")
+            .bind(assignments[0])
+            .execute(&pool)
+            .await
+            .unwrap();
+        let changes = schedule_changes_get(&pool, at("2026-10-17T10:30:00Z")).await.unwrap();
+        assert_eq!(ids(changes.clone()), vec![assignments[0]]);
+        assert_eq!(changes[0].session_id, None);
+        assert_eq!(changes[0].created_at, at("2026-10-17T09:00:00Z"));
+    }
+
+    #[sqlx::test]
+    async fn removed_assignments_are_listed_as_changes(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let room_id = insert_room(&pool, "Room A", "Here", 10).await;
+        let slot = insert_timeslot(&pool, "09:00", "10:00").await;
+        let session_id = insert_session(&pool, user_id, "Talk").await;
+        let assignment_id = assign_session(&pool, slot, room_id, session_id).await;
+        let before_removal: DateTime<Utc> = sqlx::query_scalar("SELECT clock_timestamp()").fetch_one(&pool).await.unwrap();
+
+        sqlx::query("DELETE FROM timeslot_assignments WHERE id = $1").bind(assignment_id).execute(&pool).await.unwrap();
+
+        let changes = schedule_changes_get(&pool, before_removal).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].id, changes[0].session_id, changes[0].removed), (assignment_id, Some(session_id), true));
+        assert!(schedule_changes_get(&pool, changes[0].updated_at).await.unwrap().is_empty());
+    }
+}
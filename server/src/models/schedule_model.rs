@@ -1,12 +1,16 @@
+use crate::config::SchedulerConfig;
 use crate::models::room_model::RoomErr;
-use crate::models::timeslot_assignment_model::{assign_sessions_to_timeslots, get_all_unassigned_timeslots, session_already_scheduled, space_to_add_session};
-use crate::models::{room_model::rooms_get, sessions_model::{get_all_sessions, SessionErr}, timeslot_model::{timeslot_get, ExistingTimeslot}};
+use crate::models::settings_model::schedule_locked;
+use crate::models::timeslot_assignment_model::{assign_sessions_to_timeslots, get_all_unassigned_timeslots, proposed_assignment_data_get, scheduler_data_get, session_already_scheduled, session_assignment_data_get, session_availability_get, session_exclusions_get, space_to_add_session};
+use crate::models::{room_model::rooms_get, sessions_model::{self, get_all_sessions, SessionErr}, timeslot_model::{timeslot_get, ExistingTimeslot}};
 use crate::types::ApiStatusCode;
 use axum::response::IntoResponse;
 use axum::{http::StatusCode, response::Response, Json};
+use chrono::{DateTime, Utc};
+use scheduler::{Progress, ProgressCallback, RunStats, ScoreBreakdown};
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
-use sqlx::{FromRow, Pool, Postgres};
-use std::error::Error;
+use sqlx::{FromRow, PgConnection, Pool, Postgres};
+use std::{collections::{HashMap, HashSet}, error::Error, sync::Mutex, time::Duration};
 use utoipa::ToSchema;
 
 /// An enumeration of errors that may occur
@@ -19,6 +23,7 @@ use utoipa::ToSchema;
 /// - `IoError` - An I/O error occurred
 /// - `DoesNotExist` - The schedule does not exist
 /// - `InvalidTimeFormat` - The time format is invalid
+/// - `Locked` - The schedule is locked against edits
 pub enum ScheduleErr {
     #[error("Schedule io failed: {0}")]
     IoError(String),
@@ -32,6 +37,8 @@ pub enum ScheduleErr {
     SessionAlreadyScheduled(String),
     #[error("No space to add session {0}")]
     ScheduleFull(String),
+    #[error("Schedule is locked, unlock it before making changes")]
+    Locked,
 }
 
 /// Implements the `From` trait for `std::io::Error` to convert it into a `ScheduleErr`.
@@ -116,7 +123,51 @@ impl ScheduleError {
             status,
             error: error.to_string(),
         };
-        (status, Json(error)).into_response()
+
+        let http_status = StatusCode::from_u16(status.0)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        (http_status, Json(error)).into_response()
+    }
+
+    /// Creates a `Response` instance for a schedule that couldn't be checked for edits.
+    ///
+    /// # Parameters
+    /// - `error` - The `ScheduleErr` returned by `schedule_unlocked`
+    ///
+    /// # Returns
+    /// A `Response` instance with a status code of 423 Locked if the schedule is locked, otherwise
+    /// 500 Internal Server Error.
+    pub fn locked_response(error: ScheduleErr) -> Response {
+        let status = match error {
+            ScheduleErr::Locked => StatusCode::LOCKED,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        ScheduleError::response(ApiStatusCode::from(status), Box::new(error))
+    }
+}
+
+/// Struct representing how far generating a schedule has got.
+///
+/// # Fields
+/// - `restart` - How many restarts of the scheduler have finished
+/// - `restarts` - How many restarts the scheduler will do unless it runs out of time
+/// - `best_score` - The best score found so far, lower is better
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct GenerationProgress {
+    pub restart: usize,
+    pub restarts: usize,
+    pub best_score: f32,
+}
+
+impl From<Progress> for GenerationProgress {
+    fn from(progress: Progress) -> Self {
+        Self {
+            restart: progress.restart,
+            restarts: progress.restarts,
+            best_score: progress.best_score,
+        }
     }
 }
 
@@ -143,12 +194,16 @@ pub struct RemoveSessionReq {
 /// - `id` - The unique ID of the schedule
 /// - `num_of_timeslots` - The number of timeslots in the schedule
 /// - `timeslots` - A list of timeslots in the schedule
+/// - `unscheduled` - The IDs of the sessions left off the schedule when it was generated
 pub struct Schedule {
     #[serde(skip_deserializing)]
     pub id: Option<i32>,
     pub num_of_timeslots: i32,
     #[sqlx(skip)]
     pub timeslots: Vec<ExistingTimeslot>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub unscheduled: Vec<i32>,
 }
 
 impl Schedule {
@@ -169,6 +224,7 @@ impl Schedule {
             id,
             num_of_timeslots,
             timeslots,
+            unscheduled: vec![],
         }
     }
 }
@@ -187,6 +243,128 @@ impl IntoResponse for &Schedule {
     }
 }
 
+#[derive(Debug, Clone, Serialize, ToSchema, FromRow)]
+/// Struct representing the diagnostics of a schedule generation run
+///
+/// # Fields
+/// - `id` - The ID of the run
+/// - `created_at` - When the run finished
+/// - `restarts` - How many restarts the scheduler ran before finishing or being stopped
+/// - `iterations` - The total number of local search iterations across all restarts
+/// - `duration_ms` - How long the scheduler ran for in milliseconds
+/// - `starting_score` - The score of the schedule before the scheduler filled any slots
+/// - `final_score` - The score of the generated schedule
+/// - `unscheduled` - The number of sessions the scheduler left off the schedule
+pub struct ScheduleRun {
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    pub restarts: i32,
+    pub iterations: i64,
+    pub duration_ms: i64,
+    pub starting_score: f32,
+    pub final_score: f32,
+    pub unscheduled: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+/// An enumeration of the ways the schedule can break its invariants
+///
+/// # Variants
+/// - `SessionScheduledTwice` - A session is assigned to more than one cell
+/// - `RoomDoubleBooked` - A room holds more than one session in the same timeslot
+/// - `SpeakerDoubleBooked` - A speaker has more than one session in the same timeslot
+/// - `DanglingAssignment` - An assignment points at a session, room or timeslot that doesn't exist
+/// - `OverCapacity` - A session has more votes than its room has spots
+/// - `TooManyOpenRooms` - A timeslot has more sessions than its limit of open rooms
+pub enum ScheduleViolation {
+    SessionScheduledTwice {
+        session_id: i32,
+        assignment_ids: Vec<i32>,
+    },
+    RoomDoubleBooked {
+        time_slot_id: i32,
+        room_id: i32,
+        assignment_ids: Vec<i32>,
+    },
+    SpeakerDoubleBooked {
+        user_id: i32,
+        time_slot_id: i32,
+        session_ids: Vec<i32>,
+    },
+    DanglingAssignment {
+        assignment_id: i32,
+        missing: String,
+    },
+    OverCapacity {
+        assignment_id: i32,
+        session_id: i32,
+        room_id: i32,
+        votes: i64,
+        available_spots: i32,
+    },
+    TooManyOpenRooms {
+        time_slot_id: i32,
+        open_rooms: i64,
+        max_open_rooms: i32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+/// Struct representing the result of checking the schedule's invariants
+///
+/// # Fields
+/// - `valid` - Whether the schedule has no violations
+/// - `violations` - Every violation that was found
+pub struct ScheduleIntegrityReport {
+    pub valid: bool,
+    pub violations: Vec<ScheduleViolation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+/// Struct representing a cell of a schedule proposed as a whole, e.g. by a client editing the grid
+///
+/// # Fields
+/// - `time_slot_id` - The ID of the timeslot
+/// - `room_id` - The ID of the room
+/// - `session_id` - The ID of the session in the room during the timeslot
+pub struct ProposedAssignment {
+    pub time_slot_id: i32,
+    pub room_id: i32,
+    pub session_id: i32,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+/// Struct representing the result of checking and scoring a proposed grid
+///
+/// # Fields
+/// - `valid` - Whether the grid has no violations
+/// - `violations` - Every violation that was found, referring to the cells by their position in
+///   the grid
+/// - `score` - The score the schedule would have with the grid
+pub struct GridValidation {
+    pub valid: bool,
+    pub violations: Vec<ScheduleViolation>,
+    pub score: ScoreBreakdown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+/// Struct representing an unscheduled session suggested for a free slot in the schedule
+///
+/// # Fields
+/// - `session_id` - The ID of the suggested session
+/// - `title` - The title of the suggested session
+/// - `votes` - How many votes the session has
+/// - `time_slot_id` - The ID of the timeslot the session would be added to
+/// - `room_id` - The ID of the room the session would be added to
+pub struct ScheduleSuggestion {
+    pub session_id: i32,
+    pub title: String,
+    pub votes: i64,
+    pub time_slot_id: i32,
+    pub room_id: i32,
+}
+
 /// Retrieves a paginated list of schedules from the schedule .
 ///
 /// This function retrieves a paginated list of schedules from the schedule.
@@ -214,19 +392,125 @@ pub async fn schedules_get(db_pool: &Pool<Postgres>) -> Result<Option<Schedule>,
 }
 
 
+/// Something organizers have to add before a schedule can be built
+///
+/// # Variants
+/// - `NoRooms` - There aren't any rooms
+/// - `NoTimeslots` - There aren't any timeslots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SetupStep {
+    NoRooms,
+    NoTimeslots,
+}
+
+impl SetupStep {
+    /// Lists the steps that are still missing.
+    ///
+    /// # Parameters
+    /// - `has_rooms` - Whether there are any rooms
+    /// - `has_timeslots` - Whether there are any timeslots
+    pub fn missing(has_rooms: bool, has_timeslots: bool) -> Vec<SetupStep> {
+        let mut needs_setup = Vec::new();
+        if !has_rooms {
+            needs_setup.push(SetupStep::NoRooms);
+        }
+        if !has_timeslots {
+            needs_setup.push(SetupStep::NoTimeslots);
+        }
+        needs_setup
+    }
+
+    /// Describes what organizers have to do to complete the step.
+    pub fn description(&self) -> &'static str {
+        match self {
+            SetupStep::NoRooms => "Add the rooms sessions can be held in",
+            SetupStep::NoTimeslots => "Add the timeslots sessions can be held in",
+        }
+    }
+}
+
+/// Retrieves what still has to be set up before a schedule can be built.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the missing `SetupStep`s, empty when there are rooms and timeslots, or a
+/// `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while fetching the rooms or timeslots, a `ScheduleErr` error is returned.
+pub async fn schedule_setup_get(db_pool: &Pool<Postgres>) -> Result<Vec<SetupStep>, ScheduleErr> {
+    let rooms = rooms_get(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    let timeslots = timeslot_get(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    Ok(SetupStep::missing(rooms.is_some(), !timeslots.is_empty()))
+}
+
+/// Retrieves the IDs of the sessions that aren't on the schedule.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the session IDs or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while fetching the sessions, a `ScheduleErr` error is returned.
+pub async fn unscheduled_sessions_get(db_pool: &Pool<Postgres>) -> Result<Vec<i32>, ScheduleErr> {
+    sqlx::query_scalar!(
+        "SELECT s.id FROM sessions s
+        WHERE NOT EXISTS (SELECT 1 FROM timeslot_assignments ta WHERE ta.session_id = s.id)
+        ORDER BY s.id"
+    )
+        .fetch_all(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))
+}
+
 /// Generates a schedule.
 ///
-/// This function generates a schedule by assigning sessions to timeslots.
+/// This function generates a schedule by assigning sessions to timeslots. Sessions with fewer
+/// votes than `MIN_VOTES_TO_SCHEDULE` are left out and reported in the schedule's `unscheduled`
+/// list along with any other session that didn't fit.
 ///
 /// # Parameters
 /// - `db_pool` - The database connection pool
+/// - `config` - How to generate the schedule
 ///
 /// # Returns
 /// A `Result` containing the generated `Schedule` or a `ScheduleErr` error.
 ///
 /// # Errors
 /// If an error occurs while generating the schedule, a `ScheduleErr` error is returned.
-pub async fn schedule_generate(db_pool: &Pool<Postgres>) -> Result<Schedule, ScheduleErr> {
+pub async fn schedule_generate(db_pool: &Pool<Postgres>, config: &SchedulerConfig) -> Result<Schedule, ScheduleErr> {
+    schedule_generate_with_progress(db_pool, config, None).await
+}
+
+/// Generates a schedule, reporting the progress of the scheduler as it runs.
+///
+/// Progress is only reported by the local search scheduling method, after each of its restarts.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `config` - How to generate the schedule
+/// - `progress_callback` - Called with the progress of the scheduler after each restart
+///
+/// # Returns
+/// A `Result` containing the generated `Schedule` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while generating the schedule, a `ScheduleErr` error is returned.
+pub async fn schedule_generate_with_progress(
+    db_pool: &Pool<Postgres>,
+    config: &SchedulerConfig,
+    progress_callback: Option<ProgressCallback>,
+) -> Result<Schedule, ScheduleErr> {
     let sessions = get_all_sessions(db_pool)
         .await
         .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
@@ -243,11 +527,12 @@ pub async fn schedule_generate(db_pool: &Pool<Postgres>) -> Result<Schedule, Sch
         .await
         .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
 
-    match assign_sessions_to_timeslots(&sessions, &rooms, &existing_timeslots, db_pool).await {
+    match assign_sessions_to_timeslots(&sessions, &rooms, &existing_timeslots, db_pool, config, progress_callback).await {
         Ok(_) => {
             schedule.timeslots = timeslot_get(db_pool)
                 .await
                 .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+            schedule.unscheduled = unscheduled_sessions_get(db_pool).await?;
 
             Ok(schedule)
         }
@@ -269,8 +554,16 @@ pub async fn schedule_generate(db_pool: &Pool<Postgres>) -> Result<Schedule, Sch
 /// A `Result` containing the generated `Schedule` or a `ScheduleErr` error.
 ///
 /// # Errors
+/// If the session doesn't exist, a `ScheduleErr::SessionError` error is returned.
 /// If an error occurs while generating the schedule, a `ScheduleErr` error is returned.
 pub async fn add_session(db_pool: &Pool<Postgres>, session_id: i32) -> Result<Schedule, ScheduleErr> {
+    if let Err(e) = sessions_model::get(db_pool, session_id).await {
+        return Err(match e.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::RowNotFound) => SessionErr::DoesNotExist(session_id.to_string()).into(),
+            _ => ScheduleErr::IoError(e.to_string()),
+        });
+    }
+
     if session_already_scheduled(db_pool, session_id).await? {
         return Err(ScheduleErr::SessionAlreadyScheduled(session_id.to_string()));
     }
@@ -341,6 +634,43 @@ pub async fn remove_session(
     ))
 }
 
+/// Removes a session from the schedule, wherever it is scheduled.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `session_id` - The ID of the session to unschedule
+///
+/// # Returns
+/// A `Result` containing the updated `Schedule` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If the session isn't scheduled a `ScheduleErr::DoesNotExist` error is returned, if the query
+/// fails a `ScheduleErr::IoError` error is returned.
+pub async fn unschedule_session(db_pool: &Pool<Postgres>, session_id: i32) -> Result<Schedule, ScheduleErr> {
+    let affected_rows = sqlx::query!(
+        "DELETE FROM timeslot_assignments WHERE session_id = $1",
+        session_id,
+    )
+        .execute(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?
+        .rows_affected();
+
+    if affected_rows == 0 {
+        return Err(ScheduleErr::DoesNotExist(format!("Session {session_id} is not scheduled")));
+    }
+
+    let timeslots = timeslot_get(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    Ok(Schedule::new(
+        Some(1),
+        i32::try_from(timeslots.len()).map_err(|e| ScheduleErr::IoError(e.to_string()))?,
+        timeslots,
+    ))
+}
+
 /// Clears the schedule by removing session associations with timeslots.
 ///
 /// This function clears the schedule by removing session associations with timeslots.
@@ -360,3 +690,1035 @@ pub async fn schedule_clear(db_pool: &Pool<Postgres>) -> Result<(), Box<dyn Erro
 
     Ok(())
 }
+
+/// Checks that the schedule can be edited.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing `()` if the schedule isn't locked.
+///
+/// # Errors
+/// If the schedule is locked a `ScheduleErr::Locked` error is returned, if the lock couldn't be
+/// read a `ScheduleErr::IoError` error is returned.
+pub async fn schedule_unlocked(db_pool: &Pool<Postgres>) -> Result<(), ScheduleErr> {
+    match schedule_locked(db_pool).await {
+        Ok(false) => Ok(()),
+        Ok(true) => Err(ScheduleErr::Locked),
+        Err(e) => Err(ScheduleErr::IoError(e.to_string())),
+    }
+}
+
+/// Scores the current schedule.
+///
+/// This function scores the schedule as it currently is, with every assignment treated as already
+/// assigned, using the same penalties the scheduler optimizes for. Nothing is changed.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `config` - The weights to score the schedule with
+///
+/// # Returns
+/// A `Result` containing the `ScoreBreakdown` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while loading the schedule, a `ScheduleErr` error is returned.
+pub async fn schedule_score(db_pool: &Pool<Postgres>, config: &SchedulerConfig) -> Result<ScoreBreakdown, ScheduleErr> {
+    let sessions = get_all_sessions(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    let scheduling_data = session_assignment_data_get(&sessions, db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    let scheduler_data = scheduler_data_get(db_pool, scheduling_data, config)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    Ok(scheduler_data.score_breakdown())
+}
+
+/// The score of the current schedule, kept until the schedule changes.
+///
+/// Scoring loads every session and assignment, so the score is worked out the first time it is
+/// asked for and reused until `invalidate` is called.
+///
+/// # Fields
+/// - `state` - How many times the cache has been invalidated, and the score if it is up to date
+#[derive(Debug, Default)]
+pub struct ScoreCache {
+    state: Mutex<(u64, Option<ScoreBreakdown>)>,
+}
+
+impl ScoreCache {
+    /// Forgets the cached score, so the next read scores the schedule again.
+    pub fn invalidate(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.0 += 1;
+        state.1 = None;
+    }
+
+    /// Returns the cached score, scoring the schedule if there isn't one.
+    ///
+    /// A score worked out while the cache was invalidated isn't kept, since the schedule may have
+    /// changed after it was read.
+    ///
+    /// # Parameters
+    /// - `db_pool` - The database connection pool
+    /// - `config` - The weights to score the schedule with
+    ///
+    /// # Returns
+    /// A `Result` containing the `ScoreBreakdown` or a `ScheduleErr` error.
+    ///
+    /// # Errors
+    /// If an error occurs while loading the schedule, a `ScheduleErr` error is returned.
+    pub async fn get_or_compute(&self, db_pool: &Pool<Postgres>, config: &SchedulerConfig) -> Result<ScoreBreakdown, ScheduleErr> {
+        let generation = {
+            let state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(score) = &state.1 {
+                return Ok(score.clone());
+            }
+            state.0
+        };
+
+        let score = schedule_score(db_pool, config).await?;
+
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.0 == generation {
+            state.1 = Some(score.clone());
+        }
+        Ok(score)
+    }
+}
+
+/// Records the diagnostics of a schedule generation run.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `stats` - The stats reported by the scheduler
+/// - `duration` - How long the scheduler ran for
+/// - `unscheduled` - The number of sessions the scheduler left off the schedule
+///
+/// # Returns
+/// A `Result` containing the recorded `ScheduleRun` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while saving the run, a `ScheduleErr` error is returned.
+pub async fn schedule_run_insert(
+    db_pool: &Pool<Postgres>,
+    stats: &RunStats,
+    duration: Duration,
+    unscheduled: i32,
+) -> Result<ScheduleRun, ScheduleErr> {
+    sqlx::query_as!(
+        ScheduleRun,
+        r#"INSERT INTO schedule_runs (restarts, iterations, duration_ms, starting_score, final_score, unscheduled)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, created_at, restarts, iterations, duration_ms, starting_score, final_score, unscheduled"#,
+        stats.restarts as i32,
+        stats.iterations as i64,
+        duration.as_millis() as i64,
+        stats.starting_score,
+        stats.final_score,
+        unscheduled,
+    )
+        .fetch_one(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))
+}
+
+/// Retrieves the diagnostics of the most recent schedule generation run.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the latest `ScheduleRun` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If no schedule has been generated yet, a `ScheduleErr::DoesNotExist` error is returned. If an
+/// error occurs while fetching the run, a `ScheduleErr::IoError` error is returned.
+pub async fn last_schedule_run_get(db_pool: &Pool<Postgres>) -> Result<ScheduleRun, ScheduleErr> {
+    sqlx::query_as!(
+        ScheduleRun,
+        r#"SELECT id, created_at, restarts, iterations, duration_ms, starting_score, final_score, unscheduled
+        FROM schedule_runs
+        ORDER BY created_at DESC, id DESC
+        LIMIT 1"#,
+    )
+        .fetch_optional(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?
+        .ok_or_else(|| ScheduleErr::DoesNotExist("run".to_string()))
+}
+
+/// Checks the schedule for assignments that break its invariants.
+///
+/// The web UI guards against most of these, but manual edits and migrations can bypass it. The
+/// checks are: no session scheduled twice, no speaker with two sessions in the same timeslot, no
+/// assignment pointing at a missing session, room or timeslot, no session with more votes than
+/// its room has spots, and no timeslot with more sessions than its limit of open rooms. Empty
+/// cells, with no session in them, aren't violations.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the `ScheduleIntegrityReport` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while querying the schedule, a `ScheduleErr::IoError` error is returned.
+pub async fn schedule_integrity_check(db_pool: &Pool<Postgres>) -> Result<ScheduleIntegrityReport, ScheduleErr> {
+    let assignments = sqlx::query!(
+        "SELECT id, time_slot_id, room_id, session_id FROM timeslot_assignments ORDER BY id"
+    )
+        .fetch_all(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    let cells = CheckedCells {
+        ids: assignments.iter().map(|row| row.id).collect(),
+        time_slot_ids: assignments.iter().map(|row| row.time_slot_id).collect(),
+        room_ids: assignments.iter().map(|row| row.room_id).collect(),
+        session_ids: assignments.iter().map(|row| row.session_id).collect(),
+    };
+    let mut conn = db_pool.acquire().await.map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    cells_integrity_check(&mut conn, &cells).await
+}
+
+/// Checks a proposed grid for the same violations as `schedule_integrity_check`, without saving it.
+///
+/// The violations refer to the cells by their position in the grid, starting from 0, in place of
+/// an assignment ID.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `grid` - Every cell of the proposed schedule
+///
+/// # Returns
+/// A `Result` containing the `ScheduleIntegrityReport` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while querying the sessions, rooms or timeslots, a `ScheduleErr::IoError`
+/// error is returned.
+pub async fn grid_integrity_check(db_pool: &Pool<Postgres>, grid: &[ProposedAssignment]) -> Result<ScheduleIntegrityReport, ScheduleErr> {
+    let mut conn = db_pool.acquire().await.map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    cells_integrity_check(&mut conn, &CheckedCells::from(grid)).await
+}
+
+/// Replaces the whole schedule with a proposed grid in one transaction.
+///
+/// The grid is checked the same way as `grid_integrity_check` first, inside the transaction, and
+/// the schedule is left as it was if there are any violations.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `grid` - Every cell of the new schedule
+///
+/// # Returns
+/// A `Result` containing the `ScheduleIntegrityReport` of the grid, the schedule was only replaced
+/// if the report is valid.
+///
+/// # Errors
+/// If an error occurs while checking or saving the grid, nothing is changed and a
+/// `ScheduleErr::IoError` error is returned.
+pub async fn grid_commit(db_pool: &Pool<Postgres>, grid: &[ProposedAssignment]) -> Result<ScheduleIntegrityReport, ScheduleErr> {
+    let mut transaction = db_pool.begin().await.map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    let cells = CheckedCells::from(grid);
+
+    let report = cells_integrity_check(&mut transaction, &cells).await?;
+    if !report.valid {
+        return Ok(report);
+    }
+
+    sqlx::query!("DELETE FROM timeslot_assignments")
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    sqlx::query!(
+        "INSERT INTO timeslot_assignments (time_slot_id, room_id, session_id)
+        SELECT * FROM UNNEST($1::INTEGER[], $2::INTEGER[], $3::INTEGER[])",
+        &cells.time_slot_ids as &[Option<i32>],
+        &cells.room_ids as &[Option<i32>],
+        &cells.session_ids as &[Option<i32>],
+    )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    transaction.commit().await.map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    Ok(report)
+}
+
+/// The cells of a schedule as the columns `cells_integrity_check` unnests them from.
+///
+/// # Fields
+/// - `ids` - What the violations refer to each cell by
+/// - `time_slot_ids` - The timeslot of each cell
+/// - `room_ids` - The room of each cell
+/// - `session_ids` - The session in each cell
+struct CheckedCells {
+    ids: Vec<i32>,
+    time_slot_ids: Vec<Option<i32>>,
+    room_ids: Vec<Option<i32>>,
+    session_ids: Vec<Option<i32>>,
+}
+
+impl From<&[ProposedAssignment]> for CheckedCells {
+    /// Each cell of the grid is referred to by its position in the grid
+    fn from(grid: &[ProposedAssignment]) -> Self {
+        Self {
+            ids: (0..grid.len() as i32).collect(),
+            time_slot_ids: grid.iter().map(|cell| Some(cell.time_slot_id)).collect(),
+            room_ids: grid.iter().map(|cell| Some(cell.room_id)).collect(),
+            session_ids: grid.iter().map(|cell| Some(cell.session_id)).collect(),
+        }
+    }
+}
+
+/// Runs the integrity checks over a schedule's cells, see `schedule_integrity_check`.
+///
+/// A room and timeslot holding more than one cell is checked for as well, which only a proposed
+/// grid can have since the table doesn't allow it.
+async fn cells_integrity_check(conn: &mut PgConnection, cells: &CheckedCells) -> Result<ScheduleIntegrityReport, ScheduleErr> {
+    let mut violations = Vec::new();
+
+    let scheduled_twice = sqlx::query!(
+        r#"SELECT ta.session_id as "session_id!", ARRAY_AGG(ta.id ORDER BY ta.id) as "assignment_ids!"
+        FROM UNNEST($1::INTEGER[], $2::INTEGER[], $3::INTEGER[], $4::INTEGER[]) AS ta (id, time_slot_id, room_id, session_id)
+        WHERE ta.session_id IS NOT NULL
+        GROUP BY ta.session_id
+        HAVING COUNT(*) > 1
+        ORDER BY ta.session_id"#,
+        &cells.ids,
+        &cells.time_slot_ids as &[Option<i32>],
+        &cells.room_ids as &[Option<i32>],
+        &cells.session_ids as &[Option<i32>],
+    )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    violations.extend(scheduled_twice.into_iter().map(|row| ScheduleViolation::SessionScheduledTwice {
+        session_id: row.session_id,
+        assignment_ids: row.assignment_ids,
+    }));
+
+    let cell_taken_twice = sqlx::query!(
+        r#"SELECT ta.time_slot_id as "time_slot_id!", ta.room_id as "room_id!", ARRAY_AGG(ta.id ORDER BY ta.id) as "assignment_ids!"
+        FROM UNNEST($1::INTEGER[], $2::INTEGER[], $3::INTEGER[], $4::INTEGER[]) AS ta (id, time_slot_id, room_id, session_id)
+        WHERE ta.time_slot_id IS NOT NULL AND ta.room_id IS NOT NULL
+        GROUP BY ta.time_slot_id, ta.room_id
+        HAVING COUNT(*) > 1
+        ORDER BY ta.time_slot_id, ta.room_id"#,
+        &cells.ids,
+        &cells.time_slot_ids as &[Option<i32>],
+        &cells.room_ids as &[Option<i32>],
+        &cells.session_ids as &[Option<i32>],
+    )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    violations.extend(cell_taken_twice.into_iter().map(|row| ScheduleViolation::RoomDoubleBooked {
+        time_slot_id: row.time_slot_id,
+        room_id: row.room_id,
+        assignment_ids: row.assignment_ids,
+    }));
+
+    let double_booked = sqlx::query!(
+        r#"SELECT s.user_id, ta.time_slot_id as "time_slot_id!", ARRAY_AGG(s.id ORDER BY s.id) as "session_ids!"
+        FROM UNNEST($1::INTEGER[], $2::INTEGER[], $3::INTEGER[], $4::INTEGER[]) AS ta (id, time_slot_id, room_id, session_id)
+        JOIN sessions s ON s.id = ta.session_id
+        WHERE ta.time_slot_id IS NOT NULL
+        GROUP BY s.user_id, ta.time_slot_id
+        HAVING COUNT(*) > 1
+        ORDER BY ta.time_slot_id, s.user_id"#,
+        &cells.ids,
+        &cells.time_slot_ids as &[Option<i32>],
+        &cells.room_ids as &[Option<i32>],
+        &cells.session_ids as &[Option<i32>],
+    )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    violations.extend(double_booked.into_iter().map(|row| ScheduleViolation::SpeakerDoubleBooked {
+        user_id: row.user_id,
+        time_slot_id: row.time_slot_id,
+        session_ids: row.session_ids,
+    }));
+
+    let dangling = sqlx::query!(
+        r#"SELECT
+            ta.id as "id!",
+            (ta.session_id IS NOT NULL AND s.id IS NULL) as "missing_session!",
+            (ta.room_id IS NOT NULL AND r.id IS NULL) as "missing_room!",
+            (ta.time_slot_id IS NOT NULL AND ts.id IS NULL) as "missing_timeslot!"
+        FROM UNNEST($1::INTEGER[], $2::INTEGER[], $3::INTEGER[], $4::INTEGER[]) AS ta (id, time_slot_id, room_id, session_id)
+        LEFT JOIN sessions s ON s.id = ta.session_id
+        LEFT JOIN rooms r ON r.id = ta.room_id
+        LEFT JOIN time_slots ts ON ts.id = ta.time_slot_id
+        WHERE (ta.session_id IS NOT NULL AND s.id IS NULL)
+            OR (ta.room_id IS NOT NULL AND r.id IS NULL)
+            OR (ta.time_slot_id IS NOT NULL AND ts.id IS NULL)
+        ORDER BY ta.id"#,
+        &cells.ids,
+        &cells.time_slot_ids as &[Option<i32>],
+        &cells.room_ids as &[Option<i32>],
+        &cells.session_ids as &[Option<i32>],
+    )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    for row in dangling {
+        let missing = [
+            (row.missing_session, "session"),
+            (row.missing_room, "room"),
+            (row.missing_timeslot, "timeslot"),
+        ];
+        violations.extend(missing.into_iter().filter(|(is_missing, _)| *is_missing).map(|(_, missing)| {
+            ScheduleViolation::DanglingAssignment {
+                assignment_id: row.id,
+                missing: missing.to_string(),
+            }
+        }));
+    }
+
+    let over_capacity = sqlx::query!(
+        r#"SELECT ta.id as "id!", s.id as session_id, r.id as room_id, r.available_spots, COUNT(uv.user_id) as "votes!"
+        FROM UNNEST($1::INTEGER[], $2::INTEGER[], $3::INTEGER[], $4::INTEGER[]) AS ta (id, time_slot_id, room_id, session_id)
+        JOIN sessions s ON s.id = ta.session_id
+        JOIN rooms r ON r.id = ta.room_id
+        LEFT JOIN user_votes uv ON uv.session_id = s.id
+        GROUP BY ta.id, s.id, r.id
+        HAVING COUNT(uv.user_id) > r.available_spots
+        ORDER BY ta.id"#,
+        &cells.ids,
+        &cells.time_slot_ids as &[Option<i32>],
+        &cells.room_ids as &[Option<i32>],
+        &cells.session_ids as &[Option<i32>],
+    )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    violations.extend(over_capacity.into_iter().map(|row| ScheduleViolation::OverCapacity {
+        assignment_id: row.id,
+        session_id: row.session_id,
+        room_id: row.room_id,
+        votes: row.votes,
+        available_spots: row.available_spots,
+    }));
+
+    let too_many_open_rooms = sqlx::query!(
+        r#"SELECT ts.id, ts.max_open_rooms as "max_open_rooms!", COUNT(ta.id) as "open_rooms!"
+        FROM time_slots ts
+        JOIN UNNEST($1::INTEGER[], $2::INTEGER[], $3::INTEGER[], $4::INTEGER[]) AS ta (id, time_slot_id, room_id, session_id)
+            ON ta.time_slot_id = ts.id AND ta.session_id IS NOT NULL
+        WHERE ts.max_open_rooms IS NOT NULL
+        GROUP BY ts.id
+        HAVING COUNT(ta.id) > ts.max_open_rooms
+        ORDER BY ts.id"#,
+        &cells.ids,
+        &cells.time_slot_ids as &[Option<i32>],
+        &cells.room_ids as &[Option<i32>],
+        &cells.session_ids as &[Option<i32>],
+    )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    violations.extend(too_many_open_rooms.into_iter().map(|row| ScheduleViolation::TooManyOpenRooms {
+        time_slot_id: row.id,
+        open_rooms: row.open_rooms,
+        max_open_rooms: row.max_open_rooms,
+    }));
+
+    Ok(ScheduleIntegrityReport {
+        valid: violations.is_empty(),
+        violations,
+    })
+}
+
+/// Scores a proposed grid as if it replaced the schedule, without saving it.
+///
+/// Cells pointing at a missing session, room or timeslot are left out of the score.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `config` - The weights to score the grid with
+/// - `grid` - Every cell of the proposed schedule
+///
+/// # Returns
+/// A `Result` containing the `ScoreBreakdown` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while loading the sessions, rooms or timeslots, a `ScheduleErr` error is
+/// returned.
+pub async fn grid_score(db_pool: &Pool<Postgres>, config: &SchedulerConfig, grid: &[ProposedAssignment]) -> Result<ScoreBreakdown, ScheduleErr> {
+    let sessions = get_all_sessions(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    let scheduling_data = proposed_assignment_data_get(&sessions, db_pool, grid)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    let scheduler_data = scheduler_data_get(db_pool, scheduling_data, config)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    Ok(scheduler_data.score_breakdown())
+}
+
+/// Checks and scores a proposed grid, without saving it.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `config` - The weights to score the grid with
+/// - `grid` - Every cell of the proposed schedule
+///
+/// # Returns
+/// A `Result` containing the `GridValidation` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while checking or scoring the grid, a `ScheduleErr` error is returned.
+pub async fn grid_validate(db_pool: &Pool<Postgres>, config: &SchedulerConfig, grid: &[ProposedAssignment]) -> Result<GridValidation, ScheduleErr> {
+    let ScheduleIntegrityReport { valid, violations } = grid_integrity_check(db_pool, grid).await?;
+    let score = grid_score(db_pool, config, grid).await?;
+
+    Ok(GridValidation { valid, violations, score })
+}
+
+/// A session on the schedule, or one that could be added to it, as far as the suggestions need to
+/// know to keep it out of the way of the sessions it conflicts with
+struct SlotOccupant {
+    session_id: i32,
+    speaker_id: i32,
+    is_keynote: bool,
+}
+
+impl SlotOccupant {
+    /// Whether two sessions shouldn't share a timeslot
+    ///
+    /// They do when either is a keynote, they have the same speaker, either speaker voted for the
+    /// other session or they are mutually exclusive.
+    fn conflicts_with(&self, other: &SlotOccupant, speaker_votes: &HashSet<(i32, i32)>, exclusions: &HashSet<(i32, i32)>) -> bool {
+        let pair = (self.session_id.min(other.session_id), self.session_id.max(other.session_id));
+
+        self.is_keynote
+            || other.is_keynote
+            || self.speaker_id == other.speaker_id
+            || speaker_votes.contains(&(self.speaker_id, other.session_id))
+            || speaker_votes.contains(&(other.speaker_id, self.session_id))
+            || exclusions.contains(&pair)
+    }
+}
+
+/// Suggests which unscheduled sessions to add to the free slots in the schedule.
+///
+/// The unscheduled sessions are taken most voted first, each gets the first free slot in the order
+/// `add_session` fills them that it can go in. A session can't go in a timeslot it isn't available
+/// in, or alongside a session it conflicts with: the same speaker, a speaker who voted for the
+/// other session, a mutually exclusive session or a keynote, which gets a timeslot to itself.
+/// Sessions that don't fit anywhere are skipped, so there are never more suggestions than free
+/// slots. Nothing is saved.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `keynotes` - Whether keynotes get a timeslot to themselves
+///
+/// # Returns
+/// A `Result` containing the `ScheduleSuggestion`s, most voted first, or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while querying the schedule, a `ScheduleErr::IoError` error is returned.
+pub async fn schedule_suggestions_get(db_pool: &Pool<Postgres>, keynotes: bool) -> Result<Vec<ScheduleSuggestion>, ScheduleErr> {
+    let mut free_slots = get_all_unassigned_timeslots(db_pool).await?;
+    if free_slots.is_empty() {
+        return Ok(Vec::new());
+    }
+    let io_error = |e: sqlx::Error| ScheduleErr::IoError(e.to_string());
+
+    let scheduled = sqlx::query!(
+        r#"SELECT ta.time_slot_id as "time_slot_id!", s.id, s.user_id, s.is_keynote
+        FROM timeslot_assignments ta
+        JOIN sessions s ON s.id = ta.session_id
+        WHERE ta.time_slot_id IS NOT NULL"#
+    )
+        .fetch_all(db_pool)
+        .await
+        .map_err(io_error)?;
+    let mut occupants: HashMap<i32, Vec<SlotOccupant>> = HashMap::new();
+    for session in scheduled {
+        occupants.entry(session.time_slot_id).or_default().push(SlotOccupant {
+            session_id: session.id,
+            speaker_id: session.user_id,
+            is_keynote: session.is_keynote && keynotes,
+        });
+    }
+
+    let sessions = sqlx::query!(
+        r#"SELECT s.id, s.title, s.user_id, s.is_keynote, COUNT(uv.user_id) as "votes!"
+        FROM sessions s
+        LEFT JOIN user_votes uv ON uv.session_id = s.id
+        WHERE NOT EXISTS (SELECT 1 FROM timeslot_assignments ta WHERE ta.session_id = s.id)
+        GROUP BY s.id
+        ORDER BY COUNT(uv.user_id) DESC, s.id"#
+    )
+        .fetch_all(db_pool)
+        .await
+        .map_err(io_error)?;
+
+    let speaker_votes: HashSet<(i32, i32)> = sqlx::query!(
+        r#"SELECT uv.user_id as "user_id!", uv.session_id as "session_id!"
+        FROM user_votes uv
+        WHERE EXISTS (SELECT 1 FROM sessions s WHERE s.user_id = uv.user_id)"#
+    )
+        .fetch_all(db_pool)
+        .await
+        .map_err(io_error)?
+        .into_iter()
+        .map(|vote| (vote.user_id, vote.session_id))
+        .collect();
+    let exclusions: HashSet<(i32, i32)> = session_exclusions_get(db_pool).await.map_err(io_error)?.into_iter().collect();
+    let availability = session_availability_get(db_pool).await.map_err(io_error)?;
+
+    let mut suggestions = Vec::new();
+    for session in sessions {
+        if free_slots.is_empty() {
+            break;
+        }
+        let candidate = SlotOccupant {
+            session_id: session.id,
+            speaker_id: session.user_id,
+            is_keynote: session.is_keynote && keynotes,
+        };
+        let fits = |time_slot_id: i32| {
+            availability.get(&session.id).is_none_or(|time_slot_ids| time_slot_ids.contains(&time_slot_id))
+                && occupants
+                    .get(&time_slot_id)
+                    .is_none_or(|others| others.iter().all(|other| !candidate.conflicts_with(other, &speaker_votes, &exclusions)))
+        };
+        let Some(slot_idx) = free_slots.iter().position(|slot| fits(slot.time_slot_id)) else {
+            continue;
+        };
+
+        let slot = free_slots.remove(slot_idx);
+        occupants.entry(slot.time_slot_id).or_default().push(candidate);
+        suggestions.push(ScheduleSuggestion {
+            session_id: session.id,
+            title: session.title,
+            votes: session.votes,
+            time_slot_id: slot.time_slot_id,
+            room_id: slot.room_id,
+        });
+    }
+
+    Ok(suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::timeslot_assignment_model::PenaltyNormalization;
+    use crate::test_support::{assign_session, insert_room, insert_session, insert_timeslot, insert_timeslot_with, insert_user, insert_vote};
+    use sqlx::PgPool;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn locked_response_has_the_status_and_the_api_error_shape() {
+        let response = ScheduleError::locked_response(ScheduleErr::Locked);
+
+        assert_eq!(response.status(), StatusCode::LOCKED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json, serde_json::json!({ "status": "423", "error": "Schedule is locked, unlock it before making changes" }));
+    }
+
+    #[sqlx::test]
+    async fn schedule_score_uses_current_assignments(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let voter_id = insert_user(&pool, "B", "User", "b@example.com").await;
+        let popular_id = insert_session(&pool, user_id, "Popular").await;
+        let unvoted_id = insert_session(&pool, user_id, "Unvoted").await;
+        let unscheduled_id = insert_session(&pool, user_id, "Unscheduled").await;
+        let room_id = insert_room(&pool, "Room", "Here", 10).await;
+        let other_room_id = insert_room(&pool, "Other room", "There", 10).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+
+        // The popular session has 2 votes, the unscheduled one has 1
+        sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $3), ($2, $3), ($1, $4)")
+            .bind(user_id)
+            .bind(voter_id)
+            .bind(popular_id)
+            .bind(unscheduled_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO timeslot_assignments (time_slot_id, session_id, room_id) VALUES ($1, $2, $3), ($1, $4, $5)")
+            .bind(time_slot_id)
+            .bind(popular_id)
+            .bind(room_id)
+            .bind(unvoted_id)
+            .bind(other_room_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let score = schedule_score(&pool, &SchedulerConfig::default()).await.unwrap();
+
+        // The unscheduled session beats the unvoted session by 1 vote
+        assert_eq!(score.missing, 15);
+        // The unvoted session doesn't conflict with the popular one
+        assert_eq!(score.conflicting, 0);
+
+        let assignments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM timeslot_assignments")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(assignments, 2);
+    }
+
+    #[sqlx::test]
+    async fn generating_a_schedule_records_the_run(pool: PgPool) {
+        assert!(matches!(last_schedule_run_get(&pool).await, Err(ScheduleErr::DoesNotExist(_))));
+
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        insert_room(&pool, "Room", "Here", 10).await;
+        insert_timeslot(&pool, "09:00", "10:00").await;
+        for title in ["Scheduled", "Left out"] {
+            let session_id = insert_session(&pool, user_id, title).await;
+            insert_vote(&pool, user_id, session_id).await;
+        }
+
+        let sessions = get_all_sessions(&pool).await.unwrap();
+        let scheduling_data = session_assignment_data_get(&sessions, &pool).await.unwrap();
+        crate::models::timeslot_assignment_model::local_search_scheduling(&pool, scheduling_data, &SchedulerConfig::default(), None).await.unwrap();
+
+        // One room and one timeslot only fit one of the two sessions
+        let run = last_schedule_run_get(&pool).await.unwrap();
+        assert_eq!(run.restarts, 40);
+        assert_eq!(run.iterations, 40 * 3);
+        assert_eq!(run.unscheduled, 1);
+        assert!(run.final_score <= run.starting_score);
+    }
+
+    #[sqlx::test]
+    async fn generating_a_schedule_normalizes_the_penalties_when_configured(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        insert_room(&pool, "Room", "Here", 10).await;
+        insert_room(&pool, "Other room", "There", 10).await;
+        insert_timeslot(&pool, "09:00", "10:00").await;
+        for title in ["First", "Second"] {
+            let session_id = insert_session(&pool, user_id, title).await;
+            insert_vote(&pool, user_id, session_id).await;
+        }
+        let generate = |config: SchedulerConfig| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query("DELETE FROM timeslot_assignments").execute(&pool).await.unwrap();
+                let sessions = get_all_sessions(&pool).await.unwrap();
+                let scheduling_data = session_assignment_data_get(&sessions, &pool).await.unwrap();
+                crate::models::timeslot_assignment_model::local_search_scheduling(&pool, scheduling_data, &config, None).await.unwrap();
+                last_schedule_run_get(&pool).await.unwrap().final_score
+            }
+        };
+
+        // The two sessions clash however they are placed, so scaled by their range the penalties
+        // come to nothing
+        assert!(generate(SchedulerConfig::default()).await > 0.0);
+        let normalized = SchedulerConfig { normalization: PenaltyNormalization::MinMax, ..SchedulerConfig::default() };
+        assert_eq!(generate(normalized).await, 0.0);
+    }
+
+    #[sqlx::test]
+    async fn generating_a_schedule_reports_progress_after_each_restart(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        insert_room(&pool, "Room", "Here", 10).await;
+        insert_timeslot(&pool, "09:00", "10:00").await;
+        insert_session(&pool, user_id, "Talk").await;
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let progress_callback = ProgressCallback::new({
+            let reported = reported.clone();
+            move |progress| reported.lock().unwrap().push(GenerationProgress::from(progress))
+        });
+        let sessions = get_all_sessions(&pool).await.unwrap();
+        let scheduling_data = session_assignment_data_get(&sessions, &pool).await.unwrap();
+        crate::models::timeslot_assignment_model::local_search_scheduling(&pool, scheduling_data, &SchedulerConfig::default(), Some(progress_callback)).await.unwrap();
+
+        let run = last_schedule_run_get(&pool).await.unwrap();
+        let reported = reported.lock().unwrap();
+        let restarts: Vec<usize> = reported.iter().map(|progress| progress.restart).collect();
+        assert_eq!(restarts, (1..=40).collect::<Vec<_>>());
+        assert!(reported.iter().all(|progress| progress.restarts == 40));
+        assert_eq!(reported.last().unwrap().best_score, run.final_score);
+    }
+
+    #[sqlx::test]
+    async fn integrity_check_reports_violations(pool: PgPool) {
+        let report = schedule_integrity_check(&pool).await.unwrap();
+        assert!(report.valid);
+
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let small_room = insert_room(&pool, "Small", "Here", 0).await;
+        let big_room = insert_room(&pool, "Big", "There", 10).await;
+        let time_slot_id = insert_timeslot_with(&pool, "09:00", "10:00", Some(1)).await;
+        let first = insert_session(&pool, user_id, "First").await;
+        let second = insert_session(&pool, user_id, "Second").await;
+        insert_vote(&pool, user_id, first).await;
+        let first_assignment = assign_session(&pool, time_slot_id, small_room, first).await;
+        assign_session(&pool, time_slot_id, big_room, second).await;
+
+        let report = schedule_integrity_check(&pool).await.unwrap();
+
+        assert!(!report.valid);
+        assert_eq!(report.violations, vec![
+            ScheduleViolation::SpeakerDoubleBooked { user_id, time_slot_id, session_ids: vec![first, second] },
+            ScheduleViolation::OverCapacity { assignment_id: first_assignment, session_id: first, room_id: small_room, votes: 1, available_spots: 0 },
+            ScheduleViolation::TooManyOpenRooms { time_slot_id, open_rooms: 2, max_open_rooms: 1 },
+        ]);
+    }
+
+    #[sqlx::test]
+    async fn integrity_check_ignores_empty_cells(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let room_a = insert_room(&pool, "Room A", "Here", 10).await;
+        let room_b = insert_room(&pool, "Room B", "There", 10).await;
+        let time_slot_id = insert_timeslot_with(&pool, "09:00", "10:00", Some(1)).await;
+        let session_id = insert_session(&pool, user_id, "Talk").await;
+        assign_session(&pool, time_slot_id, room_a, session_id).await;
+        sqlx::query("INSERT INTO timeslot_assignments (time_slot_id, room_id, session_id) VALUES ($1, $2, NULL)")
+            .bind(time_slot_id)
+            .bind(room_b)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let report = schedule_integrity_check(&pool).await.unwrap();
+
+        assert!(report.valid, "{:?}", report.violations);
+    }
+
+    #[sqlx::test]
+    async fn proposed_grid_is_checked_and_scored_without_saving(pool: PgPool) {
+        let speaker_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let other_id = insert_user(&pool, "B", "User", "b@example.com").await;
+        let small_room = insert_room(&pool, "Small", "Here", 0).await;
+        let big_room = insert_room(&pool, "Big", "There", 10).await;
+        let early_slot = insert_timeslot(&pool, "09:00", "10:00").await;
+        let late_slot = insert_timeslot(&pool, "10:00", "11:00").await;
+        let first = insert_session(&pool, speaker_id, "First").await;
+        let second = insert_session(&pool, speaker_id, "Second").await;
+        let third = insert_session(&pool, other_id, "Third").await;
+        insert_vote(&pool, other_id, first).await;
+        let scheduled = assign_session(&pool, late_slot, big_room, third).await;
+        let cell = |time_slot_id, room_id, session_id| ProposedAssignment { time_slot_id, room_id, session_id };
+        let config = SchedulerConfig::default();
+
+        let validation = grid_validate(&pool, &config, &[
+            cell(early_slot, small_room, first),
+            cell(early_slot, big_room, second),
+            cell(early_slot, big_room, third),
+            cell(late_slot, big_room, first),
+            cell(late_slot, small_room, third + 100),
+        ]).await.unwrap();
+
+        assert!(!validation.valid);
+        assert_eq!(validation.violations, vec![
+            ScheduleViolation::SessionScheduledTwice { session_id: first, assignment_ids: vec![0, 3] },
+            ScheduleViolation::RoomDoubleBooked { time_slot_id: early_slot, room_id: big_room, assignment_ids: vec![1, 2] },
+            ScheduleViolation::SpeakerDoubleBooked { user_id: speaker_id, time_slot_id: early_slot, session_ids: vec![first, second] },
+            ScheduleViolation::DanglingAssignment { assignment_id: 4, missing: "session".to_string() },
+            ScheduleViolation::OverCapacity { assignment_id: 0, session_id: first, room_id: small_room, votes: 1, available_spots: 0 },
+        ]);
+
+        // The voted session is left out of the schedule but not out of the proposed grid
+        let validation = grid_validate(&pool, &config, &[cell(early_slot, big_room, first), cell(late_slot, big_room, third)]).await.unwrap();
+        assert!(validation.valid);
+        assert_eq!(validation.score.missing, 0);
+        assert!(schedule_score(&pool, &config).await.unwrap().missing > 0);
+
+        let assignments: Vec<(i32, i32)> = sqlx::query_as("SELECT id, session_id FROM timeslot_assignments")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(assignments, vec![(scheduled, third)]);
+    }
+
+    #[sqlx::test]
+    async fn committing_an_invalid_grid_leaves_the_schedule_untouched(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let room_a = insert_room(&pool, "Room A", "Here", 10).await;
+        let room_b = insert_room(&pool, "Room B", "There", 10).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        let first = insert_session(&pool, user_id, "First").await;
+        let second = insert_session(&pool, user_id, "Second").await;
+        assign_session(&pool, time_slot_id, room_a, first).await;
+        let cell = |room_id, session_id| ProposedAssignment { time_slot_id, room_id, session_id };
+        let schedule = || async {
+            sqlx::query_as::<_, (i32, i32)>("SELECT room_id, session_id FROM timeslot_assignments ORDER BY room_id")
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+        };
+
+        // The speaker would be double booked, and the schedule is left as it was
+        let report = grid_commit(&pool, &[cell(room_a, second), cell(room_b, first)]).await.unwrap();
+        assert_eq!(report.violations, vec![
+            ScheduleViolation::SpeakerDoubleBooked { user_id, time_slot_id, session_ids: vec![first, second] },
+        ]);
+        assert_eq!(schedule().await, vec![(room_a, first)]);
+
+        let report = grid_commit(&pool, &[cell(room_b, second), cell(room_a, second + 100)]).await.unwrap();
+        assert!(!report.valid);
+        assert_eq!(schedule().await, vec![(room_a, first)]);
+
+        let report = grid_commit(&pool, &[cell(room_b, second)]).await.unwrap();
+        assert!(report.valid);
+        assert_eq!(schedule().await, vec![(room_b, second)]);
+    }
+
+    #[sqlx::test]
+    async fn suggestions_fill_free_slots_with_the_most_voted_sessions(pool: PgPool) {
+        let voter_id = insert_user(&pool, "B", "User", "b@example.com").await;
+        let room_a = insert_room(&pool, "Room A", "Here", 10).await;
+        let room_b = insert_room(&pool, "Room B", "There", 10).await;
+        let early_slot = insert_timeslot(&pool, "09:00", "10:00").await;
+        let late_slot = insert_timeslot(&pool, "10:00", "11:00").await;
+        let mut session_ids = Vec::new();
+        for (i, title) in ["Scheduled", "Unvoted", "One vote", "Two votes", "Also unvoted"].into_iter().enumerate() {
+            let speaker_id = insert_user(&pool, "Speaker", title, &format!("speaker{i}@example.com")).await;
+            session_ids.push(insert_session(&pool, speaker_id, title).await);
+        }
+        let [scheduled, unvoted, one_vote, two_votes, _] = session_ids[..] else { unreachable!() };
+        let other_voter_id = insert_user(&pool, "C", "User", "c@example.com").await;
+        sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $3), ($1, $4), ($2, $4), ($1, $5), ($2, $5)")
+            .bind(voter_id)
+            .bind(other_voter_id)
+            .bind(one_vote)
+            .bind(two_votes)
+            .bind(scheduled)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assign_session(&pool, early_slot, room_a, scheduled).await;
+
+        let suggestions = schedule_suggestions_get(&pool, true).await.unwrap();
+
+        let suggested: Vec<(i32, i64, i32, i32)> = suggestions
+            .iter()
+            .map(|suggestion| (suggestion.session_id, suggestion.votes, suggestion.time_slot_id, suggestion.room_id))
+            .collect();
+        assert_eq!(suggested, vec![
+            (two_votes, 2, early_slot, room_b),
+            (one_vote, 1, late_slot, room_a),
+            (unvoted, 0, late_slot, room_b),
+        ]);
+
+        let assignments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM timeslot_assignments")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(assignments, 1);
+    }
+
+    #[sqlx::test]
+    async fn suggestions_keep_sessions_out_of_conflicting_timeslots(pool: PgPool) {
+        let speaker_id = insert_user(&pool, "A", "Speaker", "a@example.com").await;
+        let room_a = insert_room(&pool, "Room A", "Here", 10).await;
+        let room_b = insert_room(&pool, "Room B", "There", 10).await;
+        let early_slot = insert_timeslot(&pool, "09:00", "10:00").await;
+        let late_slot = insert_timeslot(&pool, "10:00", "11:00").await;
+        let scheduled = insert_session(&pool, speaker_id, "Scheduled").await;
+        assign_session(&pool, early_slot, room_a, scheduled).await;
+
+        // Most voted first: the keynote, then one session per constraint, then one that fits
+        let mut session_ids = Vec::new();
+        for (i, title) in ["Keynote", "Same speaker", "Excluded", "Only late", "Speaker voted", "Fits"].into_iter().enumerate() {
+            let other_speaker_id = insert_user(&pool, "Speaker", title, &format!("speaker{i}@example.com")).await;
+            let session_speaker_id = if title == "Same speaker" { speaker_id } else { other_speaker_id };
+            let session_id = insert_session(&pool, session_speaker_id, title).await;
+            for voter in 0..(6 - i) {
+                let voter_id = insert_user(&pool, "Voter", title, &format!("voter{i}-{voter}@example.com")).await;
+                insert_vote(&pool, voter_id, session_id).await;
+            }
+            session_ids.push((session_id, session_speaker_id));
+        }
+        let [(keynote, _), (same_speaker, _), (excluded, _), (only_late, _), (_, voting_speaker_id), (fits, _)] = session_ids[..] else { unreachable!() };
+        sqlx::query("UPDATE sessions SET is_keynote = true WHERE id = $1").bind(keynote).execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO session_exclusions (session_id, other_session_id) VALUES ($1, $2)")
+            .bind(scheduled)
+            .bind(excluded)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO session_availability (session_id, time_slot_id) VALUES ($1, $2)")
+            .bind(only_late)
+            .bind(late_slot)
+            .execute(&pool)
+            .await
+            .unwrap();
+        insert_vote(&pool, voting_speaker_id, scheduled).await;
+
+        // The keynote takes the empty late timeslot for itself, leaving only the early slot
+        let suggested = |suggestions: Vec<ScheduleSuggestion>| suggestions
+            .into_iter()
+            .map(|suggestion| (suggestion.session_id, suggestion.time_slot_id, suggestion.room_id))
+            .collect::<Vec<_>>();
+        assert_eq!(suggested(schedule_suggestions_get(&pool, true).await.unwrap()), vec![
+            (keynote, late_slot, room_a),
+            (fits, early_slot, room_b),
+        ]);
+
+        // Without keynotes the keynote goes first in the early timeslot and the late one is shared
+        assert_eq!(suggested(schedule_suggestions_get(&pool, false).await.unwrap()), vec![
+            (keynote, early_slot, room_b),
+            (same_speaker, late_slot, room_a),
+            (excluded, late_slot, room_b),
+        ]);
+    }
+
+    #[sqlx::test]
+    async fn setup_lists_the_missing_rooms_and_timeslots(pool: PgPool) {
+        assert_eq!(schedule_setup_get(&pool).await.unwrap(), vec![SetupStep::NoRooms, SetupStep::NoTimeslots]);
+
+        insert_room(&pool, "Room A", "Here", 10).await;
+        assert_eq!(schedule_setup_get(&pool).await.unwrap(), vec![SetupStep::NoTimeslots]);
+
+        insert_timeslot(&pool, "09:00", "10:00").await;
+        assert!(schedule_setup_get(&pool).await.unwrap().is_empty());
+        assert_eq!(serde_json::to_value(SetupStep::NoRooms).unwrap(), "no_rooms");
+    }
+
+    #[sqlx::test]
+    async fn unscheduling_a_session_frees_whichever_cell_holds_it(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let scheduled_id = insert_session(&pool, user_id, "Scheduled").await;
+        let other_id = insert_session(&pool, user_id, "Other").await;
+        let room_id = insert_room(&pool, "Room", "Here", 10).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        let later_slot_id = insert_timeslot(&pool, "10:00", "11:00").await;
+        assign_session(&pool, later_slot_id, room_id, scheduled_id).await;
+        assign_session(&pool, time_slot_id, room_id, other_id).await;
+
+        unschedule_session(&pool, scheduled_id).await.unwrap();
+
+        let remaining: Vec<i32> = sqlx::query_scalar("SELECT session_id FROM timeslot_assignments")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![other_id]);
+        assert!(matches!(unschedule_session(&pool, scheduled_id).await, Err(ScheduleErr::DoesNotExist(_))));
+    }
+
+    #[sqlx::test]
+    async fn scheduling_a_missing_session_is_an_error(pool: PgPool) {
+        insert_room(&pool, "Room", "Here", 10).await;
+        insert_timeslot(&pool, "09:00", "10:00").await;
+
+        let result = add_session(&pool, 12345).await;
+
+        assert!(matches!(result, Err(ScheduleErr::SessionError(SessionErr::DoesNotExist(_)))));
+        let assignments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM timeslot_assignments")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(assignments, 0);
+    }
+}
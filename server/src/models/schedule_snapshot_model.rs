@@ -0,0 +1,471 @@
+use crate::models::schedule_model::{Schedule, ScheduleErr};
+use crate::models::timeslot_model::timeslot_get;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// Struct representing a single assignment stored in a schedule snapshot.
+///
+/// # Fields
+/// - `time_slot_id` - The ID of the timeslot the session was scheduled in
+/// - `room_id` - The ID of the room the session was scheduled in
+/// - `session_id` - The ID of the scheduled session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, FromRow)]
+pub struct SnapshotAssignment {
+    pub time_slot_id: i32,
+    pub room_id: i32,
+    pub session_id: i32,
+}
+
+/// Struct representing a saved copy of the schedule.
+///
+/// # Fields
+/// - `id` - The ID of the snapshot
+/// - `label` - The label given to the snapshot
+/// - `created_at` - When the snapshot was taken
+/// - `assignments` - The assignments that made up the schedule when the snapshot was taken
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScheduleSnapshot {
+    pub id: i32,
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+    pub assignments: Vec<SnapshotAssignment>,
+}
+
+/// Struct representing a schedule snapshot without its assignments, used when listing snapshots.
+///
+/// # Fields
+/// - `id` - The ID of the snapshot
+/// - `label` - The label given to the snapshot
+/// - `created_at` - When the snapshot was taken
+/// - `num_of_assignments` - The number of assignments stored in the snapshot
+#[derive(Debug, Clone, Serialize, ToSchema, FromRow)]
+pub struct ScheduleSnapshotSummary {
+    pub id: i32,
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+    pub num_of_assignments: i32,
+}
+
+/// Struct representing a request to take a schedule snapshot.
+///
+/// # Fields
+/// - `label` - The label to give the snapshot
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateSnapshotReq {
+    pub label: String,
+}
+
+/// Saves the current schedule assignments as a snapshot.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `label` - The label to give the snapshot
+///
+/// # Returns
+/// A `Result` containing the summary of the new snapshot or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while saving the snapshot, a `ScheduleErr` error is returned.
+pub async fn snapshot_create(db_pool: &Pool<Postgres>, label: &str) -> Result<ScheduleSnapshotSummary, ScheduleErr> {
+    let snapshot = sqlx::query_as!(
+        ScheduleSnapshotSummary,
+        r#"INSERT INTO schedule_snapshots (label, assignments)
+        SELECT $1, COALESCE(
+            jsonb_agg(
+                jsonb_build_object('time_slot_id', time_slot_id, 'room_id', room_id, 'session_id', session_id)
+                ORDER BY time_slot_id, room_id
+            ),
+            '[]'::jsonb
+        )
+        FROM timeslot_assignments
+        WHERE time_slot_id IS NOT NULL AND room_id IS NOT NULL AND session_id IS NOT NULL
+        RETURNING id, label, created_at, jsonb_array_length(assignments) AS "num_of_assignments!""#,
+        label,
+    )
+        .fetch_one(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    Ok(snapshot)
+}
+
+/// Retrieves the list of schedule snapshots, newest first.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the snapshot summaries or a `ScheduleErr` error.
+///
+/// # Errors
+/// If an error occurs while fetching the snapshots, a `ScheduleErr` error is returned.
+pub async fn snapshots_get(db_pool: &Pool<Postgres>) -> Result<Vec<ScheduleSnapshotSummary>, ScheduleErr> {
+    sqlx::query_as!(
+        ScheduleSnapshotSummary,
+        r#"SELECT id, label, created_at, jsonb_array_length(assignments) AS "num_of_assignments!"
+        FROM schedule_snapshots
+        ORDER BY created_at DESC, id DESC"#,
+    )
+        .fetch_all(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))
+}
+
+/// Retrieves a single schedule snapshot including its assignments.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `snapshot_id` - The ID of the snapshot
+///
+/// # Returns
+/// A `Result` containing the `ScheduleSnapshot` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If the snapshot doesn't exist a `ScheduleErr::DoesNotExist` error is returned, any other failure
+/// returns a `ScheduleErr::IoError`.
+pub async fn snapshot_get(db_pool: &Pool<Postgres>, snapshot_id: i32) -> Result<ScheduleSnapshot, ScheduleErr> {
+    let snapshot = sqlx::query!(
+        r#"SELECT id, label, created_at, assignments AS "assignments: Json<Vec<SnapshotAssignment>>"
+        FROM schedule_snapshots
+        WHERE id = $1"#,
+        snapshot_id,
+    )
+        .fetch_optional(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?
+        .ok_or_else(|| ScheduleErr::DoesNotExist(format!("snapshot {snapshot_id}")))?;
+
+    Ok(ScheduleSnapshot {
+        id: snapshot.id,
+        label: snapshot.label,
+        created_at: snapshot.created_at,
+        assignments: snapshot.assignments.0,
+    })
+}
+
+/// Restores the schedule to the state stored in a snapshot.
+///
+/// The current assignments are replaced by the snapshot's assignments in a single transaction.
+/// Assignments that refer to a session, room, or timeslot that has since been deleted are skipped.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `snapshot_id` - The ID of the snapshot to restore
+///
+/// # Returns
+/// A `Result` containing the restored `Schedule` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If the snapshot doesn't exist a `ScheduleErr::DoesNotExist` error is returned, any other failure
+/// returns a `ScheduleErr::IoError` and leaves the schedule unchanged.
+pub async fn snapshot_restore(db_pool: &Pool<Postgres>, snapshot_id: i32) -> Result<Schedule, ScheduleErr> {
+    let snapshot = snapshot_get(db_pool, snapshot_id).await?;
+
+    let (time_slot_ids, (room_ids, session_ids)): (Vec<i32>, (Vec<i32>, Vec<i32>)) = snapshot
+        .assignments
+        .iter()
+        .map(|assignment| (assignment.time_slot_id, (assignment.room_id, assignment.session_id)))
+        .unzip();
+
+    let mut tx = db_pool
+        .begin()
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    sqlx::query!("DELETE FROM timeslot_assignments")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    sqlx::query!(
+        r"INSERT INTO timeslot_assignments (time_slot_id, room_id, session_id)
+        SELECT snapshot.time_slot_id, snapshot.room_id, snapshot.session_id
+        FROM UNNEST($1::int[], $2::int[], $3::int[]) AS snapshot(time_slot_id, room_id, session_id)
+        WHERE EXISTS (SELECT 1 FROM time_slots WHERE time_slots.id = snapshot.time_slot_id)
+            AND EXISTS (SELECT 1 FROM rooms WHERE rooms.id = snapshot.room_id)
+            AND EXISTS (SELECT 1 FROM sessions WHERE sessions.id = snapshot.session_id)",
+        &time_slot_ids,
+        &room_ids,
+        &session_ids,
+    )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    let timeslots = timeslot_get(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    Ok(Schedule::new(
+        Some(1),
+        i32::try_from(timeslots.len()).map_err(|e| ScheduleErr::IoError(e.to_string()))?,
+        timeslots,
+    ))
+}
+
+/// Struct representing where a session sits in the schedule.
+///
+/// # Fields
+/// - `slot` - The ID of the timeslot
+/// - `room` - The ID of the room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct SchedulePosition {
+    pub slot: i32,
+    pub room: i32,
+}
+
+/// Struct representing how a single session changed between two snapshots.
+///
+/// # Fields
+/// - `session_id` - The ID of the session
+/// - `from` - Where the session was in the older snapshot, `None` if it was added
+/// - `to` - Where the session is in the newer snapshot, `None` if it was removed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ScheduleChange {
+    pub session_id: i32,
+    pub from: Option<SchedulePosition>,
+    pub to: Option<SchedulePosition>,
+}
+
+/// Struct representing the differences between two schedule snapshots.
+///
+/// # Fields
+/// - `moved` - Sessions scheduled in both snapshots but in a different room or timeslot
+/// - `added` - Sessions only scheduled in the newer snapshot
+/// - `removed` - Sessions only scheduled in the older snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ScheduleDiff {
+    pub moved: Vec<ScheduleChange>,
+    pub added: Vec<ScheduleChange>,
+    pub removed: Vec<ScheduleChange>,
+}
+
+/// Struct representing the query parameters for a snapshot diff.
+///
+/// # Fields
+/// - `from` - The ID of the older snapshot
+/// - `to` - The ID of the newer snapshot
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SnapshotDiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+
+/// Compares two sets of snapshot assignments.
+///
+/// Changes in each list are ordered by session ID.
+///
+/// # Parameters
+/// - `from` - The assignments of the older snapshot
+/// - `to` - The assignments of the newer snapshot
+///
+/// # Returns
+/// The `ScheduleDiff` between the two sets of assignments
+pub fn diff_assignments(from: &[SnapshotAssignment], to: &[SnapshotAssignment]) -> ScheduleDiff {
+    let position = |assignment: &SnapshotAssignment| SchedulePosition {
+        slot: assignment.time_slot_id,
+        room: assignment.room_id,
+    };
+    let from_positions: BTreeMap<i32, SchedulePosition> = from
+        .iter()
+        .map(|assignment| (assignment.session_id, position(assignment)))
+        .collect();
+    let to_positions: BTreeMap<i32, SchedulePosition> = to
+        .iter()
+        .map(|assignment| (assignment.session_id, position(assignment)))
+        .collect();
+
+    let mut diff = ScheduleDiff::default();
+    for (&session_id, &from_position) in &from_positions {
+        match to_positions.get(&session_id) {
+            Some(&to_position) if to_position != from_position => diff.moved.push(ScheduleChange {
+                session_id,
+                from: Some(from_position),
+                to: Some(to_position),
+            }),
+            Some(_) => {}
+            None => diff.removed.push(ScheduleChange {
+                session_id,
+                from: Some(from_position),
+                to: None,
+            }),
+        }
+    }
+
+    diff.added = to_positions
+        .iter()
+        .filter(|(session_id, _)| !from_positions.contains_key(session_id))
+        .map(|(&session_id, &to_position)| ScheduleChange {
+            session_id,
+            from: None,
+            to: Some(to_position),
+        })
+        .collect();
+
+    diff
+}
+
+/// Compares two schedule snapshots.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `from_id` - The ID of the older snapshot
+/// - `to_id` - The ID of the newer snapshot
+///
+/// # Returns
+/// A `Result` containing the `ScheduleDiff` or a `ScheduleErr` error.
+///
+/// # Errors
+/// If either snapshot doesn't exist a `ScheduleErr::DoesNotExist` error is returned, any other
+/// failure returns a `ScheduleErr::IoError`.
+pub async fn snapshot_diff(db_pool: &Pool<Postgres>, from_id: i32, to_id: i32) -> Result<ScheduleDiff, ScheduleErr> {
+    let from = snapshot_get(db_pool, from_id).await?;
+    let to = snapshot_get(db_pool, to_id).await?;
+
+    Ok(diff_assignments(&from.assignments, &to.assignments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assign_session, insert_room, insert_session, insert_timeslot, insert_user};
+    use sqlx::PgPool;
+
+    fn assignment(time_slot_id: i32, room_id: i32, session_id: i32) -> SnapshotAssignment {
+        SnapshotAssignment { time_slot_id, room_id, session_id }
+    }
+
+    #[test]
+    fn diff_reports_moved_added_and_removed_sessions() {
+        let from = vec![assignment(1, 1, 10), assignment(1, 2, 11), assignment(2, 1, 12)];
+        let to = vec![assignment(2, 2, 10), assignment(1, 2, 11), assignment(2, 1, 13)];
+
+        let diff = diff_assignments(&from, &to);
+
+        assert_eq!(diff.moved, vec![ScheduleChange {
+            session_id: 10,
+            from: Some(SchedulePosition { slot: 1, room: 1 }),
+            to: Some(SchedulePosition { slot: 2, room: 2 }),
+        }]);
+        assert_eq!(diff.added, vec![ScheduleChange {
+            session_id: 13,
+            from: None,
+            to: Some(SchedulePosition { slot: 2, room: 1 }),
+        }]);
+        assert_eq!(diff.removed, vec![ScheduleChange {
+            session_id: 12,
+            from: Some(SchedulePosition { slot: 2, room: 1 }),
+            to: None,
+        }]);
+    }
+
+    #[test]
+    fn diff_of_identical_snapshots_is_empty() {
+        let assignments = vec![assignment(1, 1, 10), assignment(2, 1, 11)];
+
+        assert_eq!(diff_assignments(&assignments, &assignments), ScheduleDiff::default());
+    }
+
+    async fn current_assignments(pool: &PgPool) -> Vec<SnapshotAssignment> {
+        sqlx::query_as::<_, SnapshotAssignment>(
+            "SELECT time_slot_id, room_id, session_id FROM timeslot_assignments ORDER BY time_slot_id, room_id",
+        )
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    /// Two timeslots with two rooms each, with sessions 0 and 1 in the first timeslot and session
+    /// 2 in the second. Returns the timeslot, room and session IDs.
+    async fn seed_schedule(pool: &PgPool) -> (Vec<i32>, Vec<i32>, Vec<i32>) {
+        let user_id = insert_user(pool, "A", "User", "a@example.com").await;
+        let time_slot_ids = vec![
+            insert_timeslot(pool, "09:00", "10:00").await,
+            insert_timeslot(pool, "10:00", "11:00").await,
+        ];
+        let room_ids = vec![
+            insert_room(pool, "First", "Here", 10).await,
+            insert_room(pool, "Second", "There", 10).await,
+        ];
+        let mut session_ids = vec![];
+        for title in ["Axum", "Borrowck", "Cargo"] {
+            session_ids.push(insert_session(pool, user_id, title).await);
+        }
+        assign_session(pool, time_slot_ids[0], room_ids[0], session_ids[0]).await;
+        assign_session(pool, time_slot_ids[0], room_ids[1], session_ids[1]).await;
+        assign_session(pool, time_slot_ids[1], room_ids[0], session_ids[2]).await;
+
+        (time_slot_ids, room_ids, session_ids)
+    }
+
+    #[sqlx::test]
+    async fn restoring_a_snapshot_brings_back_the_saved_schedule(pool: PgPool) {
+        let (time_slot_ids, room_ids, session_ids) = seed_schedule(&pool).await;
+        let saved = current_assignments(&pool).await;
+        let snapshot = snapshot_create(&pool, "Before the shuffle").await.unwrap();
+        assert_eq!(snapshot.num_of_assignments, 3);
+
+        sqlx::query("DELETE FROM timeslot_assignments WHERE session_id = $1")
+            .bind(session_ids[1])
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE timeslot_assignments SET room_id = $1 WHERE session_id = $2")
+            .bind(room_ids[1])
+            .bind(session_ids[2])
+            .execute(&pool)
+            .await
+            .unwrap();
+        assign_session(&pool, time_slot_ids[1], room_ids[0], session_ids[1]).await;
+        assert_ne!(current_assignments(&pool).await, saved);
+
+        snapshot_restore(&pool, snapshot.id).await.unwrap();
+
+        assert_eq!(current_assignments(&pool).await, saved);
+        assert_eq!(snapshot_get(&pool, snapshot.id).await.unwrap().assignments, saved);
+    }
+
+    #[sqlx::test]
+    async fn snapshot_diff_lists_the_changes_between_two_snapshots(pool: PgPool) {
+        let (time_slot_ids, room_ids, session_ids) = seed_schedule(&pool).await;
+        let before = snapshot_create(&pool, "Before").await.unwrap();
+
+        sqlx::query("UPDATE timeslot_assignments SET time_slot_id = $1, room_id = $2 WHERE session_id = $3")
+            .bind(time_slot_ids[1])
+            .bind(room_ids[1])
+            .bind(session_ids[0])
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM timeslot_assignments WHERE session_id = $1")
+            .bind(session_ids[2])
+            .execute(&pool)
+            .await
+            .unwrap();
+        let after = snapshot_create(&pool, "After").await.unwrap();
+
+        let diff = snapshot_diff(&pool, before.id, after.id).await.unwrap();
+
+        assert_eq!(diff.moved, vec![ScheduleChange {
+            session_id: session_ids[0],
+            from: Some(SchedulePosition { slot: time_slot_ids[0], room: room_ids[0] }),
+            to: Some(SchedulePosition { slot: time_slot_ids[1], room: room_ids[1] }),
+        }]);
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.removed, vec![ScheduleChange {
+            session_id: session_ids[2],
+            from: Some(SchedulePosition { slot: time_slot_ids[1], room: room_ids[0] }),
+            to: None,
+        }]);
+        assert_eq!(snapshot_diff(&pool, after.id, before.id).await.unwrap().added.len(), 1);
+        assert!(matches!(snapshot_diff(&pool, before.id, after.id + 1).await, Err(ScheduleErr::DoesNotExist(_))));
+    }
+}
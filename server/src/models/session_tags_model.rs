@@ -7,7 +7,7 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::{ser::SerializeStruct, Serialize, Serializer};
-use sqlx::{Pool, Postgres};
+use sqlx::{PgConnection, PgExecutor, Pool, Postgres};
 use std::error::Error;
 use utoipa::ToSchema;
 
@@ -108,7 +108,49 @@ pub(crate) async fn add_session_tag(
 
     tracing::info!("Adding tag with id {} to session: {:?}, is_staff_or_admin: {:?}", tag_id, session_id, is_staff_or_admin);
 
-    let current_tags = get_tags_for_session(db_pool, session_id).await?;
+    if !is_staff_or_admin {
+        is_users_resource(&session, &auth_session).await?;
+    }
+
+    session_tag_insert(&mut *db_pool.acquire().await?, session_id, tag_id).await?;
+
+    get_tags_for_session(db_pool, session_id).await
+}
+
+/// Reports a query that would give a session the same tag twice as the tag already being applied
+///
+/// The existing tags are checked before a tag is applied, but a concurrent request can apply it in
+/// between, which the `(session_id, tag_id)` primary key of `session_tags` then rejects.
+///
+/// # Parameters
+/// - `e`: The error the query failed with
+/// - `session_id`: The ID of the session the tag was applied to
+/// - `tag_id`: The ID of the tag
+///
+/// # Returns
+/// A `SessionTagErr::AlreadyAppliedTagForSession` error for a unique violation, otherwise `e`
+fn already_applied_on_conflict(e: sqlx::Error, session_id: i32, tag_id: i32) -> Box<dyn Error> {
+    match e.as_database_error() {
+        Some(db_err) if db_err.is_unique_violation() => Box::new(SessionTagErr::AlreadyAppliedTagForSession(
+            format!("Session {session_id} already has tag with id: {tag_id}")
+        )),
+        _ => Box::new(e),
+    }
+}
+
+/// Applies a tag to a session, without checking who the session belongs to
+///
+/// # Parameters
+/// - `conn`: The database connection, or transaction, to apply the tag in
+/// - `session_id`: The ID of the session to apply the tag to.
+/// - `tag_id`: The ID of the tag.
+///
+/// # Errors
+/// If the session already has the tag a `SessionTagErr::AlreadyAppliedTagForSession` error is
+/// returned, if the tag doesn't exist a `SessionTagErr::NonExistentTag` error is returned and if
+/// the query fails, a boxed error is returned.
+pub(crate) async fn session_tag_insert(conn: &mut PgConnection, session_id: i32, tag_id: i32) -> Result<(), Box<dyn Error>> {
+    let current_tags = get_tags_for_session(&mut *conn, session_id).await?;
 
     if current_tags.iter().any(|tag| tag.id == tag_id) {
         return Err(Box::new(SessionTagErr::AlreadyAppliedTagForSession(
@@ -120,36 +162,23 @@ pub(crate) async fn add_session_tag(
         "SELECT id FROM tags WHERE id = $1",
         tag_id
     )
-        .fetch_optional(db_pool)
+        .fetch_optional(&mut *conn)
         .await?;
 
-    match looked_up_tag_id {
-        Some(id) => id,
-        None => {
-            return Err(Box::new(SessionTagErr::NonExistentTag(tag_id.to_string())));
-        }
-    };
-
-    if is_staff_or_admin {
-        sqlx::query!(
-            "INSERT INTO session_tags (session_id, tag_id) VALUES ($1, $2)",
-            session_id,
-            tag_id,
-        )
-            .execute(db_pool)
-            .await?;
-    } else {
-        is_users_resource(&session, &auth_session).await?;
-        sqlx::query!(
-            "INSERT INTO session_tags (session_id, tag_id) VALUES ($1, $2)",
-            session_id,
-            tag_id,
-        )
-            .execute(db_pool)
-            .await?;
+    if looked_up_tag_id.is_none() {
+        return Err(Box::new(SessionTagErr::NonExistentTag(tag_id.to_string())));
     }
 
-    get_tags_for_session(db_pool, session_id).await
+    sqlx::query!(
+        "INSERT INTO session_tags (session_id, tag_id) VALUES ($1, $2)",
+        session_id,
+        tag_id,
+    )
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| already_applied_on_conflict(e, session_id, tag_id))?;
+
+    Ok(())
 }
 
 /// Removes a tag from a session
@@ -283,7 +312,8 @@ pub(crate) async fn update_session_tag(
             old_tag_id,
         )
             .execute(db_pool)
-            .await?;
+            .await
+            .map_err(|e| already_applied_on_conflict(e, session_id, new_tag_id))?;
     } else {
         is_users_resource(&session, &auth_session).await?;
         // Update the tag
@@ -294,13 +324,14 @@ pub(crate) async fn update_session_tag(
             old_tag_id,
         )
             .execute(db_pool)
-            .await?;
+            .await
+            .map_err(|e| already_applied_on_conflict(e, session_id, new_tag_id))?;
     }
 
     get_tags_for_session(db_pool, session_id).await
 }
 
-pub async fn get_tags_for_session(db_pool: &Pool<Postgres>, session_id: i32) -> Result<Vec<Tag>, Box<dyn Error>> {
+pub async fn get_tags_for_session(db_pool: impl PgExecutor<'_>, session_id: i32) -> Result<Vec<Tag>, Box<dyn Error>> {
     let session_tags = sqlx::query_as!(
         Tag,
         r#"
@@ -326,4 +357,45 @@ pub async fn get_all_tags(db_pool: &Pool<Postgres>) -> Result<Vec<Tag>, Box<dyn
         .await?;
 
     Ok(tags)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_session, insert_tag, insert_user};
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn concurrently_applying_the_same_tag_adds_it_once(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let session_id = insert_session(&pool, user_id, "Rust").await;
+        let tag_id = insert_tag(&pool, "Languages").await;
+
+        // The first request holds its tag uncommitted until the second one is blocked on the primary
+        // key, so the second one has already found no existing tag and only the key can stop it
+        let mut tx = pool.begin().await.unwrap();
+        let first = session_tag_insert(&mut tx, session_id, tag_id).await.map_err(|e| e.to_string());
+        let commit_once_blocked = async {
+            while !sqlx::query_scalar::<_, bool>(
+                "SELECT EXISTS (SELECT 1 FROM pg_stat_activity
+                WHERE wait_event_type = 'Lock' AND datname = current_database())",
+            )
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+            {}
+            tx.commit().await.unwrap();
+        };
+        let second = async {
+            let mut conn = pool.acquire().await.unwrap();
+            session_tag_insert(&mut conn, session_id, tag_id).await
+        };
+        let ((), second) = tokio::join!(commit_once_blocked, second);
+
+        assert_eq!(first, Ok(()));
+        let err = second.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SessionTagErr>(), Some(SessionTagErr::AlreadyAppliedTagForSession(_))), "{err}");
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM session_tags").fetch_one(&pool).await.unwrap();
+        assert_eq!(rows, 1);
+    }
+}
@@ -1,11 +1,14 @@
 use crate::middleware::auth::AuthSessionLayer;
+use crate::models::schedule_export_model::csv_field;
+use crate::models::settings_model::{pseudonym_key_get, user_pseudonym};
 use crate::types::ApiStatusCode;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde::{ser::SerializeStruct, Serialize, Serializer};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 use sqlx::{Pool, Postgres};
 use std::error::Error;
+use std::fmt::Write;
 use utoipa::ToSchema;
 
 
@@ -15,13 +18,23 @@ use utoipa::ToSchema;
 /// # Variants
 /// - `NonExistentVote` - The `User` does not have a vote to remove from this session
 /// - `AlreadyVotedForSession` - The `User` has already voted for the session
+/// - `SessionDoesNotExist` - The session does not exist
+/// - `InvalidTrendingWindow` - The window to count trending votes in isn't a positive number of
+///   minutes
 pub enum SessionVoteErr {
     #[error("Attempted to remove vote from Session {0} that didn't have a vote")]
     NonExistentVote(String),
     #[error("User has already voted for Session {0}")]
     AlreadyVotedForSession(String),
+    #[error("Session {0} doesn't exist")]
+    SessionDoesNotExist(String),
+    #[error("Trending window must be a positive number of minutes, got {0}")]
+    InvalidTrendingWindow(i32),
 }
 
+/// How many minutes of votes trending sessions are counted over when no window is given.
+pub const DEFAULT_TRENDING_WINDOW_MINUTES: i32 = 60;
+
 /// Struct representing an error that occurred when working with sessions.
 ///
 /// # Fields
@@ -77,6 +90,120 @@ impl SessionVoteError {
     }
 }
 
+/// Struct representing the distribution of votes across sessions.
+///
+/// # Fields
+/// - `sessions` - The number of sessions
+/// - `total_votes` - The total number of votes cast
+/// - `min_votes` - The fewest votes any session has
+/// - `max_votes` - The most votes any session has
+/// - `mean_votes` - The average number of votes per session
+/// - `median_votes` - The median number of votes per session
+/// - `at_least` - The vote threshold `sessions_with_at_least` was counted with
+/// - `sessions_with_at_least` - The number of sessions with at least `at_least` votes
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct VoteStats {
+    pub sessions: i64,
+    pub total_votes: i64,
+    pub min_votes: i64,
+    pub max_votes: i64,
+    pub mean_votes: f64,
+    pub median_votes: f64,
+    pub at_least: i32,
+    pub sessions_with_at_least: i64,
+}
+
+/// Struct representing the outcome of recounting the votes of every session.
+///
+/// # Fields
+/// - `sessions_updated` - The number of sessions whose vote count had drifted and was corrected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct VotesReconciled {
+    pub sessions_updated: u64,
+}
+
+/// Struct representing the query parameters for the vote statistics.
+///
+/// # Fields
+/// - `at_least` - The vote threshold to count sessions against, defaults to 1
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VoteStatsQuery {
+    pub at_least: Option<i32>,
+}
+
+/// Struct representing a session that has been voted for recently.
+///
+/// # Fields
+/// - `session_id` - The ID of the session
+/// - `title` - The title of the session
+/// - `recent_votes` - The number of votes the session got within the window
+/// - `total_votes` - The number of votes the session has
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct TrendingSession {
+    pub session_id: i32,
+    pub title: String,
+    pub recent_votes: i64,
+    pub total_votes: i64,
+}
+
+/// Struct representing the query parameters for the trending sessions.
+///
+/// # Fields
+/// - `window_minutes` - How many minutes back to count votes over, defaults to 60
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TrendingQuery {
+    pub window_minutes: Option<i32>,
+}
+
+/// Struct representing an attendee who voted for a session.
+///
+/// # Fields
+/// - `user_id` - The ID of the user
+/// - `fname` - The first name of the user
+/// - `lname` - The last name of the user
+/// - `email` - The email address of the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct SessionVoter {
+    pub user_id: i32,
+    pub fname: String,
+    pub lname: String,
+    pub email: String,
+}
+
+/// Struct representing who voted for a session.
+///
+/// # Fields
+/// - `session_id` - The ID of the session
+/// - `votes` - The number of votes the session has
+/// - `voters` - The attendees who voted for the session, only included when contact details were
+///   asked for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct SessionVoters {
+    pub session_id: i32,
+    pub votes: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voters: Option<Vec<SessionVoter>>,
+}
+
+/// Struct representing the query parameters for the voters of a session.
+///
+/// # Fields
+/// - `include_contact` - Whether to include who voted with their names and emails, defaults to
+///   only the number of votes
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SessionVotersQuery {
+    pub include_contact: Option<bool>,
+}
+
+/// Struct representing the query parameters for exporting the votes.
+///
+/// # Fields
+/// - `anonymize` - Whether to replace the voters with pseudonyms and the sessions with their
+///   titles, defaults to `true`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExportVotesQuery {
+    pub anonymize: Option<bool>,
+}
 
 /// Adds a vote to a session
 ///
@@ -149,4 +276,395 @@ pub async fn get_sessions_user_voted_for(db_pool: &Pool<Postgres>, user_id: i32)
         .await?,);
 
     Ok(sessions_user_voted_for)
-}
\ No newline at end of file
+}
+/// Computes the distribution of votes across sessions.
+///
+/// Sessions without any votes count as having 0 votes. When there are no sessions every
+/// statistic is 0.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `at_least`: The vote threshold to count sessions against
+///
+/// # Returns
+/// A `Result` containing the `VoteStats` or an error if the query fails.
+///
+/// # Errors
+/// If the query fails, a boxed error is returned.
+pub async fn vote_stats_get(db_pool: &Pool<Postgres>, at_least: i32) -> Result<VoteStats, Box<dyn Error>> {
+    let stats = sqlx::query!(
+        r#"
+        WITH session_votes AS (
+            SELECT s.id, COUNT(uv.session_id) AS votes
+            FROM sessions s
+            LEFT JOIN user_votes uv ON uv.session_id = s.id
+            GROUP BY s.id
+        )
+        SELECT
+            COUNT(*) AS "sessions!",
+            COALESCE(SUM(votes), 0)::BIGINT AS "total_votes!",
+            COALESCE(MIN(votes), 0) AS "min_votes!",
+            COALESCE(MAX(votes), 0) AS "max_votes!",
+            COALESCE(AVG(votes), 0)::FLOAT8 AS "mean_votes!",
+            COALESCE(PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY votes), 0)::FLOAT8 AS "median_votes!",
+            COUNT(*) FILTER (WHERE votes >= $1) AS "sessions_with_at_least!"
+        FROM session_votes
+        "#,
+        at_least as i64,
+    )
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(VoteStats {
+        sessions: stats.sessions,
+        total_votes: stats.total_votes,
+        min_votes: stats.min_votes,
+        max_votes: stats.max_votes,
+        mean_votes: stats.mean_votes,
+        median_votes: stats.median_votes,
+        at_least,
+        sessions_with_at_least: stats.sessions_with_at_least,
+    })
+}
+
+/// Retrieves the sessions that got the most votes recently.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `window_minutes`: How many minutes back to count votes over
+///
+/// # Returns
+/// A `Result` containing the sessions with at least one vote in the window, ordered by the number
+/// of votes in the window and then by session ID.
+///
+/// # Errors
+/// If the window isn't positive a `SessionVoteErr::InvalidTrendingWindow` error is returned. If the
+/// query fails, a boxed error is returned.
+pub async fn trending_sessions_get(db_pool: &Pool<Postgres>, window_minutes: i32) -> Result<Vec<TrendingSession>, Box<dyn Error>> {
+    if window_minutes <= 0 {
+        return Err(Box::new(SessionVoteErr::InvalidTrendingWindow(window_minutes)));
+    }
+
+    let sessions = sqlx::query_as!(
+        TrendingSession,
+        r#"SELECT
+            s.id as session_id,
+            s.title,
+            COUNT(*) FILTER (WHERE uv.voted_at >= now() - make_interval(mins => $1)) as "recent_votes!",
+            COUNT(*) as "total_votes!"
+        FROM sessions s
+        JOIN user_votes uv ON uv.session_id = s.id
+        GROUP BY s.id
+        HAVING COUNT(*) FILTER (WHERE uv.voted_at >= now() - make_interval(mins => $1)) > 0
+        ORDER BY 3 DESC, s.id"#,
+        window_minutes,
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    Ok(sessions)
+}
+
+/// Retrieves who voted for a session.
+///
+/// Only the number of votes is returned unless `include_contact` is set, so voters stay anonymous
+/// by default.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `session_id`: The ID of the session
+/// - `include_contact`: Whether to include the voters with their names and emails
+///
+/// # Returns
+/// A `Result` containing the `SessionVoters` or an error if the query fails.
+///
+/// # Errors
+/// If the session doesn't exist a `SessionVoteErr::SessionDoesNotExist` error is returned. If the
+/// query fails, a boxed error is returned.
+pub async fn session_voters_get(db_pool: &Pool<Postgres>, session_id: i32, include_contact: bool) -> Result<SessionVoters, Box<dyn Error>> {
+    let votes = sqlx::query_scalar!(
+        r#"SELECT COUNT(uv.user_id) as "votes!"
+        FROM sessions s
+        LEFT JOIN user_votes uv ON uv.session_id = s.id
+        WHERE s.id = $1
+        GROUP BY s.id"#,
+        session_id,
+    )
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| SessionVoteErr::SessionDoesNotExist(session_id.to_string()))?;
+
+    let voters = if include_contact {
+        let voters = sqlx::query_as!(
+            SessionVoter,
+            r#"SELECT u.id as user_id, u.fname, u.lname, u.email
+            FROM user_votes uv
+            JOIN users u ON u.id = uv.user_id
+            WHERE uv.session_id = $1
+            ORDER BY u.id"#,
+            session_id,
+        )
+            .fetch_all(db_pool)
+            .await?;
+
+        Some(voters)
+    } else {
+        None
+    };
+
+    Ok(SessionVoters { session_id, votes, voters })
+}
+
+/// Derives the pseudonym a voter is listed under in an anonymized vote export.
+///
+/// # Parameters
+/// - `key`: The key from `pseudonym_key_get`
+/// - `user_id`: The ID of the voter
+///
+/// # Returns
+/// "Voter " followed by the first 16 hex digits of the HMAC from `user_pseudonym`.
+fn voter_pseudonym(key: &str, user_id: i32) -> String {
+    user_pseudonym(key, "Voter", user_id)
+}
+
+/// Builds a CSV document with a row for every vote.
+///
+/// The rows are `user_id,session_id`, or `pseudonym,session_title` when anonymized. Each voter gets
+/// a pseudonym from `voter_pseudonym`, which stays the same across exports. The anonymized rows are
+/// sorted by pseudonym so their order doesn't give away the user IDs either.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `anonymize`: Whether to replace the voters with pseudonyms and the sessions with their titles
+///
+/// # Returns
+/// A `Result` containing the CSV document, just the header row when there are no votes, or an error
+/// if the query fails.
+///
+/// # Errors
+/// If the query fails, a boxed error is returned.
+pub async fn votes_csv_get(db_pool: &Pool<Postgres>, anonymize: bool) -> Result<String, Box<dyn Error>> {
+    let mut csv = String::new();
+
+    if anonymize {
+        let key = pseudonym_key_get(db_pool).await?;
+        let mut votes: Vec<(String, String)> = sqlx::query!(
+            "SELECT uv.user_id, s.title
+            FROM user_votes uv
+            JOIN sessions s ON s.id = uv.session_id"
+        )
+            .fetch_all(db_pool)
+            .await?
+            .into_iter()
+            .map(|vote| (voter_pseudonym(&key, vote.user_id), vote.title))
+            .collect();
+        votes.sort();
+
+        csv.push_str("pseudonym,session_title\r\n");
+        for (pseudonym, title) in votes {
+            let _ = write!(csv, "{},{}\r\n", pseudonym, csv_field(&title));
+        }
+    } else {
+        let votes = sqlx::query!("SELECT user_id, session_id FROM user_votes ORDER BY user_id, session_id")
+            .fetch_all(db_pool)
+            .await?;
+
+        csv.push_str("user_id,session_id\r\n");
+        for vote in votes {
+            let _ = write!(csv, "{},{}\r\n", vote.user_id, vote.session_id);
+        }
+    }
+
+    Ok(csv)
+}
+
+/// Recounts the votes of every session from the votes users have cast.
+///
+/// `sessions.votes` is kept alongside `user_votes` and can drift from it, e.g. after the database is
+/// edited by hand. Every session's count is set to its number of votes in one statement.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+///
+/// # Returns
+/// A `Result` containing how many sessions had the wrong count.
+///
+/// # Errors
+/// If the query fails, a boxed error is returned.
+pub async fn votes_reconcile(db_pool: &Pool<Postgres>) -> Result<VotesReconciled, Box<dyn Error>> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE sessions s
+        SET votes = counted.votes
+        FROM (
+            SELECT s.id, COUNT(uv.session_id)::INTEGER AS votes
+            FROM sessions s
+            LEFT JOIN user_votes uv ON uv.session_id = s.id
+            GROUP BY s.id
+        ) counted
+        WHERE counted.id = s.id AND s.votes IS DISTINCT FROM counted.votes
+        "#
+    )
+        .execute(db_pool)
+        .await?;
+
+    Ok(VotesReconciled { sessions_updated: result.rows_affected() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_session, insert_session_with, insert_user, insert_vote};
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn trending_sessions_only_count_votes_in_the_window(pool: PgPool) {
+        let owner_id = insert_user(&pool, "Owner", "User", "owner@example.com").await;
+        let old_id = insert_session(&pool, owner_id, "Old").await;
+        let hot_id = insert_session(&pool, owner_id, "Hot").await;
+        let warm_id = insert_session(&pool, owner_id, "Warm").await;
+        let votes = [(old_id, "3 hours"), (old_id, "2 hours"), (hot_id, "5 minutes"), (hot_id, "10 minutes"), (warm_id, "20 minutes"), (warm_id, "2 hours")];
+        for (voter, (session_id, age)) in votes.into_iter().enumerate() {
+            let voter_id = insert_user(&pool, "Voter", "User", &format!("voter{voter}@example.com")).await;
+            sqlx::query("INSERT INTO user_votes (user_id, session_id, voted_at) VALUES ($1, $2, now() - $3::INTERVAL)")
+                .bind(voter_id)
+                .bind(session_id)
+                .bind(age)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let trending = trending_sessions_get(&pool, 60).await.unwrap();
+        let counts: Vec<(&str, i64, i64)> = trending.iter().map(|session| (session.title.as_str(), session.recent_votes, session.total_votes)).collect();
+        assert_eq!(counts, vec![("Hot", 2, 2), ("Warm", 1, 2)]);
+
+        assert_eq!(trending_sessions_get(&pool, 240).await.unwrap().len(), 3);
+        let err = trending_sessions_get(&pool, 0).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SessionVoteErr>(), Some(SessionVoteErr::InvalidTrendingWindow(0))));
+    }
+
+    #[sqlx::test]
+    async fn reconciling_votes_fixes_drifted_counters(pool: PgPool) {
+        let owner_id = insert_user(&pool, "Owner", "User", "owner@example.com").await;
+        let voted_id = insert_session_with(&pool, owner_id, "Voted", "content", 1).await;
+        let drifted_id = insert_session_with(&pool, owner_id, "Drifted", "content", -2).await;
+        let inflated_id = insert_session_with(&pool, owner_id, "Inflated", "content", 7).await;
+        for session_id in [voted_id, drifted_id] {
+            insert_vote(&pool, owner_id, session_id).await;
+        }
+
+        assert_eq!(votes_reconcile(&pool).await.unwrap(), VotesReconciled { sessions_updated: 2 });
+
+        let votes: Vec<(i32, i32)> = sqlx::query_as("SELECT id, votes FROM sessions ORDER BY id").fetch_all(&pool).await.unwrap();
+        assert_eq!(votes, vec![(voted_id, 1), (drifted_id, 1), (inflated_id, 0)]);
+        assert_eq!(votes_reconcile(&pool).await.unwrap(), VotesReconciled { sessions_updated: 0 });
+    }
+
+    #[sqlx::test]
+    async fn vote_stats_are_zero_without_sessions(pool: PgPool) {
+        let stats = vote_stats_get(&pool, 1).await.unwrap();
+
+        assert_eq!(stats, VoteStats {
+            sessions: 0,
+            total_votes: 0,
+            min_votes: 0,
+            max_votes: 0,
+            mean_votes: 0.0,
+            median_votes: 0.0,
+            at_least: 1,
+            sessions_with_at_least: 0,
+        });
+    }
+
+    #[sqlx::test]
+    async fn vote_stats_count_unvoted_sessions_as_zero(pool: PgPool) {
+        let mut user_ids = vec![];
+        for email in ["a@example.com", "b@example.com", "c@example.com"] {
+            user_ids.push(insert_user(&pool, "A", "User", email).await);
+        }
+        let mut session_ids = vec![];
+        for title in ["Three", "One", "None"] {
+            session_ids.push(insert_session(&pool, user_ids[0], title).await);
+        }
+        for (user_id, session_id) in [(user_ids[0], session_ids[0]), (user_ids[1], session_ids[0]), (user_ids[2], session_ids[0]), (user_ids[0], session_ids[1])] {
+            insert_vote(&pool, user_id, session_id).await;
+        }
+
+        let stats = vote_stats_get(&pool, 2).await.unwrap();
+
+        assert_eq!(stats.sessions, 3);
+        assert_eq!(stats.total_votes, 4);
+        assert_eq!(stats.min_votes, 0);
+        assert_eq!(stats.max_votes, 3);
+        assert!((stats.mean_votes - 4.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.median_votes, 1.0);
+        assert_eq!(stats.sessions_with_at_least, 1);
+    }
+
+    #[sqlx::test]
+    async fn session_voters_are_anonymous_unless_contact_is_asked_for(pool: PgPool) {
+        let speaker_id = insert_user(&pool, "Sam", "Speaker", "sam@example.com").await;
+        let voter_id = insert_user(&pool, "Vic", "Voter", "vic@example.com").await;
+        let session_id = insert_session(&pool, speaker_id, "Talk").await;
+        let unvoted_id = insert_session(&pool, speaker_id, "Unvoted").await;
+        insert_vote(&pool, voter_id, session_id).await;
+
+        let anonymous = session_voters_get(&pool, session_id, false).await.unwrap();
+        assert_eq!(anonymous, SessionVoters { session_id, votes: 1, voters: None });
+        assert!(!serde_json::to_string(&anonymous).unwrap().contains("vic@example.com"));
+
+        let with_contact = session_voters_get(&pool, session_id, true).await.unwrap();
+        assert_eq!(with_contact.voters, Some(vec![SessionVoter {
+            user_id: voter_id,
+            fname: "Vic".to_string(),
+            lname: "Voter".to_string(),
+            email: "vic@example.com".to_string(),
+        }]));
+
+        let unvoted = session_voters_get(&pool, unvoted_id, true).await.unwrap();
+        assert_eq!(unvoted, SessionVoters { session_id: unvoted_id, votes: 0, voters: Some(vec![]) });
+
+        let missing = session_voters_get(&pool, unvoted_id + 1, false).await.unwrap_err();
+        assert!(matches!(missing.downcast_ref::<SessionVoteErr>(), Some(SessionVoteErr::SessionDoesNotExist(_))));
+    }
+
+    #[sqlx::test]
+    async fn anonymized_vote_export_has_no_user_ids_or_emails(pool: PgPool) {
+        // Push the user ids past anything a pseudonym or title could contain
+        sqlx::query("SELECT setval(pg_get_serial_sequence('users', 'id'), 4242)").execute(&pool).await.unwrap();
+        let mut user_ids = vec![];
+        for email in ["alice@example.com", "bob@example.com"] {
+            user_ids.push(insert_user(&pool, "A", "User", email).await);
+        }
+        let rust_id = insert_session(&pool, user_ids[0], "Rust, in depth").await;
+        let sql_id = insert_session(&pool, user_ids[0], "SQL").await;
+        for (user_id, session_id) in [(user_ids[0], rust_id), (user_ids[1], rust_id), (user_ids[1], sql_id)] {
+            insert_vote(&pool, user_id, session_id).await;
+        }
+
+        let anonymized = votes_csv_get(&pool, true).await.unwrap();
+        let key = pseudonym_key_get(&pool).await.unwrap();
+        let [alice, bob] = [voter_pseudonym(&key, user_ids[0]), voter_pseudonym(&key, user_ids[1])];
+        let mut rows = [format!("{alice},\"Rust, in depth\""), format!("{bob},\"Rust, in depth\""), format!("{bob},SQL")];
+        rows.sort();
+        assert_eq!(anonymized, format!("pseudonym,session_title\r\n{}\r\n", rows.join("\r\n")));
+        assert_eq!(votes_csv_get(&pool, true).await.unwrap(), anonymized);
+        for identifying in user_ids.iter().map(ToString::to_string).chain(["alice@example.com".to_string(), "bob@example.com".to_string()]) {
+            assert!(!anonymized.contains(&identifying), "{identifying} in anonymized export");
+        }
+
+        let raw = votes_csv_get(&pool, false).await.unwrap();
+        assert_eq!(raw, format!("user_id,session_id\r\n{0},{rust_id}\r\n{1},{rust_id}\r\n{1},{sql_id}\r\n", user_ids[0], user_ids[1]));
+    }
+
+    #[test]
+    fn voter_pseudonyms_depend_on_the_key_and_not_the_order_of_user_ids() {
+        let pseudonym = voter_pseudonym("key", 1);
+        assert_eq!(pseudonym, voter_pseudonym("key", 1));
+        assert_eq!(pseudonym.len(), "Voter ".len() + 16);
+        assert_ne!(pseudonym, voter_pseudonym("other key", 1));
+        assert_ne!(pseudonym, voter_pseudonym("key", 2));
+        assert_ne!(pseudonym, "Voter 1");
+    }
+}
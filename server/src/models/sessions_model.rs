@@ -1,11 +1,15 @@
 use crate::middleware::auth::{AuthInfo, AuthSessionLayer};
-use crate::types::ApiStatusCode;
+use crate::models::session_tags_model::get_tags_for_session;
+use crate::models::tags_model::Tag;
+use crate::types::{ApiStatusCode, Pagination};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::{response::Response, Json};
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
-use sqlx::{FromRow, Pool, Postgres};
+use sqlx::{FromRow, PgConnection, Pool, Postgres};
+use std::collections::HashSet;
 use std::error::Error;
+use std::time::Duration;
 use utoipa::ToSchema;
 
 #[derive(Debug, thiserror::Error, ToSchema, Serialize)]
@@ -13,6 +17,12 @@ use utoipa::ToSchema;
 ///
 /// # Variants
 /// - `DoesNotExist` - The session does not exist
+/// - `SubmissionsClosed` - Users can't submit sessions right now
+/// - `SubmissionLimitReached` - The user already has the maximum number of sessions allowed
+/// - `ExcludedFromItself` - A session can't be kept out of its own timeslot
+/// - `InvalidSimilarLimit` - The number of similar sessions asked for isn't positive
+/// - `MergedIntoItself` - A session can't be merged into itself
+/// - `FeatureDisabled` - The feature the request needs is turned off
 pub enum SessionErr {
     #[error("Session {0} doesn't exist")]
     DoesNotExist(String),
@@ -20,6 +30,18 @@ pub enum SessionErr {
     UnAuthorizedMutableAccess(String),
     #[error("Cannot add session on behalf of user that doesn't have an account using email: {0}")]
     UnableToAddSessionForUser(String),
+    #[error("Session submissions are closed")]
+    SubmissionsClosed,
+    #[error("Users can submit at most {0} sessions")]
+    SubmissionLimitReached(i64),
+    #[error("Session {0} can't be excluded from itself")]
+    ExcludedFromItself(i32),
+    #[error("Similar session limit must be positive, got {0}")]
+    InvalidSimilarLimit(i64),
+    #[error("Session {0} can't be merged into itself")]
+    MergedIntoItself(i32),
+    #[error("The {0} feature is turned off")]
+    FeatureDisabled(String),
 }
 
 /// Struct representing an error that occurred when working with sessions.
@@ -86,6 +108,7 @@ impl SessionError {
 /// - `title` - The title of the session
 /// - `content` - The content of the session
 /// - `votes` - The number of votes the session has
+/// - `is_keynote` - Whether the session is a keynote, which the scheduler gives a timeslot to itself
 /// - `tag_id` - The tag ID for the session (optional)
 pub struct Session {
     pub id: Option<i32>,
@@ -95,7 +118,91 @@ pub struct Session {
     pub content: String,
     #[serde(skip_deserializing)]
     pub votes: i32,
+    #[serde(skip_deserializing)]
+    pub is_keynote: bool,
+    pub tag_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+/// Struct representing a session being submitted.
+///
+/// # Fields
+/// - `session` - The session to add, a tag given with `tag_id` is applied first
+/// - `tag_ids` - The IDs of more tags to apply to the session (optional)
+pub struct NewSession {
+    #[serde(flatten)]
+    pub session: Session,
+    #[serde(default)]
+    pub tag_ids: Vec<i32>,
+}
+
+/// The name of the group holding the sessions without any tags.
+pub const UNTAGGED_GROUP_NAME: &str = "Untagged";
+
+/// Struct representing the sessions with a tag, used to present the sessions as tracks.
+///
+/// # Fields
+/// - `tag_id` - The ID of the tag, `None` for the sessions without any tags
+/// - `tag` - The name of the tag, "Untagged" for the sessions without any tags
+/// - `sessions` - The sessions with the tag
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionsByTag {
     pub tag_id: Option<i32>,
+    pub tag: String,
+    pub sessions: Vec<Session>,
+}
+
+/// Struct representing a request to mark a session as a keynote or not.
+///
+/// # Fields
+/// - `is_keynote` - Whether the session is a keynote
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+pub struct KeynoteUpdate {
+    pub is_keynote: bool,
+}
+
+/// Struct representing a duplicate session to fold into another.
+///
+/// # Fields
+/// - `keep_id` - The ID of the session that stays
+/// - `remove_id` - The ID of the duplicate whose votes and tags move to the kept session
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+pub struct SessionMerge {
+    pub keep_id: i32,
+    pub remove_id: i32,
+}
+
+/// Struct representing a pair of sessions the scheduler keeps out of the same timeslot.
+///
+/// # Fields
+/// - `session_id` - The ID of one of the sessions
+/// - `other_session_id` - The ID of the other session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SessionExclusion {
+    pub session_id: i32,
+    pub other_session_id: i32,
+}
+
+/// Struct representing a request to delete several sessions at once.
+///
+/// # Fields
+/// - `session_ids` - The IDs of the sessions to delete
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct BulkDeleteRequest {
+    pub session_ids: Vec<i32>,
+}
+
+/// Struct representing whether a session was deleted by a bulk delete.
+///
+/// # Fields
+/// - `session_id` - The ID of the session
+/// - `deleted` - Whether the session was deleted
+/// - `error` - Why the session wasn't deleted, if it wasn't
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct BulkDeleteResult {
+    pub session_id: i32,
+    pub deleted: bool,
+    pub error: Option<String>,
 }
 
 /// Struct representing a session submitted on a user's behalf.
@@ -114,6 +221,59 @@ pub struct SessionAddedForUser {
     pub email: String,
 }
 
+/// Struct representing where a session is on the schedule.
+///
+/// # Fields
+/// - `time_slot_id` - The ID of the timeslot the session is in
+/// - `room_id` - The ID of the room the session is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct SessionAssignment {
+    pub time_slot_id: i32,
+    pub room_id: i32,
+}
+
+/// Struct representing a session with everything its detail view shows.
+///
+/// # Fields
+/// - `session` - The session
+/// - `tags` - The tags on the session
+/// - `vote_count` - The number of users who voted for the session
+/// - `is_scheduled` - Whether the session has a room and timeslot
+/// - `assignment` - The room and timeslot the session is in, if it's scheduled
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionDetail {
+    pub session: Session,
+    pub tags: Vec<Tag>,
+    pub vote_count: i64,
+    pub is_scheduled: bool,
+    pub assignment: Option<SessionAssignment>,
+}
+
+/// How many similar sessions are returned when no limit is given.
+pub const DEFAULT_SIMILAR_SESSIONS_LIMIT: i64 = 5;
+
+/// Struct representing the query parameters for the sessions similar to a session.
+///
+/// # Fields
+/// - `limit` - How many similar sessions to return, defaults to 5
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SimilarSessionsQuery {
+    pub limit: Option<i64>,
+}
+
+/// Struct representing a session that may be a duplicate of another.
+///
+/// # Fields
+/// - `session_id` - The ID of the session
+/// - `title` - The title of the session
+/// - `score` - How similar the session is, from 0 to 1
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema, FromRow)]
+pub struct SimilarSession {
+    pub session_id: i32,
+    pub title: String,
+    pub score: f32,
+}
+
 impl Session {
     /// Creates a new `Session` instance.
     ///
@@ -135,6 +295,7 @@ impl Session {
             title,
             content,
             votes: 0,
+            is_keynote: false,
             tag_id,
         }
     }
@@ -162,8 +323,8 @@ impl IntoResponse for &Session {
 /// - `db_pool`: The database connection pool
 ///
 /// # Returns
-/// A vector of `Session` instances representing the sessions in the database or an error if the query
-/// fails.
+/// A vector of `Session` instances representing the sessions in the database, ordered by ID, or an
+/// error if the query fails.
 ///
 /// # Errors
 /// If the query fails, a Box error is returned.
@@ -171,7 +332,116 @@ pub async fn get_all_sessions(db_pool: &Pool<Postgres>) -> Result<Vec<Session>,
     let sessions: Vec<Session> = sqlx::query_as!(
         Session,
         r"
-        SELECT id, user_id, title, content, votes, NULL::INTEGER as tag_id FROM sessions",
+        SELECT id, user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id FROM sessions ORDER BY id",
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    Ok(sessions)
+}
+
+/// Retrieves the sessions, or a page of them, ordered by ID.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `pagination`: The page of sessions to retrieve, every session when `None`
+///
+/// # Returns
+/// A `Vec<Session>` of the sessions or an error if the query fails.
+///
+/// # Errors
+/// If the query fails, a Box error is returned.
+pub async fn get_sessions_page(db_pool: &Pool<Postgres>, pagination: Option<Pagination>) -> Result<Vec<Session>, Box<dyn Error>> {
+    let sessions: Vec<Session> = sqlx::query_as!(
+        Session,
+        r"
+        SELECT id, user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id
+        FROM sessions
+        ORDER BY id
+        LIMIT $1 OFFSET $2",
+        pagination.map(|pagination| pagination.limit),
+        pagination.map_or(0, |pagination| pagination.offset()),
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    Ok(sessions)
+}
+
+/// Retrieves the sessions grouped by their tags.
+///
+/// Groups are ordered by tag name with the untagged sessions last, and the sessions in each group
+/// by ID. A session with several tags is listed under each of them. Tags without any sessions are
+/// left out.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+///
+/// # Returns
+/// A `Vec<SessionsByTag>` with a group per tag or an error if the query fails.
+///
+/// # Errors
+/// If the query fails, a Box error is returned.
+pub async fn get_sessions_by_tag(db_pool: &Pool<Postgres>) -> Result<Vec<SessionsByTag>, Box<dyn Error>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT t.id as "tag_id?", t.tag_name as "tag_name?", s.id, s.user_id, s.title, s.content, s.votes, s.is_keynote
+        FROM sessions s
+        LEFT JOIN session_tags st ON st.session_id = s.id
+        LEFT JOIN tags t ON t.id = st.tag_id
+        ORDER BY t.tag_name NULLS LAST, t.id, s.id"#,
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    let mut groups: Vec<SessionsByTag> = Vec::new();
+    for row in rows {
+        let session = Session {
+            id: Some(row.id),
+            user_id: row.user_id,
+            title: row.title,
+            content: row.content,
+            votes: row.votes,
+            is_keynote: row.is_keynote,
+            tag_id: row.tag_id,
+        };
+
+        match groups.last_mut() {
+            Some(group) if group.tag_id == row.tag_id => group.sessions.push(session),
+            _ => groups.push(SessionsByTag {
+                tag_id: row.tag_id,
+                tag: row.tag_name.unwrap_or_else(|| UNTAGGED_GROUP_NAME.to_string()),
+                sessions: vec![session],
+            }),
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Retrieves the sessions nobody has voted for, or a page of them.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `pagination`: The page of sessions to retrieve, every unvoted session when `None`
+///
+/// # Returns
+/// A `Vec<Session>` of the sessions without any votes or an error if the query fails.
+///
+/// # Errors
+/// If the query fails, a Box error is returned.
+pub async fn get_unvoted_sessions(db_pool: &Pool<Postgres>, pagination: Option<Pagination>) -> Result<Vec<Session>, Box<dyn Error>> {
+    let sessions: Vec<Session> = sqlx::query_as!(
+        Session,
+        r"
+        SELECT s.id, s.user_id, s.title, s.content, s.votes, is_keynote, NULL::INTEGER as tag_id
+        FROM sessions s
+        LEFT JOIN user_votes uv ON uv.session_id = s.id
+        WHERE uv.session_id IS NULL
+        ORDER BY s.id
+        LIMIT $1 OFFSET $2",
+        pagination.map(|pagination| pagination.limit),
+        pagination.map_or(0, |pagination| pagination.offset()),
     )
         .fetch_all(db_pool)
         .await?;
@@ -194,7 +464,7 @@ pub async fn get_all_sessions(db_pool: &Pool<Postgres>) -> Result<Vec<Session>,
 pub async fn get(db_pool: &Pool<Postgres>, index: i32) -> Result<Session, Box<dyn Error>> {
     let session = sqlx::query_as!(
         Session,
-        "SELECT id, user_id, title, content, votes, NULL::INTEGER as tag_id FROM sessions where id = $1",
+        "SELECT id, user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id FROM sessions where id = $1",
         index,
     )
         .fetch_one(db_pool)
@@ -203,59 +473,373 @@ pub async fn get(db_pool: &Pool<Postgres>, index: i32) -> Result<Session, Box<dy
     Ok(session)
 }
 
-/// Adds a new session.
+/// Retrieves the sessions most similar to a session, to spot duplicates before scheduling.
+///
+/// Sessions are scored with pg_trgm's `similarity()` over their title and content when the
+/// extension is installed, otherwise with the overlap of the words in their titles. Sessions that
+/// aren't similar at all are left out.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `index`: The ID of the session
+/// - `limit`: How many similar sessions to return
+///
+/// # Returns
+/// The similar sessions, most similar first, or an error if a query fails.
+///
+/// # Errors
+/// If the limit isn't positive a `SessionErr::InvalidSimilarLimit` error is returned. If the
+/// session doesn't exist a `SessionErr::DoesNotExist` error is returned. If a query fails, a Box
+/// error is returned.
+pub async fn similar_get(db_pool: &Pool<Postgres>, index: i32, limit: i64) -> Result<Vec<SimilarSession>, Box<dyn Error>> {
+    if limit < 1 {
+        return Err(Box::new(SessionErr::InvalidSimilarLimit(limit)));
+    }
+    let session = get(db_pool, index).await.map_err(|e| {
+        if matches!(e.downcast_ref::<sqlx::Error>(), Some(sqlx::Error::RowNotFound)) {
+            Box::new(SessionErr::DoesNotExist(index.to_string())) as Box<dyn Error>
+        } else {
+            e
+        }
+    })?;
+    let has_trigrams = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'pg_trgm') as "exists!""#
+    )
+        .fetch_one(db_pool)
+        .await?;
+
+    if has_trigrams {
+        // Not checked at compile time since similarity() only exists once pg_trgm is installed
+        let similar = sqlx::query_as::<_, SimilarSession>(
+            "SELECT id AS session_id, title, score FROM (
+                SELECT id, title, GREATEST(similarity(title, $2), similarity(content, $3))::REAL AS score
+                FROM sessions
+                WHERE id <> $1
+            ) scored
+            WHERE score > 0
+            ORDER BY score DESC, id
+            LIMIT $4",
+        )
+            .bind(index)
+            .bind(&session.title)
+            .bind(&session.content)
+            .bind(limit)
+            .fetch_all(db_pool)
+            .await?;
+        return Ok(similar);
+    }
+
+    let others = sqlx::query!("SELECT id, title FROM sessions WHERE id <> $1", index)
+        .fetch_all(db_pool)
+        .await?;
+    let words = title_words(&session.title);
+    let mut similar: Vec<SimilarSession> = others
+        .into_iter()
+        .map(|other| SimilarSession {
+            score: jaccard(&words, &title_words(&other.title)),
+            session_id: other.id,
+            title: other.title,
+        })
+        .filter(|other| other.score > 0.0)
+        .collect();
+    similar.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.session_id.cmp(&b.session_id)));
+    similar.truncate(usize::try_from(limit).unwrap_or(usize::MAX));
+
+    Ok(similar)
+}
+
+/// Splits a title into its lowercase words, ignoring punctuation.
+fn title_words(title: &str) -> HashSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The share of words two titles have in common out of all the words in either.
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+/// Retrieves a session with its tags, vote count and place on the schedule.
 ///
 /// # Parameters
 /// - `db_pool`: The database connection pool
-/// - `session`: The `Session` instance to add
+/// - `index`: The ID of the session
 ///
 /// # Returns
-/// The ID of the newly added session or an error if the query fails.
+/// The `SessionDetail` of the session with the provided ID or an error if a query fails.
 ///
 /// # Errors
-/// If the query fails, a Box error is returned.
+/// If the session doesn't exist a `SessionErr::DoesNotExist` error is returned. If a query fails,
+/// a Box error is returned.
+pub async fn detail_get(db_pool: &Pool<Postgres>, index: i32) -> Result<SessionDetail, Box<dyn Error>> {
+    let session = get(db_pool, index).await.map_err(|e| {
+        if matches!(e.downcast_ref::<sqlx::Error>(), Some(sqlx::Error::RowNotFound)) {
+            Box::new(SessionErr::DoesNotExist(index.to_string())) as Box<dyn Error>
+        } else {
+            e
+        }
+    })?;
+    let tags = get_tags_for_session(db_pool, index).await?;
+    let vote_count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "votes!" FROM user_votes WHERE session_id = $1"#,
+        index,
+    )
+        .fetch_one(db_pool)
+        .await?;
+    let assignment = sqlx::query_as!(
+        SessionAssignment,
+        r#"SELECT time_slot_id as "time_slot_id!", room_id as "room_id!" FROM timeslot_assignments WHERE session_id = $1"#,
+        index,
+    )
+        .fetch_optional(db_pool)
+        .await?;
+
+    Ok(SessionDetail {
+        session,
+        tags,
+        vote_count,
+        is_scheduled: assignment.is_some(),
+        assignment,
+    })
+}
+
+/// Checks that a user has room to submit another session.
+///
+/// The user's row is locked until the transaction ends, so a concurrent submission by the same
+/// user waits for this one to be added before counting.
+///
+/// # Parameters
+/// - `conn`: The transaction the session is added in
+/// - `user_id`: The ID of the user the session will belong to
+/// - `max_sessions`: The most sessions a user may have, `None` for no limit
+///
+/// # Returns
+/// `Ok(())` if the user has fewer than `max_sessions` sessions.
+///
+/// # Errors
+/// If the user already has `max_sessions` sessions a `SessionErr::SubmissionLimitReached` error is
+/// returned. If the query fails, a Box error is returned.
+async fn check_submission_limit(
+    conn: &mut PgConnection,
+    user_id: i32,
+    max_sessions: Option<i64>,
+) -> Result<(), Box<dyn Error>> {
+    let Some(max_sessions) = max_sessions else {
+        return Ok(());
+    };
+
+    sqlx::query_scalar!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let submitted = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM sessions WHERE user_id = $1"#,
+        user_id,
+    )
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if submitted >= max_sessions {
+        return Err(Box::new(SessionErr::SubmissionLimitReached(max_sessions)));
+    }
+
+    Ok(())
+}
+
+/// The `Idempotency-Key` a session was submitted with, so a resubmission isn't added twice.
+///
+/// # Fields
+/// - `key` - The key the client sent, unique to the submission
+/// - `ttl` - How long the key is remembered for
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IdempotencyKey<'a> {
+    pub(crate) key: &'a str,
+    pub(crate) ttl: Duration,
+}
+
+/// Adds a new session along with its tags.
+///
+/// Users other than staff and admins can't add more than `max_sessions` sessions. A session
+/// submitted with an idempotency key the user already sent within its `ttl` isn't added again, the
+/// session added the first time is returned instead.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `new_session`: The `NewSession` instance to add
+/// - `user_id`: The ID of the user submitting the session
+/// - `auth_info`: The permissions of the user submitting the session
+/// - `max_sessions`: The most sessions a user may have, `None` for no limit
+/// - `idempotency_key`: The key the session was submitted with (optional)
+///
+/// # Returns
+/// The newly added `Session`, including its ID and first tag, or an error if the query fails.
+///
+/// # Errors
+/// If the user has reached the submission limit a `SessionErr::SubmissionLimitReached` error is
+/// returned. If a tag can't be applied a `SessionTagErr` error is returned and the session isn't
+/// added. If the query fails, a Box error is returned.
 pub(crate) async fn add(
     db_pool: &Pool<Postgres>,
-    session: Session,
-    auth_session: AuthSessionLayer,
+    new_session: NewSession,
+    user_id: i32,
     auth_info: AuthInfo,
-) -> Result<i32, Box<dyn Error>> {
-    let session_id = sqlx::query_scalar!(
-        "INSERT INTO sessions (user_id, title, content, votes) VALUES ($1, $2, $3, $4) RETURNING id",
-        auth_session.user.as_ref().unwrap().id,
+    max_sessions: Option<i64>,
+    idempotency_key: Option<IdempotencyKey<'_>>,
+) -> Result<Session, Box<dyn Error>> {
+    if let Some(idempotency_key) = idempotency_key
+        && let Some(session) = remembered_session_get(db_pool, user_id, idempotency_key).await?
+    {
+        return Ok(session);
+    }
+
+    let max_sessions = max_sessions.filter(|_| !auth_info.is_staff_or_admin);
+    insert_with_tags(db_pool, user_id, new_session, max_sessions, idempotency_key).await
+}
+
+/// Retrieves the session a user submitted with an idempotency key, as it was returned when it was
+/// added.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `user_id`: The ID of the user who submitted the session
+/// - `idempotency_key`: The key the session was submitted with
+///
+/// # Returns
+/// The `Session`, or `None` if the user hasn't sent the key within its `ttl`.
+///
+/// # Errors
+/// If the query fails, a Box error is returned.
+async fn remembered_session_get(db_pool: &Pool<Postgres>, user_id: i32, idempotency_key: IdempotencyKey<'_>) -> Result<Option<Session>, Box<dyn Error>> {
+    let session = sqlx::query_as!(
+        Session,
+        r#"SELECT s.id as "id?", s.user_id, s.title, s.content, s.votes, s.is_keynote, k.tag_id
+        FROM session_idempotency_keys k
+        JOIN sessions s ON s.id = k.session_id
+        WHERE k.user_id = $1 AND k.idempotency_key = $2 AND k.created_at > now() - make_interval(secs => $3)"#,
+        user_id,
+        idempotency_key.key,
+        idempotency_key.ttl.as_secs_f64(),
+    )
+        .fetch_optional(db_pool)
+        .await?;
+
+    Ok(session)
+}
+
+/// Inserts a session and applies its tags in one transaction, so if any tag can't be applied the
+/// session isn't added either.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `user_id`: The ID of the user the session belongs to
+/// - `new_session`: The session and the IDs of the tags to apply to it
+/// - `max_sessions`: The most sessions the user may have, `None` for no limit
+/// - `idempotency_key`: The key to remember the session by (optional)
+///
+/// # Returns
+/// The newly added `Session`, including its ID and first tag, or an error if the query fails. If
+/// another submission with the same key was added first, nothing is added and that session is
+/// returned instead.
+///
+/// # Errors
+/// If the user has reached the submission limit a `SessionErr::SubmissionLimitReached` error is
+/// returned. If a tag doesn't exist or is given twice a `SessionTagErr` error is returned. If the
+/// query fails, a Box error is returned.
+async fn insert_with_tags(
+    db_pool: &Pool<Postgres>,
+    user_id: i32,
+    new_session: NewSession,
+    max_sessions: Option<i64>,
+    idempotency_key: Option<IdempotencyKey<'_>>,
+) -> Result<Session, Box<dyn Error>> {
+    use crate::models::session_tags_model::session_tag_insert;
+
+    let NewSession { session, tag_ids } = new_session;
+    let mut tx = db_pool.begin().await?;
+    check_submission_limit(&mut tx, user_id, max_sessions).await?;
+    let mut new_session = sqlx::query_as!(
+        Session,
+        r#"INSERT INTO sessions (user_id, title, content, votes) VALUES ($1, $2, $3, $4)
+        RETURNING id as "id?", user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id"#,
+        user_id,
         session.title,
         session.content,
         session.votes,
     )
-        .fetch_one(db_pool)
+        .fetch_one(&mut *tx)
         .await?;
 
-    // If a tag was provided, add it to the session
-    if let Some(tag_id) = session.tag_id {
-        use crate::models::session_tags_model::add_session_tag;
-        add_session_tag(db_pool, auth_session, auth_info, session_id, tag_id).await?;
+    // Apply the tags that were provided, returning early drops the transaction and undoes the insert
+    if let Some(session_id) = new_session.id {
+        for tag_id in session.tag_id.into_iter().chain(tag_ids) {
+            session_tag_insert(&mut tx, session_id, tag_id).await?;
+            new_session.tag_id.get_or_insert(tag_id);
+        }
+    }
+
+    if let Some(idempotency_key) = idempotency_key {
+        let ttl = idempotency_key.ttl.as_secs_f64();
+        sqlx::query!(
+            "DELETE FROM session_idempotency_keys WHERE created_at <= now() - make_interval(secs => $1)",
+            ttl,
+        )
+            .execute(&mut *tx)
+            .await?;
+
+        // A submission with the same key that is still being added holds the key until it commits
+        let remembered = sqlx::query!(
+            "INSERT INTO session_idempotency_keys (user_id, idempotency_key, session_id, tag_id) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id, idempotency_key) DO NOTHING",
+            user_id,
+            idempotency_key.key,
+            new_session.id,
+            new_session.tag_id,
+        )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() > 0;
+
+        if !remembered {
+            tx.rollback().await?;
+            return remembered_session_get(db_pool, user_id, idempotency_key)
+                .await?
+                .ok_or_else(|| Box::new(SessionErr::DoesNotExist(format!("submitted with idempotency key {}", idempotency_key.key))) as Box<dyn Error>);
+        }
     }
 
-    Ok(session_id)
+    tx.commit().await?;
+
+    Ok(new_session)
 }
 
 /// Adds a new session on behalf of a user.
 ///
+/// The session counts towards that user's limit of `max_sessions` sessions.
+///
 /// # Parameters
 /// - `db_pool`: The database connection pool
 /// - `session`: The `SessionAddedForUser` instance to add
 /// - `auth_session`: Authentication session for authorization
+/// - `max_sessions`: The most sessions a user may have, `None` for no limit
 ///
 /// # Returns
 /// The ID of the newly added session or an error if the query fails.
 ///
 /// # Errors
-/// If the query fails, a Box error is returned.
+/// If the user has reached the submission limit a `SessionErr::SubmissionLimitReached` error is
+/// returned. If the query fails, a Box error is returned.
 pub(crate) async fn add_for_user(
     db_pool: &Pool<Postgres>,
     session: SessionAddedForUser,
     auth_session: AuthSessionLayer,
     auth_info: AuthInfo,
+    max_sessions: Option<i64>,
 ) -> Result<i32, Box<dyn Error>> {
     let user = sqlx::query_scalar!(
         "SELECT id FROM users WHERE email = $1",
@@ -265,6 +849,9 @@ pub(crate) async fn add_for_user(
         .await?;
 
     if let Some(user_id) = user {
+        let mut tx = db_pool.begin().await?;
+        check_submission_limit(&mut tx, user_id, max_sessions).await?;
+
         let session_id = sqlx::query_scalar!(
             "INSERT INTO sessions (user_id, title, content, votes) VALUES ($1, $2, $3, $4) RETURNING id",
             user_id,
@@ -272,8 +859,9 @@ pub(crate) async fn add_for_user(
             session.content,
             0,
         )
-            .fetch_one(db_pool)
+            .fetch_one(&mut *tx)
             .await?;
+        tx.commit().await?;
 
         // If a tag was provided, add it to the session
         if let Some(tag_id) = session.tag_id {
@@ -315,7 +903,7 @@ pub(crate) async fn delete(
 ) -> Result<(), Box<dyn Error>> {
     let session = sqlx::query_as!(
         Session,
-        "SELECT id, user_id, title, content, votes, NULL::INTEGER as tag_id FROM sessions where id = $1",
+        "SELECT id, user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id FROM sessions where id = $1",
         index,
     )
         .fetch_optional(db_pool)
@@ -354,6 +942,255 @@ pub(crate) async fn delete(
     Ok(())
 }
 
+/// Removes several sessions by their IDs in one transaction.
+///
+/// The votes, tags and schedule assignments of each session are removed with it. Sessions that
+/// don't exist are reported as not deleted without stopping the others from being deleted.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `session_ids`: The IDs of the sessions to remove
+///
+/// # Returns
+/// A `Result` containing a `BulkDeleteResult` for each ID, in the order they were given.
+///
+/// # Errors
+/// If a query fails nothing is removed and a Box error is returned.
+pub async fn bulk_delete(db_pool: &Pool<Postgres>, session_ids: &[i32]) -> Result<Vec<BulkDeleteResult>, Box<dyn Error>> {
+    let mut transaction = db_pool.begin().await?;
+    let mut results = Vec::with_capacity(session_ids.len());
+    for &session_id in session_ids {
+        sqlx::query!("DELETE FROM timeslot_assignments WHERE session_id = $1", session_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!("DELETE FROM user_votes WHERE session_id = $1", session_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!("DELETE FROM session_tags WHERE session_id = $1", session_id)
+            .execute(&mut *transaction)
+            .await?;
+        let deleted = sqlx::query!("DELETE FROM sessions WHERE id = $1", session_id)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected() > 0;
+
+        results.push(BulkDeleteResult {
+            session_id,
+            deleted,
+            error: (!deleted).then(|| SessionErr::DoesNotExist(session_id.to_string()).to_string()),
+        });
+    }
+    transaction.commit().await?;
+
+    Ok(results)
+}
+
+/// Marks a session as a keynote or not.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `index`: The ID of the session
+/// - `is_keynote`: Whether the session is a keynote
+///
+/// # Returns
+/// A `Result` containing the updated `Session` if successful, otherwise an error.
+///
+/// # Errors
+/// A `SessionErr::DoesNotExist` error is returned if the session doesn't exist. If the query fails,
+/// a Box error is returned.
+pub async fn keynote_set(db_pool: &Pool<Postgres>, index: i32, is_keynote: bool) -> Result<Session, Box<dyn Error>> {
+    sqlx::query_as!(
+        Session,
+        r#"UPDATE sessions SET is_keynote = $2 WHERE id = $1
+        RETURNING id as "id?", user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id"#,
+        index,
+        is_keynote,
+    )
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| Box::new(SessionErr::DoesNotExist(index.to_string())) as Box<dyn Error>)
+}
+
+/// Keeps two sessions out of the same timeslot when the schedule is generated.
+///
+/// The pair is stored with the lower ID first, so excluding the same pair twice in either order
+/// has no further effect.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `exclusion`: The pair of sessions to keep apart
+///
+/// # Returns
+/// A `Result` containing the stored `SessionExclusion` if successful, otherwise an error.
+///
+/// # Errors
+/// A `SessionErr::ExcludedFromItself` error is returned if both IDs are the same, and a
+/// `SessionErr::DoesNotExist` error if either session doesn't exist. If a query fails, a Box error
+/// is returned.
+pub async fn exclusion_add(db_pool: &Pool<Postgres>, exclusion: SessionExclusion) -> Result<SessionExclusion, Box<dyn Error>> {
+    let SessionExclusion { session_id, other_session_id } = exclusion;
+    if session_id == other_session_id {
+        return Err(Box::new(SessionErr::ExcludedFromItself(session_id)));
+    }
+
+    for index in [session_id, other_session_id] {
+        sqlx::query_scalar!("SELECT id FROM sessions WHERE id = $1", index)
+            .fetch_optional(db_pool)
+            .await?
+            .ok_or_else(|| SessionErr::DoesNotExist(index.to_string()))?;
+    }
+
+    let exclusion = SessionExclusion {
+        session_id: session_id.min(other_session_id),
+        other_session_id: session_id.max(other_session_id),
+    };
+    sqlx::query!(
+        "INSERT INTO session_exclusions (session_id, other_session_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        exclusion.session_id,
+        exclusion.other_session_id,
+    )
+        .execute(db_pool)
+        .await?;
+
+    Ok(exclusion)
+}
+
+/// Merges a duplicate session into the session being kept.
+///
+/// The duplicate's votes and tags move to the kept session, users who voted for both only count
+/// once, and the duplicate is then removed along with its place on the schedule. Moved votes keep
+/// when they were cast.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `session_merge`: The session to keep and the duplicate to remove
+///
+/// # Returns
+/// A `Result` containing the kept `Session` with its new vote count if successful, otherwise an
+/// error.
+///
+/// # Errors
+/// A `SessionErr::MergedIntoItself` error is returned if both IDs are the same, and a
+/// `SessionErr::DoesNotExist` error if either session doesn't exist. If a query fails nothing is
+/// merged and a Box error is returned.
+pub async fn merge(db_pool: &Pool<Postgres>, session_merge: SessionMerge) -> Result<Session, Box<dyn Error>> {
+    let SessionMerge { keep_id, remove_id } = session_merge;
+    if keep_id == remove_id {
+        return Err(Box::new(SessionErr::MergedIntoItself(keep_id)));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    for index in [keep_id, remove_id] {
+        sqlx::query_scalar!("SELECT id FROM sessions WHERE id = $1 FOR UPDATE", index)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| SessionErr::DoesNotExist(index.to_string()))?;
+    }
+
+    sqlx::query!(
+        "INSERT INTO user_votes (user_id, session_id, voted_at)
+        SELECT user_id, $1, voted_at FROM user_votes WHERE session_id = $2
+        ON CONFLICT DO NOTHING",
+        keep_id,
+        remove_id,
+    )
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO session_tags (session_id, tag_id)
+        SELECT $1, tag_id FROM session_tags WHERE session_id = $2
+        ON CONFLICT DO NOTHING",
+        keep_id,
+        remove_id,
+    )
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM timeslot_assignments WHERE session_id = $1", remove_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM sessions WHERE id = $1", remove_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let session = sqlx::query_as!(
+        Session,
+        r#"UPDATE sessions
+        SET votes = (SELECT COUNT(*) FROM user_votes WHERE session_id = $1)::INTEGER
+        WHERE id = $1
+        RETURNING id as "id?", user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id"#,
+        keep_id,
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(session)
+}
+
+/// Duplicates a session so it can be scheduled a second time.
+///
+/// The copy keeps the owner, content, and tags of the original, has "(repeat)" added to its title,
+/// and starts with no votes. Like an added session, the copy counts towards the owner's limit of
+/// `max_sessions` sessions unless staff or an admin duplicates it.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `index`: The ID of the session to duplicate
+/// - `user_id`: The ID of the user duplicating the session
+/// - `is_staff_or_admin`: Whether the user is staff or an admin
+/// - `max_sessions`: The most sessions a user may have, `None` for no limit
+///
+/// # Returns
+/// A `Result` containing the new `Session` if successful, otherwise an error.
+///
+/// # Errors
+/// A `SessionErr::DoesNotExist` error is returned if the session doesn't exist, a
+/// `SessionErr::UnAuthorizedMutableAccess` error if the user neither owns the session nor is staff
+/// or an admin, and a `SessionErr::SubmissionLimitReached` error if the owner has reached the
+/// submission limit. If a query fails, a Box error is returned.
+pub(crate) async fn duplicate(
+    db_pool: &Pool<Postgres>,
+    index: i32,
+    user_id: i32,
+    is_staff_or_admin: bool,
+    max_sessions: Option<i64>,
+) -> Result<Session, Box<dyn Error>> {
+    let original = sqlx::query_as!(
+        Session,
+        "SELECT id, user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id FROM sessions WHERE id = $1",
+        index,
+    )
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| SessionErr::DoesNotExist(index.to_string()))?;
+
+    if !is_staff_or_admin && original.user_id != user_id {
+        return Err(Box::new(SessionErr::UnAuthorizedMutableAccess(
+            "User does not own this resource to duplicate it".to_string(),
+        )));
+    }
+
+    let tag_ids = get_tags_for_session(db_pool, index)
+        .await?
+        .into_iter()
+        .map(|tag| tag.id)
+        .collect();
+    let copy = NewSession {
+        session: Session {
+            id: None,
+            title: format!("{} (repeat)", original.title),
+            votes: 0,
+            ..original
+        },
+        tag_ids,
+    };
+
+    // The copy is a new submission of the owner's, added the same way as any other
+    let max_sessions = max_sessions.filter(|_| !is_staff_or_admin);
+    insert_with_tags(db_pool, copy.session.user_id, copy, max_sessions, None).await
+}
+
 /// Updates a session by its ID.
 ///
 /// # Parameters
@@ -374,7 +1211,7 @@ pub(crate) async fn update(
 ) -> Result<Session, Box<dyn Error>> {
     let session_to_update = sqlx::query_as!(
         Session,
-        "SELECT id, user_id, title, content, votes, NULL::INTEGER as tag_id FROM sessions where id = $1",
+        "SELECT id, user_id, title, content, votes, is_keynote, NULL::INTEGER as tag_id FROM sessions where id = $1",
         index,
     )
         .fetch_optional(db_pool)
@@ -418,3 +1255,453 @@ pub(crate) async fn update(
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assign_session, insert_room, insert_session, insert_session_with, insert_tag, insert_timeslot, insert_user, insert_vote, tag_session};
+    use crate::types::pagination::MAX_PAGE_LIMIT;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn duplicate_copies_session_without_votes(pool: PgPool) {
+        let owner_id = insert_user(&pool, "Owner", "User", "owner@example.com").await;
+        let other_id = insert_user(&pool, "Other", "User", "other@example.com").await;
+        let session_id = insert_session_with(&pool, owner_id, "Rust", "All about Rust", 0).await;
+        let tag_id = insert_tag(&pool, "Languages").await;
+        sqlx::query("INSERT INTO session_tags (session_id, tag_id) VALUES ($1, $2)")
+            .bind(session_id)
+            .bind(tag_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $2)")
+            .bind(other_id)
+            .bind(session_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let copy = duplicate(&pool, session_id, owner_id, false, None).await.unwrap();
+
+        assert_ne!(copy.id, Some(session_id));
+        assert_eq!(copy.user_id, owner_id);
+        assert_eq!(copy.title, "Rust (repeat)");
+        assert_eq!(copy.content, "All about Rust");
+        assert_eq!(copy.votes, 0);
+        assert_eq!(copy.tag_id, Some(tag_id));
+
+        let copy_votes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_votes WHERE session_id = $1")
+            .bind(copy.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(copy_votes, 0);
+    }
+
+    #[sqlx::test]
+    async fn session_lists_are_ordered_by_id(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let mut session_ids = Vec::new();
+        for title in ["First", "Second", "Third"] {
+            session_ids.push(insert_session(&pool, user_id, title).await);
+        }
+        // Updating a row moves it to the end of the table, so an unordered scan would return it last
+        sqlx::query("UPDATE sessions SET votes = 5 WHERE id = $1")
+            .bind(session_ids[0])
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            let ids: Vec<i32> = get_all_sessions(&pool).await.unwrap().into_iter().filter_map(|session| session.id).collect();
+            assert_eq!(ids, session_ids);
+
+            let ids: Vec<i32> = crate::controllers::site_handler::combine_session_and_user(&pool)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|session| session.session_id)
+                .collect();
+            assert_eq!(ids, session_ids);
+        }
+    }
+
+    #[sqlx::test]
+    async fn detail_includes_tags_votes_and_assignment(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let session_id = insert_session_with(&pool, user_id, "Rust", "content", 1).await;
+        let tag_id = insert_tag(&pool, "Languages").await;
+        tag_session(&pool, session_id, tag_id).await;
+        insert_vote(&pool, user_id, session_id).await;
+
+        let detail = detail_get(&pool, session_id).await.unwrap();
+        assert_eq!(detail.session.id, Some(session_id));
+        assert_eq!(detail.tags.iter().map(|tag| tag.id).collect::<Vec<_>>(), vec![tag_id]);
+        assert_eq!(detail.vote_count, 1);
+        assert!(!detail.is_scheduled);
+        assert_eq!(detail.assignment, None);
+
+        let room_id = insert_room(&pool, "Room A", "Here", 10).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        assign_session(&pool, time_slot_id, room_id, session_id).await;
+
+        let detail = detail_get(&pool, session_id).await.unwrap();
+        assert!(detail.is_scheduled);
+        assert_eq!(detail.assignment, Some(SessionAssignment { time_slot_id, room_id }));
+
+        let missing = detail_get(&pool, session_id + 1).await.unwrap_err();
+        assert!(matches!(missing.downcast_ref::<SessionErr>(), Some(SessionErr::DoesNotExist(_))));
+    }
+
+    #[sqlx::test]
+    async fn submission_limit_is_reached_at_the_cap(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let other_id = insert_user(&pool, "B", "User", "b@example.com").await;
+        for title in ["First", "Second"] {
+            insert_session(&pool, user_id, title).await;
+        }
+
+        let mut conn = pool.acquire().await.unwrap();
+        assert!(check_submission_limit(&mut conn, user_id, None).await.is_ok());
+        assert!(check_submission_limit(&mut conn, user_id, Some(3)).await.is_ok());
+        assert!(check_submission_limit(&mut conn, other_id, Some(1)).await.is_ok());
+
+        let err = check_submission_limit(&mut conn, user_id, Some(2)).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SessionErr>(), Some(SessionErr::SubmissionLimitReached(2))));
+    }
+
+    #[sqlx::test]
+    async fn submission_limit_waits_for_a_concurrent_submission(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let mut tx = pool.begin().await.unwrap();
+        check_submission_limit(&mut tx, user_id, Some(1)).await.unwrap();
+        sqlx::query("INSERT INTO sessions (user_id, title, content, votes) VALUES ($1, 'First', 'content', 0)")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+
+        // The second submission can't count the user's sessions until the first one is added
+        let concurrent = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                let auth_info = AuthInfo { is_authenticated: true, is_staff_or_admin: false, permissions: HashSet::new() };
+                let new_session = NewSession { session: Session::new(None, user_id, "Second", "content", None), tag_ids: vec![] };
+                add(&pool, new_session, user_id, auth_info, Some(1), None).await.map_err(|e| e.to_string())
+            }
+        });
+        tx.commit().await.unwrap();
+
+        let err = concurrent.await.unwrap().unwrap_err();
+        assert_eq!(err, SessionErr::SubmissionLimitReached(1).to_string());
+    }
+
+    #[sqlx::test]
+    async fn duplicating_counts_towards_the_owners_submission_limit(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let staff_id = insert_user(&pool, "B", "User", "b@example.com").await;
+        let session_id = insert_session(&pool, user_id, "Talk").await;
+
+        let err = duplicate(&pool, session_id, user_id, false, Some(1)).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SessionErr>(), Some(SessionErr::SubmissionLimitReached(1))));
+
+        let copy = duplicate(&pool, session_id, staff_id, true, Some(1)).await.unwrap();
+        assert_eq!(copy.user_id, user_id);
+    }
+
+    #[sqlx::test]
+    async fn get_unvoted_sessions_skips_voted_sessions(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let voted_id = insert_session(&pool, user_id, "Voted").await;
+        let unvoted_id = insert_session(&pool, user_id, "Unvoted").await;
+        sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(voted_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let sessions = get_unvoted_sessions(&pool, None).await.unwrap();
+
+        let session_ids: Vec<Option<i32>> = sessions.iter().map(|session| session.id).collect();
+        assert_eq!(session_ids, vec![Some(unvoted_id)]);
+    }
+
+    #[sqlx::test]
+    async fn get_sessions_by_tag_groups_sessions_under_each_tag(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let web_id = insert_tag(&pool, "Web").await;
+        let rust_id = insert_tag(&pool, "Rust").await;
+        insert_tag(&pool, "Unused").await;
+        let mut session_ids = vec![];
+        for title in ["Axum", "Borrowck", "Lunch"] {
+            session_ids.push(insert_session(&pool, user_id, title).await);
+        }
+        for (session_id, tag_id) in [(session_ids[0], web_id), (session_ids[0], rust_id), (session_ids[1], rust_id)] {
+            tag_session(&pool, session_id, tag_id).await;
+        }
+
+        let groups = get_sessions_by_tag(&pool).await.unwrap();
+
+        let tags: Vec<(Option<i32>, &str)> = groups.iter().map(|group| (group.tag_id, group.tag.as_str())).collect();
+        assert_eq!(tags, vec![(Some(rust_id), "Rust"), (Some(web_id), "Web"), (None, UNTAGGED_GROUP_NAME)]);
+        let grouped_ids: Vec<Vec<i32>> = groups
+            .iter()
+            .map(|group| group.sessions.iter().filter_map(|session| session.id).collect())
+            .collect();
+        assert_eq!(grouped_ids, vec![vec![session_ids[0], session_ids[1]], vec![session_ids[0]], vec![session_ids[2]]]);
+    }
+
+    #[sqlx::test]
+    async fn get_sessions_page_returns_the_requested_page(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let mut session_ids = vec![];
+        for title in ["First", "Second", "Third"] {
+            session_ids.push(Some(insert_session(&pool, user_id, title).await));
+        }
+
+        let page_ids = |sessions: Vec<Session>| sessions.iter().map(|session| session.id).collect::<Vec<_>>();
+        let first_page = get_sessions_page(&pool, Some(Pagination { page: 1, limit: 2 })).await.unwrap();
+        let second_page = get_sessions_page(&pool, Some(Pagination { page: 2, limit: 2 })).await.unwrap();
+        let past_the_end = get_sessions_page(&pool, Some(Pagination { page: 3, limit: 2 })).await.unwrap();
+
+        assert_eq!(page_ids(first_page), session_ids[..2]);
+        assert_eq!(page_ids(second_page), session_ids[2..]);
+        assert!(past_the_end.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn get_sessions_page_lists_every_session_without_pagination(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        for i in 0..=MAX_PAGE_LIMIT {
+            insert_session(&pool, user_id, &format!("Talk {i}")).await;
+        }
+
+        let sessions = get_sessions_page(&pool, None).await.unwrap();
+
+        assert_eq!(sessions.len() as i64, MAX_PAGE_LIMIT + 1);
+    }
+
+    #[sqlx::test]
+    async fn duplicate_is_limited_to_owner_or_staff(pool: PgPool) {
+        let owner_id = insert_user(&pool, "Owner", "User", "owner@example.com").await;
+        let other_id = insert_user(&pool, "Other", "User", "other@example.com").await;
+        let session_id = insert_session_with(&pool, owner_id, "Rust", "All about Rust", 0).await;
+
+        let err = duplicate(&pool, session_id, other_id, false, None).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SessionErr>(), Some(SessionErr::UnAuthorizedMutableAccess(_))));
+
+        let copy = duplicate(&pool, session_id, other_id, true, None).await.unwrap();
+        assert_eq!(copy.user_id, owner_id);
+
+        let err = duplicate(&pool, session_id + 100, owner_id, true, None).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SessionErr>(), Some(SessionErr::DoesNotExist(_))));
+    }
+
+    #[sqlx::test]
+    async fn keynote_set_marks_and_unmarks_a_session(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let session_id = insert_session(&pool, user_id, "Opening").await;
+        assert!(!get(&pool, session_id).await.unwrap().is_keynote);
+
+        assert!(keynote_set(&pool, session_id, true).await.unwrap().is_keynote);
+        assert!(get(&pool, session_id).await.unwrap().is_keynote);
+        assert!(!keynote_set(&pool, session_id, false).await.unwrap().is_keynote);
+
+        let missing = keynote_set(&pool, session_id + 1, true).await.unwrap_err();
+        assert!(matches!(missing.downcast_ref::<SessionErr>(), Some(SessionErr::DoesNotExist(_))));
+    }
+
+    #[sqlx::test]
+    async fn bulk_delete_removes_existing_sessions_and_reports_missing_ones(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let spam_id = insert_session(&pool, user_id, "Spam").await;
+        let kept_id = insert_session(&pool, user_id, "Kept").await;
+        let room_id = insert_room(&pool, "Room", "Here", 10).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        let tag_id = insert_tag(&pool, "Rust").await;
+        insert_vote(&pool, user_id, spam_id).await;
+        tag_session(&pool, spam_id, tag_id).await;
+        assign_session(&pool, time_slot_id, room_id, spam_id).await;
+        let missing_id = kept_id + 100;
+
+        let results = bulk_delete(&pool, &[spam_id, missing_id]).await.unwrap();
+
+        assert_eq!(results, vec![
+            BulkDeleteResult { session_id: spam_id, deleted: true, error: None },
+            BulkDeleteResult { session_id: missing_id, deleted: false, error: Some(format!("Session {missing_id} doesn't exist")) },
+        ]);
+        let remaining: Vec<i32> = sqlx::query_scalar("SELECT id FROM sessions").fetch_all(&pool).await.unwrap();
+        assert_eq!(remaining, vec![kept_id]);
+        for table in ["user_votes", "session_tags", "timeslot_assignments"] {
+            let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table} WHERE session_id = $1"))
+                .bind(spam_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(rows, 0, "{table} still references the deleted session");
+        }
+    }
+
+    #[test]
+    fn new_session_tag_ids_are_optional() {
+        let legacy: NewSession = serde_json::from_str(r#"{"title": "Axum", "content": "content", "tag_id": 3}"#).unwrap();
+        assert_eq!((legacy.session.title.as_str(), legacy.session.tag_id, legacy.tag_ids), ("Axum", Some(3), vec![]));
+
+        let tagged: NewSession = serde_json::from_str(r#"{"title": "Axum", "content": "content", "tag_ids": [1, 2]}"#).unwrap();
+        assert_eq!((tagged.session.tag_id, tagged.tag_ids), (None, vec![1, 2]));
+    }
+
+    #[sqlx::test]
+    async fn session_is_added_with_its_tags_or_not_at_all(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let rust_tag = insert_tag(&pool, "Rust").await;
+        let web_tag = insert_tag(&pool, "Web").await;
+        let new_session = |title: &str, tag_ids: Vec<i32>| NewSession {
+            session: Session::new(None, user_id, title, "content", None),
+            tag_ids,
+        };
+
+        let added = insert_with_tags(&pool, user_id, new_session("Axum", vec![rust_tag, web_tag]), None, None).await.unwrap();
+        assert_eq!(added.tag_id, Some(rust_tag));
+        let mut tags: Vec<i32> = get_tags_for_session(&pool, added.id.unwrap()).await.unwrap().into_iter().map(|tag| tag.id).collect();
+        tags.sort();
+        assert_eq!(tags, vec![rust_tag, web_tag]);
+
+        // A missing or repeated tag rolls the whole submission back
+        assert!(insert_with_tags(&pool, user_id, new_session("Missing", vec![rust_tag, web_tag + 100]), None, None).await.is_err());
+        assert!(insert_with_tags(&pool, user_id, new_session("Repeated", vec![web_tag, web_tag]), None, None).await.is_err());
+        let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions").fetch_one(&pool).await.unwrap();
+        let session_tags: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM session_tags").fetch_one(&pool).await.unwrap();
+        assert_eq!((sessions, session_tags), (1, 2));
+
+        let untagged = insert_with_tags(&pool, user_id, new_session("Untagged", vec![]), None, None).await.unwrap();
+        assert_eq!(untagged.tag_id, None);
+    }
+
+    #[sqlx::test]
+    async fn resubmitting_with_the_same_idempotency_key_adds_one_session(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let tag_id = insert_tag(&pool, "Rust").await;
+        let auth_info = AuthInfo { is_authenticated: true, is_staff_or_admin: false, permissions: HashSet::new() };
+        let key = Some(IdempotencyKey { key: "submit-axum", ttl: Duration::from_secs(600) });
+        let submit = || add(
+            &pool,
+            NewSession { session: Session::new(None, user_id, "Axum", "content", None), tag_ids: vec![tag_id] },
+            user_id,
+            auth_info.clone(),
+            Some(5),
+            key,
+        );
+
+        let first = serde_json::to_value(submit().await.unwrap()).unwrap();
+        let replayed = serde_json::to_value(submit().await.unwrap()).unwrap();
+
+        assert_eq!(first, replayed);
+        let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions").fetch_one(&pool).await.unwrap();
+        assert_eq!(sessions, 1);
+    }
+
+    #[sqlx::test]
+    async fn similar_sessions_fall_back_to_title_words_without_pg_trgm(pool: PgPool) {
+        let user_id = insert_user(&pool, "Owner", "User", "owner@example.com").await;
+        let insert_session = |title: &'static str| {
+            let pool = pool.clone();
+            async move {
+                insert_session_with(&pool, user_id, title, "Talk", 0).await
+            }
+        };
+        let session_id = insert_session("Intro to Async Rust").await;
+        let near_duplicate = insert_session("Async Rust: an intro").await;
+        let related = insert_session("Embedded Rust").await;
+        insert_session("Gardening").await;
+
+        let similar = similar_get(&pool, session_id, 5).await.unwrap();
+
+        assert_eq!(similar, vec![
+            SimilarSession { session_id: near_duplicate, title: "Async Rust: an intro".to_string(), score: 0.6 },
+            SimilarSession { session_id: related, title: "Embedded Rust".to_string(), score: 0.2 },
+        ]);
+        assert_eq!(similar_get(&pool, session_id, 1).await.unwrap().len(), 1);
+        let missing = similar_get(&pool, -1, 5).await.unwrap_err();
+        assert!(matches!(missing.downcast_ref::<SessionErr>(), Some(SessionErr::DoesNotExist(_))));
+        let invalid = similar_get(&pool, session_id, 0).await.unwrap_err();
+        assert!(matches!(invalid.downcast_ref::<SessionErr>(), Some(SessionErr::InvalidSimilarLimit(0))));
+    }
+
+    #[sqlx::test]
+    async fn similar_sessions_use_trigram_similarity_with_pg_trgm(pool: PgPool) {
+        sqlx::query("CREATE EXTENSION pg_trgm").execute(&pool).await.unwrap();
+        let user_id = insert_user(&pool, "Owner", "User", "owner@example.com").await;
+        let session_id = insert_session_with(&pool, user_id, "Intro to Async Rust", "Futures and executors", 0).await;
+        let misspelled = insert_session_with(&pool, user_id, "Intro to Asynch Rust", "Something else", 0).await;
+        let same_content = insert_session_with(&pool, user_id, "Cooking", "Futures and executors", 0).await;
+
+        let similar = similar_get(&pool, session_id, 5).await.unwrap();
+
+        // Trigrams catch the misspelling the word overlap would miss, and content counts too
+        assert_eq!(similar.iter().map(|s| s.session_id).collect::<Vec<_>>(), vec![same_content, misspelled]);
+        assert_eq!(similar[0].score, 1.0);
+        assert!(similar[1].score > 0.5 && similar[1].score < 1.0);
+    }
+
+    #[sqlx::test]
+    async fn merging_counts_a_voter_of_both_sessions_once(pool: PgPool) {
+        let owner_id = insert_user(&pool, "Owner", "User", "owner@example.com").await;
+        let both_id = insert_user(&pool, "Both", "Voter", "both@example.com").await;
+        let removed_voter_id = insert_user(&pool, "Removed", "Voter", "removed@example.com").await;
+        let keep_id = insert_session_with(&pool, owner_id, "Async Rust", "Futures", 1).await;
+        let remove_id = insert_session_with(&pool, owner_id, "Async Rust!", "Futures", 2).await;
+        let shared_tag_id = insert_tag(&pool, "Languages").await;
+        let moved_tag_id = insert_tag(&pool, "Concurrency").await;
+        for (user_id, session_id) in [(both_id, keep_id), (both_id, remove_id)] {
+            insert_vote(&pool, user_id, session_id).await;
+        }
+        sqlx::query("INSERT INTO user_votes (user_id, session_id, voted_at) VALUES ($1, $2, '2026-01-01T09:00:00Z')")
+            .bind(removed_voter_id)
+            .bind(remove_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (session_id, tag_id) in [(keep_id, shared_tag_id), (remove_id, shared_tag_id), (remove_id, moved_tag_id)] {
+            sqlx::query("INSERT INTO session_tags (session_id, tag_id) VALUES ($1, $2)")
+                .bind(session_id)
+                .bind(tag_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let kept = merge(&pool, SessionMerge { keep_id, remove_id }).await.unwrap();
+
+        assert_eq!(kept.id, Some(keep_id));
+        assert_eq!(kept.votes, 2);
+        let mut voters: Vec<i32> = sqlx::query_scalar("SELECT user_id FROM user_votes WHERE session_id = $1")
+            .bind(keep_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        voters.sort();
+        assert_eq!(voters, vec![both_id, removed_voter_id]);
+        let voted_at: String = sqlx::query_scalar("SELECT to_char(voted_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI') FROM user_votes WHERE user_id = $1")
+            .bind(removed_voter_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(voted_at, "2026-01-01 09:00");
+        let tags: HashSet<i32> = get_tags_for_session(&pool, keep_id).await.unwrap().into_iter().map(|tag| tag.id).collect();
+        assert_eq!(tags, HashSet::from([shared_tag_id, moved_tag_id]));
+        let removed: Option<i32> = sqlx::query_scalar("SELECT id FROM sessions WHERE id = $1")
+            .bind(remove_id)
+            .fetch_optional(&pool)
+            .await
+            .unwrap();
+        assert_eq!(removed, None);
+
+        let missing = merge(&pool, SessionMerge { keep_id, remove_id }).await.unwrap_err();
+        assert!(matches!(missing.downcast_ref::<SessionErr>(), Some(SessionErr::DoesNotExist(_))));
+        let itself = merge(&pool, SessionMerge { keep_id, remove_id: keep_id }).await.unwrap_err();
+        assert!(matches!(itself.downcast_ref::<SessionErr>(), Some(SessionErr::MergedIntoItself(_))));
+    }
+}
@@ -0,0 +1,402 @@
+use crate::types::ApiStatusCode;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::NaiveDate;
+use hmac::{Hmac, Mac};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use sha2::Sha256;
+use sqlx::{PgExecutor, Pool, Postgres};
+use std::error::Error;
+use std::fmt::Write;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error, ToSchema, Serialize)]
+/// An enumeration of possible errors that can occur when working with the settings.
+///
+/// # Variants
+/// - `EmptyEventName` - The event name is blank
+/// - `InvalidEventDate` - The event date isn't a `YYYY-MM-DD` date
+/// - `IoError` - The settings couldn't be read or saved
+pub enum SettingsErr {
+    #[error("The event name can't be empty")]
+    EmptyEventName,
+    #[error("Invalid event date {0}, expected YYYY-MM-DD")]
+    InvalidEventDate(String),
+    #[error("Settings error: {0}")]
+    IoError(String),
+}
+
+/// Struct representing an error that occurred when working with the settings.
+///
+/// # Fields
+/// - `status` - The HTTP status code associated with the error
+/// - `error` - A string describing the specific error that occurred
+#[derive(Debug, ToSchema)]
+pub struct SettingsError {
+    pub status: ApiStatusCode,
+    pub error: String,
+}
+
+/// Implements the `Serialize` trait for `SettingsError`
+///
+/// This implementation serializes a `SettingsError` into a JSON object with two properties:
+/// `status` and `error`.
+impl Serialize for SettingsError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let status: String = self.status.to_string();
+        let mut state = serializer.serialize_struct("SettingsError", 2)?;
+        state.serialize_field("status", &status)?;
+        state.serialize_field("error", &self.error)?;
+        state.end()
+    }
+}
+
+impl SettingsError {
+    /// Creates a `Response` instance from a `StatusCode` and `SettingsErr`.
+    ///
+    /// # Parameters
+    /// - `status`: The HTTP status code.
+    /// - `error`: The `SettingsErr` instance.
+    ///
+    /// # Returns
+    /// `Response` instance with the status code and JSON body containing the error.
+    pub fn response(status: ApiStatusCode, error: Box<dyn Error>) -> Response {
+        let error = SettingsError {
+            status,
+            error: error.to_string(),
+        };
+
+        let http_status = StatusCode::from_u16(status.0)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        (http_status, Json(error)).into_response()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+/// Struct representing whether the schedule is locked
+///
+/// # Fields
+/// - `locked` - `true` if the schedule can't be edited
+pub struct ScheduleLock {
+    pub locked: bool,
+}
+
+/// Checks whether the schedule is locked against edits.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing `true` if the schedule is locked, otherwise a `sqlx::Error`.
+pub async fn schedule_locked(db_pool: &Pool<Postgres>) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!("SELECT schedule_locked FROM settings")
+        .fetch_one(db_pool)
+        .await
+}
+
+/// Locks or unlocks the schedule.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `locked` - Whether the schedule should be locked
+///
+/// # Returns
+/// A `Result` containing the new `ScheduleLock`, otherwise a `sqlx::Error`.
+pub async fn schedule_locked_set(db_pool: &Pool<Postgres>, locked: bool) -> Result<ScheduleLock, sqlx::Error> {
+    let locked = sqlx::query_scalar!(
+        "UPDATE settings SET schedule_locked = $1 RETURNING schedule_locked",
+        locked,
+    )
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(ScheduleLock { locked })
+}
+
+/// Retrieves the key voter and speaker pseudonyms are derived with.
+///
+/// The key is generated at random when the database is set up and never leaves it, so pseudonyms
+/// stay the same across exports but can't be traced back to the user IDs.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the key, otherwise a `sqlx::Error`.
+pub async fn pseudonym_key_get(db_pool: impl PgExecutor<'_>) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar!("SELECT pseudonym_key FROM settings")
+        .fetch_one(db_pool)
+        .await
+}
+
+/// Derives the pseudonym a user is listed under in an anonymized export.
+///
+/// The pseudonym is an HMAC-SHA256 of the label and the user ID, so it stays the same across
+/// exports without revealing the ID or when the user registered to anyone without the key. The
+/// label is part of the HMAC so a user's pseudonyms under different labels can't be matched up.
+///
+/// # Parameters
+/// - `key` - The key from `pseudonym_key_get`
+/// - `label` - What the user is listed as, such as "Voter" or "Speaker"
+/// - `user_id` - The ID of the user
+///
+/// # Returns
+/// The label followed by the first 16 hex digits of the HMAC.
+pub fn user_pseudonym(key: &str, label: &str, user_id: i32) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(label.as_bytes());
+    mac.update(&user_id.to_be_bytes());
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .take(8)
+        .fold(format!("{label} "), |mut pseudonym, byte| {
+            let _ = write!(pseudonym, "{byte:02x}");
+            pseudonym
+        })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+/// Struct representing whether new sessions can be submitted
+///
+/// # Fields
+/// - `open` - `true` if users can submit sessions, staff and admins can always add them
+pub struct SubmissionsStatus {
+    pub open: bool,
+}
+
+/// Checks whether users can submit new sessions.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing `true` if submissions are open, otherwise a `sqlx::Error`.
+pub async fn submissions_open(db_pool: &Pool<Postgres>) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!("SELECT submissions_open FROM settings")
+        .fetch_one(db_pool)
+        .await
+}
+
+/// Opens or closes session submissions.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `open` - Whether users can submit sessions
+///
+/// # Returns
+/// A `Result` containing the new `SubmissionsStatus`, otherwise a `sqlx::Error`.
+pub async fn submissions_open_set(db_pool: &Pool<Postgres>, open: bool) -> Result<SubmissionsStatus, sqlx::Error> {
+    let open = sqlx::query_scalar!(
+        "UPDATE settings SET submissions_open = $1 RETURNING submissions_open",
+        open,
+    )
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(SubmissionsStatus { open })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+/// Struct representing the name, date and description the event is presented with
+///
+/// # Fields
+/// - `name` - The name of the event
+/// - `date` - The day the event takes place on, if it's been set
+/// - `description` - A short description of the event
+pub struct EventSettings {
+    pub name: String,
+    pub date: Option<NaiveDate>,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+/// Struct representing a request to change the event settings
+///
+/// # Fields
+/// - `name` - The name of the event, can't be blank
+/// - `date` - The day the event takes place on as `YYYY-MM-DD`, clears the date when `None`
+/// - `description` - A short description of the event, empty when `None`
+pub struct EventSettingsUpdate {
+    pub name: String,
+    pub date: Option<String>,
+    pub description: Option<String>,
+}
+
+impl EventSettingsUpdate {
+    /// Checks the update and turns it into the settings to save.
+    ///
+    /// # Returns
+    /// A `Result` containing the `EventSettings` with the name and description trimmed, otherwise
+    /// a `SettingsErr` describing what is invalid.
+    pub fn validate(self) -> Result<EventSettings, SettingsErr> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(SettingsErr::EmptyEventName);
+        }
+
+        let date = self.date
+            .map(|date| NaiveDate::from_str(date.trim()).map_err(|_| SettingsErr::InvalidEventDate(date)))
+            .transpose()?;
+
+        Ok(EventSettings {
+            name: name.to_string(),
+            date,
+            description: self.description.unwrap_or_default().trim().to_string(),
+        })
+    }
+}
+
+/// Retrieves the event settings.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the `EventSettings`, otherwise a `sqlx::Error`.
+pub async fn event_settings_get(db_pool: &Pool<Postgres>) -> Result<EventSettings, sqlx::Error> {
+    sqlx::query_as!(EventSettings, "SELECT name, date, description FROM event_settings")
+        .fetch_one(db_pool)
+        .await
+}
+
+/// Validates and saves the event settings.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `update` - The new event settings
+///
+/// # Returns
+/// A `Result` containing the saved `EventSettings`, otherwise a `SettingsErr`.
+///
+/// # Errors
+/// If the name is blank a `SettingsErr::EmptyEventName` error is returned, if the date can't be
+/// parsed a `SettingsErr::InvalidEventDate` error is returned and if the query fails a
+/// `SettingsErr::IoError` is returned.
+pub async fn event_settings_set(db_pool: &Pool<Postgres>, update: EventSettingsUpdate) -> Result<EventSettings, SettingsErr> {
+    let settings = update.validate()?;
+
+    sqlx::query_as!(
+        EventSettings,
+        "UPDATE event_settings SET name = $1, date = $2, description = $3 RETURNING name, date, description",
+        settings.name,
+        settings.date,
+        settings.description,
+    )
+        .fetch_one(db_pool)
+        .await
+        .map_err(|e| SettingsErr::IoError(e.to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+/// Struct representing the event-level settings a frontend needs to adapt its UI
+///
+/// # Fields
+/// - `schedule_locked` - `true` if the schedule can't be edited
+/// - `submissions_open` - `true` if users can submit sessions
+/// - `rooms` - The number of rooms
+/// - `timeslots` - The number of timeslots
+/// - `event` - The name, date and description of the event
+pub struct EventConfig {
+    pub schedule_locked: bool,
+    pub submissions_open: bool,
+    pub rooms: i64,
+    pub timeslots: i64,
+    pub event: EventSettings,
+}
+
+/// Retrieves the event-level settings.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+///
+/// # Returns
+/// A `Result` containing the `EventConfig`, otherwise a `sqlx::Error`.
+pub async fn event_config_get(db_pool: &Pool<Postgres>) -> Result<EventConfig, sqlx::Error> {
+    let settings = sqlx::query!(
+        r#"SELECT
+            schedule_locked,
+            submissions_open,
+            (SELECT COUNT(*) FROM rooms) as "rooms!",
+            (SELECT COUNT(*) FROM time_slots) as "timeslots!"
+        FROM settings"#
+    )
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(EventConfig {
+        schedule_locked: settings.schedule_locked,
+        submissions_open: settings.submissions_open,
+        rooms: settings.rooms,
+        timeslots: settings.timeslots,
+        event: event_settings_get(db_pool).await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    fn default_event() -> EventSettings {
+        EventSettings { name: "Unconference".to_string(), date: None, description: String::new() }
+    }
+
+    #[sqlx::test]
+    async fn event_config_counts_rooms_and_timeslots(pool: PgPool) {
+        assert_eq!(event_config_get(&pool).await.unwrap(), EventConfig { schedule_locked: false, submissions_open: true, rooms: 0, timeslots: 0, event: default_event() });
+
+        sqlx::query("INSERT INTO rooms (name, location, available_spots) VALUES ('Room A', 'Here', 10), ('Room B', 'There', 10)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO time_slots (start_time, end_time, duration) VALUES ('09:00', '10:00', '1 hour')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        schedule_locked_set(&pool, true).await.unwrap();
+        submissions_open_set(&pool, false).await.unwrap();
+
+        assert_eq!(event_config_get(&pool).await.unwrap(), EventConfig { schedule_locked: true, submissions_open: false, rooms: 2, timeslots: 1, event: default_event() });
+    }
+
+    #[sqlx::test]
+    async fn submissions_can_be_closed_and_reopened(pool: PgPool) {
+        assert!(submissions_open(&pool).await.unwrap());
+
+        assert_eq!(submissions_open_set(&pool, false).await.unwrap(), SubmissionsStatus { open: false });
+        assert!(!submissions_open(&pool).await.unwrap());
+
+        assert_eq!(submissions_open_set(&pool, true).await.unwrap(), SubmissionsStatus { open: true });
+        assert!(submissions_open(&pool).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn event_settings_are_validated_and_saved(pool: PgPool) {
+        assert_eq!(event_settings_get(&pool).await.unwrap(), default_event());
+
+        let update = |name: &str, date: Option<&str>| EventSettingsUpdate {
+            name: name.to_string(),
+            date: date.map(str::to_string),
+            description: Some(" A day of talks ".to_string()),
+        };
+        assert_eq!(event_settings_set(&pool, update("  ", None)).await, Err(SettingsErr::EmptyEventName));
+        assert_eq!(event_settings_set(&pool, update("RustConf", Some("17/10/2026"))).await, Err(SettingsErr::InvalidEventDate("17/10/2026".to_string())));
+        assert_eq!(event_settings_get(&pool).await.unwrap(), default_event());
+
+        let saved = EventSettings {
+            name: "RustConf".to_string(),
+            date: NaiveDate::from_ymd_opt(2026, 10, 17),
+            description: "A day of talks".to_string(),
+        };
+        assert_eq!(event_settings_set(&pool, update(" RustConf ", Some("2026-10-17"))).await, Ok(saved.clone()));
+        assert_eq!(event_config_get(&pool).await.unwrap().event, saved);
+    }
+}
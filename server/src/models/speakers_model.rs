@@ -0,0 +1,131 @@
+use crate::types::Pagination;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Postgres};
+use utoipa::ToSchema;
+
+/// Struct representing a user who submitted at least one session.
+///
+/// # Fields
+/// - `user_id` - The ID of the user
+/// - `fname` - The user's first name
+/// - `lname` - The user's last name
+/// - `email` - The user's email
+/// - `sessions` - How many sessions the user submitted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema, FromRow)]
+pub struct Speaker {
+    pub user_id: i32,
+    pub fname: String,
+    pub lname: String,
+    pub email: String,
+    pub sessions: i64,
+}
+
+/// Struct representing the search for the speakers list.
+///
+/// # Fields
+/// - `q` - Only list speakers whose name or email contains this, ignoring case
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct SpeakerQuery {
+    pub q: Option<String>,
+}
+
+/// Escapes the characters `ILIKE` treats as wildcards, so a search only matches what was typed.
+fn like_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Retrieves the speakers, or a page of them, ordered by last name, first name and ID.
+///
+/// # Parameters
+/// - `db_pool` - The database connection pool
+/// - `pagination` - The page of speakers to retrieve, every speaker when `None`
+/// - `search` - Only list speakers whose first name, last name, full name or email contains this,
+///   ignoring case. Every speaker is listed when `None` or blank
+///
+/// # Returns
+/// A `Result` containing the speakers, empty if none match, or an error if the query
+/// fails.
+///
+/// # Errors
+/// If the query fails, a `sqlx::Error` is returned.
+pub async fn speakers_get_paginated(
+    db_pool: &Pool<Postgres>,
+    pagination: Option<Pagination>,
+    search: Option<&str>,
+) -> Result<Vec<Speaker>, sqlx::Error> {
+    let pattern = search
+        .map(str::trim)
+        .filter(|search| !search.is_empty())
+        .map(like_pattern);
+
+    sqlx::query_as!(
+        Speaker,
+        r#"SELECT u.id as user_id, u.fname, u.lname, u.email, COUNT(s.id) as "sessions!"
+        FROM users u
+        JOIN sessions s ON s.user_id = u.id
+        WHERE $1::TEXT IS NULL
+            OR u.fname ILIKE $1
+            OR u.lname ILIKE $1
+            OR u.fname || ' ' || u.lname ILIKE $1
+            OR u.email ILIKE $1
+        GROUP BY u.id
+        ORDER BY u.lname, u.fname, u.id
+        LIMIT $2 OFFSET $3"#,
+        pattern,
+        pagination.map(|pagination| pagination.limit),
+        pagination.map_or(0, |pagination| pagination.offset()),
+    )
+        .fetch_all(db_pool)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_session, insert_user};
+    use sqlx::PgPool;
+
+    async fn names(pool: &PgPool, pagination: Option<Pagination>, search: Option<&str>) -> Vec<String> {
+        speakers_get_paginated(pool, pagination, search)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|speaker| format!("{} {}", speaker.fname, speaker.lname))
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn speakers_can_be_searched_and_paged(pool: PgPool) {
+        for (fname, lname, email, sessions) in [
+            ("Grace", "Hopper", "grace@navy.example", 2),
+            ("Ada", "Lovelace", "ada@example.com", 1),
+            ("Alan", "Turing", "alan@example.com", 0),
+            ("Edsger", "Dijkstra", "edsger_d@example.com", 1),
+        ] {
+            let user_id = insert_user(&pool, fname, lname, email).await;
+            for i in 0..sessions {
+                insert_session(&pool, user_id, &format!("Talk {i}")).await;
+            }
+        }
+
+        assert_eq!(names(&pool, None, None).await, vec!["Edsger Dijkstra", "Grace Hopper", "Ada Lovelace"]);
+        assert_eq!(names(&pool, Some(Pagination { page: 2, limit: 2 }), None).await, vec!["Ada Lovelace"]);
+        assert_eq!(names(&pool, None, Some("  ")).await.len(), 3);
+
+        assert_eq!(names(&pool, None, Some("LOVE")).await, vec!["Ada Lovelace"]);
+        assert_eq!(names(&pool, None, Some("grace hop")).await, vec!["Grace Hopper"]);
+        assert_eq!(names(&pool, None, Some("navy.example")).await, vec!["Grace Hopper"]);
+        assert_eq!(names(&pool, None, Some("_d@")).await, vec!["Edsger Dijkstra"]);
+
+        assert!(names(&pool, None, Some("Turing")).await.is_empty());
+        assert!(names(&pool, None, Some("%")).await.is_empty());
+        assert!(names(&pool, Some(Pagination { page: 3, limit: 2 }), None).await.is_empty());
+
+        let hopper = speakers_get_paginated(&pool, None, Some("Hopper")).await.unwrap();
+        assert_eq!(hopper[0].sessions, 2);
+    }
+}
@@ -1,10 +1,11 @@
-use crate::middleware::auth::AuthInfo;
+use crate::middleware::auth::{AuthInfo, AuthSessionLayer};
+use crate::models::session_tags_model::add_session_tag;
 use crate::types::ApiStatusCode;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
-use sqlx::{FromRow, Pool, Postgres};
+use sqlx::{FromRow, PgExecutor, Pool, Postgres};
 use std::error::Error;
 use utoipa::ToSchema;
 
@@ -22,6 +23,8 @@ pub enum TagErr {
     TagNotFoundByName(String),
     #[error("Tag with name '{0}' already exists")]
     TagAlreadyExists(String),
+    #[error("A tag rule's keyword can't be blank")]
+    BlankKeyword,
     #[error("User does not have access to manage tags")]
     UnAuthorizedAccess(String),
     #[error("Unexpected error during query '{0}'")]
@@ -109,7 +112,7 @@ impl TagError {
 ///
 /// # Errors
 /// If the query fails, a boxed error is returned.
-pub async fn get_all_tags(db_pool: &Pool<Postgres>) -> Result<Vec<Tag>, Box<dyn Error>> {
+pub async fn get_all_tags(db_pool: impl PgExecutor<'_>) -> Result<Vec<Tag>, Box<dyn Error>> {
     let tags = sqlx::query_as!(
         Tag,
         "SELECT * FROM tags"
@@ -252,10 +255,9 @@ pub(crate) async fn update_tag(
     let _ = get_tag_by_id(db_pool, tag_id).await?;
 
     // Check if new name already exists and if it is not the same ID as the original return an Err
-    if let Ok(existing_tag_with_name) = get_tag_by_name(db_pool, new_tag_name).await {
-        if existing_tag_with_name.id != tag_id {
-            return Err(Box::new(TagErr::TagAlreadyExists(new_tag_name.to_string())));
-        }
+    if let Ok(existing_tag_with_name) = get_tag_by_name(db_pool, new_tag_name).await
+        && existing_tag_with_name.id != tag_id {
+        return Err(Box::new(TagErr::TagAlreadyExists(new_tag_name.to_string())));
     }
 
     let updated_tag = sqlx::query_as!(
@@ -323,4 +325,148 @@ pub(crate) async fn delete_tag(
     tracing::info!("Deleted tag: {} with ID: {}", tag.tag_name, tag_id);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, FromRow)]
+pub struct TagRule {
+    pub id: i32,
+    pub keyword: String,
+    pub tag_id: i32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TagRulesApplied {
+    pub applied: i32,
+}
+
+/// Creates a keyword rule for automatically tagging sessions
+///
+/// # Parameters
+/// - `db_pool`: Database connection pool
+/// - `keyword`: The keyword to look for in session titles, surrounding whitespace is trimmed
+/// - `tag_id`: The ID of the tag to apply to matching sessions
+///
+/// # Returns
+/// The newly created `TagRule`
+///
+/// # Errors
+/// Returns `BlankKeyword` if the keyword is empty or only whitespace, since it would match every
+/// session, `TagNotFound` if there isn't a tag with the specified ID, or an error if the query
+/// fails.
+pub(crate) async fn create_tag_rule(
+    db_pool: &Pool<Postgres>,
+    keyword: &str,
+    tag_id: i32,
+) -> Result<TagRule, Box<dyn Error>> {
+    let keyword = keyword.trim();
+    if keyword.is_empty() {
+        return Err(Box::new(TagErr::BlankKeyword));
+    }
+    let _ = get_tag_by_id(db_pool, tag_id).await?;
+
+    let rule = sqlx::query_as!(
+        TagRule,
+        "INSERT INTO tag_rules (keyword, tag_id) VALUES ($1, $2) RETURNING *",
+        keyword,
+        tag_id,
+    )
+        .fetch_one(db_pool)
+        .await?;
+
+    tracing::info!("Created tag rule: '{}' -> tag ID: {}", keyword, tag_id);
+
+    Ok(rule)
+}
+
+/// Finds the session tags the keyword rules would add
+///
+/// A session matches a rule when its title contains the rule's keyword, ignoring case. Sessions
+/// that already have the rule's tag are skipped.
+///
+/// # Parameters
+/// - `db_pool`: Database connection pool
+///
+/// # Returns
+/// A `Vec` of `(session_id, tag_id)` pairs still to be applied
+///
+/// # Errors
+/// If the query fails, a boxed error is returned.
+pub async fn tag_rule_matches_get(db_pool: &Pool<Postgres>) -> Result<Vec<(i32, i32)>, Box<dyn Error>> {
+    let matches = sqlx::query!(
+        r#"
+        SELECT DISTINCT s.id as session_id, tr.tag_id
+        FROM sessions s
+        JOIN tag_rules tr ON strpos(lower(s.title), lower(tr.keyword)) > 0
+        WHERE NOT EXISTS (
+            SELECT 1 FROM session_tags st WHERE st.session_id = s.id AND st.tag_id = tr.tag_id
+        )
+        ORDER BY s.id, tr.tag_id
+        "#
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    Ok(matches.into_iter().map(|m| (m.session_id, m.tag_id)).collect())
+}
+
+/// Applies the keyword rules to every session
+///
+/// # Parameters
+/// - `db_pool`: Database connection pool
+/// - `auth_session`: Authentication session for authorization
+/// - `auth_info`: An instance of `AuthInfo`
+///
+/// # Returns
+/// The number of tags that were applied
+///
+/// # Errors
+/// Returns an error if the user isn't authorized to manage tags or a tag can't be applied.
+pub(crate) async fn apply_tag_rules(
+    db_pool: &Pool<Postgres>,
+    auth_session: AuthSessionLayer,
+    auth_info: AuthInfo,
+) -> Result<TagRulesApplied, Box<dyn Error>> {
+    if !auth_info.is_staff_or_admin {
+        return Err(Box::new(TagErr::UnAuthorizedAccess(
+            "Attempted to apply tag rules".to_string()
+        )));
+    }
+
+    let matches = tag_rule_matches_get(db_pool).await?;
+    let mut applied = 0;
+    for (session_id, tag_id) in matches {
+        add_session_tag(db_pool, auth_session.clone(), auth_info.clone(), session_id, tag_id).await?;
+        applied += 1;
+    }
+
+    tracing::info!("Applied {} tags from tag rules", applied);
+
+    Ok(TagRulesApplied { applied })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_session, insert_session_with, insert_tag, insert_user};
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn tag_rule_matches_skip_tagged_sessions(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let rust_tag = insert_tag(&pool, "Rust").await;
+        let matching_id = insert_session(&pool, user_id, "Intro to RUST").await;
+        let tagged_id = insert_session(&pool, user_id, "Rust macros").await;
+        insert_session_with(&pool, user_id, "Gardening", "rust on tools", 0).await;
+        sqlx::query("INSERT INTO session_tags (session_id, tag_id) VALUES ($1, $2)")
+            .bind(tagged_id)
+            .bind(rust_tag)
+            .execute(&pool)
+            .await
+            .unwrap();
+        create_tag_rule(&pool, "rust", rust_tag).await.unwrap();
+
+        let matches = tag_rule_matches_get(&pool).await.unwrap();
+
+        assert_eq!(matches, vec![(matching_id, rust_tag)]);
+    }
+}
@@ -1,17 +1,18 @@
+use crate::config::SchedulerConfig;
 use crate::models::room_model::{rooms_get, Room};
-use crate::models::schedule_model::ScheduleErr;
+use crate::models::schedule_model::{schedule_run_insert, ProposedAssignment, ScheduleErr};
 use crate::models::sessions_model::Session;
 use crate::models::timeslot_model::{timeslot_get, ExistingTimeslot, TimeslotAssignmentForm, TimeslotAssignmentSessionAdd, TimeslotRequest};
 use chrono::NaiveTime;
-use scheduler::{RoomTimeAssignment, ScheduleRow, SchedulerData, SessionData};
+use scheduler::{ProgressCallback, RoomTimeAssignment, ScheduleRow, SchedulerData, SessionData};
 use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Postgres};
 use std::{
-    collections::HashSet,
-    env::var,
+    collections::{HashMap, HashSet},
     error::Error,
+    str::FromStr,
     sync::{atomic::{AtomicBool, Ordering}, Arc},
-    time::{Duration, Instant}
+    time::Instant
 };
 use tracing::info;
 use utoipa::ToSchema;
@@ -37,65 +38,205 @@ pub async fn session_already_scheduled(db_pool: &Pool<Postgres>, session_id: i32
 }
 
 pub async fn space_to_add_session(db_pool: &Pool<Postgres>) -> Result<bool, ScheduleErr> {
-    let total_possible_timeslots = sqlx::query_scalar!(
-        "SELECT COUNT(*) FROM time_slots ts CROSS JOIN rooms r",
-    )
-        .fetch_one(db_pool)
-        .await
-        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
-
-    let assigned_slots = sqlx::query_scalar!(
-        "SELECT COUNT(*) FROM timeslot_assignments",
-    )
-        .fetch_one(db_pool)
-        .await
-        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+    Ok(!get_all_unassigned_timeslots(db_pool).await?.is_empty())
+}
 
-    Ok(assigned_slots < total_possible_timeslots)
+/// Struct representing a room and timeslot that a session can still be scheduled in.
+///
+/// # Fields
+/// - `time_slot_id` - The ID of the timeslot
+/// - `start_time` - When the timeslot starts
+/// - `room_id` - The ID of the room
+/// - `room_name` - The name of the room
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FreeCell {
+    pub time_slot_id: i32,
+    pub start_time: NaiveTime,
+    pub room_id: i32,
+    pub room_name: String,
 }
 
-pub async fn get_all_unassigned_timeslots(db_pool: &Pool<Postgres>) -> Result<Vec<TimeslotAssignmentSessionAdd>, ScheduleErr> {
-    let unassigned_timeslots = sqlx::query_as!(
-        TimeslotAssignmentSessionAdd,
+/// Retrieves the rooms and timeslots that a session can still be scheduled in.
+///
+/// When a timeslot has `max_open_rooms` set only that many of its rooms can be used. Rooms that
+/// already hold a session count towards the cap and the rest of the free rooms, in room order, are
+/// left out.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+///
+/// # Returns
+/// A `Result` containing the free cells ordered by start time and room, otherwise a `ScheduleErr`
+/// error.
+pub async fn free_cells_get(db_pool: &Pool<Postgres>) -> Result<Vec<FreeCell>, ScheduleErr> {
+    sqlx::query_as!(
+        FreeCell,
         r#"
         SELECT
-            ts.id as time_slot_id,
-            NULL::INTEGER as session_id,
-            r.id as room_id
-        FROM time_slots ts
-        CROSS JOIN rooms r
-        WHERE NOT EXISTS (
-            SELECT 1
-            FROM timeslot_assignments ta
-            WHERE ta.time_slot_id = ts.id
-            AND ta.room_id = r.id
-        )
-        ORDER BY ts.start_time, r.id
+            free.time_slot_id as "time_slot_id!",
+            free.start_time as "start_time!",
+            free.room_id as "room_id!",
+            free.room_name as "room_name!"
+        FROM (
+            SELECT
+                ts.id as time_slot_id,
+                r.id as room_id,
+                r.name as room_name,
+                ts.start_time,
+                ts.max_open_rooms,
+                ROW_NUMBER() OVER (PARTITION BY ts.id ORDER BY r.id) as free_idx,
+                (SELECT COUNT(*) FROM timeslot_assignments ta WHERE ta.time_slot_id = ts.id) as occupied
+            FROM time_slots ts
+            CROSS JOIN rooms r
+            WHERE NOT EXISTS (
+                SELECT 1
+                FROM timeslot_assignments ta
+                WHERE ta.time_slot_id = ts.id
+                AND ta.room_id = r.id
+            )
+        ) free
+        WHERE free.max_open_rooms IS NULL OR free.occupied + free.free_idx <= free.max_open_rooms
+        ORDER BY free.start_time, free.room_id
         "#
     )
         .fetch_all(db_pool)
         .await
-        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))
+}
+
+/// Retrieves the rooms and timeslots that a session can still be scheduled in, see
+/// `free_cells_get`.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+///
+/// # Returns
+/// A `Result` containing the free room and timeslot pairs ordered by start time and room, otherwise
+/// a `ScheduleErr` error.
+pub async fn get_all_unassigned_timeslots(db_pool: &Pool<Postgres>) -> Result<Vec<TimeslotAssignmentSessionAdd>, ScheduleErr> {
+    let unassigned_timeslots = free_cells_get(db_pool)
+        .await?
+        .into_iter()
+        .map(|cell| TimeslotAssignmentSessionAdd {
+            time_slot_id: cell.time_slot_id,
+            session_id: None,
+            room_id: cell.room_id,
+        })
+        .collect();
 
     Ok(unassigned_timeslots)
 }
 
+/// Which scheduler generates schedules
+///
+/// # Variants
+/// - `Original` - Fills the free slots with the unassigned sessions in order
+/// - `LocalSearch` - Searches for the schedule with the lowest score
+/// - `MaximizeCoverage` - Schedules the most voted sessions first, then searches for the lowest
+///   score without taking any of them off the schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SchedulingMethod {
     Original,
     LocalSearch,
+    MaximizeCoverage,
 }
 
-impl SchedulingMethod {
-    
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> SchedulingMethod {
-        let scheduling_method = var("SCHEDULING_METHOD")
-            .unwrap_or(String::from("Original"));
-
-        match scheduling_method.to_lowercase().as_str() {
-            "original" => SchedulingMethod::Original,
-            "localsearch" => SchedulingMethod::LocalSearch,
-            _ => SchedulingMethod::Original,
+impl FromStr for SchedulingMethod {
+    type Err = String;
+
+    /// Parses `original`, `localsearch` or `maximizecoverage`, ignoring case.
+    fn from_str(method: &str) -> Result<Self, Self::Err> {
+        match method.to_lowercase().as_str() {
+            "original" => Ok(SchedulingMethod::Original),
+            "localsearch" => Ok(SchedulingMethod::LocalSearch),
+            "maximizecoverage" => Ok(SchedulingMethod::MaximizeCoverage),
+            _ => Err(format!("unknown scheduling method {method}")),
+        }
+    }
+}
+
+/// How many random fills of the schedule the penalty ranges are sampled from when they are
+/// normalized
+pub const NORMALIZATION_SAMPLES: usize = 50;
+
+/// How the local search scheduler scales its penalties before weighing them
+///
+/// # Variants
+/// - `Raw` - The penalties are weighed as they are
+/// - `MinMax` - Each penalty is scaled by how far it ranges across random fills of the schedule,
+///   so the weights compare the penalties on an equal footing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PenaltyNormalization {
+    #[default]
+    Raw,
+    MinMax,
+}
+
+impl FromStr for PenaltyNormalization {
+    type Err = String;
+
+    /// Parses `raw` or `minmax`, ignoring case.
+    fn from_str(normalization: &str) -> Result<Self, Self::Err> {
+        match normalization.to_lowercase().as_str() {
+            "raw" => Ok(PenaltyNormalization::Raw),
+            "minmax" => Ok(PenaltyNormalization::MinMax),
+            _ => Err(format!("unknown normalization {normalization}")),
+        }
+    }
+}
+
+/// How a session's votes are scaled before the scheduler weighs them
+///
+/// The scaled curves are stretched so the most voted session keeps its votes, which keeps the
+/// penalties on the same scale as with raw votes. Sessions keep their order, only the gaps between
+/// them change.
+///
+/// # Variants
+/// - `Linear` - The votes are used as they are
+/// - `Log` - Each extra vote counts for less, so a few very popular sessions don't dominate the
+///   schedule
+/// - `Sqrt` - Between linear and log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VoteScaling {
+    #[default]
+    Linear,
+    Log,
+    Sqrt,
+}
+
+impl VoteScaling {
+    /// Scales a session's votes
+    ///
+    /// # Parameters
+    /// - `num_votes` - The votes of the session
+    /// - `max_votes` - The most votes any session has
+    ///
+    /// # Returns
+    /// The scaled votes, rounded to the nearest whole vote
+    pub fn scale(self, num_votes: i32, max_votes: i32) -> i32 {
+        let curve = match self {
+            VoteScaling::Linear => return num_votes,
+            VoteScaling::Log => |votes: f64| votes.ln_1p(),
+            VoteScaling::Sqrt => f64::sqrt,
+        };
+        if max_votes <= 0 {
+            return num_votes;
+        }
+
+        (max_votes as f64 * curve(num_votes.max(0) as f64) / curve(max_votes as f64)).round() as i32
+    }
+}
+
+impl FromStr for VoteScaling {
+    type Err = String;
+
+    /// Parses `linear`, `log` or `sqrt`, ignoring case.
+    fn from_str(scaling: &str) -> Result<Self, Self::Err> {
+        match scaling.to_lowercase().as_str() {
+            "linear" => Ok(VoteScaling::Linear),
+            "log" => Ok(VoteScaling::Log),
+            "sqrt" => Ok(VoteScaling::Sqrt),
+            _ => Err(format!("unknown vote scaling {scaling}")),
         }
     }
 }
@@ -104,6 +245,7 @@ impl SchedulingMethod {
 pub struct UnassignedSession {
     pub session_id: i32,
     pub tag_id: Option<i32>,
+    pub is_keynote: bool,
 }
 
 pub struct SessionAssignmentData {
@@ -123,6 +265,8 @@ pub struct SessionAssignmentData {
 /// - `rooms`: A slice of `Room` instances representing the rooms to assign the sessions to
 /// - `existing_timeslots`: A slice of `TimeSlot` instances representing the existing timeslots
 /// - `schedule_id`: The ID of the schedule to assign the timeslots to
+/// - `config`: How to generate the schedule
+/// - `progress_callback`: Called with the progress of the local search scheduler after each restart
 ///
 /// # Returns
 /// A `Result` containing a vector of `TimeSlot` instances with the sessions assigned if successful,
@@ -135,11 +279,99 @@ pub async fn assign_sessions_to_timeslots(
     _rooms: &[Room],
     _existing_timeslots: &[ExistingTimeslot],
     db_pool: &Pool<Postgres>,
+    config: &SchedulerConfig,
+    progress_callback: Option<ProgressCallback>,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut scheduling_data = session_assignment_data_get(sessions, db_pool).await?;
+    vote_floor_apply(db_pool, &mut scheduling_data, config.min_votes_to_schedule).await?;
+
+    match config.method {
+        SchedulingMethod::Original => {
+            tracing::info!("Using original scheduling method");
+            let pairings: Vec<(TimeslotAssignmentSessionAdd, i32)> = scheduling_data.available_room_time_associations
+                .into_iter()
+                .zip(scheduling_data.unassigned_sessions.iter().map(|session| session.session_id))
+                .collect();
+
+            tracing::trace!("pairings: {:?}", pairings);
+
+            original_scheduling(db_pool, pairings).await
+        },
+        SchedulingMethod::LocalSearch | SchedulingMethod::MaximizeCoverage => {
+            tracing::info!("Using {:?} scheduling method", config.method);
+            match local_search_scheduling(db_pool, scheduling_data, config, progress_callback).await {
+                Ok(_) => {
+                    Ok(())
+                },
+                Err(e) => {
+                    tracing::info!("Error generating schedule {:?}", e);
+                    Err(Box::new(ScheduleErr::IoError(e.to_string())))
+                },
+            }
+        },
+    }
+}
+
+/// Removes the sessions with fewer than `min_votes` votes from the sessions waiting to be scheduled.
+///
+/// Sessions that are already on the schedule are left alone, so a session below the threshold can
+/// still be pinned by hand.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `scheduling_data`: The assigned and unassigned sessions
+/// - `min_votes`: The minimum number of votes a session needs to be scheduled
+///
+/// # Returns
+/// A `Result` containing nothing if successful, otherwise a `sqlx::Error`.
+pub async fn vote_floor_apply(
+    db_pool: &Pool<Postgres>,
+    scheduling_data: &mut SessionAssignmentData,
+    min_votes: i32,
+) -> Result<(), sqlx::Error> {
+    if min_votes <= 0 {
+        return Ok(());
+    }
+
+    let vote_counts: HashMap<i32, i64> = sqlx::query!(
+        r#"SELECT session_id as "session_id!", COUNT(*) as "num_votes!"
+        FROM user_votes
+        GROUP BY session_id"#
+    )
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.session_id, row.num_votes))
+        .collect();
+
+    scheduling_data.unassigned_sessions.retain(|session| {
+        let num_votes = vote_counts.get(&session.session_id).copied().unwrap_or(0);
+        let meets_floor = num_votes >= i64::from(min_votes);
+        if !meets_floor {
+            tracing::info!("Session {} has {} votes, below the minimum of {}", session.session_id, num_votes, min_votes);
+        }
+        meets_floor
+    });
+
+    Ok(())
+}
+
+/// Retrieves the sessions that are already assigned to a room and timeslot.
+///
+/// Every assignment is marked as `already_assigned` and carries the session's vote count, tag, and
+/// speaker so that it can be scored.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+///
+/// # Returns
+/// A `Result` containing a vector of `RoomTimeAssignment` instances if successful, otherwise a
+/// `sqlx::Error`.
+pub async fn assigned_room_times_get(db_pool: &Pool<Postgres>) -> Result<Vec<RoomTimeAssignment>, sqlx::Error> {
     // alias ta for the table timeslot_assignments
     // alias uv for user_votes table
     // alias st for session_tags
-    let all_assigned_sessions: Vec<RoomTimeAssignment> = sqlx::query_as!(
+    sqlx::query_as!(
         RoomTimeAssignment,
         r#"SELECT
             ta.id as "id?",
@@ -148,17 +380,153 @@ pub async fn assign_sessions_to_timeslots(
             ta.room_id as "room_id!",
             true as "already_assigned!",
             COALESCE(COUNT(uv.session_id), 0)::INTEGER as "num_votes!",
-            st.tag_id,
-            s.user_id as speaker_id,
-            ARRAY[]::INTEGER[] as "speaker_votes!"
+            st.tag_id as "tag_id?",
+            s.user_id as "speaker_id?",
+            COALESCE((SELECT ARRAY_AGG(sv.session_id ORDER BY sv.session_id) FROM user_votes sv WHERE sv.user_id = s.user_id), ARRAY[]::INTEGER[]) as "speaker_votes!",
+            COALESCE(s.is_keynote, false) as "is_keynote!",
+            NULL::INTEGER[] as "allowed_slots?",
+            COALESCE(r.available_spots, 0) as "room_capacity!"
         FROM timeslot_assignments ta
-        JOIN user_votes uv ON ta.session_id = uv.session_id
+        LEFT JOIN user_votes uv ON ta.session_id = uv.session_id
         LEFT JOIN session_tags st ON st.session_id = ta.session_id
         LEFT JOIN sessions s ON s.id = ta.session_id
-        GROUP BY ta.id, ta.time_slot_id, ta.session_id, ta.room_id, st.tag_id, s.user_id"#
+        LEFT JOIN rooms r ON r.id = ta.room_id
+        GROUP BY ta.id, ta.time_slot_id, ta.session_id, ta.room_id, st.tag_id, s.user_id, s.is_keynote, r.available_spots"#
     )
         .fetch_all(db_pool)
-        .await?;
+        .await
+}
+
+/// Retrieves the sessions of a proposed grid the way `assigned_room_times_get` retrieves the ones on
+/// the schedule.
+///
+/// Cells pointing at a missing session, room or timeslot are left out.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `grid`: Every cell of the proposed schedule
+///
+/// # Returns
+/// A `Result` containing a vector of `RoomTimeAssignment` instances if successful, otherwise a
+/// `sqlx::Error`.
+pub async fn proposed_room_times_get(db_pool: &Pool<Postgres>, grid: &[ProposedAssignment]) -> Result<Vec<RoomTimeAssignment>, sqlx::Error> {
+    let time_slot_ids: Vec<i32> = grid.iter().map(|cell| cell.time_slot_id).collect();
+    let room_ids: Vec<i32> = grid.iter().map(|cell| cell.room_id).collect();
+    let session_ids: Vec<i32> = grid.iter().map(|cell| cell.session_id).collect();
+
+    sqlx::query_as!(
+        RoomTimeAssignment,
+        r#"SELECT
+            NULL::INTEGER as "id?",
+            ta.time_slot_id as "time_slot_id!",
+            ta.session_id as "session_id!",
+            ta.room_id as "room_id!",
+            true as "already_assigned!",
+            COALESCE(COUNT(uv.session_id), 0)::INTEGER as "num_votes!",
+            st.tag_id as "tag_id?",
+            s.user_id as "speaker_id?",
+            COALESCE((SELECT ARRAY_AGG(sv.session_id ORDER BY sv.session_id) FROM user_votes sv WHERE sv.user_id = s.user_id), ARRAY[]::INTEGER[]) as "speaker_votes!",
+            s.is_keynote as "is_keynote!",
+            NULL::INTEGER[] as "allowed_slots?",
+            r.available_spots as "room_capacity!"
+        FROM UNNEST($1::INTEGER[], $2::INTEGER[], $3::INTEGER[]) WITH ORDINALITY AS ta (time_slot_id, room_id, session_id, position)
+        JOIN sessions s ON s.id = ta.session_id
+        JOIN rooms r ON r.id = ta.room_id
+        JOIN time_slots ts ON ts.id = ta.time_slot_id
+        LEFT JOIN user_votes uv ON ta.session_id = uv.session_id
+        LEFT JOIN session_tags st ON st.session_id = ta.session_id
+        GROUP BY ta.position, ta.time_slot_id, ta.session_id, ta.room_id, st.tag_id, s.user_id, s.is_keynote, r.available_spots
+        ORDER BY ta.position"#,
+        &time_slot_ids,
+        &room_ids,
+        &session_ids,
+    )
+        .fetch_all(db_pool)
+        .await
+}
+
+/// Splits the sessions into the ones on a proposed grid and the ones left off it, the way
+/// `session_assignment_data_get` splits them for the schedule.
+///
+/// The free cells are the ones the grid leaves empty, up to each timeslot's limit of open rooms,
+/// the same as `free_cells_get` finds them on the schedule.
+///
+/// # Parameters
+/// - `sessions`: A slice of `Session` instances representing all the sessions
+/// - `db_pool`: The database connection pool
+/// - `grid`: Every cell of the proposed schedule
+///
+/// # Returns
+/// A `Result` containing the `SessionAssignmentData` if successful, otherwise an error.
+pub async fn proposed_assignment_data_get(
+    sessions: &[Session],
+    db_pool: &Pool<Postgres>,
+    grid: &[ProposedAssignment],
+) -> Result<SessionAssignmentData, Box<dyn Error + Send + Sync>> {
+    let proposed = proposed_room_times_get(db_pool, grid).await?;
+    let mut rooms: Vec<i32> = rooms_get(db_pool)
+        .await?
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|room| room.id)
+        .collect();
+    rooms.sort();
+    let timeslots = timeslot_get(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
+
+    let taken: HashSet<(i32, i32)> = proposed
+        .iter()
+        .map(|item| (item.time_slot_id, item.room_id))
+        .collect();
+    let mut free_roomtimes = Vec::new();
+    for timeslot in &timeslots {
+        let occupied = rooms.iter().filter(|&&room_id| taken.contains(&(timeslot.id, room_id))).count();
+        let open = timeslot.max_open_rooms.map_or(usize::MAX, |max_open_rooms| (max_open_rooms.max(0) as usize).saturating_sub(occupied));
+        free_roomtimes.extend(rooms
+            .iter()
+            .filter(|&&room_id| !taken.contains(&(timeslot.id, room_id)))
+            .take(open)
+            .map(|&room_id| TimeslotAssignmentSessionAdd {
+                time_slot_id: timeslot.id,
+                session_id: None,
+                room_id,
+            }));
+    }
+
+    let used_sessions: HashSet<i32> = proposed
+        .iter()
+        .filter_map(|item| item.session_id)
+        .collect();
+
+    Ok(SessionAssignmentData {
+        already_assigned_room_time_associations: proposed,
+        available_room_time_associations: free_roomtimes,
+        unassigned_sessions: sessions
+            .iter()
+            .filter_map(|session| session.id.filter(|session_id| !used_sessions.contains(session_id)).map(|session_id| UnassignedSession {
+                session_id,
+                tag_id: session.tag_id,
+                is_keynote: session.is_keynote,
+            }))
+            .collect(),
+    })
+}
+
+/// Splits the sessions into the ones already on the schedule and the ones that still need a room
+/// and timeslot.
+///
+/// # Parameters
+/// - `sessions`: A slice of `Session` instances representing all the sessions
+/// - `db_pool`: The database connection pool
+///
+/// # Returns
+/// A `Result` containing the `SessionAssignmentData` if successful, otherwise an error.
+pub async fn session_assignment_data_get(
+    sessions: &[Session],
+    db_pool: &Pool<Postgres>,
+) -> Result<SessionAssignmentData, Box<dyn Error + Send + Sync>> {
+    let all_assigned_sessions = assigned_room_times_get(db_pool).await?;
 
     tracing::trace!("all assigned sessions: {:?}", all_assigned_sessions);
 
@@ -181,45 +549,22 @@ pub async fn assign_sessions_to_timeslots(
     let free_roomtimes = get_all_unassigned_timeslots(db_pool).await?;
     tracing::trace!("free_roomtimes: {:?}", free_roomtimes);
 
-    match SchedulingMethod::new() {
-        SchedulingMethod::Original => {
-            tracing::info!("Using original scheduling method");
-            let pairings: Vec<(TimeslotAssignmentSessionAdd, i32)> = free_roomtimes
-                .into_iter()
-                .zip(free_sessions.copied())
-                .collect();
-
-            tracing::trace!("pairings: {:?}", pairings);
-
-            original_scheduling(db_pool, pairings).await
-        },
-        SchedulingMethod::LocalSearch => {
-            tracing::info!("Using localsearch scheduling method");
-            let scheduling_data = SessionAssignmentData {
-                already_assigned_room_time_associations: all_assigned_sessions,
-                available_room_time_associations: free_roomtimes,
-                unassigned_sessions: free_sessions
-                    .map(|&session_id| {
-                        let tag_id = sessions
-                            .iter()
-                            .find(|s| s.id == Some(session_id))
-                            .and_then(|s| s.tag_id);
-                        UnassignedSession { session_id, tag_id }
-                    })
-                    .collect(),
-            };
-
-            match local_search_scheduling(db_pool, scheduling_data).await {
-                Ok(_) => {
-                    Ok(())
-                },
-                Err(e) => {
-                    tracing::info!("Error generating schedule {:?}", e);
-                    Err(Box::new(ScheduleErr::IoError(e.to_string())))
-                },
-            }
-        },
-    }
+    Ok(SessionAssignmentData {
+        already_assigned_room_time_associations: all_assigned_sessions,
+        available_room_time_associations: free_roomtimes,
+        unassigned_sessions: free_sessions
+            .map(|&session_id| {
+                let session = sessions
+                    .iter()
+                    .find(|s| s.id == Some(session_id));
+                UnassignedSession {
+                    session_id,
+                    tag_id: session.and_then(|s| s.tag_id),
+                    is_keynote: session.is_some_and(|s| s.is_keynote),
+                }
+            })
+            .collect(),
+    })
 }
 
 pub async fn original_scheduling(db_pool: &Pool<Postgres>, pairings: Vec<(TimeslotAssignmentSessionAdd, i32)>) -> Result<(), Box<dyn Error + Send + Sync>> {
@@ -235,36 +580,103 @@ pub async fn original_scheduling(db_pool: &Pool<Postgres>, pairings: Vec<(Timesl
     Ok(())
 }
 
+/// Retrieves the timeslots each session with limited availability may be placed in.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+///
+/// # Returns
+/// A `Result` containing the allowed timeslot IDs keyed by session ID, sessions that can be placed
+/// in any timeslot are left out, otherwise a `sqlx::Error`.
+pub async fn session_availability_get(db_pool: &Pool<Postgres>) -> Result<HashMap<i32, Vec<i32>>, sqlx::Error> {
+    let availability = sqlx::query!(
+        r#"SELECT session_id, ARRAY_AGG(time_slot_id ORDER BY time_slot_id) as "time_slot_ids!"
+        FROM session_availability
+        GROUP BY session_id"#
+    )
+        .fetch_all(db_pool)
+        .await?;
 
-pub async fn local_search_scheduling(db_pool: &Pool<Postgres>, scheduling_data: SessionAssignmentData) -> Result<(), Box<dyn Error + Send + Sync>> {
+    Ok(availability
+        .into_iter()
+        .map(|row| (row.session_id, row.time_slot_ids))
+        .collect())
+}
+
+/// Retrieves the pairs of sessions the scheduler keeps out of the same timeslot.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+///
+/// # Returns
+/// A `Result` containing each pair of session IDs with the lower ID first, otherwise a
+/// `sqlx::Error`.
+pub async fn session_exclusions_get(db_pool: &Pool<Postgres>) -> Result<Vec<(i32, i32)>, sqlx::Error> {
+    let exclusions = sqlx::query!(
+        "SELECT session_id, other_session_id FROM session_exclusions ORDER BY session_id, other_session_id"
+    )
+        .fetch_all(db_pool)
+        .await?;
+
+    Ok(exclusions
+        .into_iter()
+        .map(|row| (row.session_id, row.other_session_id))
+        .collect())
+}
+
+/// Builds the grid the local search scheduler works on.
+///
+/// Every room and timeslot gets a cell, the sessions that are already assigned are placed into
+/// their cells and the rest are left as unassigned sessions. Empty cells beyond a timeslot's
+/// `max_open_rooms` are marked as already assigned so the scheduler leaves them empty. A session's
+/// votes are the sum of its voters' `vote_weight`s, scaled by the configured `VoteScaling`.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `scheduling_data`: The assigned and unassigned sessions
+/// - `config`: The options to score the grid with
+///
+/// # Returns
+/// A `Result` containing the `SchedulerData` if successful, otherwise an error.
+pub async fn scheduler_data_get(db_pool: &Pool<Postgres>, scheduling_data: SessionAssignmentData, config: &SchedulerConfig) -> Result<SchedulerData, Box<dyn Error + Send + Sync>> {
     tracing::trace!("unassigned_sessions: {:?}", scheduling_data.unassigned_sessions);
-    // We should not be able to get here if rooms is None
-    let rooms: Vec<Room> = rooms_get(db_pool).await?.unwrap();
-    // We should not be able to get here if timeslots is Err
-    let timeslots: Vec<ExistingTimeslot> = timeslot_get(db_pool).await.unwrap();
+    let rooms: Vec<Room> = rooms_get(db_pool).await?.unwrap_or_default();
+    let timeslots: Vec<ExistingTimeslot> = timeslot_get(db_pool)
+        .await
+        .map_err(|e| ScheduleErr::IoError(e.to_string()))?;
     let num_rooms = rooms.len();
     let num_timeslots = timeslots.len();
 
     tracing::info!("Getting session data");
-    let session_and_votes: Vec<SessionData> = sqlx::query_as!(
+    let mut session_and_votes: Vec<SessionData> = sqlx::query_as!(
         SessionData,
         "SELECT uv.session_id as \"session_id!\", \
-        COALESCE(COUNT(*)::INTEGER, 0) as \"num_votes!\", \
+        COALESCE(SUM(u.vote_weight)::INTEGER, 0) as \"num_votes!\", \
         st.tag_id as \"tag_id?\", \
         s.user_id as \"speaker_id?\", \
-        ARRAY[]::INTEGER[] as \"speaker_votes!\" \
+        COALESCE((SELECT ARRAY_AGG(sv.session_id ORDER BY sv.session_id) FROM user_votes sv WHERE sv.user_id = s.user_id), ARRAY[]::INTEGER[]) as \"speaker_votes!\", \
+        COALESCE(s.is_keynote, false) as \"is_keynote!\", \
+        NULL::INTEGER[] as \"allowed_slots?\" \
         from user_votes uv \
+        JOIN users u ON u.id = uv.user_id \
         LEFT JOIN session_tags st ON st.session_id = uv.session_id \
         LEFT JOIN sessions s ON s.id = uv.session_id \
-        GROUP BY uv.session_id, st.tag_id, s.user_id"
+        GROUP BY uv.session_id, st.tag_id, s.user_id, s.is_keynote"
     )
         .fetch_all(db_pool)
         .await?;
 
+    let max_votes = session_and_votes.iter().map(|session_data| session_data.num_votes).max().unwrap_or(0);
+    for session_data in &mut session_and_votes {
+        session_data.num_votes = config.vote_scaling.scale(session_data.num_votes, max_votes);
+    }
+
+    let mut availability = session_availability_get(db_pool).await?;
+
     tracing::info!("Getting unassigned sessions");
     let unassigned_sessions: Vec<SessionData> = scheduling_data.unassigned_sessions
         .iter()
-        .map(|&UnassignedSession { session_id, tag_id }| {
+        .map(|&UnassignedSession { session_id, tag_id, is_keynote }| {
             let session_data = session_and_votes
                 .iter()
                 .find(|session_data| session_data.session_id.is_some() && session_data.session_id.unwrap() == session_id);
@@ -279,6 +691,8 @@ pub async fn local_search_scheduling(db_pool: &Pool<Postgres>, scheduling_data:
                 tag_id,
                 speaker_id,
                 speaker_votes,
+                is_keynote: is_keynote && config.keynotes,
+                allowed_slots: availability.remove(&session_id),
             }
         })
         .collect();
@@ -287,7 +701,9 @@ pub async fn local_search_scheduling(db_pool: &Pool<Postgres>, scheduling_data:
         schedule_rows: vec![],
         capacity: (num_rooms * num_timeslots) as i32,
         unassigned_sessions,
+        options: config.options(),
     };
+    scheduler_data.options.mutual_exclusions = session_exclusions_get(db_pool).await?;
 
     for timeslot in timeslots {
         let mut schedule_row: ScheduleRow = ScheduleRow {
@@ -304,6 +720,9 @@ pub async fn local_search_scheduling(db_pool: &Pool<Postgres>, scheduling_data:
                 tag_id: None,
                 speaker_id: None,
                 speaker_votes: vec![],
+                is_keynote: false,
+                allowed_slots: None,
+                room_capacity: room.available_spots,
             };
 
             schedule_row.schedule_items.push(item);
@@ -311,6 +730,11 @@ pub async fn local_search_scheduling(db_pool: &Pool<Postgres>, scheduling_data:
         scheduler_data.schedule_rows.push(schedule_row);
     }
 
+    let free_room_times: HashSet<(i32, i32)> = scheduling_data.available_room_time_associations
+        .iter()
+        .map(|room_time| (room_time.time_slot_id, room_time.room_id))
+        .collect();
+
     for room_time_assgn in scheduling_data.already_assigned_room_time_associations {
         if let Some(schedule_item) = scheduler_data.schedule_rows
             .iter_mut()
@@ -321,6 +745,10 @@ pub async fn local_search_scheduling(db_pool: &Pool<Postgres>, scheduling_data:
             schedule_item.session_id = room_time_assgn.session_id;
             schedule_item.id = room_time_assgn.id;
             schedule_item.already_assigned = room_time_assgn.already_assigned;
+            schedule_item.tag_id = room_time_assgn.tag_id;
+            schedule_item.speaker_id = room_time_assgn.speaker_id;
+            schedule_item.speaker_votes = room_time_assgn.speaker_votes;
+            schedule_item.is_keynote = room_time_assgn.is_keynote && config.keynotes;
 
             if let Some(session_id) = room_time_assgn.session_id {
                 schedule_item.num_votes = session_and_votes
@@ -332,29 +760,62 @@ pub async fn local_search_scheduling(db_pool: &Pool<Postgres>, scheduling_data:
         }
     }
 
+    for schedule_item in scheduler_data.schedule_rows
+        .iter_mut()
+        .flat_map(|row| row.schedule_items.iter_mut())
+        .filter(|item| !item.is_assigned() && !free_room_times.contains(&(item.time_slot_id, item.room_id)))
+    {
+        schedule_item.already_assigned = true;
+        scheduler_data.capacity -= 1;
+    }
+
+    Ok(scheduler_data)
+}
+
+pub async fn local_search_scheduling(
+    db_pool: &Pool<Postgres>,
+    scheduling_data: SessionAssignmentData,
+    config: &SchedulerConfig,
+    progress_callback: Option<ProgressCallback>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut scheduler_data = scheduler_data_get(db_pool, scheduling_data, config).await?;
+    scheduler_data.options.progress_callback = progress_callback;
+    let normalization = config.normalization;
+
     tracing::info!("Starting scheduler");
     let start = Instant::now();
 
     let stop_flag = Arc::new(AtomicBool::new(false));
+    let maximize_coverage = config.method == SchedulingMethod::MaximizeCoverage;
 
     // Run the scheduler on a blocking thread to prevent blocking futures
     let handle = tokio::task::spawn_blocking({
         let stop_flag = stop_flag.clone();
         move || {
-            let score = scheduler_data.improve_with_restarts(40, stop_flag);
-            (score, scheduler_data)
+            // The ranges are sampled from the schedule before it is filled
+            if normalization == PenaltyNormalization::MinMax {
+                scheduler_data.normalize_min_max(NORMALIZATION_SAMPLES);
+            }
+            let stats = if maximize_coverage {
+                scheduler_data.improve_maximizing_coverage_stats(40, stop_flag)
+            } else {
+                scheduler_data.improve_with_restarts_stats(40, stop_flag)
+            };
+            (stats, scheduler_data)
         }
     });
 
-    // After 60 seconds update the stop_flag AtomicBoolean so the scheduler won't do additional iterations
+    // Once the time budget is used up update the stop_flag AtomicBoolean so the scheduler won't do
+    // additional iterations
+    let time_budget = config.time_budget;
     tokio::spawn({
         async move {
-            tokio::time::sleep(Duration::from_secs(60)).await;
+            tokio::time::sleep(time_budget).await;
             stop_flag.store(true, Ordering::Relaxed);
         }
     });
 
-    let (current_score, scheduler_data) = handle.await?;
+    let (stats, scheduler_data) = handle.await?;
 
     let best_scheduler_data = &scheduler_data;
 
@@ -363,34 +824,90 @@ pub async fn local_search_scheduling(db_pool: &Pool<Postgres>, scheduling_data:
     let duration = start.elapsed();
     tracing::info!("scheduling_data:\n{}", best_scheduler_data);
     tracing::info!("duration: {:?}", duration);
-    tracing::trace!("best score: {:?}", current_score);
+    tracing::trace!("best score: {:?}", stats.final_score);
 
-    for schedule_row in &best_scheduler_data.schedule_rows {
-        for schedule_item in &schedule_row.schedule_items {
-            if schedule_item.already_assigned || schedule_item.session_id.is_none() {
-                continue;
-            } else {
-                let assignment = TimeslotAssignmentForm {
-                    session_id: schedule_item.session_id.unwrap(),
+    for (time_slot_id, assignment) in new_assignments(best_scheduler_data)? {
+        insert_assignment(db_pool, time_slot_id, assignment).await?;
+    }
+
+    // Counted once the assignments are in so the sessions held back by the vote floor, which
+    // the scheduler never saw, are included
+    let unscheduled = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "unscheduled!"
+        FROM sessions s
+        WHERE NOT EXISTS (SELECT 1 FROM timeslot_assignments ta WHERE ta.session_id = s.id)"#
+    )
+        .fetch_one(db_pool)
+        .await?;
+    schedule_run_insert(db_pool, &stats, duration, unscheduled as i32).await?;
+
+    Ok(())
+}
+
+/// Collects the assignments the scheduler added to the schedule.
+///
+/// Every session may only be on the schedule once, so the assignments are checked against each
+/// other and against the sessions that were already assigned before anything is written.
+///
+/// # Parameters
+/// - `scheduler_data`: The schedule produced by the scheduler
+///
+/// # Returns
+/// A `Result` containing the timeslot ID and assignment of each new assignment if successful,
+/// otherwise a `ScheduleErr` error.
+///
+/// # Errors
+/// If a session appears in more than one cell a `ScheduleErr::SessionAlreadyScheduled` error is
+/// returned.
+fn new_assignments(scheduler_data: &SchedulerData) -> Result<Vec<(i32, TimeslotAssignmentForm)>, ScheduleErr> {
+    let mut scheduled_sessions = HashSet::new();
+    let mut assignments = Vec::new();
+
+    for schedule_item in scheduler_data.schedule_rows.iter().flat_map(|row| &row.schedule_items) {
+        let Some(session_id) = schedule_item.session_id else {
+            continue;
+        };
+
+        if !scheduled_sessions.insert(session_id) {
+            tracing::error!("Scheduler placed session {} more than once", session_id);
+            return Err(ScheduleErr::SessionAlreadyScheduled(session_id.to_string()));
+        }
+
+        if !schedule_item.already_assigned {
+            assignments.push((
+                schedule_item.time_slot_id,
+                TimeslotAssignmentForm {
+                    session_id,
                     room_id: schedule_item.room_id,
                     old_room_id: 0,
-                };
-
-                insert_assignment(db_pool, schedule_item.time_slot_id, assignment).await?;
-            }
+                },
+            ));
         }
     }
 
-    Ok(())
+    Ok(assignments)
 }
 
+/// Assigns a session to a room and timeslot.
+///
+/// If the room and timeslot already have a session it is replaced, so a schedule can be generated
+/// again over cells that were filled in the meantime.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `timeslot_id`: The ID of the timeslot
+/// - `assignment`: The session and room to assign
+///
+/// # Returns
+/// A `Result` containing `()` if successful, otherwise an error.
 async fn insert_assignment(
     db_pool: &Pool<Postgres>,
     timeslot_id: i32,
     assignment: TimeslotAssignmentForm,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     sqlx::query(
-        "INSERT INTO timeslot_assignments (time_slot_id, session_id, room_id) VALUES ($1, $2, $3)"
+        "INSERT INTO timeslot_assignments (time_slot_id, session_id, room_id) VALUES ($1, $2, $3)
+        ON CONFLICT (time_slot_id, room_id) DO UPDATE SET session_id = EXCLUDED.session_id, updated_at = now()"
         )
         .bind(timeslot_id)
         .bind(assignment.session_id)
@@ -435,7 +952,7 @@ pub async fn timeslot_assignment_update(
             );
             let assignment_id = sqlx::query_scalar!(
                 "UPDATE timeslot_assignments
-                SET time_slot_id = $1, session_id = $2, room_id = $3
+                SET time_slot_id = $1, session_id = $2, room_id = $3, updated_at = now()
                 WHERE time_slot_id = $4 AND room_id = $5
                 RETURNING id",
                 new_timeslot_id,
@@ -459,27 +976,375 @@ pub async fn timeslot_assignment_swap(
     request: TimeslotSwapRequest,
 ) -> Result<(), Box<dyn Error>> {
     let mut tx = db_pool.begin().await?;
+    let cells = [
+        (request.timeslot_id_1, request.room_id_1),
+        (request.timeslot_id_2, request.room_id_2),
+    ];
 
-    sqlx::query!(
-        "UPDATE timeslot_assignments t1
-        SET
-            session_id = t2.session_id
-        FROM (
-            SELECT id, session_id
-            FROM timeslot_assignments
-            WHERE (time_slot_id, room_id) IN (($1, $2), ($3, $4))
-        ) t2
-        WHERE t1.id != t2.id
-        AND (t1.time_slot_id, t1.room_id) IN (($1, $2), ($3, $4))",
-        request.timeslot_id_1,
-        request.room_id_1,
-        request.timeslot_id_2,
-        request.room_id_2,
-    )
-        .execute(&mut *tx)
-        .await?;
+    let mut session_ids = Vec::with_capacity(cells.len());
+    for (time_slot_id, room_id) in cells {
+        let session_id = sqlx::query_scalar!(
+            "SELECT session_id FROM timeslot_assignments WHERE time_slot_id = $1 AND room_id = $2 FOR UPDATE",
+            time_slot_id,
+            room_id,
+        )
+            .fetch_one(&mut *tx)
+            .await?;
+        session_ids.push(session_id);
+    }
+
+    // Both cells are emptied first, a session may only be scheduled once even halfway through
+    for (time_slot_id, room_id) in cells {
+        sqlx::query!(
+            "UPDATE timeslot_assignments SET session_id = NULL WHERE time_slot_id = $1 AND room_id = $2",
+            time_slot_id,
+            room_id,
+        )
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for ((time_slot_id, room_id), session_id) in cells.into_iter().zip(session_ids.into_iter().rev()) {
+        sqlx::query!(
+            "UPDATE timeslot_assignments
+            SET session_id = $1, updated_at = now()
+            WHERE time_slot_id = $2 AND room_id = $3",
+            session_id,
+            time_slot_id,
+            room_id,
+        )
+            .execute(&mut *tx)
+            .await?;
+    }
 
     tx.commit().await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::account_model::vote_weight_set;
+    use crate::models::schedule_model::last_schedule_run_get;
+    use crate::models::sessions_model::{exclusion_add, get_all_sessions, SessionErr, SessionExclusion};
+    use crate::test_support::{assign_session, insert_room, insert_session, insert_timeslot, insert_timeslot_with, insert_user, insert_vote};
+    use scheduler::SchedulerOptions;
+    use sqlx::PgPool;
+
+    fn scheduled_cell(time_slot_id: i32, room_id: i32, session_id: i32, already_assigned: bool) -> RoomTimeAssignment {
+        RoomTimeAssignment {
+            room_id,
+            time_slot_id,
+            session_id: Some(session_id),
+            num_votes: 0,
+            id: None,
+            already_assigned,
+            tag_id: None,
+            speaker_id: None,
+            speaker_votes: vec![],
+            is_keynote: false,
+            allowed_slots: None,
+            room_capacity: 0,
+        }
+    }
+
+    fn scheduler_data(schedule_items: Vec<RoomTimeAssignment>) -> SchedulerData {
+        SchedulerData {
+            capacity: schedule_items.len() as i32,
+            schedule_rows: vec![ScheduleRow { schedule_items }],
+            unassigned_sessions: vec![],
+            options: SchedulerOptions::default(),
+        }
+    }
+
+    #[test]
+    fn log_scaling_narrows_the_gap_behind_the_most_voted_session() {
+        let votes = [100, 10, 9, 0];
+        let scaled = |scaling: VoteScaling| votes.map(|num_votes| scaling.scale(num_votes, 100));
+
+        assert_eq!(scaled(VoteScaling::Linear), [100, 10, 9, 0]);
+        assert_eq!(scaled(VoteScaling::Sqrt), [100, 32, 30, 0]);
+        assert_eq!(scaled(VoteScaling::Log), [100, 52, 50, 0]);
+
+        // The sessions are ranked the same way, the runners up just count for more next to the
+        // most voted session
+        for scaling in [VoteScaling::Sqrt, VoteScaling::Log] {
+            assert!(scaled(scaling).windows(2).all(|pair| pair[0] > pair[1]));
+        }
+        assert_eq!(VoteScaling::Log.scale(5, 0), 5);
+        assert_eq!("SQRT".parse(), Ok(VoteScaling::Sqrt));
+        assert!("cubic".parse::<VoteScaling>().is_err());
+    }
+
+    #[test]
+    fn new_assignments_skips_already_assigned_sessions() {
+        let data = scheduler_data(vec![
+            scheduled_cell(1, 1, 10, true),
+            scheduled_cell(1, 2, 20, false),
+        ]);
+
+        let assignments = new_assignments(&data).unwrap();
+
+        assert_eq!(assignments.len(), 1);
+        assert_eq!(assignments[0].0, 1);
+        assert_eq!(assignments[0].1.session_id, 20);
+        assert_eq!(assignments[0].1.room_id, 2);
+    }
+
+    #[test]
+    fn new_assignments_rejects_a_session_placed_twice() {
+        let placed_twice = scheduler_data(vec![
+            scheduled_cell(1, 1, 10, false),
+            scheduled_cell(1, 2, 10, false),
+        ]);
+        assert!(matches!(
+            new_assignments(&placed_twice),
+            Err(ScheduleErr::SessionAlreadyScheduled(session_id)) if session_id == "10"
+        ));
+
+        let placed_over_existing = scheduler_data(vec![
+            scheduled_cell(1, 1, 10, true),
+            scheduled_cell(1, 2, 10, false),
+        ]);
+        assert!(matches!(
+            new_assignments(&placed_over_existing),
+            Err(ScheduleErr::SessionAlreadyScheduled(_))
+        ));
+    }
+
+    #[sqlx::test]
+    async fn insert_assignment_replaces_occupied_cell(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let first_session_id = insert_session(&pool, user_id, "First").await;
+        let second_session_id = insert_session(&pool, user_id, "Second").await;
+        let room_id = insert_room(&pool, "Room", "Here", 10).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+
+        for session_id in [first_session_id, second_session_id] {
+            let assignment = TimeslotAssignmentForm {
+                session_id,
+                room_id,
+                old_room_id: 0,
+            };
+            insert_assignment(&pool, time_slot_id, assignment).await.unwrap();
+        }
+
+        let assigned_sessions: Vec<i32> = sqlx::query_scalar("SELECT session_id FROM timeslot_assignments WHERE time_slot_id = $1 AND room_id = $2")
+            .bind(time_slot_id)
+            .bind(room_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(assigned_sessions, vec![second_session_id]);
+    }
+
+    #[sqlx::test]
+    async fn vote_floor_apply_excludes_sessions_below_threshold(pool: PgPool) {
+        let first_user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let second_user_id = insert_user(&pool, "B", "User", "b@example.com").await;
+        let one_vote_id = insert_session(&pool, first_user_id, "One vote").await;
+        let two_votes_id = insert_session(&pool, first_user_id, "Two votes").await;
+        insert_room(&pool, "A", "Here", 10).await;
+        insert_timeslot(&pool, "09:00", "10:00").await;
+        insert_timeslot(&pool, "10:00", "11:00").await;
+        for (user_id, session_id) in [(first_user_id, one_vote_id), (first_user_id, two_votes_id), (second_user_id, two_votes_id)] {
+            sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $2)")
+                .bind(user_id)
+                .bind(session_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let sessions = get_all_sessions(&pool).await.unwrap();
+        let mut scheduling_data = session_assignment_data_get(&sessions, &pool).await.unwrap();
+
+        vote_floor_apply(&pool, &mut scheduling_data, 2).await.unwrap();
+
+        let session_ids: Vec<i32> = scheduling_data.unassigned_sessions.iter().map(|session| session.session_id).collect();
+        assert_eq!(session_ids, vec![two_votes_id]);
+
+        // The excluded session is left off the schedule even with a free slot, and the run says so
+        local_search_scheduling(&pool, scheduling_data, &SchedulerConfig::default(), None).await.unwrap();
+        let assigned_sessions: Vec<i32> = sqlx::query_scalar("SELECT session_id FROM timeslot_assignments WHERE session_id IS NOT NULL")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(assigned_sessions, vec![two_votes_id]);
+        assert_eq!(last_schedule_run_get(&pool).await.unwrap().unscheduled, 1);
+    }
+
+    #[sqlx::test]
+    async fn capped_timeslot_never_gets_more_sessions_than_open_rooms(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let time_slot_id = insert_timeslot_with(&pool, "09:00", "10:00", Some(2)).await;
+        let mut room_ids = vec![];
+        for name in ["A", "B", "C"] {
+            room_ids.push(insert_room(&pool, name, "Here", 10).await);
+        }
+        let mut session_ids = vec![];
+        for title in ["First", "Second", "Third"] {
+            let session_id = insert_session(&pool, user_id, title).await;
+            insert_vote(&pool, user_id, session_id).await;
+            session_ids.push(session_id);
+        }
+        let pinned = TimeslotAssignmentForm { session_id: session_ids[0], room_id: room_ids[2], old_room_id: 0 };
+        insert_assignment(&pool, time_slot_id, pinned).await.unwrap();
+
+        // The pinned session takes one of the two open rooms, leaving only the first room free
+        let free_room_ids: Vec<i32> = get_all_unassigned_timeslots(&pool).await.unwrap().iter().map(|free| free.room_id).collect();
+        assert_eq!(free_room_ids, vec![room_ids[0]]);
+
+        let sessions = get_all_sessions(&pool).await.unwrap();
+        let scheduling_data = session_assignment_data_get(&sessions, &pool).await.unwrap();
+        local_search_scheduling(&pool, scheduling_data, &SchedulerConfig::default(), None).await.unwrap();
+
+        let num_assigned: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM timeslot_assignments WHERE time_slot_id = $1")
+            .bind(time_slot_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(num_assigned, 2);
+        assert!(!space_to_add_session(&pool).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn scheduler_data_has_the_sessions_each_speaker_voted_for(pool: PgPool) {
+        let speaker_id = insert_user(&pool, "A", "Speaker", "a@example.com").await;
+        let other_speaker_id = insert_user(&pool, "B", "Speaker", "b@example.com").await;
+        let room_id = insert_room(&pool, "Room", "Here", 10).await;
+        let time_slot_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        let scheduled_id = insert_session(&pool, speaker_id, "Scheduled").await;
+        let unscheduled_id = insert_session(&pool, other_speaker_id, "Unscheduled").await;
+        sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $3), ($1, $4), ($2, $3)")
+            .bind(speaker_id)
+            .bind(other_speaker_id)
+            .bind(scheduled_id)
+            .bind(unscheduled_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        insert_assignment(&pool, time_slot_id, TimeslotAssignmentForm { session_id: scheduled_id, room_id, old_room_id: 0 }).await.unwrap();
+
+        let sessions = get_all_sessions(&pool).await.unwrap();
+        let scheduling_data = session_assignment_data_get(&sessions, &pool).await.unwrap();
+        let scheduler_data = scheduler_data_get(&pool, scheduling_data, &SchedulerConfig::default()).await.unwrap();
+
+        let scheduled = &scheduler_data.schedule_rows[0].schedule_items[0];
+        assert_eq!(scheduled.session_id, Some(scheduled_id));
+        assert_eq!(scheduled.speaker_votes, vec![scheduled_id, unscheduled_id]);
+        assert_eq!(scheduler_data.unassigned_sessions[0].session_id, Some(unscheduled_id));
+        assert_eq!(scheduler_data.unassigned_sessions[0].speaker_votes, vec![scheduled_id]);
+    }
+
+    #[sqlx::test]
+    async fn scheduler_data_has_the_timeslots_each_session_is_allowed_in(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "Speaker", "a@example.com").await;
+        insert_room(&pool, "Room", "Here", 10).await;
+        let morning_id = insert_timeslot(&pool, "09:00", "10:00").await;
+        insert_timeslot(&pool, "14:00", "15:00").await;
+        let morning_only_id = insert_session(&pool, user_id, "Morning").await;
+        let anytime_id = insert_session(&pool, user_id, "Anytime").await;
+        sqlx::query("INSERT INTO session_availability (session_id, time_slot_id) VALUES ($1, $2)")
+            .bind(morning_only_id)
+            .bind(morning_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let sessions = get_all_sessions(&pool).await.unwrap();
+        let scheduling_data = session_assignment_data_get(&sessions, &pool).await.unwrap();
+        let scheduler_data = scheduler_data_get(&pool, scheduling_data, &SchedulerConfig::default()).await.unwrap();
+
+        let allowed_slots = |session_id: i32| scheduler_data.unassigned_sessions
+            .iter()
+            .find(|session| session.session_id == Some(session_id))
+            .unwrap()
+            .allowed_slots
+            .clone();
+        assert_eq!(allowed_slots(morning_only_id), Some(vec![morning_id]));
+        assert_eq!(allowed_slots(anytime_id), None);
+    }
+
+    #[sqlx::test]
+    async fn scheduler_data_has_the_excluded_session_pairs(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "Speaker", "a@example.com").await;
+        insert_room(&pool, "Room", "Here", 10).await;
+        let first_id = insert_session(&pool, user_id, "First").await;
+        let second_id = insert_session(&pool, user_id, "Second").await;
+
+        // The pair is stored the same way whichever order it is given in
+        let exclusion = SessionExclusion { session_id: second_id, other_session_id: first_id };
+        assert_eq!(exclusion_add(&pool, exclusion).await.unwrap(), SessionExclusion { session_id: first_id, other_session_id: second_id });
+        exclusion_add(&pool, exclusion).await.unwrap();
+
+        let missing = exclusion_add(&pool, SessionExclusion { session_id: first_id, other_session_id: second_id + 100 }).await.unwrap_err();
+        assert!(matches!(missing.downcast_ref::<SessionErr>(), Some(SessionErr::DoesNotExist(_))));
+        let itself = exclusion_add(&pool, SessionExclusion { session_id: first_id, other_session_id: first_id }).await.unwrap_err();
+        assert!(matches!(itself.downcast_ref::<SessionErr>(), Some(SessionErr::ExcludedFromItself(_))));
+
+        let sessions = get_all_sessions(&pool).await.unwrap();
+        let scheduling_data = session_assignment_data_get(&sessions, &pool).await.unwrap();
+        let scheduler_data = scheduler_data_get(&pool, scheduling_data, &SchedulerConfig::default()).await.unwrap();
+        assert_eq!(scheduler_data.options.mutual_exclusions, vec![(first_id, second_id)]);
+    }
+
+    #[sqlx::test]
+    async fn committee_votes_count_for_more(pool: PgPool) {
+        let mut voters = Vec::new();
+        for email in ["a@example.com", "b@example.com", "committee@example.com"] {
+            voters.push(insert_user(&pool, "A", "Voter", email).await);
+        }
+        let [first_voter, second_voter, committee_member] = voters[..] else { unreachable!() };
+        let popular_id = insert_session(&pool, first_voter, "Popular").await;
+        let picked_id = insert_session(&pool, first_voter, "Picked").await;
+        sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $3), ($2, $3), ($4, $5)")
+            .bind(first_voter)
+            .bind(second_voter)
+            .bind(popular_id)
+            .bind(committee_member)
+            .bind(picked_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let num_votes = || async {
+            let sessions = get_all_sessions(&pool).await.unwrap();
+            let scheduling_data = session_assignment_data_get(&sessions, &pool).await.unwrap();
+            let scheduler_data = scheduler_data_get(&pool, scheduling_data, &SchedulerConfig::default()).await.unwrap();
+            let votes_for = |session_id| scheduler_data.unassigned_sessions
+                .iter()
+                .find(|session| session.session_id == Some(session_id))
+                .unwrap()
+                .num_votes;
+            (votes_for(popular_id), votes_for(picked_id))
+        };
+
+        assert_eq!(num_votes().await, (2, 1));
+
+        vote_weight_set(&pool, committee_member, 3).await.unwrap();
+        assert_eq!(num_votes().await, (2, 3));
+    }
+
+    #[sqlx::test]
+    async fn free_cells_are_the_empty_rooms_ordered_by_time_then_room(pool: PgPool) {
+        let user_id = insert_user(&pool, "A", "User", "a@example.com").await;
+        let late_slot = insert_timeslot(&pool, "10:00", "11:00").await;
+        let early_slot = insert_timeslot(&pool, "09:00", "10:00").await;
+        let room_a = insert_room(&pool, "Room A", "Here", 10).await;
+        let room_b = insert_room(&pool, "Room B", "There", 10).await;
+        let session_id = insert_session(&pool, user_id, "Talk").await;
+        assign_session(&pool, early_slot, room_a, session_id).await;
+
+        let cells: Vec<(i32, String, i32, String)> = free_cells_get(&pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|cell| (cell.time_slot_id, cell.start_time.format("%H:%M").to_string(), cell.room_id, cell.room_name))
+            .collect();
+        assert_eq!(cells, vec![
+            (early_slot, "09:00".to_string(), room_b, "Room B".to_string()),
+            (late_slot, "10:00".to_string(), room_a, "Room A".to_string()),
+            (late_slot, "10:00".to_string(), room_b, "Room B".to_string()),
+        ]);
+    }
+}
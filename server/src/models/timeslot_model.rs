@@ -1,9 +1,11 @@
+use crate::models::room_model::Room;
 use crate::types::ApiStatusCode;
+use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::{response::Response, Json};
-use chrono::NaiveTime;
+use chrono::{Duration, NaiveTime};
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
-use sqlx::{FromRow, Pool, Postgres};
+use sqlx::{FromRow, PgExecutor, Pool, Postgres};
 use std::error::Error;
 use utoipa::ToSchema;
 
@@ -13,10 +15,19 @@ type BoxedError = Box<dyn Error + Send + Sync>;
 ///
 /// # Variants
 /// - `IoError` - An I/O error occurred
+/// - `InvalidRequest` - The requested timeslots can't be created
+/// - `Overlaps` - The requested timeslots overlap existing timeslots
+/// - `DoesNotExist` - The timeslot does not exist
 #[derive(Debug, thiserror::Error, ToSchema, Serialize)]
 pub enum TimeSlotErr {
     #[error("TimeSlot io failed: {0}")]
     IoError(String),
+    #[error("Invalid timeslot request: {0}")]
+    InvalidRequest(String),
+    #[error("Timeslots overlap existing timeslots between {0}")]
+    Overlaps(String),
+    #[error("Timeslot {0} doesn't exist")]
+    DoesNotExist(i32),
 }
 
 /// Implements the `From` trait for `std::io::Error` to convert it into a `TimeSlotErr`.
@@ -85,7 +96,11 @@ impl TimeSlotError {
             status,
             error: error.to_string(),
         };
-        (status, Json(error)).into_response()
+
+        let http_status = StatusCode::from_u16(status.0)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        (http_status, Json(error)).into_response()
     }
 }
 
@@ -100,6 +115,9 @@ pub struct TimeslotAssignmentForm {
 pub struct TimeslotForm {
     pub start_time: String,
     pub duration: i32,
+    /// How many rooms can be used in the timeslot, all of them when not set
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_open_rooms: Option<i32>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub assignments: Vec<TimeslotAssignmentForm>,
 }
@@ -114,6 +132,24 @@ pub struct TimeslotRequestWrapper {
     pub timeslot_request: TimeslotRequest,
 }
 
+/// Struct representing a request to create a run of back to back timeslots
+///
+/// Either `count` or `end_time` decides how many timeslots are created. With `end_time` as many
+/// whole timeslots as fit before it are created.
+///
+/// # Fields
+/// - `start_time` - The start time of the first timeslot, formatted as `HH:MM`
+/// - `slot_minutes` - The length of each timeslot in minutes
+/// - `count` - The number of timeslots to create
+/// - `end_time` - The time the last timeslot has to end by, formatted as `HH:MM`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TimeslotGenerateRequest {
+    pub start_time: String,
+    pub slot_minutes: i32,
+    pub count: Option<i32>,
+    pub end_time: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TimeslotUpdateRequest {
     pub start_time: String,
@@ -137,6 +173,34 @@ pub struct ExistingTimeslot {
     pub start_time: NaiveTime,
     pub end_time: NaiveTime,
     pub duration: i32,
+    #[serde(default)]
+    pub max_open_rooms: Option<i32>,
+}
+
+/// Struct representing the session held in a room during a timeslot.
+///
+/// # Fields
+/// - `session_id` - The ID of the session
+/// - `session_title` - The title of the session
+/// - `speaker` - The name of the user who submitted the session
+/// - `votes` - The number of votes the session received
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct TimeslotRoomSession {
+    pub session_id: i32,
+    pub session_title: String,
+    pub speaker: String,
+    pub votes: i64,
+}
+
+/// Struct representing a room during a timeslot and the session held in it, if any.
+///
+/// # Fields
+/// - `room` - The room
+/// - `session` - The session held in the room, `None` when the room is empty
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TimeslotRoomAssignment {
+    pub room: Room,
+    pub session: Option<TimeslotRoomSession>,
 }
 
 #[derive(Debug, Deserialize, FromRow, Clone)]
@@ -166,12 +230,12 @@ pub struct TimeslotAssignmentSessionAdd {
 /// # Errors
 /// If the query fails, a boxed error is returned.
 pub async fn timeslot_get(
-    db_pool: &Pool<Postgres>,
+    db_pool: impl PgExecutor<'_>,
 ) -> Result<Vec<ExistingTimeslot>, Box<dyn Error>> {
     let timeslots = sqlx::query_as!(
         ExistingTimeslot,
         r#"SELECT id, start_time as "start_time!: NaiveTime", end_time as "end_time!: NaiveTime",
-        (EXTRACT(EPOCH FROM duration) / 60)::integer as "duration!"
+        (EXTRACT(EPOCH FROM duration) / 60)::integer as "duration!", max_open_rooms
         FROM time_slots"#,
     )
         .fetch_all(db_pool)
@@ -186,14 +250,16 @@ async fn insert_timeslot(
     db_pool: &Pool<Postgres>,
     start_time: NaiveTime,
     duration: i64,
+    max_open_rooms: Option<i32>,
 ) -> Result<i32, Box<dyn Error>> {
     let end_time = start_time + chrono::Duration::minutes(duration);
     let duration_interval = format!("{duration} minutes");
     let id = sqlx::query_scalar!(
-        "INSERT INTO time_slots (start_time, end_time, duration) VALUES ($1, $2, $3::interval) RETURNING id",
+        "INSERT INTO time_slots (start_time, end_time, duration, max_open_rooms) VALUES ($1, $2, $3::interval, $4) RETURNING id",
         start_time as _,
         end_time as _,
         duration_interval as _,
+        max_open_rooms,
     )
         .fetch_one(db_pool)
         .await?;
@@ -221,7 +287,7 @@ pub async fn timeslots_add(
     let mut timeslot_ids = Vec::new();
     for timeslot in timeslots.timeslots {
         let start_time = NaiveTime::parse_from_str(&timeslot.start_time, "%H:%M")?;
-        let id = insert_timeslot(db_pool, start_time, i64::from(timeslot.duration)).await?;
+        let id = insert_timeslot(db_pool, start_time, i64::from(timeslot.duration), timeslot.max_open_rooms).await?;
         if !timeslot.assignments.is_empty() {
             tracing::debug!("Adding assignments: {:?}", timeslot.assignments);
             //insert_assignments(db_pool, id, timeslot.assignments).await?;
@@ -239,4 +305,337 @@ pub async fn get_num_timeslots(db_pool: &Pool<Postgres>) -> Result<i32, BoxedErr
 
     // This is safe to unwrap since it should always return a number
     Ok(num_timeslots.unwrap())
-}
\ No newline at end of file
+}
+
+/// Works out the start and end times of a run of back to back timeslots.
+///
+/// # Parameters
+/// - `request`: The request describing the timeslots
+///
+/// # Returns
+/// A `Result` containing the start and end time of each timeslot in order if successful, otherwise
+/// a `TimeSlotErr` error.
+///
+/// # Errors
+/// A `TimeSlotErr::InvalidRequest` error is returned if a time can't be parsed, the slot length or
+/// count isn't positive, both or neither of `count` and `end_time` are given, no timeslot fits
+/// before `end_time`, or the timeslots would run past midnight.
+pub fn timeslot_times_generate(request: &TimeslotGenerateRequest) -> Result<Vec<(NaiveTime, NaiveTime)>, TimeSlotErr> {
+    let parse_time = |time: &str| {
+        NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| TimeSlotErr::InvalidRequest(format!("{time} isn't a time formatted as HH:MM")))
+    };
+
+    let start_time = parse_time(&request.start_time)?;
+    if request.slot_minutes <= 0 {
+        return Err(TimeSlotErr::InvalidRequest("slot_minutes must be greater than 0".to_string()));
+    }
+    let slot_length = Duration::minutes(i64::from(request.slot_minutes));
+
+    let count = match (request.count, &request.end_time) {
+        (Some(count), None) => i64::from(count),
+        (None, Some(end_time)) => {
+            let end_time = parse_time(end_time)?;
+            if end_time <= start_time {
+                return Err(TimeSlotErr::InvalidRequest("end_time must be after start_time".to_string()));
+            }
+            (end_time - start_time).num_minutes() / i64::from(request.slot_minutes)
+        },
+        _ => return Err(TimeSlotErr::InvalidRequest("Exactly one of count or end_time is required".to_string())),
+    };
+    if count <= 0 {
+        return Err(TimeSlotErr::InvalidRequest("No timeslots fit the requested window".to_string()));
+    }
+
+    let mut times = Vec::new();
+    let mut slot_start = start_time;
+    for _ in 0..count {
+        let (slot_end, wrapped_seconds) = slot_start.overflowing_add_signed(slot_length);
+        if wrapped_seconds != 0 {
+            return Err(TimeSlotErr::InvalidRequest("Timeslots can't run past midnight".to_string()));
+        }
+        times.push((slot_start, slot_end));
+        slot_start = slot_end;
+    }
+
+    Ok(times)
+}
+
+/// Creates a run of back to back timeslots.
+///
+/// The timeslots are only created if none of them overlap an existing timeslot.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `request`: The request describing the timeslots
+///
+/// # Returns
+/// A `Result` containing the created `TimeSlot` instances if successful, otherwise a `TimeSlotErr`
+/// error.
+///
+/// # Errors
+/// A `TimeSlotErr::InvalidRequest` error is returned if the request is invalid, a
+/// `TimeSlotErr::Overlaps` error if the timeslots overlap existing ones, and a
+/// `TimeSlotErr::IoError` error if the query fails.
+pub async fn timeslots_generate(
+    db_pool: &Pool<Postgres>,
+    request: TimeslotGenerateRequest,
+) -> Result<Vec<TimeSlot>, TimeSlotErr> {
+    let times = timeslot_times_generate(&request)?;
+    // The timeslots are back to back so together they cover the window between the first start
+    // and the last end
+    let window_start = times[0].0;
+    let window_end = times[times.len() - 1].1;
+    let duration_interval = format!("{} minutes", request.slot_minutes);
+
+    let mut tx = db_pool
+        .begin()
+        .await
+        .map_err(|e| TimeSlotErr::IoError(e.to_string()))?;
+
+    let overlapping = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM time_slots WHERE start_time < $2 AND end_time > $1"#,
+        window_start as _,
+        window_end as _,
+    )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| TimeSlotErr::IoError(e.to_string()))?;
+    if overlapping > 0 {
+        return Err(TimeSlotErr::Overlaps(format!(
+            "{} and {}",
+            window_start.format("%H:%M"),
+            window_end.format("%H:%M"),
+        )));
+    }
+
+    let mut timeslots = Vec::with_capacity(times.len());
+    for (start_time, end_time) in times {
+        let timeslot = sqlx::query_as!(
+            TimeSlot,
+            r#"INSERT INTO time_slots (start_time, end_time, duration) VALUES ($1, $2, $3::interval)
+            RETURNING id as "id?", start_time as "start_time!: NaiveTime", end_time as "end_time!: NaiveTime""#,
+            start_time as _,
+            end_time as _,
+            duration_interval as _,
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| TimeSlotErr::IoError(e.to_string()))?;
+        timeslots.push(timeslot);
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| TimeSlotErr::IoError(e.to_string()))?;
+
+    Ok(timeslots)
+}
+
+/// Retrieves every room during a timeslot along with the session held in it.
+///
+/// # Parameters
+/// - `db_pool`: The database connection pool
+/// - `timeslot_id`: The ID of the timeslot
+///
+/// # Returns
+/// A `Result` containing a `TimeslotRoomAssignment` for each room ordered by room, otherwise a
+/// `TimeSlotErr` error.
+///
+/// # Errors
+/// If the timeslot doesn't exist a `DoesNotExist` error is returned, if the query fails an
+/// `IoError` is returned.
+pub async fn timeslot_assignments_get(
+    db_pool: &Pool<Postgres>,
+    timeslot_id: i32,
+) -> Result<Vec<TimeslotRoomAssignment>, TimeSlotErr> {
+    let timeslot_exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM time_slots WHERE id = $1) as "exists!""#,
+        timeslot_id,
+    )
+        .fetch_one(db_pool)
+        .await
+        .map_err(|e| TimeSlotErr::IoError(e.to_string()))?;
+
+    if !timeslot_exists {
+        return Err(TimeSlotErr::DoesNotExist(timeslot_id));
+    }
+
+    let rows = sqlx::query!(
+        r#"SELECT
+            r.id,
+            r.name,
+            r.location,
+            r.available_spots,
+            s.id as "session_id?",
+            s.title as "session_title?",
+            u.fname || ' ' || u.lname as "speaker?",
+            (SELECT COUNT(*) FROM user_votes uv WHERE uv.session_id = s.id) as "votes!"
+        FROM rooms r
+        LEFT JOIN timeslot_assignments ta ON ta.room_id = r.id AND ta.time_slot_id = $1
+        LEFT JOIN sessions s ON s.id = ta.session_id
+        LEFT JOIN users u ON u.id = s.user_id
+        ORDER BY r.id"#,
+        timeslot_id,
+    )
+        .fetch_all(db_pool)
+        .await
+        .map_err(|e| TimeSlotErr::IoError(e.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TimeslotRoomAssignment {
+            room: Room::new(Some(row.id), row.available_spots, row.name, row.location),
+            session: row.session_id.map(|session_id| TimeslotRoomSession {
+                session_id,
+                session_title: row.session_title.unwrap_or_default(),
+                speaker: row.speaker.unwrap_or_default(),
+                votes: row.votes,
+            }),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    #[tokio::test]
+    async fn error_response_has_the_status_and_the_api_error_shape() {
+        let response = TimeSlotError::response(ApiStatusCode::from(StatusCode::NOT_FOUND), Box::new(TimeSlotErr::DoesNotExist(7)));
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json, serde_json::json!({ "status": "404", "error": "Timeslot 7 doesn't exist" }));
+    }
+
+    fn request(start_time: &str, slot_minutes: i32, count: Option<i32>, end_time: Option<&str>) -> TimeslotGenerateRequest {
+        TimeslotGenerateRequest {
+            start_time: start_time.to_string(),
+            slot_minutes,
+            count,
+            end_time: end_time.map(str::to_string),
+        }
+    }
+
+    fn time(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn generates_timeslots_from_a_count() {
+        let times = timeslot_times_generate(&request("09:00", 30, Some(3), None)).unwrap();
+
+        assert_eq!(times, vec![
+            (time("09:00"), time("09:30")),
+            (time("09:30"), time("10:00")),
+            (time("10:00"), time("10:30")),
+        ]);
+    }
+
+    #[test]
+    fn generates_whole_timeslots_that_fit_before_the_end_time() {
+        let times = timeslot_times_generate(&request("09:00", 45, None, Some("11:00"))).unwrap();
+
+        assert_eq!(times, vec![
+            (time("09:00"), time("09:45")),
+            (time("09:45"), time("10:30")),
+        ]);
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let invalid_requests = [
+            request("9am", 30, Some(2), None),
+            request("09:00", 0, Some(2), None),
+            request("09:00", 30, Some(0), None),
+            request("09:00", 30, None, None),
+            request("09:00", 30, Some(2), Some("10:00")),
+            request("09:00", 90, None, Some("10:00")),
+            request("10:00", 30, None, Some("09:00")),
+            request("23:00", 30, Some(3), None),
+        ];
+
+        for invalid_request in &invalid_requests {
+            assert!(
+                matches!(timeslot_times_generate(invalid_request), Err(TimeSlotErr::InvalidRequest(_))),
+                "{invalid_request:?} should be rejected",
+            );
+        }
+    }
+
+    #[sqlx::test]
+    async fn timeslots_generate_rejects_overlapping_timeslots(pool: PgPool) {
+        let timeslots = timeslots_generate(&pool, request("09:00", 30, Some(4), None)).await.unwrap();
+        assert_eq!(timeslots.len(), 4);
+        assert_eq!(timeslots[3].end_time, time("11:00"));
+
+        let result = timeslots_generate(&pool, request("10:45", 30, Some(2), None)).await;
+        assert!(matches!(result, Err(TimeSlotErr::Overlaps(_))));
+
+        let timeslots = timeslots_generate(&pool, request("11:00", 60, None, Some("13:00"))).await.unwrap();
+        assert_eq!(timeslots.len(), 2);
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM time_slots")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 6);
+    }
+
+    #[sqlx::test]
+    async fn timeslot_assignments_get_lists_every_room(pool: PgPool) {
+        let user_id: i32 = sqlx::query_scalar("INSERT INTO users (fname, lname, email, password) VALUES ('Ada', 'Lovelace', 'a@example.com', 'x') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let session_id: i32 = sqlx::query_scalar("INSERT INTO sessions (user_id, title, content, votes) VALUES ($1, 'Engines', 'content', 0) RETURNING id")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(session_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let used_room_id: i32 = sqlx::query_scalar("INSERT INTO rooms (name, location, available_spots) VALUES ('A', 'Here', 10) RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let empty_room_id: i32 = sqlx::query_scalar("INSERT INTO rooms (name, location, available_spots) VALUES ('B', 'There', 10) RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let timeslot = timeslots_generate(&pool, request("09:00", 60, Some(1), None)).await.unwrap().remove(0);
+        let timeslot_id = timeslot.id.unwrap();
+        sqlx::query("INSERT INTO timeslot_assignments (time_slot_id, room_id, session_id) VALUES ($1, $2, $3)")
+            .bind(timeslot_id)
+            .bind(used_room_id)
+            .bind(session_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let assignments = timeslot_assignments_get(&pool, timeslot_id).await.unwrap();
+
+        assert_eq!(assignments.len(), 2);
+        assert_eq!(assignments[0].room.id, Some(used_room_id));
+        assert_eq!(assignments[0].session, Some(TimeslotRoomSession {
+            session_id,
+            session_title: "Engines".to_string(),
+            speaker: "Ada Lovelace".to_string(),
+            votes: 1,
+        }));
+        assert_eq!(assignments[1].room.id, Some(empty_room_id));
+        assert_eq!(assignments[1].session, None);
+
+        assert!(matches!(
+            timeslot_assignments_get(&pool, timeslot_id + 1).await,
+            Err(TimeSlotErr::DoesNotExist(id)) if id == timeslot_id + 1
+        ));
+    }
+}
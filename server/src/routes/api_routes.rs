@@ -1,14 +1,20 @@
 use crate::config::AppState;
+use crate::controllers::account_handler::{current_user, delete_current_user, set_vote_weight};
+use crate::controllers::bundle_handler::{export_bundle, import_bundle};
 use crate::controllers::index_handler::add_index_markdown;
 use crate::controllers::registration_handler::{registration_handler, staff_registers_user_handler};
-use crate::controllers::schedule_handler::{add_session_to_schedule, remove_session_from_schedule};
+use crate::controllers::schedule_handler::{add_session_to_schedule, remove_session_from_schedule, unschedule_session_from_schedule};
+use crate::controllers::schedule_snapshot_handler::{create_snapshot, diff_snapshots, restore_snapshot, snapshots};
 use crate::controllers::sessions_handler::post_session_for_user;
-use crate::controllers::tags_handler::{create_tag, delete_tag, update_tag};
-use crate::controllers::{login_handler::{login_handler, logout_handler}, room_handler::{delete_room, post_rooms, rooms}, schedule_handler::{clear, generate}, session_tags_handler::{add_tag_for_session, remove_tag_for_session, update_tag_for_session}, session_voting_handler::{add_vote_for_session, subtract_vote_for_session}, sessions_handler::{
-    delete_session, get_session, post_session, sessions, update_session,
-}, timeslot_handler::{add_timeslots, swap_timeslots, update_timeslot}};
-use crate::middleware::auth::{auth_middleware, current_user_handler};
+use crate::controllers::settings_handler::{event_config, event_settings, features, set_event_settings, set_submissions};
+use crate::controllers::speakers_handler::speakers;
+use crate::controllers::tags_handler::{apply_tag_rules, create_tag, create_tag_rule, delete_tag, update_tag};
+use crate::controllers::{login_handler::{login_handler, logout_handler}, room_handler::{delete_room, post_rooms, room_occupancy, room_schedule_csv, room_schedule_ics, room_stats, rooms}, schedule_handler::{clear, commit_grid, free_cells, generate, generate_with_progress, last_run, schedule_changes, schedule_grid, lock_schedule, score, suggestions, unlock_schedule, validate, validate_grid}, session_tags_handler::{add_tag_for_session, remove_tag_for_session, update_tag_for_session}, session_voting_handler::{add_vote_for_session, export_votes_csv, reconcile_votes, session_voters, subtract_vote_for_session, trending_sessions, vote_stats}, sessions_handler::{
+    bulk_delete_sessions, delete_session, exclude_sessions, duplicate_session, get_session, get_session_full, post_session, sessions, set_keynote, sessions_by_tag, similar_sessions, merge_sessions, unvoted_sessions, update_session,
+}, timeslot_handler::{add_timeslots, generate_timeslots, swap_timeslots, timeslot_assignments, update_timeslot}};
+use crate::middleware::auth::{auth_middleware, current_user_handler, staff_or_superuser_middleware};
 use crate::middleware::unauth::unauth_middleware;
+use crate::middleware::unconference_password::unconference_password_api_middleware;
 use crate::models::auth_model::Backend;
 use axum::{
     middleware::from_fn_with_state,
@@ -35,40 +41,94 @@ pub fn get_routes(app_state: &Arc<RwLock<AppState>>) -> Router<Arc<RwLock<AppSta
         .route("/registration", post(registration_handler))
         .route("/sessions", get(sessions))
         .route("/sessions/{id}", get(get_session))
+        .route("/sessions/{id}/full", get(get_session_full))
+        .route("/sessions/by-tag", get(sessions_by_tag))
+        .route("/sessions/trending", get(trending_sessions))
         .route("/rooms", get(rooms))
+        .route("/rooms/occupancy", get(room_occupancy))
+        .route("/rooms/{id}/schedule.ics", get(room_schedule_ics))
+        .route("/rooms/{id}/schedule.csv", get(room_schedule_csv))
+        .route("/config", get(event_config))
+        .route("/me", get(current_user))
+        .route("/config/event", get(event_settings))
+        .route("/config/features", get(features))
+        .route("/schedules/grid", get(schedule_grid))
+        .route("/schedules/changes", get(schedule_changes))
+        .route("/timeslots/{id}/assignments", get(timeslot_assignments))
         .route_layer(from_fn_with_state(app_state.clone(), unauth_middleware));
 
     let auth_routes = Router::new()
         .route("/logout", post(logout_handler))
         .route("/current_user", get(current_user_handler))
+        .route("/me", delete(delete_current_user))
         .route("/sessions/add", post(post_session))
         .route("/sessions/{id}", delete(delete_session))
         .route("/sessions/{id}", put(update_session))
+        .route("/sessions/{id}/duplicate", post(duplicate_session))
         .route("/sessions/{id}/increment", put(add_vote_for_session))
         .route("/sessions/{id}/decrement", put(subtract_vote_for_session))
         .route("/sessions/{id}/tags", post(add_tag_for_session).put(update_tag_for_session).delete(remove_tag_for_session))
-        .route_layer(from_fn_with_state(app_state.clone(), auth_middleware));
+        .route_layer(from_fn_with_state(app_state.clone(), auth_middleware))
+        .route_layer(from_fn_with_state(app_state.clone(), unconference_password_api_middleware));
 
     let staff_or_admin_routes = Router::new()
         .route("/sessions/add_for_user", post(post_session_for_user))
         .route("/registration_on_user_behalf", post(staff_registers_user_handler))
-        .route_layer(from_fn_with_state(app_state.clone(), auth_middleware));
+        .route_layer(from_fn_with_state(app_state.clone(), auth_middleware))
+        .route_layer(from_fn_with_state(app_state.clone(), unconference_password_api_middleware));
+
+    let staff_only_routes = Router::new()
+        .route("/sessions/unvoted", get(unvoted_sessions))
+        .route("/sessions/{id}/keynote", put(set_keynote))
+        .route("/sessions/bulk-delete", post(bulk_delete_sessions))
+        .route("/sessions/exclude", post(exclude_sessions))
+        .route("/sessions/merge", post(merge_sessions))
+        .route("/sessions/{id}/voters", get(session_voters))
+        .route("/sessions/{id}/similar", get(similar_sessions))
+        .route("/speakers", get(speakers))
+        .route("/stats/votes", get(vote_stats))
+        .route("/stats/rooms", get(room_stats))
+        .route("/export/bundle", get(export_bundle))
+        .route("/schedules/lock", post(lock_schedule))
+        .route("/schedules/unlock", post(unlock_schedule))
+        .route("/schedules/free-cells", get(free_cells))
+        .route("/config/submissions", post(set_submissions))
+        .route("/config/event", put(set_event_settings))
+        .route_layer(from_fn_with_state(app_state.clone(), staff_or_superuser_middleware))
+        .route_layer(from_fn_with_state(app_state.clone(), unconference_password_api_middleware));
 
     let admin_routes = Router::new()
         .route("/rooms/add", post(post_rooms))
         .route("/rooms/{id}", delete(delete_room))
-        .route("/schedules/generate", post(generate))
         .route("/schedules/clear", post(clear))
+        .route("/schedules/score", get(score))
+        .route("/schedules/last-run", get(last_run))
+        .route("/schedules/validate", get(validate).post(validate_grid))
+        .route("/schedules/grid", put(commit_grid))
+        .route("/users/{id}/vote_weight", put(set_vote_weight))
+        .route("/export/votes.csv", get(export_votes_csv))
+        .route("/admin/reconcile-votes", post(reconcile_votes))
+        .route("/schedules/suggestions", get(suggestions))
         .route("/schedules/add_session", post(add_session_to_schedule))
         .route("/schedules/remove_session", post(remove_session_from_schedule))
+        .route("/schedules/session/{session_id}", delete(unschedule_session_from_schedule))
+        .route("/schedules/snapshot", post(create_snapshot))
+        .route("/schedules/snapshots", get(snapshots))
+        .route("/schedules/restore/{snapshot_id}", post(restore_snapshot))
+        .route("/schedules/diff", get(diff_snapshots))
         .route("/timeslots/{id}", put(update_timeslot))
         .route("/timeslots/add", post(add_timeslots))
+        .route("/timeslots/generate", post(generate_timeslots))
         .route("/timeslots/swap", put(swap_timeslots))
         .route("/tags", post(create_tag))
         .route("/tags/{id}", put(update_tag))
         .route("/tags/{id}", delete(delete_tag))
+        .route("/tags/rules", post(create_tag_rule))
+        .route("/tags/apply-rules", post(apply_tag_rules))
         .route("/index/markdown", post(add_index_markdown))
+        .route("/import/bundle", post(import_bundle))
         .route_layer(from_fn_with_state(app_state.clone(), auth_middleware))
+        .route_layer(from_fn_with_state(app_state.clone(), unconference_password_api_middleware))
         .route_layer(permission_required!(
             Backend,
             "superuser"
@@ -77,5 +137,30 @@ pub fn get_routes(app_state: &Arc<RwLock<AppState>>) -> Router<Arc<RwLock<AppSta
     public_routes
         .merge(auth_routes)
         .merge(staff_or_admin_routes)
+        .merge(staff_only_routes)
         .merge(admin_routes)
 }
+
+/// Returns a router with the API routes that may run longer than the default request timeout
+///
+/// These routes are kept apart from the rest of the API so they can be given a longer timeout. They
+/// require the same permissions as the other admin routes. The timeout only covers how long the
+/// response takes to start, so streamed responses such as the schedule generation progress aren't
+/// cut off.
+///
+/// # Parameters
+/// - `app_state` - The shared application state wrapped in an `Arc` and `RwLock`
+///
+/// # Returns
+/// A router with the long running routes for the API
+pub fn get_long_running_routes(app_state: &Arc<RwLock<AppState>>) -> Router<Arc<RwLock<AppState>>> {
+    Router::new()
+        .route("/schedules/generate", post(generate))
+        .route("/sse/schedule-generate", get(generate_with_progress))
+        .route_layer(from_fn_with_state(app_state.clone(), auth_middleware))
+        .route_layer(from_fn_with_state(app_state.clone(), unconference_password_api_middleware))
+        .route_layer(permission_required!(
+            Backend,
+            "superuser"
+        ))
+}
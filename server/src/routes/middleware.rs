@@ -1,5 +1,6 @@
 use crate::config::AppState;
-use axum::Router;
+use crate::middleware::score_cache::score_cache_middleware;
+use axum::{http::StatusCode, middleware::from_fn_with_state, Router};
 use axum_login::{
     tower_sessions::{Expiry, SessionManagerLayer},
     AuthManagerLayerBuilder,
@@ -11,21 +12,26 @@ use tower_cookies::cookie::time::Duration;
 use tower_http::{
     compression::CompressionLayer,
     cors::{Any, CorsLayer},
+    timeout::TimeoutLayer,
     trace,
 };
 use tower_sessions_sqlx_store::PostgresStore;
 
 /// Configures middleware for the application
 ///
-/// This function configures middleware for the application. It adds compression, CORS, and tracing
-/// middleware to the application.
+/// This function configures middleware for the application. It adds request timeouts,
+/// compression, CORS, and tracing middleware to the application, and invalidates the cached
+/// schedule score after each change. Requests that run past their timeout are cancelled with a 504
+/// Gateway Timeout. The long running routes get the scheduler's time budget on top of the default
+/// timeout, so generating a schedule isn't cut off while the scheduler is still allowed to run.
 ///
 /// # Parameters
 /// - `app` - The application to configure the middleware for
+/// - `long_running_app` - The routes that may run longer than the default request timeout
 ///
 /// # Returns
 /// The application with the configured middleware
-pub async fn configure_middleware(app: Router, app_state: Arc<RwLock<AppState>>) -> Router {
+pub async fn configure_middleware(app: Router, long_running_app: Router, app_state: Arc<RwLock<AppState>>) -> Router {
     let read_lock = app_state.read().await;
     let session_store = PostgresStore::new(read_lock.unconf_data.read().await.unconf_db.clone());
     let session_layer = SessionManagerLayer::new(session_store)
@@ -33,6 +39,15 @@ pub async fn configure_middleware(app: Router, app_state: Arc<RwLock<AppState>>)
     let auth_layer =
         AuthManagerLayerBuilder::new(read_lock.auth_backend.clone(), session_layer).build();
 
+    let timeout = read_lock.config.request_timeout;
+    let app = app
+        .layer(TimeoutLayer::with_status_code(StatusCode::GATEWAY_TIMEOUT, timeout))
+        .merge(long_running_app.layer(TimeoutLayer::with_status_code(
+            StatusCode::GATEWAY_TIMEOUT,
+            timeout + read_lock.config.scheduler.time_budget,
+        )))
+        .layer(from_fn_with_state(app_state.clone(), score_cache_middleware));
+
     app.layer(CompressionLayer::new())
         .layer(
             CorsLayer::new()
@@ -50,3 +65,56 @@ pub async fn configure_middleware(app: Router, app_state: Arc<RwLock<AppState>>)
         )
         .layer(auth_layer)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, SchedulerConfig};
+    use crate::test_support::{app_state, send};
+    use axum::body::Body;
+    use axum::extract::Request;
+    use axum::routing::{get, post};
+    use sqlx::PgPool;
+    use std::time::Duration;
+
+    async fn slow_handler() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        "done"
+    }
+
+    /// The full middleware stack with a 50ms request timeout and a 1s scheduler budget, around a
+    /// slow route and slow stand-ins for the long running schedule generation routes.
+    async fn router(pool: &PgPool) -> Router {
+        let config = Config {
+            request_timeout: Duration::from_millis(50),
+            scheduler: SchedulerConfig { time_budget: Duration::from_secs(1), ..SchedulerConfig::default() },
+            ..Config::default()
+        };
+        let app = Router::new().route("/api/v1/slow", get(slow_handler));
+        let long_running_app = Router::new()
+            .route("/api/v1/schedules/generate", post(slow_handler))
+            .route("/api/v1/sse/schedule-generate", get(slow_handler));
+
+        configure_middleware(app, long_running_app, app_state(pool, config)).await
+    }
+
+    #[sqlx::test]
+    async fn requests_past_the_timeout_are_cancelled(pool: PgPool) {
+        let router = router(&pool).await;
+
+        let response = send(&router, Request::get("/api/v1/slow").body(Body::empty()).unwrap()).await;
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[sqlx::test]
+    async fn long_running_routes_get_the_scheduler_budget_on_top_of_the_timeout(pool: PgPool) {
+        let router = router(&pool).await;
+
+        let generate = send(&router, Request::post("/api/v1/schedules/generate").body(Body::empty()).unwrap()).await;
+        let progress = send(&router, Request::get("/api/v1/sse/schedule-generate").body(Body::empty()).unwrap()).await;
+
+        assert_eq!(generate.status(), StatusCode::OK);
+        assert_eq!(progress.status(), StatusCode::OK);
+    }
+}
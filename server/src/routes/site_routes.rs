@@ -1,8 +1,10 @@
-use crate::config::AppState;
+use crate::config::{AppState, Config};
+use crate::controllers::embed_handler::embed_schedule;
 use crate::controllers::login_handler::{login_page_handler, unconference_password_page_handler, unconference_password_submit_handler};
 use crate::controllers::registration_handler::registration_page_handler;
-use crate::controllers::site_handler::{config_handler, index_handler, schedule_handler, session_handler, unconf_timeslots_handler, users_handler};
+use crate::controllers::site_handler::{config_handler, index_handler, print_schedule_handler, schedule_handler, session_handler, unconf_timeslots_handler, users_handler};
 use crate::middleware::auth::auth_middleware;
+use crate::middleware::rate_limit::{rate_limit_middleware, RateLimiter};
 use crate::middleware::unauth::unauth_middleware;
 use crate::middleware::unconference_password::unconference_password_middleware;
 use crate::models::auth_model::Backend;
@@ -19,16 +21,20 @@ use tower_http::services::ServeDir;
 /// This function configures routes for the site:
 /// - The index page is served at `/`
 /// - The schedule page is served at `/unconf_schedule`
+/// - The printable schedule is served at `/schedule/print`
 /// - The login page is served at `/login`
 /// - The sessions page is served at `/sessions`
 /// - Static assets served from `/scripts` and `/styles`
+/// - The read-only embeddable schedule is served at `/embed/schedule`, rate limited and guarded by
+///   the embed token instead of a login
 ///
 /// # Parameters
 /// - `app_state` - Thread-safe shared state wrapped in an `Arc` and `RwLock`
+/// - `config` - The settings read at startup, for the embedded schedule's rate limit
 ///
 /// # Returns
 /// A Router with the site routes
-pub fn get_routes(app_state: Arc<RwLock<AppState>>) -> Router<Arc<RwLock<AppState>>> {
+pub fn get_routes(app_state: Arc<RwLock<AppState>>, config: &Config) -> Router<Arc<RwLock<AppState>>> {
     let scripts_dir = var("SCRIPTS_DIR").unwrap();
     let styles_dir = var("STYLES_DIR").unwrap();
 
@@ -40,6 +46,7 @@ pub fn get_routes(app_state: Arc<RwLock<AppState>>) -> Router<Arc<RwLock<AppStat
     let site_routes = Router::new()
         .route("/", get(index_handler))
         .route("/unconf_schedule", get(schedule_handler))
+        .route("/schedule/print", get(print_schedule_handler))
         .route("/login", get(login_page_handler))
         .route("/registration", get(registration_page_handler))
         .route("/sessions", get(session_handler))
@@ -50,6 +57,10 @@ pub fn get_routes(app_state: Arc<RwLock<AppState>>) -> Router<Arc<RwLock<AppStat
         .nest_service("/styles", ServeDir::new(&styles_dir))
         .with_state(app_state.clone());
 
+    let embed_routes = Router::new()
+        .route("/embed/schedule", get(embed_schedule))
+        .route_layer(from_fn_with_state(Arc::new(RateLimiter::per_minute(config.embed_rate_limit_per_minute)), rate_limit_middleware));
+
     let staff_or_admin_routes = Router::new()
         .route("/users", get(users_handler))
         .route_layer(from_fn_with_state(app_state.clone(), auth_middleware));
@@ -65,6 +76,7 @@ pub fn get_routes(app_state: Arc<RwLock<AppState>>) -> Router<Arc<RwLock<AppStat
 
     unconference_auth_routes
         .merge(site_routes)
+        .merge(embed_routes)
         .merge(staff_or_admin_routes)
         .merge(admin_site_routes)
 }
@@ -0,0 +1,129 @@
+//! Fixtures shared by the database tests.
+//!
+//! Each insert binds its values rather than formatting them into the SQL, and returns the ID of
+//! the new row where it has one.
+
+use crate::config::{AppState, Config, UnconfData};
+use crate::models::auth_model::Backend;
+use crate::models::schedule_model::ScoreCache;
+use axum::extract::Request;
+use axum::response::Response;
+use axum::Router;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+/// Inserts a user with the password `x`.
+pub(crate) async fn insert_user(pool: &PgPool, fname: &str, lname: &str, email: &str) -> i32 {
+    sqlx::query_scalar("INSERT INTO users (fname, lname, email, password) VALUES ($1, $2, $3, 'x') RETURNING id")
+        .bind(fname)
+        .bind(lname)
+        .bind(email)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Inserts a session with the content `content` and no votes.
+pub(crate) async fn insert_session(pool: &PgPool, user_id: i32, title: &str) -> i32 {
+    insert_session_with(pool, user_id, title, "content", 0).await
+}
+
+/// Inserts a session with the given content and vote counter.
+pub(crate) async fn insert_session_with(pool: &PgPool, user_id: i32, title: &str, content: &str, votes: i32) -> i32 {
+    sqlx::query_scalar("INSERT INTO sessions (user_id, title, content, votes) VALUES ($1, $2, $3, $4) RETURNING id")
+        .bind(user_id)
+        .bind(title)
+        .bind(content)
+        .bind(votes)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Inserts a room.
+pub(crate) async fn insert_room(pool: &PgPool, name: &str, location: &str, available_spots: i32) -> i32 {
+    sqlx::query_scalar("INSERT INTO rooms (name, location, available_spots) VALUES ($1, $2, $3) RETURNING id")
+        .bind(name)
+        .bind(location)
+        .bind(available_spots)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Inserts a timeslot lasting from `start_time` to `end_time`, both formatted as `HH:MM`.
+pub(crate) async fn insert_timeslot(pool: &PgPool, start_time: &str, end_time: &str) -> i32 {
+    insert_timeslot_with(pool, start_time, end_time, None).await
+}
+
+/// Inserts a timeslot that at most `max_open_rooms` rooms are used in.
+pub(crate) async fn insert_timeslot_with(pool: &PgPool, start_time: &str, end_time: &str, max_open_rooms: Option<i32>) -> i32 {
+    sqlx::query_scalar(
+        "INSERT INTO time_slots (start_time, end_time, duration, max_open_rooms)
+        VALUES ($1::TIME, $2::TIME, $2::TIME - $1::TIME, $3)
+        RETURNING id",
+    )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(max_open_rooms)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Inserts a tag.
+pub(crate) async fn insert_tag(pool: &PgPool, tag_name: &str) -> i32 {
+    sqlx::query_scalar("INSERT INTO tags (tag_name) VALUES ($1) RETURNING id")
+        .bind(tag_name)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Applies a tag to a session.
+pub(crate) async fn tag_session(pool: &PgPool, session_id: i32, tag_id: i32) {
+    sqlx::query("INSERT INTO session_tags (session_id, tag_id) VALUES ($1, $2)")
+        .bind(session_id)
+        .bind(tag_id)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+/// Records a user's vote for a session.
+pub(crate) async fn insert_vote(pool: &PgPool, user_id: i32, session_id: i32) {
+    sqlx::query("INSERT INTO user_votes (user_id, session_id) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(session_id)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+/// Puts a session on the schedule.
+pub(crate) async fn assign_session(pool: &PgPool, time_slot_id: i32, room_id: i32, session_id: i32) -> i32 {
+    sqlx::query_scalar("INSERT INTO timeslot_assignments (time_slot_id, room_id, session_id) VALUES ($1, $2, $3) RETURNING id")
+        .bind(time_slot_id)
+        .bind(room_id)
+        .bind(session_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Builds the shared state the handlers run with, on top of the test database.
+pub(crate) fn app_state(pool: &PgPool, config: Config) -> Arc<RwLock<AppState>> {
+    Arc::new(RwLock::new(AppState {
+        unconf_data: Arc::new(RwLock::new(UnconfData { unconf_db: pool.clone() })),
+        auth_backend: Backend::new(pool.clone()),
+        config,
+        score_cache: ScoreCache::default(),
+    }))
+}
+
+/// Sends a request through a router.
+pub(crate) async fn send(router: &Router, request: Request) -> Response {
+    router.clone().oneshot(request).await.unwrap()
+}
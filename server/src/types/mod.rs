@@ -1,2 +1,4 @@
+pub mod pagination;
 pub mod status_code;
+pub use pagination::Pagination;
 pub use status_code::ApiStatusCode;
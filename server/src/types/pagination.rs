@@ -0,0 +1,217 @@
+use crate::types::ApiStatusCode;
+use axum::extract::{FromRequestParts, OptionalFromRequestParts, Query};
+use axum::http::{request::Parts, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use utoipa::ToSchema;
+
+/// The page used when the query doesn't give one.
+pub const DEFAULT_PAGE: i64 = 1;
+
+/// The most items a single page can hold, also used when the query only gives a page.
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+/// An enumeration of errors that may occur when parsing the pagination of a request.
+///
+/// # Variants
+/// - `InvalidQuery` - The query string couldn't be parsed, e.g. `page` isn't a number
+/// - `InvalidPage` - The page is less than 1
+/// - `InvalidLimit` - The limit is outside of `1..=MAX_PAGE_LIMIT`
+#[derive(Debug, PartialEq, Eq, thiserror::Error, ToSchema, Serialize)]
+pub enum PaginationErr {
+    #[error("Invalid pagination query: {0}")]
+    InvalidQuery(String),
+    #[error("page must be at least 1, got {0}")]
+    InvalidPage(i64),
+    #[error("limit must be between 1 and {MAX_PAGE_LIMIT}, got {0}")]
+    InvalidLimit(i64),
+}
+
+/// Struct representing an error that occurred when parsing the pagination of a request.
+///
+/// # Fields
+/// - `status` - The HTTP status code associated with the error
+/// - `error` - A string describing the specific error that occurred
+#[derive(Debug, ToSchema)]
+pub struct PaginationError {
+    pub status: ApiStatusCode,
+    pub error: String,
+}
+
+/// Implements the `Serialize` trait for `PaginationError`
+///
+/// This implementation serializes a `PaginationError` into a JSON object with two properties:
+/// `status` and `error`.
+impl Serialize for PaginationError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let status: String = self.status.to_string();
+        let mut state = serializer.serialize_struct("PaginationError", 2)?;
+        state.serialize_field("status", &status)?;
+        state.serialize_field("error", &self.error)?;
+        state.end()
+    }
+}
+
+/// Rejects requests with invalid pagination with a 400 Bad Request.
+impl IntoResponse for PaginationErr {
+    fn into_response(self) -> Response {
+        let error = PaginationError {
+            status: ApiStatusCode::from(StatusCode::BAD_REQUEST),
+            error: self.to_string(),
+        };
+
+        (StatusCode::BAD_REQUEST, Json(error)).into_response()
+    }
+}
+
+/// The `page` and `limit` query parameters as they were sent.
+#[derive(Debug, Deserialize)]
+struct PaginationQuery {
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+/// Struct representing a validated page of results, extracted from the `page` and `limit` query
+/// parameters.
+///
+/// # Fields
+/// - `page` - The page number, starting at 1
+/// - `limit` - How many items are on each page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub page: i64,
+    pub limit: i64,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            page: DEFAULT_PAGE,
+            limit: MAX_PAGE_LIMIT,
+        }
+    }
+}
+
+impl Pagination {
+    /// Creates a `Pagination`, using the defaults for anything not given.
+    ///
+    /// # Parameters
+    /// - `page` - The page number, starting at 1
+    /// - `limit` - How many items are on each page, at most `MAX_PAGE_LIMIT`
+    ///
+    /// # Returns
+    /// A `Result` containing the `Pagination` or a `PaginationErr` describing what is invalid.
+    pub fn new(page: Option<i64>, limit: Option<i64>) -> Result<Self, PaginationErr> {
+        let page = page.unwrap_or(DEFAULT_PAGE);
+        if page < 1 {
+            return Err(PaginationErr::InvalidPage(page));
+        }
+
+        let limit = limit.unwrap_or(MAX_PAGE_LIMIT);
+        if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+            return Err(PaginationErr::InvalidLimit(limit));
+        }
+
+        Ok(Self { page, limit })
+    }
+
+    /// The number of items before the start of the page.
+    pub fn offset(&self) -> i64 {
+        (self.page - 1).saturating_mul(self.limit)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Pagination {
+    type Rejection = PaginationErr;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PaginationQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| PaginationErr::InvalidQuery(e.body_text()))?;
+
+        Pagination::new(query.page, query.limit)
+    }
+}
+
+/// Only paginates when the query gives a `page` or a `limit`, so handlers taking an
+/// `Option<Pagination>` list everything otherwise.
+impl<S: Send + Sync> OptionalFromRequestParts<S> for Pagination {
+    type Rejection = PaginationErr;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        let Query(query) = Query::<PaginationQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| PaginationErr::InvalidQuery(e.body_text()))?;
+
+        if query.page.is_none() && query.limit.is_none() {
+            return Ok(None);
+        }
+
+        Pagination::new(query.page, query.limit).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn extract(uri: &str) -> Result<Pagination, PaginationErr> {
+        let (mut parts, _) = Request::builder().uri(uri).body(()).unwrap().into_parts();
+        <Pagination as FromRequestParts<()>>::from_request_parts(&mut parts, &()).await
+    }
+
+    async fn extract_optional(uri: &str) -> Result<Option<Pagination>, PaginationErr> {
+        let (mut parts, _) = Request::builder().uri(uri).body(()).unwrap().into_parts();
+        <Pagination as OptionalFromRequestParts<()>>::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn pagination_defaults_to_the_first_full_page() {
+        assert_eq!(extract("/sessions").await, Ok(Pagination { page: 1, limit: MAX_PAGE_LIMIT }));
+        assert_eq!(extract("/sessions?page=3").await, Ok(Pagination { page: 3, limit: MAX_PAGE_LIMIT }));
+        assert_eq!(extract("/sessions?limit=5").await, Ok(Pagination { page: 1, limit: 5 }));
+    }
+
+    #[tokio::test]
+    async fn optional_pagination_is_only_given_when_the_query_asks_for_a_page() {
+        assert_eq!(extract_optional("/sessions").await, Ok(None));
+        assert_eq!(extract_optional("/sessions?q=rust").await, Ok(None));
+        assert_eq!(extract_optional("/sessions?page=2").await, Ok(Some(Pagination { page: 2, limit: MAX_PAGE_LIMIT })));
+        assert_eq!(extract_optional("/sessions?limit=5").await, Ok(Some(Pagination { page: 1, limit: 5 })));
+        assert_eq!(extract_optional("/sessions?limit=101").await, Err(PaginationErr::InvalidLimit(101)));
+    }
+
+    #[tokio::test]
+    async fn pagination_accepts_boundary_values() {
+        assert_eq!(extract("/sessions?page=1&limit=1").await, Ok(Pagination { page: 1, limit: 1 }));
+        assert_eq!(extract("/sessions?page=1&limit=100").await, Ok(Pagination { page: 1, limit: 100 }));
+    }
+
+    #[tokio::test]
+    async fn pagination_rejects_out_of_range_values() {
+        assert_eq!(extract("/sessions?page=0").await, Err(PaginationErr::InvalidPage(0)));
+        assert_eq!(extract("/sessions?page=-1").await, Err(PaginationErr::InvalidPage(-1)));
+        assert_eq!(extract("/sessions?limit=0").await, Err(PaginationErr::InvalidLimit(0)));
+        assert_eq!(extract("/sessions?limit=101").await, Err(PaginationErr::InvalidLimit(101)));
+    }
+
+    #[tokio::test]
+    async fn pagination_rejects_values_that_are_not_numbers() {
+        assert!(matches!(extract("/sessions?page=first").await, Err(PaginationErr::InvalidQuery(_))));
+        assert!(matches!(extract("/sessions?limit=").await, Err(PaginationErr::InvalidQuery(_))));
+
+        let response = extract("/sessions?page=first").await.unwrap_err().into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn offset_skips_the_previous_pages() {
+        assert_eq!(Pagination { page: 1, limit: 20 }.offset(), 0);
+        assert_eq!(Pagination { page: 3, limit: 20 }.offset(), 40);
+    }
+}
@@ -1,4 +1,3 @@
-use chrono::NaiveTime;
 use clap::Parser;
 use dotenvy::dotenv;
 use fake::faker::internet::raw::*;
@@ -8,11 +7,11 @@ use fake::Fake;
 use rand::Rng;
 use serde_json::Value;
 use server::{
-    config::AppState,
+    config::{AppState, Config},
     models::auth_model::Backend,
     models::auth_model::RegistrationRequestWithRole,
     models::room_model::{rooms_add, CreateRoomsForm, Room},
-    models::timeslot_model::{timeslots_add, TimeslotForm, TimeslotRequest},
+    models::timeslot_model::{timeslots_generate, TimeslotGenerateRequest},
 };
 use sqlx::{Pool, Postgres};
 use std::error::Error;
@@ -144,7 +143,7 @@ async fn main() {
 
 impl Params {
     async fn generate_data(&self) -> Result<(), Box<dyn Error>> {
-        let app_state = Arc::new(RwLock::new(AppState::new().await?));
+        let app_state = Arc::new(RwLock::new(AppState::new(Config::from_env()?).await?));
         let app_state_lock = app_state.read().await;
         let db_pool = &app_state_lock.unconf_data.read().await.unconf_db;
         let backend = &app_state_lock.auth_backend;
@@ -193,28 +192,14 @@ impl Params {
     }
 
     async fn generate_timeslots(&self, db_pool: &Pool<Postgres>) -> Result<(), Box<dyn Error>> {
-        let mut start_time = NaiveTime::parse_from_str("08:00", "%H:%M")?;
-        let mut end_time = NaiveTime::parse_from_str("08:30", "%H:%M")?;
-        let duration = end_time - start_time;
-        let mut timeslots = vec![];
-        dbg!(duration);
-        for _ in 1..=self.timeslots {
-            let timeslot = TimeslotForm {
-                start_time: start_time.format("%H:%M").to_string(),
-                duration: duration.num_minutes() as i32,
-                assignments: vec![],
-            };
-
-            timeslots.push(timeslot);
-            start_time += duration;
-            end_time += duration;
-        }
-
-        let timeslots_req = TimeslotRequest {
-            timeslots,
+        let timeslots_req = TimeslotGenerateRequest {
+            start_time: "08:00".to_string(),
+            slot_minutes: 30,
+            count: Some(self.timeslots as i32),
+            end_time: None,
         };
 
-        timeslots_add(db_pool, timeslots_req).await?;
+        timeslots_generate(db_pool, timeslots_req).await?;
 
         Ok(())
     }